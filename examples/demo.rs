@@ -110,9 +110,9 @@ async fn main() {
             packet_identifier: _,
             reason_string: _,
             user_properties: _,
-            reason_code,
+            reason_codes,
         })) => {
-            info!("Subscribed with reason code {reason_code:?}");
+            info!("Subscribed with reason codes {reason_codes:?}");
         }
         Ok(e) => {
             error!("Expected Suback but received event {e:?}");
@@ -218,9 +218,9 @@ async fn main() {
             packet_identifier: _,
             reason_string: _,
             user_properties: _,
-            reason_code,
+            reason_codes,
         })) => {
-            info!("Unsubscribed with reason code {reason_code:?}");
+            info!("Unsubscribed with reason codes {reason_codes:?}");
         }
         Ok(e) => {
             info!("Expected Unsuback but received event {e:?}");
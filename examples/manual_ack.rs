@@ -96,8 +96,8 @@ async fn main() {
             packet_identifier: _,
             reason_string: _,
             user_properties: _,
-            reason_code,
-        }) if reason_code.is_success() => {}
+            reason_codes,
+        }) if reason_codes.iter().all(|r| r.is_success()) => {}
         _ => panic!("subscription failed"),
     }
 
@@ -0,0 +1,379 @@
+//! Contains the topic alias mappings of incoming publications and the trait used to assign
+//! topic aliases to outgoing publications.
+
+use core::num::NonZero;
+
+use heapless::{String, Vec};
+
+/// The topic reference an outgoing publication is sent with, as determined by a
+/// [`TopicAliasManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alias {
+    /// The topic alias has been mapped to the topic name by an earlier PUBLISH packet in the
+    /// current network connection. The PUBLISH packet is sent with the topic alias only.
+    Existing(NonZero<u16>),
+
+    /// The topic alias is (re)assigned to the topic name. The PUBLISH packet is sent with both
+    /// the topic name and the topic alias.
+    New(NonZero<u16>),
+}
+
+/// Assigns topic aliases to the topic names of outgoing publications.
+///
+/// A manager is set with [`Client::manage_topic_aliases_with`] and is then asked for every
+/// PUBLISH packet sent with [`TopicReference::Name`]. Topic aliases only last for the duration of
+/// a single network connection, which is why the manager is reset with the server's topic alias
+/// maximum after every successful connection.
+///
+/// [`Client::manage_topic_aliases_with`]: crate::client::Client::manage_topic_aliases_with
+/// [`TopicReference::Name`]: crate::client::options::TopicReference::Name
+pub trait TopicAliasManager {
+    /// Removes all mappings. Called after the server has accepted a connection.
+    /// `topic_alias_maximum` is the server's topic alias maximum of the new connection.
+    fn reset(&mut self, topic_alias_maximum: u16);
+
+    /// Called before a PUBLISH packet with the topic name `topic` is sent.
+    ///
+    /// # Returns:
+    /// The topic alias to send the PUBLISH packet with or [`None`] if the topic name is sent
+    /// without a topic alias. The alias must be less than or equal to the topic alias maximum
+    /// passed to [`Self::reset`].
+    fn alias(&mut self, topic: &str) -> Option<Alias>;
+
+    /// Called if a PUBLISH packet with an [`Alias::New`] returned by [`Self::alias`] has not been
+    /// sent. The mapping of `alias` must not be treated as existing anymore.
+    fn invalidate(&mut self, alias: NonZero<u16>);
+}
+
+/// A [`TopicAliasManager`] evicting the least recently used topic alias mapping when all topic
+/// aliases are in use.
+///
+/// Configuration via const parameters:
+///
+/// - `N`: The maximum amount of topic aliases mapped at the same time. The client uses the minimum
+///   of this value and the server's topic alias maximum.
+/// - `L`: The maximum length of a topic name in bytes. Longer topic names are sent without topic
+///   alias.
+///
+/// If more topic names than topic aliases are published in rotation, every publication evicts a
+/// mapping. In this case, the PUBLISH packets are larger than without topic aliases.
+#[derive(Debug)]
+pub struct LruTopicAliases<const N: usize, const L: usize> {
+    mappings: [Mapping<L>; N],
+    topic_alias_maximum: u16,
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Mapping<const L: usize> {
+    /// The mapped topic name. Empty if the alias is unused as topic names are never empty.
+    topic: Vec<u8, L>,
+    last_used: u64,
+}
+
+impl<const N: usize, const L: usize> Default for LruTopicAliases<N, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const L: usize> LruTopicAliases<N, L> {
+    /// Creates a manager without any mappings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            mappings: [const {
+                Mapping {
+                    topic: Vec::new(),
+                    last_used: 0,
+                }
+            }; N],
+            topic_alias_maximum: 0,
+            clock: 0,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        N.min(self.topic_alias_maximum as usize)
+    }
+}
+
+impl<const N: usize, const L: usize> TopicAliasManager for LruTopicAliases<N, L> {
+    fn reset(&mut self, topic_alias_maximum: u16) {
+        self.topic_alias_maximum = topic_alias_maximum;
+        self.clock = 0;
+        for m in &mut self.mappings {
+            m.topic.clear();
+            m.last_used = 0;
+        }
+    }
+
+    fn alias(&mut self, topic: &str) -> Option<Alias> {
+        let capacity = self.capacity();
+        let mappings = &mut self.mappings[..capacity];
+
+        self.clock += 1;
+
+        if let Some((i, m)) = mappings
+            .iter_mut()
+            .enumerate()
+            .find(|(_, m)| m.topic == topic.as_bytes())
+        {
+            m.last_used = self.clock;
+            return Some(Alias::Existing(index_to_alias(i)));
+        }
+
+        if topic.len() > L {
+            return None;
+        }
+
+        // Unused mappings have a last use of 0 and are therefore picked first.
+        let (i, m) = mappings
+            .iter_mut()
+            .enumerate()
+            .min_by_key(|(_, m)| m.last_used)?;
+
+        m.topic.clear();
+        m.topic
+            .extend_from_slice(topic.as_bytes())
+            .expect("topic name is no longer than L");
+        m.last_used = self.clock;
+
+        Some(Alias::New(index_to_alias(i)))
+    }
+
+    fn invalidate(&mut self, alias: NonZero<u16>) {
+        if let Some(m) = self.mappings.get_mut(alias.get() as usize - 1) {
+            m.topic.clear();
+            m.last_used = 0;
+        }
+    }
+}
+
+/// Converts the index of a mapping into its topic alias.
+fn index_to_alias(index: usize) -> NonZero<u16> {
+    NonZero::new(index as u16 + 1).expect("index + 1 is non-zero")
+}
+
+/// The topic alias mappings established by the server within the current network connection.
+///
+/// Topic aliases are in the range `1..=N`, the mapping of alias `a` is stored at index `a - 1`.
+/// The topic names are copied as the memory of received packets is reused by the
+/// [`BufferProvider`](crate::buffer::BufferProvider) while the mappings are still in place.
+#[derive(Debug)]
+pub(crate) struct TopicAliases<const N: usize, const L: usize> {
+    /// The mapped topic names. Empty if the alias is unmapped as topic names are never empty.
+    mappings: [String<L>; N],
+}
+
+impl<const N: usize, const L: usize> TopicAliases<N, L> {
+    pub const fn new() -> Self {
+        Self {
+            mappings: [const { String::new() }; N],
+        }
+    }
+
+    /// Removes all mappings. Called whenever a new network connection is established.
+    pub fn clear(&mut self) {
+        self.mappings.iter_mut().for_each(String::clear);
+    }
+
+    /// Returns the topic name mapped to `alias` if a mapping exists.
+    pub fn get(&self, alias: NonZero<u16>) -> Option<&str> {
+        self.mappings
+            .get(alias.get() as usize - 1)
+            .map(String::as_str)
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Maps `alias` to `topic`, replacing an existing mapping.
+    ///
+    /// # Returns:
+    /// [`None`] if `alias` is greater than `N` or `topic` is longer than `L`. An existing mapping
+    /// of `alias` is removed in the latter case.
+    pub fn insert(&mut self, alias: NonZero<u16>, topic: &str) -> Option<()> {
+        let mapping = self.mappings.get_mut(alias.get() as usize - 1)?;
+        mapping.clear();
+        mapping.push_str(topic).ok()
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::client::alias::{Alias, LruTopicAliases, TopicAliasManager, TopicAliases};
+
+    #[test]
+    fn insert_get() {
+        let mut aliases = TopicAliases::<2, 8>::new();
+        let one = NonZero::new(1).unwrap();
+        let two = NonZero::new(2).unwrap();
+
+        assert_eq!(aliases.get(one), None);
+        assert_eq!(aliases.insert(one, "a/b"), Some(()));
+        assert_eq!(aliases.insert(two, "c"), Some(()));
+        assert_eq!(aliases.get(one), Some("a/b"));
+        assert_eq!(aliases.get(two), Some("c"));
+
+        assert_eq!(aliases.insert(one, "d"), Some(()));
+        assert_eq!(aliases.get(one), Some("d"));
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut aliases = TopicAliases::<2, 8>::new();
+        let three = NonZero::new(3).unwrap();
+
+        assert_eq!(aliases.insert(three, "a"), None);
+        assert_eq!(aliases.get(three), None);
+
+        let mut aliases = TopicAliases::<0, 8>::new();
+        assert_eq!(aliases.insert(NonZero::new(1).unwrap(), "a"), None);
+    }
+
+    #[test]
+    fn topic_too_long() {
+        let mut aliases = TopicAliases::<1, 3>::new();
+        let one = NonZero::new(1).unwrap();
+
+        assert_eq!(aliases.insert(one, "abc"), Some(()));
+        assert_eq!(aliases.insert(one, "abcd"), None);
+        assert_eq!(aliases.get(one), None);
+    }
+
+    #[test]
+    fn clear() {
+        let mut aliases = TopicAliases::<1, 8>::new();
+        let one = NonZero::new(1).unwrap();
+
+        aliases.insert(one, "a").unwrap();
+        aliases.clear();
+        assert_eq!(aliases.get(one), None);
+    }
+
+    #[test]
+    fn lru_assign_reuse() {
+        let mut lru = LruTopicAliases::<4, 16>::new();
+        let one = NonZero::new(1).unwrap();
+        let two = NonZero::new(2).unwrap();
+
+        assert_eq!(lru.alias("a"), None);
+
+        lru.reset(2);
+        assert_eq!(lru.alias("a"), Some(Alias::New(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::New(two)));
+        assert_eq!(lru.alias("a"), Some(Alias::Existing(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::Existing(two)));
+    }
+
+    #[test]
+    fn lru_evict() {
+        let mut lru = LruTopicAliases::<2, 16>::new();
+        let one = NonZero::new(1).unwrap();
+        let two = NonZero::new(2).unwrap();
+
+        lru.reset(10);
+        assert_eq!(lru.alias("a"), Some(Alias::New(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::New(two)));
+        assert_eq!(lru.alias("a"), Some(Alias::Existing(one)));
+        assert_eq!(lru.alias("c"), Some(Alias::New(two)));
+        assert_eq!(lru.alias("a"), Some(Alias::Existing(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::New(two)));
+    }
+
+    #[test]
+    fn lru_topic_too_long() {
+        let mut lru = LruTopicAliases::<2, 3>::new();
+
+        lru.reset(2);
+        assert_eq!(lru.alias("abcd"), None);
+        assert_eq!(lru.alias("abc"), Some(Alias::New(NonZero::new(1).unwrap())));
+    }
+
+    #[test]
+    fn lru_reset_invalidate() {
+        let mut lru = LruTopicAliases::<2, 16>::new();
+        let one = NonZero::new(1).unwrap();
+        let two = NonZero::new(2).unwrap();
+
+        lru.reset(2);
+        assert_eq!(lru.alias("a"), Some(Alias::New(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::New(two)));
+
+        lru.invalidate(one);
+        assert_eq!(lru.alias("c"), Some(Alias::New(one)));
+        assert_eq!(lru.alias("b"), Some(Alias::Existing(two)));
+
+        lru.reset(1);
+        assert_eq!(lru.alias("b"), Some(Alias::New(one)));
+        assert_eq!(lru.alias("c"), Some(Alias::New(one)));
+    }
+
+    #[cfg(feature = "bump")]
+    #[tokio::test]
+    #[test_log::test]
+    async fn mapping_outlives_buffer_reset() {
+        use embedded_io_adapters::tokio_1::FromTokio;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt, duplex},
+            join,
+        };
+        use tokio_test::assert_ok;
+
+        use crate::{
+            buffer::BumpBuffer,
+            client::{Client, event::Event, options::ConnectOptions},
+            types::MqttString,
+        };
+
+        let mut b = [0; 64];
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0, 1, 8>::new(&mut b);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+
+            // PUBLISH mapping topic alias 1 to "a/b"
+            assert_ok!(
+                s.write_all(&[
+                    0x30, 0x0A, 0x00, 0x03, b'a', b'/', b'b', 0x03, 0x23, 0x00, 0x01, b'x',
+                ])
+                .await
+            );
+            // PUBLISH with topic alias 1 only
+            assert_ok!(
+                s.write_all(&[0x30, 0x07, 0x00, 0x00, 0x03, 0x23, 0x00, 0x01, b'y'])
+                    .await
+            );
+        };
+        let client = async {
+            let options = ConnectOptions::new().clean_start().topic_alias_maximum(1);
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+
+            let Event::Publish(p) = assert_ok!(client.poll().await) else {
+                panic!("expected PUBLISH");
+            };
+            assert_eq!(p.topic.as_ref().as_str(), "a/b");
+            drop(p);
+
+            // Safety: No value returned by the client is alive.
+            unsafe { client.buffer_mut().reset() };
+
+            let Event::Publish(p) = assert_ok!(client.poll().await) else {
+                panic!("expected PUBLISH");
+            };
+            assert_eq!(p.topic.as_ref().as_str(), "a/b");
+            assert_eq!(&*p.message, b"y");
+        };
+
+        join!(server, client);
+    }
+}
@@ -0,0 +1,869 @@
+//! Implements a client negotiating the MQTT protocol version with the server at runtime.
+
+use heapless::Vec;
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError,
+        event::{Connected, Event, Publish},
+        options::{
+            AckOptions, ConnectOptions, DisconnectOptions, FilterOptions, PublicationOptions,
+            SubscribeOptions, SubscriptionOptions, UnsubscriptionOptions,
+        },
+        v3,
+    },
+    config::{ClientConfig, ServerConfig, SharedConfig},
+    eio::{Error as _, ErrorType, ReadReady},
+    fmt::{info, unreachable},
+    header::FixedHeader,
+    io::Transport,
+    session::Session,
+    time::{Clock, Instant},
+    types::{MqttString, PacketIdentifier, ReasonCode, TopicFilter},
+};
+
+/// The MQTT protocol version of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProtocolVersion {
+    /// MQTT version 3.1.1 (protocol level 4).
+    V3_1_1,
+    /// MQTT version 5.0 (protocol level 5).
+    V5,
+}
+
+enum Inner<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    V5(
+        super::Client<
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    ),
+    V3(v3::Client<'c, N, B, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>),
+
+    /// Only present while the buffer provider and session are moved from one client to the other.
+    Switching,
+}
+
+/// Calls the same method on the inner client of either protocol version.
+macro_rules! dispatch {
+    ($inner:expr, $c:ident => $e:expr) => {
+        match $inner {
+            Inner::V5($c) => $e,
+            Inner::V3($c) => $e,
+            Inner::Switching => unreachable!(),
+        }
+    };
+}
+
+/// An MQTT client connecting with MQTT version 5.0 and falling back to MQTT version 3.1.1
+/// if the server does not support MQTT version 5.0.
+///
+/// The client wraps the MQTT version 5.0 [`Client`](super::Client) or the MQTT version 3.1.1
+/// [`v3::Client`] depending on the negotiated [`ProtocolVersion`] and offers their common
+/// publish, subscribe and poll API. Events and errors are the MQTT version 5.0 [`Event`] and
+/// [`MqttError`] types. Over an MQTT version 3.1.1 connection, the fields only existing in MQTT
+/// version 5.0 are [`None`] or empty and the options which MQTT version 3.1.1 cannot express are
+/// ignored as documented by [`v3::Client`].
+///
+/// The session state and the buffer provider are passed on when the protocol version changes.
+/// Features only available in MQTT version 5.0 such as enhanced authentication and topic alias
+/// management require using the MQTT version 5.0 [`Client`](super::Client) directly.
+///
+/// The const parameters are the ones of the MQTT version 5.0 [`Client`](super::Client).
+pub struct Client<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
+> {
+    inner: Inner<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+
+    manual_ack_when:
+        &'c dyn Fn(&Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>) -> bool,
+    clock: Option<&'c dyn Clock>,
+}
+
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.inner {
+            Inner::V5(c) => f.debug_tuple("Client").field(c).finish(),
+            Inner::V3(c) => f.debug_tuple("Client").field(c).finish(),
+            Inner::Switching => f.debug_tuple("Client").finish_non_exhaustive(),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        match &self.inner {
+            Inner::V5(c) => defmt::write!(fmt, "Client({:?})", c),
+            Inner::V3(c) => defmt::write!(fmt, "Client({:?})", c),
+            Inner::Switching => defmt::write!(fmt, "Client(..)"),
+        }
+    }
+}
+
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates a new, disconnected MQTT client using a buffer provider to store
+    /// dynamically sized fields of received packets. The first connection attempt
+    /// uses MQTT version 5.0.
+    /// The session state is initialised as a new session. If you want to start the
+    /// client with an existing session, use [`Self::with_session`].
+    /// All publications and acknowledgements will be acknowledged automatically.
+    pub fn new(buffer: &'c mut B) -> Self {
+        Self {
+            inner: Inner::V5(super::Client::new(buffer)),
+            manual_ack_when: &|_| false,
+            clock: None,
+        }
+    }
+
+    /// Creates a new, disconnected MQTT client using a buffer provider to store
+    /// dynamically sized fields of received packets.
+    pub fn with_session(
+        session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        buffer: &'c mut B,
+    ) -> Self {
+        Self {
+            inner: Inner::V5(super::Client::with_session(session, buffer)),
+            manual_ack_when: &|_| false,
+            clock: None,
+        }
+    }
+
+    /// Sets the predicate which selects whether the quality of service handshakes of an incoming
+    /// publication are executed automatically by the client or manually by the user. Refer to
+    /// [`Client::ack_manually_when`](super::Client::ack_manually_when).
+    pub fn ack_manually_when(
+        &mut self,
+        predicate: &'c dyn Fn(
+            &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        ) -> bool,
+    ) {
+        self.manual_ack_when = predicate;
+
+        if let Inner::V5(c) = &mut self.inner {
+            c.ack_manually_when(predicate);
+        }
+    }
+
+    /// Sets a buffer in which received packets are reassembled before they are processed, making
+    /// [`Self::poll`] cancel-safe. The buffer is kept when the protocol version is switched. Refer
+    /// to [`Client::reassemble_packets_with`](super::Client::reassemble_packets_with).
+    pub fn reassemble_packets_with(&mut self, buffer: &'c mut [u8]) {
+        dispatch!(&mut self.inner, c => c.reassemble_packets_with(buffer))
+    }
+
+    /// Sets the [`Clock`] with which the client tracks the keep alive. The clock is kept when the
+    /// protocol version is switched. Refer to
+    /// [`Client::keep_alive_with`](super::Client::keep_alive_with).
+    pub fn keep_alive_with(&mut self, clock: &'c dyn Clock) {
+        self.clock = Some(clock);
+        dispatch!(&mut self.inner, c => c.keep_alive_with(clock))
+    }
+
+    /// Returns the protocol version of the current or last connection, or the protocol version
+    /// of the next connection attempt if the client has not connected yet.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        match self.inner {
+            Inner::V5(_) => ProtocolVersion::V5,
+            Inner::V3(_) => ProtocolVersion::V3_1_1,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Returns configuration for this client.
+    #[inline]
+    pub fn client_config(&self) -> &ClientConfig {
+        dispatch!(&self.inner, c => c.client_config())
+    }
+
+    /// Returns configuration for this client that was negotiated with the server. [`None`] for
+    /// MQTT version 3.1.1 as its CONNACK packet does not contain any server configuration.
+    #[inline]
+    pub fn server_config(&self) -> Option<&ServerConfig> {
+        match &self.inner {
+            Inner::V5(c) => Some(c.server_config()),
+            Inner::V3(_) => None,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Returns configuration for the current connection that was negotiated with the server.
+    #[inline]
+    pub fn shared_config(&self) -> &SharedConfig {
+        dispatch!(&self.inner, c => c.shared_config())
+    }
+
+    /// Returns session related configuration and tracking information.
+    #[inline]
+    pub fn session(&self) -> &Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM> {
+        dispatch!(&self.inner, c => c.session())
+    }
+
+    /// Returns an immutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer(&self) -> &B {
+        dispatch!(&self.inner, c => c.buffer())
+    }
+
+    /// Returns a mutable reference to the supplied [`BufferProvider`] implementation.
+    ///
+    /// This can for example be used to reset the underlying buffer if using `BumpBuffer`.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut B {
+        dispatch!(&mut self.inner, c => c.buffer_mut())
+    }
+
+    /// Moves the session state and the buffer provider into an inner client of the other
+    /// protocol version.
+    fn switch_to(&mut self, protocol_version: ProtocolVersion) {
+        if self.protocol_version() == protocol_version {
+            return;
+        }
+
+        let (session, buffer, reassembly_buffer) =
+            match core::mem::replace(&mut self.inner, Inner::Switching) {
+                Inner::V5(c) => c.into_parts(),
+                Inner::V3(c) => c.into_parts(),
+                Inner::Switching => unreachable!(),
+            };
+
+        self.inner = match protocol_version {
+            ProtocolVersion::V5 => {
+                let mut c = super::Client::with_session(session, buffer);
+                c.ack_manually_when(self.manual_ack_when);
+                Inner::V5(c)
+            }
+            ProtocolVersion::V3_1_1 => Inner::V3(v3::Client::with_session(session, buffer)),
+        };
+
+        if let Some(b) = reassembly_buffer {
+            self.reassemble_packets_with(b);
+        }
+        if let Some(c) = self.clock {
+            self.keep_alive_with(c);
+        }
+    }
+
+    /// Connects the client to an MQTT server on the other end of a network connection opened by
+    /// `open`.
+    ///
+    /// If the client has not yet connected over MQTT version 3.1.1, a CONNECT packet with MQTT
+    /// version 5.0 is sent first. If the server rejects it with [`ReasonCode::UnsupportedProtocolVersion`]
+    /// or the MQTT version 3.1.1 CONNACK return code 0x01 (unacceptable protocol version), `open` is
+    /// called again and the client retries with MQTT version 3.1.1. Once connected over MQTT version
+    /// 3.1.1, later calls connect with MQTT version 3.1.1 directly as the server's session state
+    /// belongs to the protocol version it was created with.
+    ///
+    /// Only call this when
+    /// - the client is newly constructed.
+    /// - a non-recoverable error has occured and [`Self::abort`] has been called.
+    /// - [`Self::disconnect`] has been called.
+    ///
+    /// # Returns:
+    /// Information about the session/connection. Use [`Self::protocol_version`] to find out
+    /// which protocol version has been negotiated.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::Network`] if `open` returned an error
+    /// * The errors that [`Client::connect`](super::Client::connect) and [`v3::Client::connect`]
+    ///   return.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the cases that [`Client::connect`](super::Client::connect) and
+    /// [`v3::Client::connect`] panic.
+    pub async fn connect<'d>(
+        &mut self,
+        mut open: impl AsyncFnMut() -> Result<N, N::Error>,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Result<Connected<'d, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>>
+    where
+        'c: 'd,
+    {
+        if let Inner::V5(c) = &mut self.inner {
+            let net = Self::open(&mut open).await?;
+
+            match c.connect(net, options, client_identifier.clone()).await {
+                Err(MqttError::Disconnect {
+                    reason: ReasonCode::UnsupportedProtocolVersion,
+                    ..
+                }) => {
+                    info!(
+                        "server does not support MQTT version 5.0, retrying with MQTT version 3.1.1"
+                    );
+                    self.switch_to(ProtocolVersion::V3_1_1);
+                }
+                r => return r,
+            }
+        }
+
+        let Inner::V3(c) = &mut self.inner else {
+            unreachable!()
+        };
+
+        let net = Self::open(&mut open).await?;
+
+        let Connected {
+            session_present,
+            client_identifier,
+            user_properties: _,
+            response_information,
+            server_reference,
+        } = c
+            .connect(net, options, client_identifier)
+            .await
+            .map_err(MqttError::inflate)?;
+
+        Ok(Connected {
+            session_present,
+            client_identifier,
+            user_properties: Vec::new(),
+            response_information,
+            server_reference,
+        })
+    }
+
+    async fn open(
+        open: &mut impl AsyncFnMut() -> Result<N, N::Error>,
+    ) -> Result<N, MqttError<'c, MAX_USER_PROPERTIES>> {
+        open()
+            .await
+            .map_err(|e| MqttError::Network(<N as ErrorType>::Error::kind(&e)))
+    }
+
+    /// Start a ping handshake by sending a PINGREQ packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    pub async fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.ping().await)
+    }
+
+    /// Returns the point in time at which [`Self::check_keep_alive`] has to be called next. Refer to
+    /// [`Client::next_ping_deadline`](super::Client::next_ping_deadline).
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        dispatch!(&self.inner, c => c.next_ping_deadline())
+    }
+
+    /// Maintains the keep alive once [`Self::next_ping_deadline`] has passed. Refer to
+    /// [`Client::check_keep_alive`](super::Client::check_keep_alive).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `check_keep_alive` returns.
+    pub async fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.check_keep_alive().await)
+    }
+
+    /// Subscribes to a single topic with the given options. Refer to
+    /// [`Client::subscribe`](super::Client::subscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `subscribe` returns.
+    pub async fn subscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.subscribe(topic_filter, options).await)
+    }
+
+    /// Subscribes to multiple topics with a single SUBSCRIBE packet. The options are ignored over
+    /// MQTT version 3.1.1. Refer to [`Client::subscribe_many`](super::Client::subscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `subscribe_many` returns.
+    pub async fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.subscribe_many(subscriptions, options).await,
+            Inner::V3(c) => c.subscribe_many(subscriptions).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Unsubscribes from a single topic filter. The options are ignored over MQTT version 3.1.1.
+    /// Refer to [`Client::unsubscribe`](super::Client::unsubscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `unsubscribe` returns.
+    pub async fn unsubscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.unsubscribe(topic_filter, options).await,
+            Inner::V3(c) => c.unsubscribe(topic_filter).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Unsubscribes from multiple topic filters with a single UNSUBSCRIBE packet. The options are
+    /// ignored over MQTT version 3.1.1. Refer to
+    /// [`Client::unsubscribe_many`](super::Client::unsubscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `unsubscribe_many` returns.
+    pub async fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.unsubscribe_many(topic_filters, options).await,
+            Inner::V3(c) => c.unsubscribe_many(topic_filters).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Start the publication of a message. Refer to [`Client::publish`](super::Client::publish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `publish` returns.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the cases that the inner client's `publish` panics. Notably,
+    /// the topic must be a [`TopicReference::Name`](crate::client::options::TopicReference::Name)
+    /// over MQTT version 3.1.1.
+    pub async fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.publish(options, message).await)
+    }
+
+    /// Resends a PUBLISH packet with DUP flag set. Refer to
+    /// [`Client::republish`](super::Client::republish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `republish` returns.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the cases that the inner client's `republish` panics.
+    pub async fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.republish(packet_identifier, options, message).await)
+    }
+
+    /// Resends all pending PUBREL packets of [`AckMode::Automatic`] publication flows. Refer to
+    /// [`Client::rerelease`](super::Client::rerelease).
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    ///
+    /// [`AckMode::Automatic`]: crate::client::options::AckMode::Automatic
+    pub async fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.rerelease().await)
+    }
+
+    /// Sends a PUBACK packet responding to an incoming manually acknowledged [`QoS::AtLeastOnce`]
+    /// publication. The reason code and options are ignored over MQTT version 3.1.1. Refer to
+    /// [`Client::manual_acknowledge`](super::Client::manual_acknowledge).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `manual_acknowledge` returns.
+    ///
+    /// [`QoS::AtLeastOnce`]: crate::types::QoS::AtLeastOnce
+    pub async fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => {
+                c.manual_acknowledge(packet_identifier, reason_code, options)
+                    .await
+            }
+            Inner::V3(c) => c.manual_acknowledge(packet_identifier).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Sends a PUBREC packet responding to an incoming manually acknowledged [`QoS::ExactlyOnce`]
+    /// publication. The reason code and options are ignored over MQTT version 3.1.1. Refer to
+    /// [`Client::manual_receive`](super::Client::manual_receive).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `manual_receive` returns.
+    ///
+    /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
+    pub async fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => {
+                c.manual_receive(packet_identifier, reason_code, options)
+                    .await
+            }
+            Inner::V3(c) => c.manual_receive(packet_identifier).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Sends a PUBREL packet responding to an incoming PUBREC packet of a manually acknowledged
+    /// outgoing publication. The options are ignored over MQTT version 3.1.1. Refer to
+    /// [`Client::manual_release`](super::Client::manual_release).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `manual_release` returns.
+    pub async fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.manual_release(packet_identifier, options).await,
+            Inner::V3(c) => c.manual_release(packet_identifier).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Sends a PUBCOMP packet responding to an incoming PUBREL packet of a manually acknowledged
+    /// incoming publication. The options are ignored over MQTT version 3.1.1. Refer to
+    /// [`Client::manual_complete`](super::Client::manual_complete).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `manual_complete` returns.
+    pub async fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.manual_complete(packet_identifier, options).await,
+            Inner::V3(c) => c.manual_complete(packet_identifier).await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Closes the network connection after an error occured. Over MQTT version 5.0, a DISCONNECT
+    /// packet is sent if the error requires it.
+    ///
+    /// After this, the session can be recovered by calling [`Self::connect`] again without clean
+    /// start.
+    pub async fn abort(&mut self) {
+        match &mut self.inner {
+            Inner::V5(c) => c.abort().await,
+            Inner::V3(c) => c.abort(),
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Disconnects gracefully from the server by sending a DISCONNECT packet. The options are
+    /// ignored over MQTT version 3.1.1. Refer to [`Client::disconnect`](super::Client::disconnect).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `disconnect` returns.
+    pub async fn disconnect(
+        &mut self,
+        options: &DisconnectOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        match &mut self.inner {
+            Inner::V5(c) => c.disconnect(options).await,
+            Inner::V3(c) => c.disconnect().await,
+            Inner::Switching => unreachable!(),
+        }
+    }
+
+    /// Combines [`Self::poll_header`] and [`Self::poll_body`].
+    ///
+    /// Polls the network for a full packet. Not cancel-safe unless a reassembly buffer has been set
+    /// with [`Self::reassemble_packets_with`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::poll_header`] and [`Self::poll_body`] return.
+    pub async fn poll(
+        &mut self,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        let header = self.poll_header().await.map_err(MqttError::inflate)?;
+        self.poll_body(header).await
+    }
+
+    /// Polls the network for a fixed header in a cancel-safe way. Refer to
+    /// [`Client::poll_header`](super::Client::poll_header).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `poll_header` returns.
+    pub async fn poll_header(&mut self) -> Result<FixedHeader, MqttError<'c, 0>> {
+        dispatch!(&mut self.inner, c => c.poll_header().await)
+    }
+
+    /// Combines [`Self::try_poll_header`] and [`Self::poll_body`].
+    ///
+    /// Polls the network for a full packet if data is ready to be read. Refer to
+    /// [`Client::try_poll`](super::Client::try_poll).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::try_poll_header`] and [`Self::poll_body`] return.
+    pub async fn try_poll(
+        &mut self,
+    ) -> Result<
+        Option<Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    >
+    where
+        N: ReadReady,
+    {
+        match self.try_poll_header().await.map_err(MqttError::inflate)? {
+            Some(header) => self.poll_body(header).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Polls the network for a fixed header in a cancel-safe way without waiting for data that is
+    /// not ready to be read yet. Refer to
+    /// [`Client::try_poll_header`](super::Client::try_poll_header).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `try_poll_header` returns.
+    pub async fn try_poll_header(&mut self) -> Result<Option<FixedHeader>, MqttError<'c, 0>>
+    where
+        N: ReadReady,
+    {
+        dispatch!(&mut self.inner, c => c.try_poll_header().await)
+    }
+
+    /// Polls the network for the variable header and payload of a packet. Not cancel-safe unless a
+    /// reassembly buffer has been set with [`Self::reassemble_packets_with`].
+    /// Refer to [`Client::poll_body`](super::Client::poll_body).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that the inner client's `poll_body` returns.
+    pub async fn poll_body(
+        &mut self,
+        header: FixedHeader,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        match &mut self.inner {
+            Inner::V5(c) => c.poll_body(header).await,
+            Inner::V3(c) => c
+                .recv_body(header, self.manual_ack_when)
+                .await
+                .map_err(MqttError::inflate),
+            Inner::Switching => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, duplex},
+        join,
+    };
+    use tokio_test::assert_ok;
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::client::{
+        any::{Client, ProtocolVersion},
+        options::ConnectOptions,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn connect_falls_back_to_v3() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c5, mut s5) = duplex(64);
+        let (c3, mut s3) = duplex(64);
+        let mut nets = [c5, c3].into_iter();
+
+        let mut c = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+
+        let tx = async {
+            let mut buf = [0; 64];
+
+            let n = assert_ok!(s5.read(&mut buf).await);
+            assert!(n > 9);
+            assert_eq!(buf[0], 0x10);
+            assert_eq!(buf[8], 5);
+            assert_ok!(s5.write_all(&[0x20, 0x02, 0x00, 0x01]).await);
+
+            let n = assert_ok!(s3.read(&mut buf).await);
+            assert!(n > 9);
+            assert_eq!(buf[0], 0x10);
+            assert_eq!(buf[8], 4);
+            assert_ok!(s3.write_all(&[0x20, 0x02, 0x00, 0x00]).await);
+        };
+        let rx = async {
+            let connected = assert_ok!(
+                c.connect(
+                    async || Ok(FromTokio::new(nets.next().unwrap())),
+                    &ConnectOptions::new().clean_start(),
+                    None,
+                )
+                .await
+            );
+
+            assert!(!connected.session_present);
+            assert_eq!(c.protocol_version(), ProtocolVersion::V3_1_1);
+            assert!(c.server_config().is_none());
+        };
+
+        join!(rx, tx);
+    }
+}
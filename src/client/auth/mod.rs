@@ -0,0 +1,263 @@
+//! Contains the trait used to respond to the server in an enhanced authentication exchange.
+
+use crate::types::{MqttBinary, ReasonCode};
+
+#[cfg(feature = "scram")]
+pub mod scram;
+
+/// The maximum length of an authentication method in bytes. The client keeps the authentication
+/// method sent in the CONNECT packet to check that the server and re-authentications use the same
+/// method.
+pub const MAX_AUTHENTICATION_METHOD_LEN: usize = 32;
+
+/// The client side of an enhanced authentication exchange as described in
+/// <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901256>.
+///
+/// The authentication method and the initial authentication data are sent in the CONNECT packet
+/// and are configured in [`ConnectOptions`]. If the server responds with an AUTH packet instead of
+/// a CONNACK packet, the [`Authenticator`] set with [`Client::authenticate_with`] computes the
+/// authentication data of the AUTH packet the client responds with. This is repeated until the
+/// server completes the exchange with a CONNACK packet.
+///
+/// A re-authentication started with [`Client::reauthenticate`] is driven the same way by
+/// [`Client::poll`] and is completed by the server with an AUTH packet with
+/// [`ReasonCode::Success`] instead of a CONNACK packet.
+///
+/// If a method returns an error, the client closes the connection with a DISCONNECT packet
+/// containing the returned [`ReasonCode`] and returns [`MqttError::Authentication`]. The reason
+/// code should be one that a client is allowed to send in a DISCONNECT packet, e.g.
+/// [`ReasonCode::UnspecifiedError`] or [`ReasonCode::ImplementationSpecificError`].
+///
+/// [`ConnectOptions`]: crate::client::options::ConnectOptions
+/// [`Client::authenticate_with`]: crate::client::Client::authenticate_with
+/// [`Client::reauthenticate`]: crate::client::Client::reauthenticate
+/// [`Client::poll`]: crate::client::Client::poll
+/// [`MqttError::Authentication`]: crate::client::MqttError::Authentication
+pub trait Authenticator {
+    /// Called when the server continues the authentication exchange with an AUTH packet with the
+    /// reason code [`ReasonCode::ContinueAuthentication`]. `data` is the content of the
+    /// packet's authentication data property.
+    ///
+    /// # Returns:
+    /// The authentication data the client sends in its responding AUTH packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`ReasonCode`] to close the connection with if the authentication exchange
+    /// cannot be continued.
+    fn continue_authentication(
+        &mut self,
+        data: Option<&[u8]>,
+    ) -> Result<Option<MqttBinary<'_>>, ReasonCode>;
+
+    /// Called when the server completes the authentication exchange with a successful CONNACK
+    /// packet or, in case of a re-authentication, with an AUTH packet with [`ReasonCode::Success`].
+    /// `data` is the content of the packet's authentication data property.
+    ///
+    /// The default implementation accepts any completion.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`ReasonCode`] to close the connection with if the completion is not accepted,
+    /// e.g. because the server failed to prove its identity.
+    fn complete_authentication(&mut self, data: Option<&[u8]>) -> Result<(), ReasonCode> {
+        let _ = data;
+        Ok(())
+    }
+}
+
+/// The state of enhanced authentication within the current network connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum State {
+    /// The connection was established without an authentication method.
+    Disabled,
+
+    /// The connection was established with an authentication method and no re-authentication
+    /// is in progress.
+    Authenticated,
+
+    /// A re-authentication has been started and has not been completed yet.
+    Reauthenticating,
+}
+
+#[cfg(test)]
+mod unit {
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, duplex},
+        join,
+    };
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        client::{
+            Client, MqttError,
+            auth::Authenticator,
+            event::Event,
+            options::{AuthOptions, ConnectOptions},
+        },
+        types::{MqttBinary, MqttString, ReasonCode},
+    };
+
+    struct Echo;
+
+    impl Authenticator for Echo {
+        fn continue_authentication(
+            &mut self,
+            data: Option<&[u8]>,
+        ) -> Result<Option<MqttBinary<'_>>, ReasonCode> {
+            assert_eq!(data, Some(&b"s"[..]));
+            Ok(Some(MqttBinary::try_from(&b"r"[..]).unwrap()))
+        }
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn reauthenticate() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+        let mut authenticator = Echo;
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.authenticate_with(&mut authenticator);
+
+        const REAUTHENTICATE: [u8; 8] = [0xF0, 0x06, 0x19, 0x04, 0x15, 0x00, 0x01, b'M'];
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(
+                s.write_all(&[0x20, 0x07, 0x00, 0x00, 0x04, 0x15, 0x00, 0x01, b'M'])
+                    .await
+            );
+
+            assert_ok!(s.read_exact(&mut buf[..8]).await);
+            assert_eq!(buf[..8], REAUTHENTICATE);
+            assert_ok!(
+                s.write_all(&[
+                    0xF0, 0x0A, 0x18, 0x08, 0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b's',
+                ])
+                .await
+            );
+            assert_ok!(s.read_exact(&mut buf[..12]).await);
+            assert_eq!(
+                buf[..12],
+                [
+                    0xF0, 0x0A, 0x18, 0x08, 0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b'r'
+                ]
+            );
+            assert_ok!(
+                s.write_all(&[0xF0, 0x06, 0x00, 0x04, 0x15, 0x00, 0x01, b'M'])
+                    .await
+            );
+
+            // The server continues with another authentication method.
+            assert_ok!(s.read_exact(&mut buf[..8]).await);
+            assert_eq!(buf[..8], REAUTHENTICATE);
+            assert_ok!(
+                s.write_all(&[0xF0, 0x06, 0x18, 0x04, 0x15, 0x00, 0x01, b'X'])
+                    .await
+            );
+            assert_ok!(s.read_exact(&mut buf[..4]).await);
+            assert_eq!(buf[..4], [0xE0, 0x02, 0x82, 0x00]);
+        };
+        let client = async {
+            let method = MqttString::try_from("M").unwrap();
+            let options = ConnectOptions::new()
+                .clean_start()
+                .authentication_method(method.clone());
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+
+            assert_eq!(
+                client
+                    .reauthenticate(&AuthOptions::new(MqttString::try_from("X").unwrap()))
+                    .await,
+                Err(MqttError::IllegalReauthentication)
+            );
+
+            let options = AuthOptions::new(method);
+            assert_ok!(client.reauthenticate(&options).await);
+            assert_eq!(
+                client.reauthenticate(&options).await,
+                Err(MqttError::IllegalReauthentication)
+            );
+            assert!(matches!(
+                assert_ok!(client.poll().await),
+                Event::ReauthenticationContinued(_)
+            ));
+            assert!(matches!(
+                assert_ok!(client.poll().await),
+                Event::Reauthenticated(_)
+            ));
+
+            assert_ok!(client.reauthenticate(&options).await);
+            assert_eq!(assert_err!(client.poll().await), MqttError::Server);
+            client.abort().await;
+        };
+
+        join!(server, client);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn reauthentication_rejected() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+        let mut authenticator = Echo;
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.authenticate_with(&mut authenticator);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(
+                s.write_all(&[0x20, 0x07, 0x00, 0x00, 0x04, 0x15, 0x00, 0x01, b'M'])
+                    .await
+            );
+
+            assert_ok!(s.read_exact(&mut buf[..8]).await);
+            assert_ok!(s.write_all(&[0xE0, 0x02, 0x87, 0x00]).await);
+        };
+        let client = async {
+            let method = MqttString::try_from("M").unwrap();
+            let options = ConnectOptions::new()
+                .clean_start()
+                .authentication_method(method.clone());
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+
+            let options = AuthOptions::new(method);
+            assert_ok!(client.reauthenticate(&options).await);
+            match assert_ok!(client.poll().await) {
+                Event::ReauthenticationFailed(d) => {
+                    assert_eq!(d.reason_code, ReasonCode::NotAuthorized);
+                }
+                e => panic!("unexpected event {:?}", e),
+            }
+
+            // The server has closed the connection.
+            assert_eq!(client.ping().await, Err(MqttError::RecoveryRequired));
+        };
+
+        join!(server, client);
+    }
+}
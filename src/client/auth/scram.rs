@@ -0,0 +1,650 @@
+//! Contains an [`Authenticator`] implementing the SCRAM-SHA-1 and SCRAM-SHA-256 enhanced
+//! authentication methods as described in [RFC 5802](https://www.rfc-editor.org/rfc/rfc5802) and
+//! [RFC 7677](https://www.rfc-editor.org/rfc/rfc7677).
+
+use core::marker::PhantomData;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use hmac::{Hmac, Mac};
+
+use crate::{
+    client::{auth::Authenticator, options::ConnectOptions},
+    fmt::{assert, debug, error},
+    types::{MqttBinary, MqttString, ReasonCode},
+};
+
+/// The maximum length of the client nonce in bytes.
+pub const MAX_NONCE_LEN: usize = 48;
+
+/// The default maximum iteration count accepted from the server, see [`Scram::max_iterations`].
+pub const DEFAULT_MAX_ITERATIONS: u32 = 100_000;
+
+/// The maximum length of the salt sent by the server in bytes.
+const MAX_SALT_LEN: usize = 128;
+
+/// The maximum length of a hash output of a [`Mechanism`] in bytes.
+const MAX_HASH_LEN: usize = 64;
+
+/// The gs2-header of a client not supporting channel binding and without authorization identity.
+const GS2_HEADER: &[u8] = b"n,,";
+
+/// The base64-encoded [`GS2_HEADER`] sent in the channel binding attribute.
+const CHANNEL_BINDING: &[u8] = b"biws";
+
+/// The hash function of a SCRAM authentication method.
+pub trait Mechanism {
+    /// The name of the authentication method, e.g. `SCRAM-SHA-256`.
+    const NAME: &'static str;
+
+    /// The output of the hash function.
+    type Output: AsRef<[u8]> + AsMut<[u8]> + Default;
+
+    /// Computes the hash of `data`.
+    fn hash(data: &[u8]) -> Self::Output;
+
+    /// Computes the HMAC of the concatenation of `parts` using `key`.
+    fn hmac(key: &[u8], parts: &[&[u8]]) -> Self::Output;
+
+    /// Computes PBKDF2 using HMAC as pseudorandom function.
+    fn salted_password(password: &[u8], salt: &[u8], iterations: u32) -> Self::Output;
+}
+
+/// The SCRAM-SHA-1 authentication method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sha1;
+
+/// The SCRAM-SHA-256 authentication method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sha256;
+
+macro_rules! mechanism {
+    ($mechanism:ty, $hash:ty, $name:literal, $len:literal) => {
+        impl Mechanism for $mechanism {
+            const NAME: &'static str = $name;
+
+            type Output = [u8; $len];
+
+            fn hash(data: &[u8]) -> Self::Output {
+                use hmac::digest::Digest;
+
+                <$hash>::digest(data).into()
+            }
+
+            fn hmac(key: &[u8], parts: &[&[u8]]) -> Self::Output {
+                let mut mac =
+                    <Hmac<$hash>>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                for part in parts {
+                    mac.update(part);
+                }
+                mac.finalize().into_bytes().into()
+            }
+
+            fn salted_password(password: &[u8], salt: &[u8], iterations: u32) -> Self::Output {
+                let mut output = Self::Output::default();
+                pbkdf2::pbkdf2_hmac::<$hash>(password, salt, iterations, &mut output);
+                output
+            }
+        }
+    };
+}
+
+mechanism!(Sha1, sha1::Sha1, "SCRAM-SHA-1", 20);
+mechanism!(Sha256, sha2::Sha256, "SCRAM-SHA-256", 32);
+
+/// A SCRAM-SHA-1 authenticator.
+pub type ScramSha1<'a, const N: usize> = Scram<'a, Sha1, N>;
+
+/// A SCRAM-SHA-256 authenticator.
+pub type ScramSha256<'a, const N: usize> = Scram<'a, Sha256, N>;
+
+/// The client side of a SCRAM authentication exchange.
+///
+/// The exchange consists of the client-first message sent in the CONNECT packet, the server-first
+/// message the server continues the exchange with, the client-final message computed by
+/// [`Scram::continue_authentication`] and the server-final message sent in the CONNACK packet.
+/// The server signature contained in the server-final message is verified before the connection
+/// is accepted.
+///
+/// Configuration via const parameters:
+///
+/// - `N`: The size of the buffer the client-final message is computed in. It has to fit the
+///   escaped user name, the nonce chosen by the server and the proof of the client.
+///   128 bytes are sufficient for common servers.
+///
+/// The password is used as is. Callers must apply SASLprep as described in
+/// [RFC 4013](https://www.rfc-editor.org/rfc/rfc4013) if the server expects it. Channel binding
+/// and authorization identities are not supported.
+///
+/// The iteration count of the key derivation is chosen by the server. To bound the time spent
+/// computing the client-final message, the exchange is rejected if the server requires more than
+/// [`DEFAULT_MAX_ITERATIONS`] iterations unless another maximum is set with
+/// [`Scram::max_iterations`].
+///
+/// The same nonce is used for every exchange driven by the authenticator, including exchanges
+/// after reconnecting and re-authentications. A new authenticator with a fresh nonce should be
+/// created for every connection.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut scram = ScramSha256::<128>::new("user", b"pencil", &nonce);
+/// let mut client_first = [0; 64];
+///
+/// let connect_options = scram
+///     .connect_options(ConnectOptions::new(), &mut client_first)
+///     .unwrap();
+///
+/// client.authenticate_with(&mut scram);
+/// client.connect(transport, &connect_options, None).await?;
+/// ```
+pub struct Scram<'a, M: Mechanism, const N: usize> {
+    user_name: &'a str,
+    password: &'a [u8],
+    nonce: &'a [u8],
+    max_iterations: u32,
+
+    server_signature: Option<M::Output>,
+
+    buffer: [u8; N],
+    len: usize,
+
+    _mechanism: PhantomData<M>,
+}
+
+impl<'a, M: Mechanism, const N: usize> Scram<'a, M, N> {
+    /// Creates an authenticator. `nonce` has to be generated by a cryptographically secure random
+    /// number generator. It is base64-encoded before it is sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `nonce` is empty or longer than [`MAX_NONCE_LEN`].
+    pub fn new(user_name: &'a str, password: &'a [u8], nonce: &'a [u8]) -> Self {
+        assert!(
+            !nonce.is_empty() && nonce.len() <= MAX_NONCE_LEN,
+            "the nonce must contain between 1 and MAX_NONCE_LEN bytes"
+        );
+
+        Self {
+            user_name,
+            password,
+            nonce,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            server_signature: None,
+            buffer: [0; N],
+            len: 0,
+            _mechanism: PhantomData,
+        }
+    }
+
+    /// Sets the maximum iteration count accepted from the server. The default is
+    /// [`DEFAULT_MAX_ITERATIONS`].
+    #[must_use]
+    pub const fn max_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Returns the name of the authentication method.
+    pub const fn authentication_method() -> MqttString<'static> {
+        MqttString::from_str_unchecked(M::NAME)
+    }
+
+    /// Writes the client-first message used as authentication data of the CONNECT packet into
+    /// `buffer`.
+    ///
+    /// # Returns:
+    /// The client-first message or [`None`] if `buffer` is too small.
+    pub fn client_first<'b>(&self, buffer: &'b mut [u8]) -> Option<MqttBinary<'b>> {
+        let mut writer = Writer::new(buffer);
+        writer.write(GS2_HEADER)?;
+        write_client_first_bare(&mut writer, self.user_name, self.nonce)?;
+        let len = writer.len;
+
+        let buffer: &'b [u8] = writer.buffer;
+        MqttBinary::from_slice(&buffer[..len]).ok()
+    }
+
+    /// Sets the authentication method and the client-first message as authentication data of
+    /// `options`. `buffer` is used to store the client-first message.
+    ///
+    /// # Returns:
+    /// The updated options or [`None`] if `buffer` is too small.
+    pub fn connect_options<'b>(
+        &self,
+        options: ConnectOptions<'b>,
+        buffer: &'b mut [u8],
+    ) -> Option<ConnectOptions<'b>> {
+        let client_first = self.client_first(buffer)?;
+
+        Some(
+            options
+                .authentication_method(Self::authentication_method())
+                .authentication_data(client_first),
+        )
+    }
+
+    fn encoded_nonce<'b>(&self, buffer: &'b mut [u8; MAX_NONCE_LEN * 4 / 3]) -> &'b [u8] {
+        let len = STANDARD
+            .encode_slice(self.nonce, buffer)
+            .expect("the nonce is no longer than MAX_NONCE_LEN");
+        &buffer[..len]
+    }
+
+    /// Computes the client-final message in the buffer and the expected server signature.
+    fn process_server_first(&mut self, server_first: &[u8]) -> Result<(), ReasonCode> {
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+
+        for attribute in server_first.split(|b| *b == b',') {
+            match attribute {
+                [b'r', b'=', value @ ..] => nonce = Some(value),
+                [b's', b'=', value @ ..] => salt = Some(value),
+                [b'i', b'=', value @ ..] => iterations = Some(value),
+                [b'm', b'=', ..] => {
+                    error!("server requires an unsupported SCRAM extension");
+                    return Err(ReasonCode::UnspecifiedError);
+                }
+                _ => {}
+            }
+        }
+
+        let (Some(nonce), Some(salt), Some(iterations)) = (nonce, salt, iterations) else {
+            error!("server-first message is missing an attribute");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+
+        let mut client_nonce = [0; MAX_NONCE_LEN * 4 / 3];
+        let client_nonce = self.encoded_nonce(&mut client_nonce);
+        if nonce.len() <= client_nonce.len() || !nonce.starts_with(client_nonce) {
+            error!("server nonce does not extend the client nonce");
+            return Err(ReasonCode::UnspecifiedError);
+        }
+
+        let mut salt_buffer = [0; MAX_SALT_LEN];
+        let Ok(salt_len) = STANDARD.decode_slice(salt, &mut salt_buffer) else {
+            error!("server sent an invalid salt");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+        let salt = &salt_buffer[..salt_len];
+
+        let Some(iterations) = core::str::from_utf8(iterations)
+            .ok()
+            .and_then(|i| i.parse::<u32>().ok())
+            .filter(|i| *i > 0)
+        else {
+            error!("server sent an invalid iteration count");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+
+        if iterations > self.max_iterations {
+            error!(
+                "server requires {} > {} (max_iterations) iterations",
+                iterations, self.max_iterations
+            );
+            return Err(ReasonCode::UnspecifiedError);
+        }
+
+        // The buffer temporarily contains client-first-message-bare followed by
+        // client-final-message-without-proof which are both part of the AuthMessage.
+        let mut writer = Writer::new(&mut self.buffer);
+        let Some((bare_len, without_proof_len)) =
+            write_auth_message_parts(&mut writer, self.user_name, self.nonce, nonce)
+        else {
+            error!("SCRAM buffer is too small");
+            return Err(ReasonCode::ImplementationSpecificError);
+        };
+
+        let (client_first_bare, without_proof) = self.buffer.split_at(bare_len);
+        let without_proof = &without_proof[..without_proof_len];
+        let auth_message: [&[u8]; 5] = [client_first_bare, b",", server_first, b",", without_proof];
+
+        let salted_password = M::salted_password(self.password, salt, iterations);
+        let client_key = M::hmac(salted_password.as_ref(), &[b"Client Key"]);
+        let stored_key = M::hash(client_key.as_ref());
+        let client_signature = M::hmac(stored_key.as_ref(), &auth_message);
+        let server_key = M::hmac(salted_password.as_ref(), &[b"Server Key"]);
+        let server_signature = M::hmac(server_key.as_ref(), &auth_message);
+
+        let mut proof = client_key;
+        for (p, s) in proof.as_mut().iter_mut().zip(client_signature.as_ref()) {
+            *p ^= s;
+        }
+
+        self.buffer
+            .copy_within(bare_len..bare_len + without_proof_len, 0);
+
+        let mut writer = Writer::new(&mut self.buffer);
+        writer.len = without_proof_len;
+        writer
+            .write(b",p=")
+            .ok_or(ReasonCode::ImplementationSpecificError)?;
+        writer
+            .write_base64(proof.as_ref())
+            .ok_or(ReasonCode::ImplementationSpecificError)?;
+
+        self.len = writer.len;
+        self.server_signature = Some(server_signature);
+
+        Ok(())
+    }
+}
+
+impl<M: Mechanism, const N: usize> Authenticator for Scram<'_, M, N> {
+    fn continue_authentication(
+        &mut self,
+        data: Option<&[u8]>,
+    ) -> Result<Option<MqttBinary<'_>>, ReasonCode> {
+        self.server_signature = None;
+
+        let Some(server_first) = data else {
+            error!("server-first message is missing");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+
+        debug!("computing SCRAM client-final message");
+        self.process_server_first(server_first)?;
+
+        MqttBinary::from_slice(&self.buffer[..self.len])
+            .map(Some)
+            .map_err(|_| ReasonCode::ImplementationSpecificError)
+    }
+
+    fn complete_authentication(&mut self, data: Option<&[u8]>) -> Result<(), ReasonCode> {
+        let Some(expected) = self.server_signature.take() else {
+            error!("server completed the SCRAM exchange without proving its identity");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+
+        let signature = match data {
+            Some([b'v', b'=', signature @ ..]) => signature,
+            Some([b'e', b'=', ..]) => {
+                error!("server rejected the SCRAM exchange");
+                return Err(ReasonCode::UnspecifiedError);
+            }
+            _ => {
+                error!("server-final message is missing or invalid");
+                return Err(ReasonCode::UnspecifiedError);
+            }
+        };
+
+        let mut buffer = [0; MAX_HASH_LEN];
+        let Ok(len) = STANDARD.decode_slice(signature, &mut buffer) else {
+            error!("server sent an invalid signature");
+            return Err(ReasonCode::UnspecifiedError);
+        };
+
+        if !constant_time_eq(&buffer[..len], expected.as_ref()) {
+            error!("server signature does not match");
+            return Err(ReasonCode::UnspecifiedError);
+        }
+
+        debug!("verified SCRAM server signature");
+        Ok(())
+    }
+}
+
+fn write_client_first_bare(writer: &mut Writer<'_>, user_name: &str, nonce: &[u8]) -> Option<()> {
+    writer.write(b"n=")?;
+    for b in user_name.bytes() {
+        match b {
+            b'=' => writer.write(b"=3D")?,
+            b',' => writer.write(b"=2C")?,
+            b => writer.write(&[b])?,
+        }
+    }
+    writer.write(b",r=")?;
+    writer.write_base64(nonce)
+}
+
+/// Writes client-first-message-bare followed by client-final-message-without-proof.
+///
+/// # Returns:
+/// The lengths of both messages.
+fn write_auth_message_parts(
+    writer: &mut Writer<'_>,
+    user_name: &str,
+    client_nonce: &[u8],
+    nonce: &[u8],
+) -> Option<(usize, usize)> {
+    write_client_first_bare(writer, user_name, client_nonce)?;
+    let bare_len = writer.len;
+    writer.write(b"c=")?;
+    writer.write(CHANNEL_BINDING)?;
+    writer.write(b",r=")?;
+    writer.write(nonce)?;
+
+    Some((bare_len, writer.len - bare_len))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+struct Writer<'b> {
+    buffer: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn new(buffer: &'b mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.len.checked_add(bytes.len())?;
+        self.buffer.get_mut(self.len..end)?.copy_from_slice(bytes);
+        self.len = end;
+        Some(())
+    }
+
+    fn write_base64(&mut self, bytes: &[u8]) -> Option<()> {
+        let len = STANDARD
+            .encode_slice(bytes, &mut self.buffer[self.len..])
+            .ok()?;
+        self.len += len;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    use crate::{
+        client::auth::{
+            Authenticator,
+            scram::{Scram, ScramSha1, ScramSha256, Sha1, Sha256},
+        },
+        types::ReasonCode,
+    };
+
+    fn nonce(encoded: &str) -> [u8; 18] {
+        let mut nonce = [0; 18];
+        let len = STANDARD.decode_slice(encoded, &mut nonce).unwrap();
+        assert_eq!(len * 4 / 3, encoded.len());
+        nonce
+    }
+
+    #[test]
+    fn authentication_method() {
+        assert_eq!(
+            Scram::<Sha1, 0>::authentication_method().as_str(),
+            "SCRAM-SHA-1"
+        );
+        assert_eq!(
+            Scram::<Sha256, 0>::authentication_method().as_str(),
+            "SCRAM-SHA-256"
+        );
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc5802#section-5
+    #[test]
+    fn sha1_rfc5802() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let mut scram = ScramSha1::<128>::new("user", b"pencil", &nonce);
+
+        let mut buffer = [0; 64];
+        let client_first = scram.client_first(&mut buffer).unwrap();
+        assert_eq!(
+            client_first.as_bytes(),
+            b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+
+        let client_final = scram
+            .continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client_final.as_bytes(),
+            b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="
+        );
+
+        assert_eq!(
+            scram.complete_authentication(Some(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=")),
+            Ok(())
+        );
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc7677#section-3
+    #[test]
+    fn sha256_rfc7677() {
+        let nonce = nonce("rOprNGfwEbeRWgbNEkqO");
+        let mut scram = ScramSha256::<128>::new("user", b"pencil", &nonce[..15]);
+
+        let mut buffer = [0; 64];
+        let client_first = scram.client_first(&mut buffer).unwrap();
+        assert_eq!(client_first.as_bytes(), b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+
+        let client_final = scram
+            .continue_authentication(Some(
+                b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            ))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            client_final.as_bytes(),
+            b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+
+        assert_eq!(
+            scram.complete_authentication(Some(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn escape_user_name() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let scram = ScramSha256::<128>::new("a=b,c", b"pencil", &nonce);
+
+        let mut buffer = [0; 64];
+        let client_first = scram.client_first(&mut buffer).unwrap();
+        assert_eq!(
+            client_first.as_bytes(),
+            b"n,,n=a=3Db=2Cc,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+
+        let mut buffer = [0; 16];
+        assert!(scram.client_first(&mut buffer).is_none());
+    }
+
+    #[test]
+    fn reject_foreign_nonce() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let mut scram = ScramSha1::<128>::new("user", b"pencil", &nonce);
+
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawX3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096"
+            )),
+            Err(ReasonCode::UnspecifiedError)
+        );
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL,s=QSXCR+Q6sek8bf92,i=4096"
+            )),
+            Err(ReasonCode::UnspecifiedError)
+        );
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=0"
+            )),
+            Err(ReasonCode::UnspecifiedError)
+        );
+        assert_eq!(
+            scram.continue_authentication(None),
+            Err(ReasonCode::UnspecifiedError)
+        );
+    }
+
+    #[test]
+    fn reject_iteration_count() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let mut scram = ScramSha1::<128>::new("user", b"pencil", &nonce);
+
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4294967295"
+            )),
+            Err(ReasonCode::UnspecifiedError)
+        );
+
+        let mut scram = ScramSha1::<128>::new("user", b"pencil", &nonce).max_iterations(4095);
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096"
+            )),
+            Err(ReasonCode::UnspecifiedError)
+        );
+    }
+
+    #[test]
+    fn reject_server_signature() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let mut scram = ScramSha1::<128>::new("user", b"pencil", &nonce);
+
+        assert_eq!(
+            scram.complete_authentication(Some(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=")),
+            Err(ReasonCode::UnspecifiedError)
+        );
+
+        let server_first: &[u8] =
+            b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096";
+
+        assert!(scram.continue_authentication(Some(server_first)).is_ok());
+        assert_eq!(
+            scram.complete_authentication(Some(b"v=rmF9pqV8S7suAoZWja4dJRkFsKA=")),
+            Err(ReasonCode::UnspecifiedError)
+        );
+
+        assert!(scram.continue_authentication(Some(server_first)).is_ok());
+        assert_eq!(
+            scram.complete_authentication(Some(b"e=invalid-proof")),
+            Err(ReasonCode::UnspecifiedError)
+        );
+
+        assert!(scram.continue_authentication(Some(server_first)).is_ok());
+        assert_eq!(
+            scram.complete_authentication(None),
+            Err(ReasonCode::UnspecifiedError)
+        );
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        let nonce = nonce("fyko+d2lbbFgONRv9qkxdawL");
+        let mut scram = ScramSha1::<64>::new("user", b"pencil", &nonce);
+
+        assert_eq!(
+            scram.continue_authentication(Some(
+                b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096",
+            )),
+            Err(ReasonCode::ImplementationSpecificError)
+        );
+    }
+}
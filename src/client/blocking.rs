@@ -0,0 +1,779 @@
+//! Implements a blocking client on top of [`embedded_io::Read`] and [`embedded_io::Write`].
+
+use embedded_io::{Error as _, ErrorKind, Read, Write};
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError,
+        alias::TopicAliasManager,
+        auth::Authenticator,
+        codec::EncodePayload,
+        event::{Connected, Event, Publish},
+        options::{
+            AckOptions, AuthOptions, ConnectOptions, DisconnectOptions, FilterOptions,
+            PublicationOptions, SubscribeOptions, SubscriptionOptions, UnsubscriptionOptions,
+        },
+        queue::PublishQueue,
+        registry::SubscriptionRegistry,
+        sans_io::{self, Pipe},
+        store::MessageStore,
+    },
+    config::{ClientConfig, ServerConfig, SharedConfig},
+    fmt::{error, info, warn},
+    session::Session,
+    time::{Clock, Instant},
+    types::{MqttString, PacketIdentifier, ReasonCode, TopicFilter},
+};
+
+/// A blocking MQTT client.
+///
+/// The client runs the protocol implementation of the async [`Client`](super::Client) through a
+/// [`sans_io::Client`]: Every method sending packets writes them to the network connection and
+/// flushes it before returning, [`Self::connect`] and [`Self::poll`] block until a packet has been
+/// received.
+///
+/// The [`Pipe`] buffers the bytes exchanged with the network connection. Its receive buffer must
+/// be able to hold the largest packet the client accepts and its transmit buffer must be able to
+/// hold the largest packet the client sends.
+///
+/// The const parameters, the session handling and the acknowledgement modes are the ones of the
+/// async [`Client`](super::Client).
+pub struct Client<
+    'c,
+    N: Read + Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
+> {
+    net: Option<N>,
+    inner: sans_io::Client<
+        'c,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+}
+
+impl<
+    'c,
+    N: Read + Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("connected", &self.net.is_some())
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    'c,
+    N: Read + Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Client {{ connected: {}, inner: {:?}, .. }}",
+            self.net.is_some(),
+            self.inner
+        );
+    }
+}
+
+impl<
+    'c,
+    N: Read + Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Client<
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates a new, disconnected MQTT client buffering network traffic in `pipe` and using a
+    /// buffer provider to store dynamically sized fields of received packets.
+    /// The session state is initialised as a new session. If you want to start the
+    /// client with an existing session, use [`Self::with_session`].
+    pub fn new(pipe: Pipe<'c>, buffer: &'c mut B) -> Self {
+        Self {
+            net: None,
+            inner: sans_io::Client::new(pipe, buffer),
+        }
+    }
+
+    /// Creates a new, disconnected MQTT client buffering network traffic in `pipe` and using a
+    /// buffer provider to store dynamically sized fields of received packets.
+    pub fn with_session(
+        pipe: Pipe<'c>,
+        session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        buffer: &'c mut B,
+    ) -> Self {
+        Self {
+            net: None,
+            inner: sans_io::Client::with_session(pipe, session, buffer),
+        }
+    }
+
+    /// Refer to [`Client::ack_manually_when`](super::Client::ack_manually_when).
+    pub fn ack_manually_when(
+        &mut self,
+        predicate: &'c dyn Fn(
+            &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        ) -> bool,
+    ) {
+        self.inner.ack_manually_when(predicate);
+    }
+
+    /// Refer to [`Client::authenticate_with`](super::Client::authenticate_with).
+    pub fn authenticate_with(&mut self, authenticator: &'c mut dyn Authenticator) {
+        self.inner.authenticate_with(authenticator);
+    }
+
+    /// Refer to [`Client::manage_topic_aliases_with`](super::Client::manage_topic_aliases_with).
+    pub fn manage_topic_aliases_with(&mut self, manager: &'c mut dyn TopicAliasManager) {
+        self.inner.manage_topic_aliases_with(manager);
+    }
+
+    /// Refer to [`Client::keep_alive_with`](super::Client::keep_alive_with).
+    pub fn keep_alive_with(&mut self, clock: &'c dyn Clock) {
+        self.inner.keep_alive_with(clock);
+    }
+
+    /// Refer to [`Client::store_messages_in`](super::Client::store_messages_in).
+    pub fn store_messages_in(&mut self, store: &'c mut dyn MessageStore) {
+        self.inner.store_messages_in(store);
+    }
+
+    /// Refer to [`Client::queue_publications_in`](super::Client::queue_publications_in).
+    pub fn queue_publications_in(&mut self, queue: &'c mut dyn PublishQueue) {
+        self.inner.queue_publications_in(queue);
+    }
+
+    /// Refer to [`Client::track_subscriptions_in`](super::Client::track_subscriptions_in).
+    pub fn track_subscriptions_in(&mut self, registry: &'c mut dyn SubscriptionRegistry) {
+        self.inner.track_subscriptions_in(registry);
+    }
+
+    /// Refer to [`Client::encode_payloads_with`](super::Client::encode_payloads_with).
+    pub fn encode_payloads_with(&mut self, buffer: &'c mut [u8]) {
+        self.inner.encode_payloads_with(buffer);
+    }
+
+    /// Refer to [`Client::queued_publications`](super::Client::queued_publications).
+    #[inline]
+    pub fn queued_publications(&self) -> usize {
+        self.inner.queued_publications()
+    }
+
+    /// Refer to [`Client::subscriptions`](super::Client::subscriptions).
+    #[inline]
+    pub fn subscriptions(&self) -> Option<&dyn SubscriptionRegistry> {
+        self.inner.subscriptions()
+    }
+
+    /// Returns configuration for this client.
+    #[inline]
+    pub fn client_config(&self) -> &ClientConfig {
+        self.inner.client_config()
+    }
+
+    /// Returns configuration for this client that was negotiated with the server.
+    #[inline]
+    pub fn server_config(&self) -> &ServerConfig {
+        self.inner.server_config()
+    }
+
+    /// Returns configuration for the current connection that was negotiated with the server.
+    #[inline]
+    pub fn shared_config(&self) -> &SharedConfig {
+        self.inner.shared_config()
+    }
+
+    /// Returns session related configuration and tracking information.
+    #[inline]
+    pub fn session(&self) -> &Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM> {
+        self.inner.session()
+    }
+
+    /// Returns an immutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer(&self) -> &B {
+        self.inner.buffer()
+    }
+
+    /// Returns a mutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut B {
+        self.inner.buffer_mut()
+    }
+
+    /// Drops the network connection after it failed and returns the error to report.
+    fn fail(&mut self, kind: ErrorKind) -> MqttError<'c, 0> {
+        self.inner.terminate();
+        self.net = None;
+        MqttError::Network(kind)
+    }
+
+    /// Writes the pending bytes of the transmit buffer to the network connection.
+    fn transmit(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(net) = &mut self.net else {
+            return Ok(());
+        };
+
+        match self.inner.transmit_to(net) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let kind = e.kind();
+                error!("I/O error during send: {:?}", kind);
+                Err(self.fail(kind))
+            }
+        }
+    }
+
+    /// Reads bytes from the network connection into the receive buffer, blocking until at least
+    /// one byte has been received.
+    fn receive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(net) = &mut self.net else {
+            warn!("attempted to receive from a closed network connection");
+            return Err(MqttError::RecoveryRequired);
+        };
+
+        match self.inner.receive_from(net) {
+            Ok(0) => {
+                error!("network connection closed by the server");
+                Err(self.fail(ErrorKind::NotConnected))
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let kind = e.kind();
+                error!("I/O error during receive: {:?}", kind);
+                Err(self.fail(kind))
+            }
+        }
+    }
+
+    /// Transmits the packets encoded by a sending method if it was successful.
+    fn sent<T>(&mut self, r: Result<T, MqttError<'c, 0>>) -> Result<T, MqttError<'c, 0>> {
+        let t = r?;
+        self.transmit()?;
+        Ok(t)
+    }
+
+    /// Establishes an MQTT-level connection over the network connection `net`, blocking until
+    /// the CONNACK packet has been received.
+    ///
+    /// Only call this when
+    /// - the client is newly constructed.
+    /// - a non-recoverable error has occured and [`Self::abort`] has been called.
+    /// - [`Self::disconnect`] has been called.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::connect`](super::Client::connect) returns.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the cases that
+    /// [`sans_io::Client::connect`] panics.
+    pub fn connect<'d>(
+        &mut self,
+        net: N,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Result<Connected<'d, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>>
+    where
+        'c: 'd,
+    {
+        self.net = Some(net);
+
+        self.inner.connect(options, client_identifier.as_ref())?;
+        self.transmit().map_err(MqttError::inflate)?;
+
+        loop {
+            let r = self.inner.poll_connack(options, client_identifier.clone());
+
+            // Answers to AUTH packets of an enhanced authentication exchange
+            self.transmit().map_err(MqttError::inflate)?;
+
+            if let Some(r) = r {
+                return r;
+            }
+
+            self.receive().map_err(MqttError::inflate)?;
+        }
+    }
+
+    /// Blocks until the next packet has been received and processed. Acknowledgements and other
+    /// responses to the packet are sent before returning.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::poll`](super::Client::poll) returns.
+    pub fn poll(
+        &mut self,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        loop {
+            if let Some(r) = self.inner.poll() {
+                self.transmit().map_err(MqttError::inflate)?;
+                return r;
+            }
+
+            self.receive().map_err(MqttError::inflate)?;
+        }
+    }
+
+    /// Refer to [`Client::reauthenticate`](super::Client::reauthenticate).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::reauthenticate`](super::Client::reauthenticate) returns.
+    pub fn reauthenticate(&mut self, options: &AuthOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.reauthenticate(options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::ping`](super::Client::ping).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::ping`](super::Client::ping) returns.
+    pub fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.ping();
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::next_ping_deadline`](super::Client::next_ping_deadline).
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        self.inner.next_ping_deadline()
+    }
+
+    /// Refer to [`Client::check_keep_alive`](super::Client::check_keep_alive).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::check_keep_alive`](super::Client::check_keep_alive)
+    /// returns.
+    pub fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.check_keep_alive();
+        if r == Err(MqttError::KeepAliveTimeout) {
+            self.net = None;
+        }
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::subscribe`](super::Client::subscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe`](super::Client::subscribe) returns.
+    pub fn subscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self.inner.subscribe(topic_filter, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::subscribe_many`](super::Client::subscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe_many`](super::Client::subscribe_many) returns.
+    pub fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self.inner.subscribe_many(subscriptions, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::unsubscribe`](super::Client::unsubscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe`](super::Client::unsubscribe) returns.
+    pub fn unsubscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self.inner.unsubscribe(topic_filter, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::unsubscribe_many`](super::Client::unsubscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe_many`](super::Client::unsubscribe_many)
+    /// returns.
+    pub fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self.inner.unsubscribe_many(topic_filters, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::publish`](super::Client::publish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish`](super::Client::publish) returns.
+    pub fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let r = self.inner.publish(options, message);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::publish_typed`](super::Client::publish_typed).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish_typed`](super::Client::publish_typed) returns.
+    pub fn publish_typed<C: EncodePayload<T>, T: ?Sized>(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        value: &T,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let r = self.inner.publish_typed::<C, T>(options, value);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::republish`](super::Client::republish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::republish`](super::Client::republish) returns.
+    pub fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.republish(packet_identifier, options, message);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::rerelease`](super::Client::rerelease).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::rerelease`](super::Client::rerelease) returns.
+    pub fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.rerelease();
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::resume`](super::Client::resume).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resume`](super::Client::resume) returns.
+    pub fn resume(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.resume();
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::drain_queue`](super::Client::drain_queue).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::drain_queue`](super::Client::drain_queue) returns.
+    pub fn drain_queue(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.drain_queue();
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::resubscribe_all`](super::Client::resubscribe_all).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resubscribe_all`](super::Client::resubscribe_all) returns.
+    pub fn resubscribe_all(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.resubscribe_all();
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::manual_acknowledge`](super::Client::manual_acknowledge).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_acknowledge`](super::Client::manual_acknowledge)
+    /// returns.
+    pub fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self
+            .inner
+            .manual_acknowledge(packet_identifier, reason_code, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::manual_receive`](super::Client::manual_receive).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_receive`](super::Client::manual_receive) returns.
+    pub fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self
+            .inner
+            .manual_receive(packet_identifier, reason_code, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::manual_release`](super::Client::manual_release).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_release`](super::Client::manual_release) returns.
+    pub fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.manual_release(packet_identifier, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::manual_complete`](super::Client::manual_complete).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_complete`](super::Client::manual_complete) returns.
+    pub fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.manual_complete(packet_identifier, options);
+        self.sent(r)
+    }
+
+    /// Refer to [`Client::abort`](super::Client::abort).
+    pub fn abort(&mut self) {
+        self.inner.abort();
+
+        match self.transmit() {
+            Ok(()) => info!("connection aborted"),
+            Err(e) => warn!("connection abort failed: {:?}", e),
+        }
+
+        self.net = None;
+    }
+
+    /// Refer to [`Client::disconnect`](super::Client::disconnect).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::disconnect`](super::Client::disconnect) returns.
+    pub fn disconnect(&mut self, options: &DisconnectOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let r = self.inner.disconnect(options);
+        let r = self.sent(r);
+        self.net = None;
+        r
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::{convert::Infallible, num::NonZero};
+    use std::vec::Vec;
+
+    use embedded_io::{ErrorKind, ErrorType, Read, Write};
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        client::{
+            MqttError,
+            blocking::Client,
+            event::Event,
+            options::{ConnectOptions, PublicationOptions, TopicReference},
+            sans_io::Pipe,
+        },
+        types::{MqttString, TopicName},
+    };
+
+    /// Returns the scripted bytes in chunks of at most 3 bytes and records the written bytes.
+    struct Net<'a> {
+        rx: &'a [u8],
+        tx: &'a mut Vec<u8>,
+    }
+
+    impl ErrorType for Net<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for Net<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let n = buf.len().min(self.rx.len()).min(3);
+            buf[..n].copy_from_slice(&self.rx[..n]);
+            self.rx = &self.rx[n..];
+            Ok(n)
+        }
+    }
+
+    impl Write for Net<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test_log::test]
+    fn connect_publish_receive() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+
+        let mut sent = Vec::new();
+        let net = Net {
+            rx: &[
+                // CONNACK
+                0x20, 0x03, 0x00, 0x00, 0x00, //
+                // PUBACK
+                0x40, 0x02, 0x00, 0x01, //
+                // PUBLISH with QoS 1
+                0x32, 0x07, 0x00, 0x01, b't', 0x00, 0x01, 0x00, b'm',
+            ],
+            tx: &mut sent,
+        };
+
+        let mut c = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(pipe, &mut b);
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+
+        let connected =
+            assert_ok!(c.connect(net, &options, Some(MqttString::try_from("a").unwrap())));
+        assert!(!connected.session_present);
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let pid = assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Name(topic)).at_least_once(),
+            "m".as_bytes().into(),
+        ))
+        .unwrap();
+        assert_eq!(pid.get().get(), 1);
+
+        let event = assert_ok!(c.poll());
+        assert!(matches!(event, Event::PublishAcknowledged(p) if p.packet_identifier == pid));
+
+        let event = assert_ok!(c.poll());
+        assert!(matches!(event, Event::Publish(p) if p.message.as_ref() == b"m"));
+
+        let err = assert_err!(c.poll());
+        assert_eq!(err, MqttError::Network(ErrorKind::NotConnected));
+        assert_eq!(assert_err!(c.poll()), MqttError::RecoveryRequired);
+        c.abort();
+        drop(c);
+
+        // CONNECT, PUBLISH and the PUBACK of the received PUBLISH
+        assert_eq!(sent[0], 0x10);
+        let publish = &sent[usize::from(sent[1]) + 2..];
+        assert_eq!(publish[0], 0x32);
+        let puback = &publish[usize::from(publish[1]) + 2..];
+        assert_eq!(puback, &[0x40, 0x04, 0x00, 0x01, 0x00, 0x00]);
+    }
+}
@@ -0,0 +1,357 @@
+//! Contains the traits with which application messages are encoded into and decoded from typed
+//! values and the codecs enabled by the `json`, `cbor` and `postcard` features.
+
+#[cfg(feature = "cbor")]
+pub use cbor::Cbor;
+#[cfg(feature = "json")]
+pub use json::Json;
+#[cfg(feature = "postcard")]
+pub use postcard::Postcard;
+
+/// A format of application messages.
+///
+/// The format is announced by the content type of the publications it encodes, which
+/// [`Publish::decode`](crate::client::event::Publish::decode) checks before decoding.
+pub trait PayloadCodec {
+    /// The content type of application messages in this format.
+    const CONTENT_TYPE: &'static str;
+
+    /// Whether application messages in this format are UTF-8 encoded character data, which is
+    /// announced by the payload format indicator.
+    const UTF8: bool;
+}
+
+/// Encodes values of type `T` into application messages.
+pub trait EncodePayload<T: ?Sized>: PayloadCodec {
+    /// Encodes `value` into `buffer` and returns the length of the application message.
+    ///
+    /// # Errors
+    ///
+    /// [`CodecError::Encode`] if the value cannot be encoded or the buffer is too small.
+    fn encode(value: &T, buffer: &mut [u8]) -> Result<usize, CodecError>;
+}
+
+/// Decodes values of type `T` from application messages, which they may borrow from.
+pub trait DecodePayload<'de, T>: PayloadCodec {
+    /// Decodes a value from an application message.
+    ///
+    /// # Errors
+    ///
+    /// [`CodecError::Decode`] if the application message is not a valid encoding of a `T`.
+    fn decode(payload: &'de [u8]) -> Result<T, CodecError>;
+}
+
+/// Error returned when encoding or decoding an application message failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CodecError {
+    /// The value could not be encoded, e.g. because the buffer is too small.
+    Encode,
+
+    /// The application message is not a valid encoding of the requested type.
+    Decode,
+
+    /// The content type of the publication is not the one of the codec.
+    ContentType,
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use serde::{Deserialize, Serialize};
+
+    use crate::client::codec::{CodecError, DecodePayload, EncodePayload, PayloadCodec};
+
+    /// JSON via `serde-json-core`. Decoding does not unescape borrowed strings.
+    #[derive(Debug, Clone, Copy)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Json;
+
+    impl PayloadCodec for Json {
+        const CONTENT_TYPE: &'static str = "application/json";
+        const UTF8: bool = true;
+    }
+
+    impl<T: Serialize + ?Sized> EncodePayload<T> for Json {
+        fn encode(value: &T, buffer: &mut [u8]) -> Result<usize, CodecError> {
+            serde_json_core::to_slice(value, buffer).map_err(|_| CodecError::Encode)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> DecodePayload<'de, T> for Json {
+        fn decode(payload: &'de [u8]) -> Result<T, CodecError> {
+            serde_json_core::from_slice(payload)
+                .map(|(value, _)| value)
+                .map_err(|_| CodecError::Decode)
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use minicbor::{Decode, Encode, encode::write::Cursor};
+
+    use crate::client::codec::{CodecError, DecodePayload, EncodePayload, PayloadCodec};
+
+    /// CBOR via `minicbor`.
+    #[derive(Debug, Clone, Copy)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Cbor;
+
+    impl PayloadCodec for Cbor {
+        const CONTENT_TYPE: &'static str = "application/cbor";
+        const UTF8: bool = false;
+    }
+
+    impl<T: Encode<()> + ?Sized> EncodePayload<T> for Cbor {
+        fn encode(value: &T, buffer: &mut [u8]) -> Result<usize, CodecError> {
+            let mut cursor = Cursor::new(buffer);
+            minicbor::encode(value, &mut cursor).map_err(|_| CodecError::Encode)?;
+
+            Ok(cursor.position())
+        }
+    }
+
+    impl<'de, T: Decode<'de, ()>> DecodePayload<'de, T> for Cbor {
+        fn decode(payload: &'de [u8]) -> Result<T, CodecError> {
+            minicbor::decode(payload).map_err(|_| CodecError::Decode)
+        }
+    }
+}
+
+#[cfg(feature = "postcard")]
+mod postcard {
+    use serde::{Deserialize, Serialize};
+
+    use crate::client::codec::{CodecError, DecodePayload, EncodePayload, PayloadCodec};
+
+    /// Postcard via `postcard`.
+    #[derive(Debug, Clone, Copy)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Postcard;
+
+    impl PayloadCodec for Postcard {
+        const CONTENT_TYPE: &'static str = "application/x-postcard";
+        const UTF8: bool = false;
+    }
+
+    impl<T: Serialize + ?Sized> EncodePayload<T> for Postcard {
+        fn encode(value: &T, buffer: &mut [u8]) -> Result<usize, CodecError> {
+            ::postcard::to_slice(value, buffer)
+                .map(|encoded| encoded.len())
+                .map_err(|_| CodecError::Encode)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> DecodePayload<'de, T> for Postcard {
+        fn decode(payload: &'de [u8]) -> Result<T, CodecError> {
+            ::postcard::from_bytes(payload).map_err(|_| CodecError::Decode)
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use heapless::Vec;
+
+    #[cfg(any(feature = "json", feature = "cbor", feature = "postcard"))]
+    use crate::client::codec::EncodePayload;
+    use crate::{
+        bytes::Bytes,
+        client::{
+            codec::{CodecError, DecodePayload, PayloadCodec},
+            event::Publish,
+            options::AckMode,
+        },
+        types::{IdentifiedQoS, MqttString, TopicName},
+    };
+
+    struct Text;
+
+    impl PayloadCodec for Text {
+        const CONTENT_TYPE: &'static str = "text/plain";
+        const UTF8: bool = true;
+    }
+
+    impl<'de> DecodePayload<'de, &'de str> for Text {
+        fn decode(payload: &'de [u8]) -> Result<&'de str, CodecError> {
+            core::str::from_utf8(payload).map_err(|_| CodecError::Decode)
+        }
+    }
+
+    #[test]
+    fn decode_checks_content_type() {
+        let mut publish = Publish::<'_, 0, 0> {
+            ack_mode: AckMode::Automatic,
+            dup: false,
+            identified_qos: IdentifiedQoS::AtMostOnce,
+            retain: false,
+            topic: TopicName::new(MqttString::try_from("t").unwrap()).unwrap(),
+            payload_format_indicator: None,
+            message_expiry_interval: None,
+            response_topic: None,
+            correlation_data: None,
+            user_properties: Vec::new(),
+            subscription_identifiers: Vec::new(),
+            content_type: None,
+            message: Bytes::Borrowed(b"m"),
+        };
+        assert_eq!(publish.decode::<Text, _>(), Err(CodecError::ContentType));
+        assert_eq!(publish.decode_unchecked::<Text, _>(), Ok("m"));
+
+        publish.content_type = Some(MqttString::try_from("text/plain").unwrap());
+        assert_eq!(publish.decode::<Text, _>(), Ok("m"));
+
+        publish.content_type = Some(MqttString::try_from("application/json").unwrap());
+        assert_eq!(publish.decode::<Text, _>(), Err(CodecError::ContentType));
+
+        publish.content_type = Some(MqttString::try_from("text/plain").unwrap());
+        publish.message = Bytes::Borrowed(&[0xFF]);
+        assert_eq!(publish.decode::<Text, _>(), Err(CodecError::Decode));
+    }
+
+    #[cfg(any(feature = "json", feature = "postcard"))]
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Reading<'a> {
+        sensor: &'a str,
+        value: i32,
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_roundtrip() {
+        use crate::client::codec::Json;
+
+        let reading = Reading {
+            sensor: "t1",
+            value: -4,
+        };
+        let mut buffer = [0; 32];
+
+        let len = Json::encode(&reading, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], br#"{"sensor":"t1","value":-4}"#);
+        assert_eq!(Json::decode(&buffer[..len]), Ok(reading));
+
+        assert_eq!(
+            Json::encode(
+                &Reading {
+                    sensor: "t1",
+                    value: 0
+                },
+                &mut buffer[..8]
+            ),
+            Err(CodecError::Encode)
+        );
+        assert_eq!(
+            <Json as DecodePayload<'_, Reading<'_>>>::decode(b"{\"sensor\":1}"),
+            Err(CodecError::Decode)
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_roundtrip() {
+        use crate::client::codec::Cbor;
+
+        let mut buffer = [0; 16];
+
+        let len = Cbor::encode(&("t1", -4i32), &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], [0x82, 0x62, b't', b'1', 0x23]);
+        assert_eq!(Cbor::decode(&buffer[..len]), Ok(("t1", -4i32)));
+
+        assert_eq!(
+            Cbor::encode(&("t1", -4i32), &mut buffer[..2]),
+            Err(CodecError::Encode)
+        );
+        assert_eq!(
+            <Cbor as DecodePayload<'_, (&str, i32)>>::decode(&[0x82, 0x62]),
+            Err(CodecError::Decode)
+        );
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn postcard_roundtrip() {
+        use crate::client::codec::Postcard;
+
+        let reading = Reading {
+            sensor: "t1",
+            value: -4,
+        };
+        let mut buffer = [0; 16];
+
+        let len = Postcard::encode(&reading, &mut buffer).unwrap();
+        assert_eq!(&buffer[..len], [0x02, b't', b'1', 0x07]);
+        assert_eq!(Postcard::decode(&buffer[..len]), Ok(reading));
+
+        assert_eq!(
+            Postcard::encode(
+                &Reading {
+                    sensor: "t1",
+                    value: 0
+                },
+                &mut buffer[..2]
+            ),
+            Err(CodecError::Encode)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    #[test_log::test]
+    async fn cancelled_publish_typed_keeps_buffer() {
+        use core::time::Duration;
+
+        use embedded_io_adapters::tokio_1::FromTokio;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt, duplex},
+            join,
+            time::timeout,
+        };
+        use tokio_test::{assert_err, assert_ok};
+
+        #[cfg(feature = "alloc")]
+        use crate::buffer::AllocBuffer;
+        #[cfg(feature = "bump")]
+        use crate::buffer::BumpBuffer;
+        use crate::client::{
+            Client,
+            codec::Json,
+            options::{ConnectOptions, PublicationOptions, TopicReference},
+        };
+
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut payload = [0; 128];
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.encode_payloads_with(&mut payload);
+
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new().clean_start();
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+        };
+        join!(server, connect);
+
+        // The server does not read, so the PUBLISH packet does not fit into the transport.
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let options = PublicationOptions::new(TopicReference::Name(topic));
+        let reading = Reading {
+            sensor: core::str::from_utf8(&[b's'; 80]).unwrap(),
+            value: 0,
+        };
+        let publish = client.publish_typed::<Json, _>(&options, &reading);
+        assert_err!(timeout(Duration::from_millis(10), publish).await);
+        assert!(client.protocol.payload_buffer.is_some());
+    }
+}
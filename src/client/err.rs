@@ -50,15 +50,25 @@ pub enum Error<'e, const MAX_USER_PROPERTIES: usize> {
     /// [`Client::abort`]: crate::client::Client::abort
     Alloc,
 
-    /// An AUTH packet header has been received by the client. AUTH packets are not supported by the client.
-    /// The client has scheduled a DISCONNECT packet with [`ReasonCode::ImplementationSpecificError`].
-    /// The packet body has not been decoded.
+    /// An AUTH packet has been received by the client which it cannot respond to. This is the case if
+    /// no [`Authenticator`] has been set or if the AUTH packet was received outside of an authentication
+    /// exchange. The client has scheduled a DISCONNECT packet.
     ///
     /// Unrecoverable error. [`Client::abort`] should be called.
     ///
+    /// [`Authenticator`]: crate::client::auth::Authenticator
     /// [`Client::abort`]: crate::client::Client::abort
     AuthPacketReceived,
 
+    /// The [`Authenticator`] did not accept the server's part of the authentication exchange. The client
+    /// has scheduled a DISCONNECT packet with the reason code returned by the [`Authenticator`].
+    ///
+    /// Unrecoverable error. [`Client::abort`] should be called.
+    ///
+    /// [`Authenticator`]: crate::client::auth::Authenticator
+    /// [`Client::abort`]: crate::client::Client::abort
+    Authentication,
+
     /// The client could not connect to the broker or the broker has sent a DISCONNECT packet.
     ///
     /// Unrecoverable error. [`Client::abort`] should be called.
@@ -82,6 +92,14 @@ pub enum Error<'e, const MAX_USER_PROPERTIES: usize> {
         server_reference: Option<MqttString<'e>>,
     },
 
+    /// No PINGRESP packet has been received within the keep alive interval after sending a PINGREQ
+    /// packet. The client has closed the network connection.
+    ///
+    /// Unrecoverable error. [`Client::abort`] should be called.
+    ///
+    /// [`Client::abort`]: crate::client::Client::abort
+    KeepAliveTimeout,
+
     /// Another unrecoverable error has been returned earlier. The underlying connection is in a state,
     /// in which it refuses/is not able to perform regular communication.
     ///
@@ -168,12 +186,15 @@ pub enum Error<'e, const MAX_USER_PROPERTIES: usize> {
 
     /// A packet was too long to encode its length with the variable byte integer.
     ///
-    /// This can currently only be returned from [`Client::publish`] or [`Client::republish`].
+    /// This can currently only be returned from [`Client::publish`], [`Client::republish`],
+    /// [`Client::subscribe_many`] or [`Client::unsubscribe_many`].
     ///
     /// Recoverable error. No action has been taken by the client.
     ///
     /// [`Client::publish`]: crate::client::Client::publish
     /// [`Client::republish`]: crate::client::Client::republish
+    /// [`Client::subscribe_many`]: crate::client::Client::subscribe_many
+    /// [`Client::unsubscribe_many`]: crate::client::Client::unsubscribe_many
     PacketMaximumLengthExceeded,
 
     /// A packet is too long and would exceed the servers maximum packet size.
@@ -246,6 +267,42 @@ pub enum Error<'e, const MAX_USER_PROPERTIES: usize> {
     /// [`SessionExpiryInterval::NeverEnd`]: crate::config::SessionExpiryInterval::NeverEnd
     /// [`SessionExpiryInterval::Seconds`]: crate::config::SessionExpiryInterval::Seconds
     IllegalDisconnectSessionExpiryInterval,
+
+    /// A re-authentication was attempted although the connection was established without an authentication
+    /// method, another re-authentication is still in progress or with an authentication method other than
+    /// the one sent in the CONNECT packet. This would cause a protocol error.
+    ///
+    /// Recoverable error. No action has been taken by the client. In case of a re-authentication in progress,
+    /// try again after [`Event::Reauthenticated`] has been emitted.
+    ///
+    /// [`Event::Reauthenticated`]: crate::client::event::Event::Reauthenticated
+    IllegalReauthentication,
+
+    /// An application message could not be encoded by the [`PayloadCodec`], e.g. because the
+    /// buffer set with [`Client::encode_payloads_with`] is too small.
+    ///
+    /// Recoverable error. No action has been taken by the client.
+    ///
+    /// [`PayloadCodec`]: crate::client::codec::PayloadCodec
+    /// [`Client::encode_payloads_with`]: crate::client::Client::encode_payloads_with
+    PayloadEncoding,
+
+    /// A [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`] publication to a [`TopicReference::Alias`] was
+    /// attempted while a [`MessageStore`] is set. Topic alias mappings only last for a network
+    /// connection, so [`Client::resume`] could not retransmit the stored PUBLISH packet after a
+    /// reconnection.
+    ///
+    /// Recoverable error. No action has been taken by the client. Publish with
+    /// [`TopicReference::Name`] or [`TopicReference::Mapping`] instead.
+    ///
+    /// [`QoS::AtLeastOnce`]: crate::types::QoS::AtLeastOnce
+    /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
+    /// [`TopicReference::Alias`]: crate::client::options::TopicReference::Alias
+    /// [`TopicReference::Name`]: crate::client::options::TopicReference::Name
+    /// [`TopicReference::Mapping`]: crate::client::options::TopicReference::Mapping
+    /// [`MessageStore`]: crate::client::store::MessageStore
+    /// [`Client::resume`]: crate::client::Client::resume
+    UnstorableTopicAlias,
 }
 
 impl<const MAX_USER_PROPERTIES: usize> Error<'_, MAX_USER_PROPERTIES> {
@@ -267,6 +324,9 @@ impl<const MAX_USER_PROPERTIES: usize> Error<'_, MAX_USER_PROPERTIES> {
                 | Self::UnsupportedByServer
                 | Self::IllegalNoLocalSharedSubscription
                 | Self::IllegalDisconnectSessionExpiryInterval
+                | Self::IllegalReauthentication
+                | Self::PayloadEncoding
+                | Self::UnstorableTopicAlias
         )
     }
 }
@@ -283,6 +343,7 @@ impl<'e> Error<'e, 0> {
             Self::Server => Error::Server,
             Self::Alloc => Error::Alloc,
             Self::AuthPacketReceived => Error::AuthPacketReceived,
+            Self::Authentication => Error::Authentication,
             Self::Disconnect {
                 reason,
                 reason_string,
@@ -294,6 +355,7 @@ impl<'e> Error<'e, 0> {
                 user_properties: user_properties.into_iter().collect(),
                 server_reference,
             },
+            Self::KeepAliveTimeout => Error::KeepAliveTimeout,
             Self::RecoveryRequired => Error::RecoveryRequired,
             Self::PacketIdentifierNotInFlight => Error::PacketIdentifierNotInFlight,
             Self::AllPacketIdentifiersUsed => Error::AllPacketIdentifiersUsed,
@@ -310,6 +372,9 @@ impl<'e> Error<'e, 0> {
             Self::IllegalDisconnectSessionExpiryInterval => {
                 Error::IllegalDisconnectSessionExpiryInterval
             }
+            Self::IllegalReauthentication => Error::IllegalReauthentication,
+            Self::PayloadEncoding => Error::PayloadEncoding,
+            Self::UnstorableTopicAlias => Error::UnstorableTopicAlias,
         }
     }
 }
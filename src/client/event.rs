@@ -2,14 +2,18 @@
 
 use heapless::Vec;
 
+#[cfg(feature = "v5")]
+use crate::v5::{packet::GenericPubackPacket, property::Property};
 use crate::{
     bytes::Bytes,
-    client::AckMode,
+    client::{
+        codec::{CodecError, DecodePayload},
+        options::AckMode,
+    },
     types::{
         IdentifiedQoS, MqttBinary, MqttString, MqttStringPair, PacketIdentifier, ReasonCode,
-        TopicName, VarByteInt,
+        ReasonCodes, TopicName, VarByteInt,
     },
-    v5::{packet::GenericPubackPacket, property::Property},
 };
 
 /// Contains information taken from a connection handshake which the client does not have to
@@ -71,14 +75,15 @@ pub enum Event<'e, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PRO
 
     /// The server sent a SUBACK packet matching a SUBSCRIBE packet.
     ///
-    /// The subscription process is complete and was successful if the [`ReasonCode`] indicates
-    /// success. The SUBSCRIBE packet won't have to be resent.
+    /// The subscription process is complete. The subscription to a topic filter was successful if
+    /// its [`ReasonCode`] indicates success. The SUBSCRIBE packet won't have to be resent.
     Suback(Suback<'e, MAX_USER_PROPERTIES>),
 
     /// The server sent an UNSUBACK packet matching an UNSUBSCRIBE packet.
     ///
-    /// The unsubscription process is complete and was successful if the [`ReasonCode`]
-    /// indicates success. The UNSUBSCRIBE packet won't have to be resent.
+    /// The unsubscription process is complete. The unsubscription from a topic filter was
+    /// successful if its [`ReasonCode`] indicates success. The UNSUBSCRIBE packet won't have to
+    /// be resent.
     Unsuback(Suback<'e, MAX_USER_PROPERTIES>),
 
     /// The server sent a PUBACK, PUBREC or PUBCOMP with an erroneous [`ReasonCode`],
@@ -191,13 +196,40 @@ pub enum Event<'e, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PRO
     /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
     /// [`Client::ack_manually_when`]: crate::client::Client::ack_manually_when
     Duplicate(Publish<'e, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>),
+
+    /// The server sent an AUTH packet with [`ReasonCode::ContinueAuthentication`] within a
+    /// re-authentication started with [`Client::reauthenticate`]. The client has responded with
+    /// an AUTH packet containing the authentication data computed by the [`Authenticator`].
+    ///
+    /// [`Client::reauthenticate`]: crate::client::Client::reauthenticate
+    /// [`Authenticator`]: crate::client::auth::Authenticator
+    ReauthenticationContinued(Auth<'e, MAX_USER_PROPERTIES>),
+
+    /// The server sent an AUTH packet with [`ReasonCode::Success`] completing a re-authentication
+    /// started with [`Client::reauthenticate`]. The [`Authenticator`] has accepted the completion.
+    ///
+    /// [`Client::reauthenticate`]: crate::client::Client::reauthenticate
+    /// [`Authenticator`]: crate::client::auth::Authenticator
+    Reauthenticated(Auth<'e, MAX_USER_PROPERTIES>),
+
+    /// The server rejected a re-authentication started with [`Client::reauthenticate`] by sending
+    /// a DISCONNECT packet, e.g. with [`ReasonCode::NotAuthorized`].
+    ///
+    /// The network connection has been closed, the client has to reconnect to continue. The
+    /// session state is unaffected. If the [`Authenticator`] rejects the server's part of the
+    /// exchange instead, [`MqttError::Authentication`] is returned.
+    ///
+    /// [`Client::reauthenticate`]: crate::client::Client::reauthenticate
+    /// [`Authenticator`]: crate::client::auth::Authenticator
+    /// [`MqttError::Authentication`]: crate::client::MqttError::Authentication
+    ReauthenticationFailed(Disconnect<'e, MAX_USER_PROPERTIES>),
 }
 
 /// Content of [`Event::Suback`].
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Suback<'s, const MAX_USER_PROPERTIES: usize> {
-    /// Packet identifier of the acknowledged SUBSCRIBE packet.
+    /// Packet identifier of the acknowledged SUBSCRIBE/UNSUBSCRIBE packet.
     pub packet_identifier: PacketIdentifier,
 
     /// The reason string of the SUBACK/UNSUBACK packet.
@@ -206,8 +238,37 @@ pub struct Suback<'s, const MAX_USER_PROPERTIES: usize> {
     /// If the vector is full, this list might not be exhaustive.
     pub user_properties: Vec<MqttStringPair<'s>, MAX_USER_PROPERTIES>,
 
-    /// Reason code returned for the subscription.
+    /// Reason codes returned for the topic filters, one per topic filter in the same order as
+    /// the topic filters in the SUBSCRIBE/UNSUBSCRIBE packet.
+    pub reason_codes: ReasonCodes<'s>,
+}
+
+/// Content of [`Event::ReauthenticationContinued`] and [`Event::Reauthenticated`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Auth<'a, const MAX_USER_PROPERTIES: usize> {
+    /// The reason string of the AUTH packet.
+    pub reason_string: Option<MqttString<'a>>,
+    /// The user property entries in the AUTH packet.
+    /// If the vector is full, this list might not be exhaustive.
+    pub user_properties: Vec<MqttStringPair<'a>, MAX_USER_PROPERTIES>,
+}
+
+/// Content of [`Event::ReauthenticationFailed`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Disconnect<'d, const MAX_USER_PROPERTIES: usize> {
+    /// The [`ReasonCode`] of the DISCONNECT packet. It is always erroneous.
     pub reason_code: ReasonCode,
+
+    /// The reason string of the DISCONNECT packet.
+    pub reason_string: Option<MqttString<'d>>,
+    /// The user property entries in the DISCONNECT packet.
+    /// If the vector is full, this list might not be exhaustive.
+    pub user_properties: Vec<MqttStringPair<'d>, MAX_USER_PROPERTIES>,
+
+    /// Another server which can be used.
+    pub server_reference: Option<MqttString<'d>>,
 }
 
 /// Content of [`Event::Publish`] or [`Event::Duplicate`]. In the latter case, it is **NOT** a valid
@@ -239,7 +300,8 @@ pub struct Publish<'p, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER
     /// the retain as published flag of the matching subscription.
     pub retain: bool,
 
-    /// The exact topic of this publication.
+    /// The exact topic of this publication. If the server used a topic alias, this is the topic
+    /// name mapped to the alias.
     pub topic: TopicName<'p>,
 
     /// If present, indicates whether the payload is UTF-8. This value is set by the publisher and is
@@ -276,6 +338,40 @@ pub struct Publish<'p, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER
     pub message: Bytes<'p>,
 }
 
+impl<const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize>
+    Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    /// Decodes the application message into a `T` with the codec `C`, e.g.
+    /// `publish.decode::<Json, Reading>()`.
+    ///
+    /// # Errors
+    ///
+    /// * [`CodecError::ContentType`] if the publication has no content type or one differing from
+    ///   the one of the codec
+    /// * [`CodecError::Decode`] if the application message is not a valid encoding of a `T`
+    pub fn decode<'d, C: DecodePayload<'d, T>, T>(&'d self) -> Result<T, CodecError> {
+        if self
+            .content_type
+            .as_ref()
+            .is_none_or(|c| c.as_ref() != C::CONTENT_TYPE)
+        {
+            return Err(CodecError::ContentType);
+        }
+
+        self.decode_unchecked::<C, T>()
+    }
+
+    /// Decodes the application message into a `T` with the codec `C` without checking the
+    /// content type, e.g. for publications of senders that do not set it.
+    ///
+    /// # Errors
+    ///
+    /// * [`CodecError::Decode`] if the application message is not a valid encoding of a `T`
+    pub fn decode_unchecked<'d, C: DecodePayload<'d, T>, T>(&'d self) -> Result<T, CodecError> {
+        C::decode(&self.message)
+    }
+}
+
 /// Content of [`Event::PublishAcknowledged`], [`Event::PublishReceived`],
 /// [`Event::PublishReleased`], and [`Event::PublishComplete`].
 ///
@@ -300,6 +396,7 @@ pub struct Puback<'p, const MAX_USER_PROPERTIES: usize> {
     pub user_properties: Vec<MqttStringPair<'p>, MAX_USER_PROPERTIES>,
 }
 
+#[cfg(feature = "v5")]
 impl<'p, const MAX_USER_PROPERTIES: usize> Puback<'p, MAX_USER_PROPERTIES> {
     pub(crate) fn new<T>(
         packet: GenericPubackPacket<'p, T, MAX_USER_PROPERTIES>,
@@ -338,6 +435,7 @@ pub struct Pubrej<'p, const MAX_USER_PROPERTIES: usize> {
     pub user_properties: Vec<MqttStringPair<'p>, MAX_USER_PROPERTIES>,
 }
 
+#[cfg(feature = "v5")]
 impl<'p, T, const MAX_USER_PROPERTIES: usize> From<GenericPubackPacket<'p, T, MAX_USER_PROPERTIES>>
     for Pubrej<'p, MAX_USER_PROPERTIES>
 {
@@ -1,43 +1,79 @@
 //! Implements full client functionality with session and configuration handling and Quality of Service flows.
 
-use core::{matches, num::NonZero};
-
+#[cfg(feature = "v5")]
 use crate::{
     buffer::BufferProvider,
     bytes::Bytes,
     client::{
-        event::{Connected, Event, Puback, Publish, Pubrej, Suback},
+        alias::TopicAliasManager,
+        auth::Authenticator,
+        codec::EncodePayload,
+        event::{Connected, Event, Publish},
         options::{
-            AckMode, AckOptions, ConnectOptions, DisconnectOptions, PublicationOptions,
-            SubscriptionOptions, TopicReference, UnsubscriptionOptions,
+            AckOptions, AuthOptions, ConnectOptions, DisconnectOptions, FilterOptions,
+            PublicationOptions, SubscribeOptions, SubscriptionOptions, UnsubscriptionOptions,
         },
-        raw::Raw,
+        protocol::{Dequeued, Incoming, Outgoing, Protocol, encode_payload, leaves_queue},
+        queue::PublishQueue,
+        raw::{Raw, RawError},
+        registry::{Pending, SubscriptionRegistry},
+        store::MessageStore,
     },
-    config::{ClientConfig, MaximumPacketSize, ServerConfig, SessionExpiryInterval, SharedConfig},
-    fmt::{assert, const_assert, debug, error, info, panic, trace, unreachable, warn},
+    config::{ClientConfig, ServerConfig, SharedConfig},
+    eio::ReadReady,
+    fmt::{debug, info, panic, warn},
     header::{FixedHeader, PacketType},
-    io::Transport,
-    packet::{Packet, TxPacket},
-    session::{Error as SmError, Event as SmEvent, LocalPublishState, Response, Session},
-    types::{
-        IdentifiedQoS, MqttBinary, MqttString, MqttStringPair, NoLocalSharedSubscription,
-        PacketIdentifier, QoS, ReasonCode, SubscriptionFilter, TopicFilter, TopicName, VarByteInt,
-    },
-    v5::{
-        packet::{
-            ConnackPacket, ConnectPacket, DisconnectPacket, PingreqPacket, PingrespPacket,
-            PubackPacket, PubcompPacket, PublishPacket, PubrecPacket, PubrelPacket, SubackPacket,
-            SubscribePacket, UnsubackPacket, UnsubscribePacket,
-        },
-        property::Property,
+    io::{Split, Transport},
+    session::Session,
+    time::{Clock, Instant},
+    types::{MqttString, PacketIdentifier, ReasonCode, TopicFilter},
+    v5::packet::{AuthPacket, PingreqPacket},
+};
+
+#[cfg(all(doc, feature = "v5"))]
+use crate::{
+    client::{
+        auth::MAX_AUTHENTICATION_METHOD_LEN,
+        options::{AckMode, TopicReference},
     },
+    config::{KeepAlive, SessionExpiryInterval},
+    types::QoS,
 };
 
 mod err;
-
+#[cfg(feature = "v5")]
+mod protocol;
+
+#[cfg(feature = "v5")]
+pub mod alias;
+#[cfg(all(feature = "v3", feature = "v5"))]
+pub mod any;
+#[cfg(feature = "v5")]
+pub mod auth;
+#[cfg(all(feature = "blocking", feature = "v5"))]
+pub mod blocking;
+pub mod codec;
 pub mod event;
 pub mod options;
+#[cfg(feature = "v5")]
+pub mod queue;
 pub mod raw;
+#[cfg(feature = "v5")]
+pub mod registry;
+#[cfg(feature = "v5")]
+pub mod request;
+#[cfg(feature = "v5")]
+pub mod router;
+#[cfg(feature = "v5")]
+pub mod sans_io;
+#[cfg(feature = "v5")]
+pub mod split;
+#[cfg(feature = "v5")]
+pub mod store;
+#[cfg(feature = "v5")]
+pub mod supervisor;
+#[cfg(feature = "v3")]
+pub mod v3;
 
 pub use err::Error as MqttError;
 
@@ -46,7 +82,8 @@ pub use err::Error as MqttError;
 /// Configuration via const parameters:
 ///
 /// - `SUBSCRIBE_MAXIMUM`: The maximum amount of in-flight/unacknowledged packets allowed for SUBSCRIBE and UNSUBSCRIBE packets
-///   each (one slot per call to [`Client::subscribe`] or [`Client::unsubscribe`]). Must not be greater than 65535.
+///   each (one slot per call to [`Client::subscribe`], [`Client::subscribe_many`], [`Client::unsubscribe`] or
+///   [`Client::unsubscribe_many`]). Must not be greater than 65535.
 /// - `RECEIVE_MAXIMUM`: MQTT's control flow mechanism. The maximum amount of incoming [`QoS::AtLeastOnce`] and
 ///   [`QoS::ExactlyOnce`] publications (accumulated). Must not be 0 and must not be greater than 65535.
 /// - `SEND_MAXIMUM`: The maximum amount of outgoing [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`] publications. The server
@@ -58,6 +95,12 @@ pub use err::Error as MqttError;
 ///   - It is recommended (but not strictly required) to use a value >= 1, because if the value is 0, the client does not
 ///     guarantee to detect the protocol error and disconnect from the server when the request problem information property in
 ///     CONNECT is 0 and the server sends user properties in a packet other than CONNACK, DISCONNECT or PUBLISH.
+/// - `TOPIC_ALIAS_MAXIMUM`: The maximum amount of topic aliases the server can establish for PUBLISH packets sent to the client.
+///   The topic alias maximum sent to the server is configured in [`ConnectOptions`] and must not be greater than this value.
+///   Must not be greater than 65535. Defaults to 0, which disables incoming topic aliases.
+/// - `MAX_ALIASED_TOPIC_LEN`: The maximum length in bytes of a topic name the server can map to a topic alias. The client
+///   copies mapped topic names into its own memory and disconnects with [`MqttError::Alloc`] if the server maps a longer
+///   topic name. Must be greater than 0 if `TOPIC_ALIAS_MAXIMUM` is greater than 0.
 ///
 /// The client has two modes of how the acknowledgements within handshakes of [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`]
 /// publications are handled. These modes are the default [`AckMode::Automatic`] and the proactively configurable
@@ -145,6 +188,7 @@ pub use err::Error as MqttError;
 /// - Only PUBLISH packets can instantiate a session entry with an associated [`AckMode`]. For this reason, when acknowledgement
 ///   packets with unused packet identifiers that require a responding packet are received (PUBREC and PUBREL), no session entry
 ///   is created and the responding packet (PUBREL and PUBCOMP) is sent automatically by the client.
+#[cfg(feature = "v5")]
 pub struct Client<
     'c,
     N: Transport,
@@ -154,18 +198,23 @@ pub struct Client<
     const SEND_MAXIMUM: usize,
     const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
     const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
 > {
-    client_config: ClientConfig,
-    shared_config: SharedConfig,
-    server_config: ServerConfig,
-    session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
-
+    protocol: Protocol<
+        'c,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
     raw: Raw<'c, N, B>,
-
-    manual_ack_when:
-        &'c dyn Fn(&Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>) -> bool,
 }
 
+#[cfg(feature = "v5")]
 impl<
     'c,
     N: Transport,
@@ -175,6 +224,8 @@ impl<
     const SEND_MAXIMUM: usize,
     const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
     const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
 > core::fmt::Debug
     for Client<
         'c,
@@ -185,20 +236,23 @@ impl<
         SEND_MAXIMUM,
         MAX_SUBSCRIPTION_IDENTIFIERS,
         MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
     >
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Client")
-            .field("client_config", &self.client_config)
-            .field("shared_config", &self.shared_config)
-            .field("server_config", &self.server_config)
-            .field("session", &self.session)
+            .field("client_config", &self.protocol.client_config)
+            .field("shared_config", &self.protocol.shared_config)
+            .field("server_config", &self.protocol.server_config)
+            .field("session", &self.protocol.session)
             .field("raw", &self.raw)
+            .field("auth_state", &self.protocol.auth_state)
             .finish_non_exhaustive()
     }
 }
 
-#[cfg(feature = "defmt")]
+#[cfg(all(feature = "defmt", feature = "v5"))]
 impl<
     'c,
     N: Transport,
@@ -208,6 +262,8 @@ impl<
     const SEND_MAXIMUM: usize,
     const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
     const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
 > defmt::Format
     for Client<
         'c,
@@ -218,21 +274,25 @@ impl<
         SEND_MAXIMUM,
         MAX_SUBSCRIPTION_IDENTIFIERS,
         MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
     >
 {
     fn format(&self, fmt: defmt::Formatter) {
         defmt::write!(
             fmt,
-            "Client {{ client_config: {:?}, shared_config: {:?}, server_config: {:?}, session: {:?}, raw: {:?}, .. }}",
-            self.client_config,
-            self.shared_config,
-            self.server_config,
-            self.session,
+            "Client {{ client_config: {:?}, shared_config: {:?}, server_config: {:?}, session: {:?}, raw: {:?}, auth_state: {:?}, .. }}",
+            self.protocol.client_config,
+            self.protocol.shared_config,
+            self.protocol.server_config,
+            self.protocol.session,
             self.raw,
+            self.protocol.auth_state,
         );
     }
 }
 
+#[cfg(feature = "v5")]
 impl<
     'c,
     N: Transport,
@@ -242,6 +302,8 @@ impl<
     const SEND_MAXIMUM: usize,
     const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
     const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
 >
     Client<
         'c,
@@ -252,6 +314,8 @@ impl<
         SEND_MAXIMUM,
         MAX_SUBSCRIPTION_IDENTIFIERS,
         MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
     >
 {
     /// Creates a new, disconnected MQTT client using a buffer provider to store
@@ -260,33 +324,9 @@ impl<
     /// client with an existing session, use [`Self::with_session`].
     /// All publications and acknowledgements will be acknowledged automatically.
     pub fn new(buffer: &'c mut B) -> Self {
-        const {
-            const_assert!(
-                SUBSCRIBE_MAXIMUM <= 65535,
-                "SUBSCRIBE_MAXIMUM must be less than or equal to 65535"
-            );
-            const_assert!(
-                RECEIVE_MAXIMUM <= 65535,
-                "RECEIVE_MAXIMUM must be less than or equal to 65535"
-            );
-            const_assert!(
-                RECEIVE_MAXIMUM > 0,
-                "RECEIVE_MAXIMUM must be greater than 0"
-            );
-            const_assert!(
-                MAX_USER_PROPERTIES <= 1021,
-                "MAX_USER_PROPERTIES must be less than or equal to 1021"
-            );
-        }
-
         Self {
-            client_config: ClientConfig::default(),
-            shared_config: SharedConfig::default(),
-            server_config: ServerConfig::default(),
-            session: Session::default(),
+            protocol: Protocol::new(),
             raw: Raw::new_disconnected(buffer),
-
-            manual_ack_when: &|_| false,
         }
     }
 
@@ -297,7 +337,7 @@ impl<
         buffer: &'c mut B,
     ) -> Self {
         let mut s = Self::new(buffer);
-        s.session = session;
+        s.protocol.session = session;
         s
     }
 
@@ -313,37 +353,127 @@ impl<
             &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
         ) -> bool,
     ) {
-        self.manual_ack_when = predicate;
+        self.protocol.manual_ack_when = predicate;
+    }
+
+    /// Sets the [`Authenticator`] which responds to the AUTH packets of the server in an enhanced
+    /// authentication exchange. The exchange is started by setting an authentication method in
+    /// the [`ConnectOptions`] passed to [`Self::connect`].
+    pub fn authenticate_with(&mut self, authenticator: &'c mut dyn Authenticator) {
+        self.protocol.authenticator = Some(authenticator);
+    }
+
+    /// Sets the [`TopicAliasManager`] which assigns topic aliases to outgoing publications sent
+    /// with [`TopicReference::Name`]. The manager is reset whenever a connection is established
+    /// with [`Self::connect`].
+    ///
+    /// While a manager is set, topic aliases must not be used in [`PublicationOptions`] directly.
+    pub fn manage_topic_aliases_with(&mut self, manager: &'c mut dyn TopicAliasManager) {
+        self.protocol.topic_alias_manager = Some(manager);
+    }
+
+    /// Sets a buffer in which the variable header and payload of received packets are reassembled
+    /// before they are processed. This makes [`Self::poll`] cancel-safe, so that it can be raced
+    /// against other futures: If the future is dropped while a packet is being received, the next
+    /// call continues with the remainder of the packet.
+    ///
+    /// Responses to a received packet, such as the PUBACK packet of an incoming [`QoS::AtLeastOnce`]
+    /// publication, are encoded into this buffer as well and written by the next call to
+    /// [`Self::poll`] or the next method sending a packet, so that no event is lost if the future
+    /// is dropped while a response is being written. Responses which do not fit into the buffer
+    /// are written before [`Self::poll`] returns.
+    ///
+    /// The buffer must be able to hold the largest packet the client accepts, which is configured
+    /// with the maximum packet size in [`ConnectOptions`]. Only call this while the client is
+    /// not connected.
+    pub fn reassemble_packets_with(&mut self, buffer: &'c mut [u8]) {
+        self.raw.reassemble_with(buffer);
+    }
+
+    /// Sets the buffer into which [`Self::publish_typed`] encodes application messages. It must be
+    /// able to hold the largest encoded message.
+    pub fn encode_payloads_with(&mut self, buffer: &'c mut [u8]) {
+        self.protocol.payload_buffer = Some(buffer);
     }
 
-    /// Returns the amount of publications the client is allowed to make according to the server's
-    /// receive maximum. Does not account local space for storing publication state.
-    fn remaining_send_quota(&self) -> u16 {
-        self.server_config.receive_maximum.get() - self.session.active_outbound_publishes()
+    /// Sets the [`Clock`] with which the client tracks the keep alive negotiated in
+    /// [`Self::connect`]. The client then records when packets are sent and when PINGREQ packets
+    /// are answered, so that [`Self::next_ping_deadline`] tells when to call
+    /// [`Self::check_keep_alive`].
+    pub fn keep_alive_with(&mut self, clock: &'c dyn Clock) {
+        self.raw.keep_alive_with(clock);
+    }
+
+    /// Sets the [`MessageStore`] into which outgoing [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`]
+    /// PUBLISH packets are written, so that [`Self::resume`] can retransmit them after a
+    /// reconnection. The store is cleared whenever [`Self::connect`] starts a new session.
+    ///
+    /// The store should be empty or belong to the session of this client. PUBLISH packets creating
+    /// a topic alias mapping are stored with the topic name only, as the mapping ends with the
+    /// network connection.
+    pub fn store_messages_in(&mut self, store: &'c mut dyn MessageStore) {
+        self.protocol.message_store = Some(store);
+    }
+
+    /// Sets the [`PublishQueue`] into which [`Self::publish`] pushes publications while the client
+    /// is not connected. [`Self::drain_queue`] publishes them after the next connection has been
+    /// established.
+    ///
+    /// If a [`Clock`] is set with [`Self::keep_alive_with`], the time a publication spends in the
+    /// queue is deducted from its message expiry interval, and it is dropped once the interval has
+    /// passed.
+    pub fn queue_publications_in(&mut self, queue: &'c mut dyn PublishQueue) {
+        self.protocol.publish_queue = Some(queue);
+        self.protocol.queue_front_published = false;
+    }
+
+    /// Returns the number of publications in the [`PublishQueue`] set with
+    /// [`Self::queue_publications_in`].
+    #[inline]
+    pub fn queued_publications(&self) -> usize {
+        self.protocol
+            .publish_queue
+            .as_deref()
+            .map_or(0, PublishQueue::len)
+    }
+
+    /// Sets the [`SubscriptionRegistry`] in which the client records the topic filters it
+    /// subscribes to with their options and granted [`QoS`], so that [`Self::resubscribe_all`]
+    /// can restore them after reconnecting.
+    ///
+    /// The registry should be empty or belong to the session of this client.
+    pub fn track_subscriptions_in(&mut self, registry: &'c mut dyn SubscriptionRegistry) {
+        self.protocol.subscription_registry = Some(registry);
+    }
+
+    /// Returns the [`SubscriptionRegistry`] set with [`Self::track_subscriptions_in`].
+    #[inline]
+    pub fn subscriptions(&self) -> Option<&dyn SubscriptionRegistry> {
+        self.protocol.subscription_registry.as_deref()
     }
 
     /// Returns configuration for this client.
     #[inline]
     pub fn client_config(&self) -> &ClientConfig {
-        &self.client_config
+        &self.protocol.client_config
     }
 
     /// Returns the configuration of the currently or last connected server if there is one.
     #[inline]
     pub fn server_config(&self) -> &ServerConfig {
-        &self.server_config
+        &self.protocol.server_config
     }
 
     /// Returns the configuration negotiated between the client and server.
     #[inline]
     pub fn shared_config(&self) -> &SharedConfig {
-        &self.shared_config
+        &self.protocol.shared_config
     }
 
     /// Returns session related configuration and tracking information.
     #[inline]
     pub fn session(&self) -> &Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM> {
-        &self.session
+        &self.protocol.session
     }
 
     /// Returns an immutable reference to the supplied [`BufferProvider`] implementation.
@@ -360,6 +490,20 @@ impl<
         self.raw.buffer_mut()
     }
 
+    /// Drops the network connection and returns the session state, the buffer provider and the
+    /// buffer set with [`Self::reassemble_packets_with`].
+    #[cfg(feature = "v3")]
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        &'c mut B,
+        Option<&'c mut [u8]>,
+    ) {
+        let (buffer, reassembly_buffer) = self.raw.into_buffers();
+        (self.protocol.session, buffer, reassembly_buffer)
+    }
+
     /// Connect the client to an MQTT server on the other end of the `net` argument.
     /// Sends a CONNECT message and awaits the CONNACK response by the server.
     ///
@@ -378,6 +522,10 @@ impl<
     /// If the server does not have a session present, the client's session is cleared. In case you would want
     /// to keep the session state, you can call [`Self::session`] and clone the session before.
     ///
+    /// If an authentication method is set in [`ConnectOptions`], the server may respond with AUTH packets
+    /// before sending the CONNACK packet. These are answered using the [`Authenticator`] set with
+    /// [`Self::authenticate_with`] until the server sends the CONNACK packet.
+    ///
     /// # Returns:
     /// Information about the session/connection that the client does currently not use and therefore  not store
     /// in its configuration fields.
@@ -391,7 +539,12 @@ impl<
     ///   * the server causes a protocol error
     ///   * the server sends Response Information despite `request_response_information` in [`ConnectOptions`]
     ///     being 0
+    ///   * the server sends an AUTH packet or a CONNACK packet with an authentication method other than
+    ///     the one in [`ConnectOptions`]
     /// * [`MqttError::Disconnect`] if the CONNACK packet's reason code is not successful (>= 0x80)
+    /// * [`MqttError::AuthPacketReceived`] if the server sends an AUTH packet and no [`Authenticator`]
+    ///   has been set
+    /// * [`MqttError::Authentication`] if the [`Authenticator`] rejects the authentication exchange
     /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
     /// * [`MqttError::Alloc`] if the underlying [`BufferProvider`] returned an error
     ///
@@ -399,7 +552,10 @@ impl<
     ///
     /// This function panics if the length of the `user_properties` slice in the [`ConnectOptions`]
     /// or the length of the `user_properties` slice in the will in [`ConnectOptions`] is greater
-    /// than `MAX_USER_PROPERTIES`.
+    /// than `MAX_USER_PROPERTIES`, if the authentication method in the [`ConnectOptions`] is
+    /// longer than [`MAX_AUTHENTICATION_METHOD_LEN`] or if a reassembly buffer has been set with
+    /// [`Self::reassemble_packets_with`] that cannot hold a packet of the maximum packet size in
+    /// the [`ConnectOptions`].
     pub async fn connect<'d>(
         &mut self,
         net: N,
@@ -409,243 +565,89 @@ impl<
     where
         'c: 'd,
     {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send CONNECT with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-        if let Some(ref will) = options.will {
-            assert!(
-                will.user_properties.len() <= MAX_USER_PROPERTIES,
-                "attempted to send Will with {} > {} (MAX_USER_PROPERTIES) properties",
-                will.user_properties.len(),
-                MAX_USER_PROPERTIES
-            );
-        }
-
-        self.raw.set_net(net);
-
-        // Set client session expiry interval because it is relevant to determine
-        // which session expiry interval can be sent in DISCONNECT packet.
-        self.client_config.session_expiry_interval = options.session_expiry_interval;
-
-        // Set request problem information because it is required to detect protocol
-        // errors when server sends a reason string or user properties in any packet
-        // other than PUBLISH, CONNACK, or DISCONNECT
-        self.client_config.request_problem_information = options.request_problem_information;
-
-        // Empirical maximum packet size mapping
-        // -------------------------------------------------------------------------------------------------------
-        //         remaining length              | fixed header length |              max packet size
-        //                               0..=127 |                   2 |                                   2..=129
-        //                          128..=16_383 |                   3 |                              131..=16_386
-        //                    16_384..=2_097_151 |                   4 |                        16_388..=2_097_155
-        // 2_097_152..=VarByteInt::MAX_ENCODABLE |                   5 | 2_097_157..=(VarByteInt::MAX_ENCODABLE+5)
-
-        const MAX_POSSIBLE_PACKET_SIZE: u32 = VarByteInt::MAX_ENCODABLE + 5;
-
-        self.client_config.maximum_accepted_remaining_length = match options.maximum_packet_size {
-            MaximumPacketSize::Unlimited => u32::MAX,
-            MaximumPacketSize::Limit(l) => match l.get() {
-                0 => unreachable!("NonZero invariant"),
-                1 => panic!(
-                    "every MQTT packet is at least 2 bytes long, a smaller maximum packet size makes no sense"
-                ),
-                2..=129 => l.get() - 2,
-                130..=16_386 => l.get() - 3,
-                16_387..=2_097_155 => l.get() - 4,
-                2_097_156..MAX_POSSIBLE_PACKET_SIZE => l.get() - 5,
-                MAX_POSSIBLE_PACKET_SIZE.. => VarByteInt::MAX_ENCODABLE,
-            },
-        };
-
-        trace!(
-            "maximum accepted remaining length set to {:?}",
-            self.client_config.maximum_accepted_remaining_length
-        );
-
         {
-            let packet_client_identifier = client_identifier
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .unwrap_or_default();
-
-            let mut packet = ConnectPacket::<MAX_USER_PROPERTIES>::new(
-                packet_client_identifier,
-                options.clean_start,
-                options.keep_alive,
-                options.maximum_packet_size,
-                options.session_expiry_interval,
-                // Safety: `Self::new` panics if `RECEIVE_MAXIMUM` is 0. Thus, this
-                // code is only reached when `RECEIVE_MAXIMUM` is greater than 0.
-                unsafe { NonZero::new_unchecked(RECEIVE_MAXIMUM as u16) },
-                options.request_response_information,
-                options.request_problem_information,
-                options
-                    .user_properties
-                    .iter()
-                    .map(MqttStringPair::as_borrowed)
-                    .map(Into::into)
-                    .collect(),
-            );
-
-            if let Some(ref user_name) = options.user_name {
-                packet.add_user_name(user_name.as_borrowed());
-            }
-            if let Some(ref password) = options.password {
-                packet.add_password(password.as_borrowed());
-            }
-
-            if let Some(ref will) = options.will {
-                let will_qos = will.will_qos;
-                let will_retain = will.will_retain;
-
-                packet.add_will(will.as_borrowed_will(), will_qos, will_retain);
+            let packet = self.protocol.connect(options, client_identifier.as_ref());
+
+            self.raw.set_net(net);
+
+            if let Some(capacity) = self.raw.reassembly_capacity() {
+                assert!(
+                    self.protocol
+                        .client_config
+                        .maximum_accepted_remaining_length as usize
+                        <= capacity,
+                    "the maximum packet size must be limited to fit into the reassembly buffer of {} bytes",
+                    capacity
+                );
             }
 
-            debug!("sending CONNECT packet");
             self.raw.send(&packet).await?;
             self.raw.flush().await?;
         }
 
-        let header = self.raw.recv_header().await?;
-
-        match header.packet_type() {
-            Ok(ConnackPacket::<MAX_USER_PROPERTIES>::PACKET_TYPE) => debug!(
-                "received CONNACK packet header (remaining length: {})",
-                header.remaining_len.value()
-            ),
-            Ok(t) => {
-                error!("received unexpected {:?} packet header", t);
-
-                self.raw.close_with(Some(ReasonCode::ProtocolError));
-                return Err(MqttError::Server);
-            }
-            Err(_) => {
-                error!("received invalid header {:?}", header);
-                self.raw.close_with(Some(ReasonCode::MalformedPacket));
-                return Err(MqttError::Server);
+        // AUTH packets received before the CONNACK packet are answered until it arrives.
+        let header = loop {
+            let header = self.raw.recv_header().await?;
+
+            match self
+                .protocol
+                .handshake_header(&mut self.raw, &header, options)?
+            {
+                PacketType::Auth => {
+                    let auth = self
+                        .raw
+                        .recv_body::<AuthPacket<MAX_USER_PROPERTIES>>(&header)
+                        .await?;
+                    let packet = self
+                        .protocol
+                        .handshake_auth(&mut self.raw, auth, options)
+                        .map_err(MqttError::inflate)?;
+
+                    self.raw.send(&packet).await?;
+                    self.raw.flush().await?;
+                }
+                _ => break header,
             }
-        }
+        };
 
-        let ConnackPacket::<MAX_USER_PROPERTIES> {
-            session_present,
-            reason_code,
-            session_expiry_interval,
-            receive_maximum,
-            maximum_qos,
-            retain_available,
-            maximum_packet_size,
-            assigned_client_identifier,
-            topic_alias_maximum,
-            reason_string,
-            user_properties,
-            wildcard_subscription_available,
-            subscription_identifier_available,
-            shared_subscription_available,
-            server_keep_alive,
-            response_information,
-            server_reference,
-        } = self.raw.recv_body(&header).await?;
-
-        if !options.request_response_information && response_information.is_some() {
-            error!("server sent response information when request response information was false");
-            self.raw.close_with(Some(ReasonCode::ProtocolError));
-            return Err(MqttError::Server);
-        }
+        let connack = self.raw.recv_body(&header).await?;
 
-        if reason_code.is_success() {
-            debug!("CONNACK packet indicates success");
-
-            let client_identifier = assigned_client_identifier
-                .map(Property::into_inner)
-                .or(client_identifier)
-                .ok_or_else(|| {
-                    error!("server did not assign a client identifier when it was required.");
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    MqttError::Server
-                })?;
-
-            if session_present {
-                if options.clean_start {
-                    error!("server set the session present flag when clean start was set");
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                } else {
-                    info!("connected to server and reconnected to session");
-                    self.session.reconnect();
-                }
-            } else {
-                #[allow(clippy::if_same_then_else)]
-                if options.clean_start {
-                    info!("connected to server");
-                } else {
-                    info!(
-                        "connected to server but server does not have the requested session present"
-                    );
-                }
-                self.session.clear();
-            }
+        self.protocol
+            .connack(&mut self.raw, connack, options, client_identifier)
+    }
 
-            self.shared_config.session_expiry_interval =
-                session_expiry_interval.unwrap_or(options.session_expiry_interval);
-            self.shared_config.keep_alive =
-                server_keep_alive.map_or(options.keep_alive, Property::into_inner);
+    /// Starts a re-authentication by sending an AUTH packet with [`ReasonCode::ReAuthenticate`].
+    /// The following AUTH packets of the server are handled by [`Self::poll`] using the
+    /// [`Authenticator`] set with [`Self::authenticate_with`]. The session state is unaffected
+    /// by the re-authentication.
+    ///
+    /// The re-authentication is complete once [`Event::Reauthenticated`] is emitted. If the server
+    /// rejects it, it disconnects and [`Event::ReauthenticationFailed`] is emitted instead.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::IllegalReauthentication`] if the connection was established without an
+    ///   authentication method, another re-authentication is still in progress or the
+    ///   authentication method in the [`AuthOptions`] is not the one sent in the CONNECT packet
+    /// * [`MqttError::ServerMaximumPacketSizeExceeded`] if the server's maximum packet size would be
+    ///   exceeded by sending this AUTH packet
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the length of the `user_properties` slice in the [`AuthOptions`]
+    /// is greater than `MAX_USER_PROPERTIES`.
+    pub async fn reauthenticate(
+        &mut self,
+        options: &AuthOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.reauthenticate(options)?;
 
-            if let Some(r) = receive_maximum {
-                self.server_config.receive_maximum = r.into_inner();
-            }
-            if let Some(m) = maximum_qos {
-                self.server_config.maximum_qos = m.into_inner();
-            }
-            if let Some(r) = retain_available {
-                self.server_config.retain_supported = r.into_inner();
-            }
-            if let Some(m) = maximum_packet_size {
-                self.server_config.maximum_packet_size = m;
-            }
-            if let Some(t) = topic_alias_maximum {
-                self.server_config.topic_alias_maximum = t.into_inner();
-            }
-            if let Some(w) = wildcard_subscription_available {
-                self.server_config.wildcard_subscription_supported = w.into_inner();
-            }
-            if let Some(s) = subscription_identifier_available {
-                self.server_config.subscription_identifiers_supported = s.into_inner();
-            }
-            if let Some(s) = shared_subscription_available {
-                self.server_config.shared_subscription_supported = s.into_inner();
-            }
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
 
-            Ok(Connected {
-                session_present,
-                client_identifier,
-                user_properties: user_properties
-                    .into_iter()
-                    .map(Property::into_inner)
-                    .collect(),
-                response_information: response_information.map(Property::into_inner),
-                server_reference: server_reference.map(Property::into_inner),
-            })
-        } else {
-            debug!("CONNACK packet indicates rejection");
-            info!("connection rejected by server (reason: {:?})", reason_code);
-
-            self.raw.close_with(None);
-
-            info!("disconnected from server");
-
-            Err(MqttError::Disconnect {
-                reason: reason_code,
-                reason_string: reason_string.map(Property::into_inner),
-                user_properties: user_properties
-                    .into_iter()
-                    .map(Property::into_inner)
-                    .collect(),
-                server_reference: server_reference.map(Property::into_inner),
-            })
-        }
+        Ok(())
     }
 
     /// Start a ping handshake by sending a PINGRESP packet.
@@ -665,6 +667,40 @@ impl<
         Ok(())
     }
 
+    /// Returns the point in time at which [`Self::check_keep_alive`] has to be called next.
+    ///
+    /// While no PINGREQ packet is pending, this is when the keep alive interval negotiated in
+    /// [`Self::connect`] has passed since the last packet was sent. Otherwise, this is when the
+    /// keep alive interval has passed since the first unanswered PINGREQ packet was sent.
+    ///
+    /// # Returns:
+    /// [`None`] if no [`Clock`] has been set with [`Self::keep_alive_with`], the keep alive is
+    /// [`KeepAlive::Infinite`] or the client is not connected.
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        self.protocol.next_ping_deadline(&self.raw)
+    }
+
+    /// Maintains the keep alive once [`Self::next_ping_deadline`] has passed. Does nothing before.
+    ///
+    /// Sends a PINGREQ packet if no packet has been sent within the keep alive interval. If a
+    /// PINGREQ packet has not been answered within the keep alive interval, the network
+    /// connection is closed.
+    ///
+    /// The PINGRESP packet is received by [`Self::poll`] as [`Event::Pingresp`], so the client has
+    /// to be polled in the meantime.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::KeepAliveTimeout`] if no PINGRESP packet has been received in time
+    /// * The errors that [`Self::ping`] returns
+    pub async fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        if self.protocol.check_keep_alive(&mut self.raw)? {
+            self.ping().await
+        } else {
+            Ok(())
+        }
+    }
+
     /// Subscribes to a single topic with the given options.
     ///
     /// The client keeps track of the packet identifier sent in the SUBSCRIBE packet.
@@ -710,60 +746,53 @@ impl<
         topic_filter: TopicFilter<'_>,
         options: &SubscriptionOptions<'_>,
     ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send SUBSCRIBE with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        if !self.server_config.wildcard_subscription_supported && topic_filter.has_wildcard() {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if !self.server_config.subscription_identifiers_supported
-            && options.subscription_identifier.is_some()
-        {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if !self.server_config.shared_subscription_supported && topic_filter.is_shared() {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        let subscribe_filter = SubscriptionFilter::new(topic_filter, options)
-            .map_err(|NoLocalSharedSubscription| MqttError::IllegalNoLocalSharedSubscription)?;
-        let subscribe_filters = [subscribe_filter].into();
-
-        let Some(handle) = self.session.free_handle() else {
-            info!("no free packet identifier");
-            return Err(MqttError::AllPacketIdentifiersUsed);
-        };
-        let pid = handle.packet_identifier;
-
-        handle.outbound_sub().map_err(|_| {
-            info!("maximum concurrent subscriptions reached");
-            MqttError::SessionBuffer
-        })?;
-
-        let packet = SubscribePacket::<1, MAX_USER_PROPERTIES>::new(
-            pid,
-            options.subscription_identifier.map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-            subscribe_filters,
+        self.subscribe_many(
+            &[(topic_filter, options.filter_options())],
+            &options.subscribe_options(),
         )
-        .expect("SUBSCRIBE with a single topic can not exceed VarByteInt::MAX_ENCODABLE");
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
+        .await
+    }
 
-        debug!("sending SUBSCRIBE packet");
+    /// Subscribes to multiple topics with a single SUBSCRIBE packet. Each topic filter is
+    /// subscribed to with the [`FilterOptions`] it is paired with. The subscription identifier
+    /// and the user properties in the [`SubscribeOptions`] apply to the whole SUBSCRIBE packet.
+    ///
+    /// The client keeps track of the packet identifier and the amount of topic filters sent in the
+    /// SUBSCRIBE packet. The [`Event::Suback`] contains one reason code per topic filter in the
+    /// order of `subscriptions`. If no [`Event::Suback`] is received within a custom time,
+    /// this method can be used to send the SUBSCRIBE packet again.
+    ///
+    /// The notes of [`Client::subscribe`] apply to every topic filter. If a violation occurs for
+    /// any of the topic filters, the client will not subscribe to any of them.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent SUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing SUBSCRIBE packet identifiers is full
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the SUBSCRIBE packet is too long to be encoded
+    /// * [`MqttError::ServerMaximumPacketSizeExceeded`] if the server's maximum packet size would be
+    ///   exceeded by sending this SUBSCRIBE packet
+    /// * [`MqttError::UnsupportedByServer`] in the cases listed in [`Client::subscribe`] for any of
+    ///   the topic filters or the [`SubscribeOptions`]
+    /// * [`MqttError::IllegalNoLocalSharedSubscription`] if the no local flag is set for any of the
+    ///   topic filters of shared subscriptions
+    ///
+    /// # Panics
+    ///
+    /// This function panics if
+    /// * `subscriptions` is empty
+    /// * the length of the `user_properties` slice in the [`SubscribeOptions`] is greater than
+    ///   `MAX_USER_PROPERTIES`
+    pub async fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.subscribe_many(subscriptions, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -797,42 +826,40 @@ impl<
         topic_filter: TopicFilter<'_>,
         options: &UnsubscriptionOptions<'_>,
     ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send UNSUBSCRIBE with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        let Some(handle) = self.session.free_handle() else {
-            info!("no free packet identifier");
-            return Err(MqttError::AllPacketIdentifiersUsed);
-        };
-        let pid = handle.packet_identifier;
-
-        handle.outbound_unsub().map_err(|_| {
-            info!("maximum concurrent unsubscriptions reached");
-            MqttError::SessionBuffer
-        })?;
-
-        let topic_filters = [topic_filter].into();
-        let packet = UnsubscribePacket::<1, MAX_USER_PROPERTIES>::new(
-            pid,
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-            topic_filters,
-        )
-        .expect("UNSUBSCRIBE with a single topic cannot exceed VarByteInt::MAX_ENCODABLE");
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
+        self.unsubscribe_many(&[topic_filter], options).await
+    }
 
-        debug!("sending UNSUBSCRIBE packet");
+    /// Unsubscribes from multiple topic filters with a single UNSUBSCRIBE packet.
+    ///
+    /// The client keeps track of the packet identifier and the amount of topic filters sent in the
+    /// UNSUBSCRIBE packet. The [`Event::Unsuback`] contains one reason code per topic filter in the
+    /// order of `topic_filters`. If no [`Event::Unsuback`] is received within a custom time,
+    /// this method can be used to send the UNSUBSCRIBE packet again.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent UNSUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing UNSUBSCRIBE packet identifiers is full
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the UNSUBSCRIBE packet is too long to be encoded
+    /// * [`MqttError::ServerMaximumPacketSizeExceeded`] if the server's maximum packet size would be
+    ///   exceeded by sending this UNSUBSCRIBE packet
+    ///
+    /// # Panics
+    ///
+    /// This function panics if
+    /// * `topic_filters` is empty
+    /// * the length of the `user_properties` slice in the [`UnsubscriptionOptions`] is greater than
+    ///   `MAX_USER_PROPERTIES`
+    pub async fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.unsubscribe_many(topic_filters, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -853,8 +880,16 @@ impl<
     /// If a violation occurs, the client will not publish but prevent the protocol error
     /// and return an error.
     ///
+    /// If a [`TopicAliasManager`] is set with [`Client::manage_topic_aliases_with`], the topic
+    /// name is replaced by the topic alias the manager assigns.
+    ///
+    /// If a [`PublishQueue`] is set with [`Client::queue_publications_in`] and the client is not
+    /// connected, the publication is pushed into the queue instead and published by
+    /// [`Client::drain_queue`] later. Publications to a topic alias without a topic name are never
+    /// queued. The constraints of the server are checked once the publication leaves the queue.
+    ///
     /// # Returns:
-    /// - In case of [`QoS::AtMostOnce`]: [`None`]
+    /// - In case of [`QoS::AtMostOnce`] or a queued publication: [`None`]
     /// - In case of [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`]: [`Some`] with the packet identifier
     ///   of the published packet. This value is required in case of a republication attempt.
     ///
@@ -864,9 +899,10 @@ impl<
     /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
     /// * [`MqttError::SendQuotaExceeded`] if the server's control flow limit is reached and sending
     ///   the PUBLISH would exceed the limit causing a protocol error
-    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing PUBLISH packet identifiers is full
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing PUBLISH packet identifiers, the
+    ///   [`MessageStore`] set with [`Client::store_messages_in`] or the [`PublishQueue`] is full
     /// * [`MqttError::PacketMaximumLengthExceeded`] if the PUBLISH packet is too long to be encoded
-    ///   with MQTT's [`VarByteInt`]
+    ///   with MQTT's [`VarByteInt`](crate::types::VarByteInt)
     /// * [`MqttError::ServerMaximumPacketSizeExceeded`] if the server's maximum packet size would be
     ///   exceeded by sending this PUBLISH packet
     /// * [`MqttError::UnsupportedByServer`]
@@ -880,123 +916,64 @@ impl<
     ///   of outgoing publications, SUBSCRIBEs and UNSUBSCRIBEs
     /// * [`MqttError::ManualAckNotAllowed`] if the [`QoS`] is [`QoS::AtMostOnce`] or
     ///   [`QoS::AtLeastOnce`] and [`PublicationOptions::ack_mode`] is [`AckMode::Manual`]
+    /// * [`MqttError::UnstorableTopicAlias`] if a [`MessageStore`] is set and the [`QoS`] is
+    ///   [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`] and the topic is a [`TopicReference::Alias`]
     ///
     /// # Panics
     ///
     /// This function panics if the length of the `user_properties` slice in the [`PublicationOptions`]
     /// is greater than `MAX_USER_PROPERTIES`.
+    /// This function panics if a [`TopicAliasManager`] is set and the topic of the
+    /// [`PublicationOptions`] is not a [`TopicReference::Name`].
     pub async fn publish(
         &mut self,
         options: &PublicationOptions<'_>,
         message: Bytes<'_>,
     ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to publish {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        if (matches!(options.qos, QoS::AtMostOnce | QoS::AtLeastOnce)
-            && options.ack_mode == AckMode::Manual)
-        {
-            return Err(MqttError::ManualAckNotAllowed);
-        }
-
-        if options.qos > self.server_config.maximum_qos {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if !self.server_config.retain_supported && options.retain {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if options
-            .topic
-            .alias()
-            .map(NonZero::get)
-            .is_some_and(|a| a > self.server_config.topic_alias_maximum)
-        {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        let (identified_qos, handle) = if options.qos > QoS::AtMostOnce {
-            if self.remaining_send_quota() == 0 {
-                info!("server receive maximum reached");
-                return Err(MqttError::SendQuotaExceeded);
-            }
-
-            let Some(handle) = self.session.free_handle() else {
-                return Err(MqttError::AllPacketIdentifiersUsed);
-            };
-
-            match options.qos {
-                QoS::AtMostOnce => unreachable!(),
-                QoS::AtLeastOnce => (
-                    IdentifiedQoS::AtLeastOnce(handle.packet_identifier),
-                    Some(handle),
-                ),
-                QoS::ExactlyOnce => (
-                    IdentifiedQoS::ExactlyOnce(handle.packet_identifier),
-                    Some(handle),
-                ),
-            }
-        } else {
-            (IdentifiedQoS::AtMostOnce, None)
+        let Some(packet) = self.protocol.publish(&self.raw, options, message)? else {
+            return Ok(None);
         };
 
-        let packet = PublishPacket::<0, MAX_USER_PROPERTIES>::new(
-            false,
-            identified_qos,
-            options.retain,
-            options.topic.as_borrowed(),
-            options.payload_format_indicator.map(Into::into),
-            options.message_expiry_interval.map(Into::into),
-            options.response_topic.as_ref().map(TopicName::as_borrowed),
-            options
-                .correlation_data
-                .as_ref()
-                .map(MqttBinary::as_borrowed),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-            options
-                .content_type
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            message,
-        )?;
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
 
-        if let Some(handle) = handle {
-            // Treat the packet as sent before successfully sending. In case of a network error,
-            // we have tracked the packet as in flight and can republish it.
-            if let Err(e) = handle.outbound_publish(options.qos, options.ack_mode) {
-                match e {
-                    SmError::NoCapacity => return Err(MqttError::SessionBuffer),
-                    SmError::PacketIdentifierUnused
-                    | SmError::QoSMismatched
-                    | SmError::HandshakeStateMismatched => unreachable!(),
-                }
-            }
-        }
+        Ok(packet.identified_qos.packet_identifier())
+    }
 
-        match identified_qos.packet_identifier() {
-            Some(pid) => debug!("sending PUBLISH packet with packet identifier {}", pid),
-            None => debug!("sending PUBLISH packet"),
-        }
+    /// Encodes `value` with the codec `C` into the buffer set with
+    /// [`Client::encode_payloads_with`] and publishes it like [`Client::publish`], e.g.
+    /// `client.publish_typed::<Json, _>(&options, &reading)`.
+    ///
+    /// The content type and payload format indicator in `options` are replaced by the ones of the
+    /// codec. If the returned future is cancelled, the buffer stays set.
+    ///
+    /// # Returns:
+    /// The return value of [`Client::publish`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::PayloadEncoding`] if `value` cannot be encoded into the buffer
+    /// * the errors of [`Client::publish`]
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no buffer has been set with [`Client::encode_payloads_with`] and
+    /// in the cases listed in [`Client::publish`].
+    pub async fn publish_typed<C: EncodePayload<T>, T: ?Sized>(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        value: &T,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.payload_buffer) else {
+            panic!("attempted to publish a typed value without a payload buffer");
+        };
+        let (client, buffer) = taken.split();
 
-        self.raw.send(&packet).await?;
-        self.raw.flush().await?;
+        let (options, len) = encode_payload::<C, T>(options, value, buffer)?;
 
-        Ok(identified_qos.packet_identifier())
+        client
+            .publish(&options, Bytes::Borrowed(&buffer[..len]))
+            .await
     }
 
     /// Resends a PUBLISH packet with DUP flag set.
@@ -1037,7 +1014,7 @@ impl<
     /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
     ///   an outgoing publication in the client's session
     /// * [`MqttError::PacketMaximumLengthExceeded`] if the PUBLISH packet is too long to be encoded
-    ///   with MQTT's [`VarByteInt`]
+    ///   with MQTT's [`VarByteInt`](crate::types::VarByteInt)
     /// * [`MqttError::ServerMaximumPacketSizeExceeded`] if the server's maximum packet size would be
     ///   exceeded by sending this PUBLISH packet
     /// * [`MqttError::UnsupportedByServer`]
@@ -1047,105 +1024,25 @@ impl<
     ///     the retain flag set to true is attempted
     ///   * if a topic alias is used and its value is greater than the maximum value specified in the
     ///     server's CONNACK packet
+    /// * [`MqttError::UnstorableTopicAlias`] if a [`MessageStore`] is set and the topic is a
+    ///   [`TopicReference::Alias`]
     ///
     /// # Panics
     ///
     /// This function may panic if the [`QoS`] in the `options` is [`QoS::AtMostOnce`].
     /// This function panics if the length of the `user_properties` slice in the [`PublicationOptions`]
     /// is greater than `MAX_USER_PROPERTIES`.
+    /// This function panics if a [`TopicAliasManager`] is set and the topic of the
+    /// [`PublicationOptions`] is not a [`TopicReference::Name`].
     pub async fn republish(
         &mut self,
         packet_identifier: PacketIdentifier,
         options: &PublicationOptions<'_>,
         message: Bytes<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to publish {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        assert_ne!(
-            options.qos,
-            QoS::AtMostOnce,
-            "QoS 0 packets cannot be republished"
-        );
-
-        if options.qos > self.server_config.maximum_qos {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if !self.server_config.retain_supported && options.retain {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        if options
-            .topic
-            .alias()
-            .map(NonZero::get)
-            .is_some_and(|a| a > self.server_config.topic_alias_maximum)
-        {
-            return Err(MqttError::UnsupportedByServer);
-        }
-
-        let identified_qos = match options.qos {
-            QoS::AtMostOnce => unreachable!(),
-            QoS::AtLeastOnce => IdentifiedQoS::AtLeastOnce(packet_identifier),
-            QoS::ExactlyOnce => IdentifiedQoS::ExactlyOnce(packet_identifier),
-        };
-
-        let packet = PublishPacket::<0, MAX_USER_PROPERTIES>::new(
-            true,
-            identified_qos,
-            options.retain,
-            options.topic.as_borrowed(),
-            options.payload_format_indicator.map(Into::into),
-            options.message_expiry_interval.map(Into::into),
-            options.response_topic.as_ref().map(TopicName::as_borrowed),
-            options
-                .correlation_data
-                .as_ref()
-                .map(MqttBinary::as_borrowed),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-            options
-                .content_type
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            message,
-        )?;
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        if let Err(e) = self.session.outbound_republish(identified_qos) {
-            match e {
-                SmError::NoCapacity => {
-                    unreachable!("a republish can not fail due to missing capacity")
-                }
-                SmError::PacketIdentifierUnused => {
-                    return Err(MqttError::PacketIdentifierNotInFlight);
-                }
-                SmError::QoSMismatched => {
-                    return Err(MqttError::QoSMismatched);
-                }
-                SmError::HandshakeStateMismatched => {
-                    return Err(MqttError::HandshakeStateMismatched);
-                }
-            }
-        }
-
-        debug!(
-            "resending PUBLISH packet with packet identifier {}",
-            packet_identifier
-        );
+        let packet = self
+            .protocol
+            .republish(packet_identifier, options, message)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1171,34 +1068,226 @@ impl<
     /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
     /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
     pub async fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
-        let Some(mut handle) = self.session.outbound_iter() else {
+        self.resend(false).await
+    }
+
+    /// Retransmits all due PUBLISH packets from the [`MessageStore`] set with
+    /// [`Client::store_messages_in`] with the DUP flag set, as well as all PUBREL packets
+    /// [`Client::rerelease`] would send. The packets are sent in the order in which the
+    /// publications were started.
+    ///
+    /// This method should only be called once after a reconnection with clean start set to 0 and
+    /// before any new publications are made, as resending packets at any other time is a protocol
+    /// error and is prevented by the client anyway.
+    /// (Compare [Message delivery retry](https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901238), \[MQTT-4.4.0-1\]).
+    ///
+    /// Publications without a stored PUBLISH packet, e.g. because no message store was set when
+    /// they were started, are skipped and can still be republished with [`Client::republish`].
+    /// PUBREL packets of [`AckMode::Manual`] flows must still be resent with
+    /// [`Client::manual_release`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    pub async fn resume(&mut self) -> Result<(), MqttError<'c, 0>> {
+        self.resend(true).await
+    }
+
+    /// Sends the packets retransmitting the outgoing publications of the session in the order in
+    /// which they were started. Stored PUBLISH packets are only sent if `publishes` is true.
+    async fn resend(&mut self, publishes: bool) -> Result<(), MqttError<'c, 0>> {
+        if self.protocol.session.outbound_publishes.is_empty() {
             return Ok(());
-        };
+        }
+
+        for i in 0..self.protocol.session.outbound_publishes.len() {
+            if let Some(packet) = self.protocol.resend(i, publishes) {
+                Self::send_outgoing(&mut self.raw, &packet).await?;
+            }
+        }
+
+        self.raw.flush().await?;
+
+        Ok(())
+    }
 
-        loop {
-            if handle.state == LocalPublishState::DueRel(AckMode::Automatic) {
-                handle.outbound_pubrel().unwrap();
+    /// Sends a packet built by the [`Protocol`] without flushing the network connection.
+    async fn send_outgoing(
+        raw: &mut Raw<'c, N, B>,
+        packet: &Outgoing<'_>,
+    ) -> Result<(), RawError<B::ProvisionError>> {
+        match packet {
+            Outgoing::Puback(p) => raw.send(p).await,
+            Outgoing::Pubrec(p) => raw.send(p).await,
+            Outgoing::Pubrel(p) => raw.send(p).await,
+            Outgoing::Pubcomp(p) => raw.send(p).await,
+            Outgoing::Auth(p) => raw.send(p).await,
+            Outgoing::Stored(p) => raw.send(p).await,
+        }
+    }
+
+    /// Sends a packet built by the [`Protocol`] in response to a received packet. If a reassembly
+    /// buffer is set, it is kept there and written by the next call to [`Self::poll`] or the next
+    /// packet sent.
+    async fn respond(
+        raw: &mut Raw<'c, N, B>,
+        packet: &Outgoing<'_>,
+    ) -> Result<(), RawError<B::ProvisionError>> {
+        match packet {
+            Outgoing::Puback(p) => raw.respond(p).await,
+            Outgoing::Pubrec(p) => raw.respond(p).await,
+            Outgoing::Pubrel(p) => raw.respond(p).await,
+            Outgoing::Pubcomp(p) => raw.respond(p).await,
+            Outgoing::Auth(p) => raw.respond(p).await,
+            Outgoing::Stored(p) => raw.respond(p).await,
+        }
+    }
 
-                let pubrel =
-                    PubrelPacket::<0>::minimal(handle.packet_identifier(), ReasonCode::Success);
+    /// Publishes the publications from the [`PublishQueue`] set with
+    /// [`Client::queue_publications_in`] in the order in which they were queued.
+    ///
+    /// This method should be called after a connection has been established with
+    /// [`Client::connect`] and, in case of a present session, after the due packets have been
+    /// retransmitted with [`Client::resume`].
+    ///
+    /// Draining stops while the server's receive maximum, the client's packet identifiers or the
+    /// session buffer are exhausted. The remaining publications stay in the queue and are published
+    /// by calling this method again, e.g. after [`Event::PublishAcknowledged`] or
+    /// [`Event::PublishComplete`] has been emitted.
+    ///
+    /// Publications whose message expiry interval has passed while they were queued, which the
+    /// server does not support or which exceed its maximum packet size are dropped.
+    ///
+    /// If the returned future is cancelled while a publication is being sent, that publication
+    /// leaves the queue once this method is called again and is not published twice.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    ///
+    /// The publication during which the error occured has left the queue. In case of
+    /// [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`], it is tracked in the session and can be
+    /// retransmitted after reconnecting.
+    pub async fn drain_queue(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.publish_queue) else {
+            return Ok(());
+        };
+        let (client, queue) = taken.split();
 
-                debug!("sending PUBREL packet {}", pubrel.packet_identifier);
+        client.drain(&mut **queue).await
+    }
 
-                // Don't check whether length exceeds servers maximum packet size because we don't
-                // add properties to automatically retransmitted PUBREL packets -> length is always
-                // minimal at 6 bytes. The server really shouldn't reject this.
-                self.raw.send(&pubrel).await?;
+    /// Publishes the publications from `queue` until it is empty or a publication has to wait.
+    async fn drain(&mut self, queue: &mut dyn PublishQueue) -> Result<(), MqttError<'c, 0>> {
+        while let Some(record) = queue.front() {
+            let result = self.publish_queued(record).await;
+            self.protocol.queue_front_published = false;
+
+            match result {
+                Ok(true) => queue.pop(),
+                Ok(false) => return Ok(()),
+                Err(e) => {
+                    queue.pop();
+                    return Err(e);
+                }
             }
-            if let Some(next) = handle.next() {
-                handle = next;
-            } else {
+        }
+
+        Ok(())
+    }
+
+    /// Subscribes again to the topic filters recorded in the [`SubscriptionRegistry`] set with
+    /// [`Client::track_subscriptions_in`] which are neither granted in the current session nor
+    /// awaiting a SUBACK or UNSUBACK packet. Each topic filter is sent in its own SUBSCRIBE packet
+    /// with the options it was last subscribed to with.
+    ///
+    /// This method should be called after every successful [`Client::connect`]. If the returned
+    /// [`Connected`] has `session_present` set to false, all recorded subscriptions are reset to
+    /// not granted and subscribed to again. If the session is present, only the subscriptions
+    /// whose SUBSCRIBE packet was in flight when the connection was lost and which have not been
+    /// granted before are sent again. The server's SUBACK packets are emitted as
+    /// [`Event::Suback`] by [`Client::poll`] and update the registry.
+    ///
+    /// If the returned future is cancelled, the registry stays set and calling this method again
+    /// sends the subscription that was being sent and the remaining ones.
+    ///
+    /// # Errors
+    ///
+    /// The errors of [`Client::subscribe`]. Subscriptions that have not been sent when the error
+    /// occured are sent by calling this method again, e.g. once a SUBACK packet has freed a packet
+    /// identifier in case of [`MqttError::AllPacketIdentifiersUsed`] or
+    /// [`MqttError::SessionBuffer`].
+    pub async fn resubscribe_all(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.subscription_registry) else {
+            return Ok(());
+        };
+        let (client, registry) = taken.split();
+
+        client.resubscribe(&mut **registry).await
+    }
+
+    /// Subscribes to the topic filters in `registry` which are neither granted nor pending.
+    async fn resubscribe(
+        &mut self,
+        registry: &mut dyn SubscriptionRegistry,
+    ) -> Result<(), MqttError<'c, 0>> {
+        for i in 0..registry.len() {
+            let Some((topic_filter, mut subscription)) = registry.get(i) else {
                 break;
+            };
+
+            if subscription.granted_qos.is_some() || subscription.pending.is_some() {
+                continue;
+            }
+
+            debug!("resubscribing to recorded topic filter");
+
+            let pid = self.subscribe(topic_filter, &subscription.options).await?;
+
+            subscription.pending = Some(Pending::Subscribe {
+                packet_identifier: pid,
+                index: 0,
+            });
+            registry.set(i, subscription);
+        }
+
+        Ok(())
+    }
+
+    /// Publishes a record of a [`PublishQueue`]. Returns whether the record leaves the queue,
+    /// which it does unless the publication has to wait for free session state.
+    async fn publish_queued(&mut self, record: &[u8]) -> Result<bool, MqttError<'c, 0>> {
+        match self.protocol.dequeue(&self.raw, record) {
+            Dequeued::Drop => Ok(true),
+            Dequeued::Wait => Ok(false),
+            Dequeued::Publish(publication) => {
+                let options = publication.options();
+                let message = Bytes::Borrowed(publication.message);
+
+                leaves_queue(self.publish_dequeued(&options, message).await)
             }
         }
+    }
 
+    /// Publishes a publication taken from the front of a [`PublishQueue`] and marks it as
+    /// published as soon as the session tracks it, so that a cancelled drain does not send it
+    /// again.
+    async fn publish_dequeued(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(packet) = self.protocol.publish(&self.raw, options, message)? else {
+            return Ok(None);
+        };
+        self.protocol.queue_front_published = true;
+
+        self.raw.send(&packet).await?;
         self.raw.flush().await?;
 
-        Ok(())
+        Ok(packet.identified_qos.packet_identifier())
     }
 
     /// Sends a PUBACK packet responding to an incoming [`QoS::AtLeastOnce`] PUBLISH packet, that
@@ -1234,59 +1323,9 @@ impl<
         reason_code: ReasonCode,
         options: &AckOptions<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send PUBACK with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        // Not allowed:
-        // ReasonCode::NoMatchingSubscribers - only sent by the server
-        if !matches!(
-            reason_code,
-            ReasonCode::Success
-                | ReasonCode::UnspecifiedError
-                | ReasonCode::ImplementationSpecificError
-                | ReasonCode::NotAuthorized
-                | ReasonCode::TopicNameInvalid
-                | ReasonCode::PacketIdentifierInUse
-                | ReasonCode::QuotaExceeded
-                | ReasonCode::PayloadFormatInvalid
-        ) {
-            return Err(MqttError::IllegalReasonCode);
-        }
-
-        let packet = PubackPacket::<MAX_USER_PROPERTIES>::new(
-            packet_identifier,
-            reason_code,
-            options
-                .reason_string
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-        );
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        self.session
-            .outbound_puback(packet_identifier)
-            .map_err(|e| match e {
-                SmError::NoCapacity => unreachable!(),
-                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
-                SmError::QoSMismatched => MqttError::QoSMismatched,
-                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
-            })?;
-
-        debug!("sending PUBACK packet {}", packet.packet_identifier);
+        let packet = self
+            .protocol
+            .manual_acknowledge(packet_identifier, reason_code, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1329,59 +1368,9 @@ impl<
         reason_code: ReasonCode,
         options: &AckOptions<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send PUBREC with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        // Not allowed:
-        // ReasonCode::NoMatchingSubscribers - only sent by the server
-        if !matches!(
-            reason_code,
-            ReasonCode::Success
-                | ReasonCode::UnspecifiedError
-                | ReasonCode::ImplementationSpecificError
-                | ReasonCode::NotAuthorized
-                | ReasonCode::TopicNameInvalid
-                | ReasonCode::PacketIdentifierInUse
-                | ReasonCode::QuotaExceeded
-                | ReasonCode::PayloadFormatInvalid
-        ) {
-            return Err(MqttError::IllegalReasonCode);
-        }
-
-        let packet = PubrecPacket::<MAX_USER_PROPERTIES>::new(
-            packet_identifier,
-            reason_code,
-            options
-                .reason_string
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-        );
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        self.session
-            .outbound_pubrec(packet_identifier, reason_code)
-            .map_err(|e| match e {
-                SmError::NoCapacity => unreachable!(),
-                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
-                SmError::QoSMismatched => MqttError::QoSMismatched,
-                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
-            })?;
-
-        debug!("sending PUBREC packet {}", packet.packet_identifier);
+        let packet = self
+            .protocol
+            .manual_receive(packet_identifier, reason_code, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1419,45 +1408,7 @@ impl<
         packet_identifier: PacketIdentifier,
         options: &AckOptions<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        const REASON_CODE: ReasonCode = ReasonCode::Success;
-
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send PUBREL with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        let packet = PubrelPacket::<MAX_USER_PROPERTIES>::new(
-            packet_identifier,
-            REASON_CODE,
-            options
-                .reason_string
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-        );
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        self.session
-            .outbound_pubrel(packet_identifier)
-            .map_err(|e| match e {
-                SmError::NoCapacity => unreachable!(),
-                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
-                SmError::QoSMismatched => MqttError::QoSMismatched,
-                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
-            })?;
-
-        debug!("sending PUBREL packet {}", packet.packet_identifier);
+        let packet = self.protocol.manual_release(packet_identifier, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1494,45 +1445,7 @@ impl<
         packet_identifier: PacketIdentifier,
         options: &AckOptions<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        const REASON_CODE: ReasonCode = ReasonCode::Success;
-
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send PUBCOMP with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        let packet = PubcompPacket::<MAX_USER_PROPERTIES>::new(
-            packet_identifier,
-            REASON_CODE,
-            options
-                .reason_string
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-        );
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        self.session
-            .outbound_pubcomp(packet_identifier)
-            .map_err(|e| match e {
-                SmError::NoCapacity => unreachable!(),
-                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
-                SmError::QoSMismatched => MqttError::QoSMismatched,
-                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
-            })?;
-
-        debug!("sending PUBCOMP packet {}", packet.packet_identifier);
+        let packet = self.protocol.manual_complete(packet_identifier, options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1588,82 +1501,7 @@ impl<
         &mut self,
         options: &DisconnectOptions<'_>,
     ) -> Result<(), MqttError<'c, 0>> {
-        assert!(
-            options.user_properties.len() <= MAX_USER_PROPERTIES,
-            "attempted to send DISCONNECT with {} > {} (MAX_USER_PROPERTIES) properties",
-            options.user_properties.len(),
-            MAX_USER_PROPERTIES
-        );
-
-        // Not allowed:
-        // ReasonCode::NotAuthorized - only sent by the server
-        // ReasonCode::ServerBusy - only sent by the server
-        // ReasonCode::ServerShuttingDown - only sent by the server
-        // ReasonCode::KeepAliveTimeout - only sent by the server
-        // ReasonCode::SessionTakenOver - only sent by the server
-        // ReasonCode::TopicFilterInvalid - only sent by the server
-        // ReasonCode::RetainNotSupported - only sent by the server
-        // ReasonCode::QoSNotSupported - only sent by the server
-        // ReasonCode::UseAnotherServer - only sent by the server
-        // ReasonCode::ServerMoved - only sent by the server
-        // ReasonCode::SharedSubscriptionsNotSupported - only sent by the server
-        // ReasonCode::ConnectionRateExceeded - only sent by the server
-        // ReasonCode::MaximumConnectTime - only sent by the server
-        // ReasonCode::SubscriptionIdentifiersNotSupported - only sent by the server
-        // ReasonCode::WildcardSubscriptionsNotSupported - only sent by the server
-        if !matches!(
-            options.reason_code,
-            ReasonCode::Success
-                | ReasonCode::DisconnectWithWillMessage
-                | ReasonCode::UnspecifiedError
-                | ReasonCode::MalformedPacket
-                | ReasonCode::ProtocolError
-                | ReasonCode::ImplementationSpecificError
-                | ReasonCode::TopicNameInvalid
-                | ReasonCode::ReceiveMaximumExceeded
-                | ReasonCode::TopicAliasInvalid
-                | ReasonCode::PacketTooLarge
-                | ReasonCode::MessageRateTooHigh
-                | ReasonCode::QuotaExceeded
-                | ReasonCode::AdministrativeAction
-                | ReasonCode::PayloadFormatInvalid
-        ) {
-            return Err(MqttError::IllegalReasonCode);
-        }
-
-        let connect_session_expiry_interval_was_zero =
-            self.client_config.session_expiry_interval == SessionExpiryInterval::EndOnDisconnect;
-        let disconnect_session_expiry_interval_is_non_zero = options
-            .session_expiry_interval
-            .is_some_and(|s| s != SessionExpiryInterval::EndOnDisconnect);
-
-        if connect_session_expiry_interval_was_zero
-            && disconnect_session_expiry_interval_is_non_zero
-        {
-            return Err(MqttError::IllegalDisconnectSessionExpiryInterval);
-        }
-
-        let packet = DisconnectPacket::<MAX_USER_PROPERTIES>::new(
-            options.reason_code,
-            options.session_expiry_interval,
-            options
-                .reason_string
-                .as_ref()
-                .map(MqttString::as_borrowed)
-                .map(Into::into),
-            options
-                .user_properties
-                .iter()
-                .map(MqttStringPair::as_borrowed)
-                .map(Into::into)
-                .collect(),
-        );
-
-        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
-            return Err(MqttError::ServerMaximumPacketSizeExceeded);
-        }
-
-        debug!("sending DISCONNECT packet");
+        let packet = self.protocol.disconnect(options)?;
 
         self.raw.send(&packet).await?;
         self.raw.flush().await?;
@@ -1678,7 +1516,8 @@ impl<
 
     /// Combines [`Self::poll_header`] and [`Self::poll_body`].
     ///
-    /// Polls the network for a full packet. Not cancel-safe.
+    /// Polls the network for a full packet. Not cancel-safe unless a reassembly buffer has been set
+    /// with [`Self::reassemble_packets_with`].
     ///
     /// # Preconditions:
     /// - The last MQTT packet was received completely
@@ -1701,6 +1540,40 @@ impl<
         self.poll_body(header).await
     }
 
+    /// Combines [`Self::try_poll_header`] and [`Self::poll_body`].
+    ///
+    /// Polls the network for a full packet if data is ready to be read, so that the client can be
+    /// serviced in a loop together with other work. Bytes are only read once [`ReadReady::read_ready`]
+    /// returns [`true`]. After a complete fixed header has been received, the variable header and
+    /// payload are awaited like in [`Self::poll`].
+    ///
+    /// # Preconditions:
+    /// - The last MQTT packet was received completely
+    /// - The client did not return a non-recoverable Error before
+    ///
+    /// # Returns:
+    /// - [`None`] if the network has no data ready to be read before a complete fixed header has
+    ///   been received.
+    /// - MQTT Events otherwise. Their further meaning is documented in [`Event`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::try_poll_header`] and [`Self::poll_body`] return.
+    pub async fn try_poll(
+        &mut self,
+    ) -> Result<
+        Option<Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    >
+    where
+        N: ReadReady,
+    {
+        match self.try_poll_header().await.map_err(MqttError::inflate)? {
+            Some(header) => self.poll_body(header).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
     /// Polls the network for a fixed header in a cancel-safe way.
     ///
     /// If a fixed header is received, the first 4 bits (packet type) are checked for correctness.
@@ -1721,32 +1594,34 @@ impl<
     ///   * the packet following this header exceeds the client's maximum packet size
     pub async fn poll_header(&mut self) -> Result<FixedHeader, MqttError<'c, 0>> {
         let header = self.raw.recv_header().await?;
+        self.protocol.check_header(&mut self.raw, header)
+    }
 
-        if let Ok(p) = header.packet_type() {
-            debug!(
-                "received {:?} packet header (remaining length: {})",
-                p,
-                header.remaining_len.value()
-            );
-        } else {
-            error!("received invalid header {:?}", header);
-            self.raw.close_with(Some(ReasonCode::MalformedPacket));
-            return Err(MqttError::Server);
-        }
-
-        if header.remaining_len.value() > self.client_config.maximum_accepted_remaining_length {
-            error!(
-                "received a packet exceeding maximum packet size, remaining length={:?}",
-                header.remaining_len.value()
-            );
-            self.raw.close_with(Some(ReasonCode::PacketTooLarge));
-            return Err(MqttError::Server);
+    /// Polls the network for a fixed header in a cancel-safe way without waiting for data that is
+    /// not ready to be read yet.
+    ///
+    /// Bytes of the fixed header are only read while [`ReadReady::read_ready`] returns [`true`].
+    /// They are kept across calls until the fixed header is complete.
+    ///
+    /// # Returns:
+    /// The received fixed header with a valid packet type like [`Self::poll_header`] or [`None`] if the
+    /// network has no data ready to be read before the fixed header is complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::poll_header`] returns.
+    pub async fn try_poll_header(&mut self) -> Result<Option<FixedHeader>, MqttError<'c, 0>>
+    where
+        N: ReadReady,
+    {
+        match self.raw.try_recv_header().await? {
+            Some(header) => self.protocol.check_header(&mut self.raw, header).map(Some),
+            None => Ok(None),
         }
-
-        Ok(header)
     }
 
-    /// Polls the network for the variable header and payload of a packet. Not cancel-safe.
+    /// Polls the network for the variable header and payload of a packet. Not cancel-safe unless a
+    /// reassembly buffer has been set with [`Self::reassemble_packets_with`].
     ///
     /// # Preconditions:
     /// - The [`FixedHeader`] argument was received from the network right before.
@@ -1770,8 +1645,13 @@ impl<
     ///   * the server sends a PUBACK/PUBREC/PUBREL/PUBCOMP packet which mismatches what
     ///     the client expects for this packet identifier from its session state
     ///   * the fixed header has the packet type CONNECT/SUBSCRIBE/UNSUBSCRIBE/PINGREQ
+    ///   * the server sends an AUTH packet with an authentication method other than the one in
+    ///     the CONNECT packet
     /// * [`MqttError::Disconnect`] if a DISCONNECT packet is received
-    /// * [`MqttError::AuthPacketReceived`] if the fixed header has the packet type AUTH
+    /// * [`MqttError::AuthPacketReceived`] if:
+    ///   * the fixed header has the packet type AUTH and no re-authentication is in progress
+    ///   * the server continues a re-authentication and no [`Authenticator`] has been set
+    /// * [`MqttError::Authentication`] if the [`Authenticator`] rejects the re-authentication exchange
     pub async fn poll_body(
         &mut self,
         header: FixedHeader,
@@ -1779,481 +1659,400 @@ impl<
         Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
         MqttError<'c, MAX_USER_PROPERTIES>,
     > {
-        let event = match header.packet_type()? {
-            PacketType::Pingresp => {
-                self.raw.recv_body::<PingrespPacket>(&header).await?;
-                Event::Pingresp
-            }
-            PacketType::Suback => {
-                // We only send SUBSCRIBE packets with exactly 1 topic
-                // -> Packets with more than 1 reason code are currently rejected by the RxPacket::receive implementation
-                //    with RxError::Protocol error. This is correct as long as we only send SUBSCRIBE packets with 1 topic.
-                let suback = self
-                    .raw
-                    .recv_body::<SubackPacket<1, MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (suback.reason_string.is_some() || !suback.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+        self.protocol.check_packet_type(&mut self.raw, &header)?;
 
-                let pid = suback.packet_identifier;
-
-                if let Some(h) = self.session.sub_handle(pid) {
-                    h.remove();
-
-                    // We only send SUBSCRIBE packets with exactly 1 topic
-                    let [r] = suback.reason_codes.as_slice() else {
-                        error!("received mismatched SUBACK");
-                        self.raw.close_with(Some(ReasonCode::ProtocolError));
-                        return Err(MqttError::Server);
-                    };
-
-                    Event::Suback(Suback {
-                        packet_identifier: pid,
-                        reason_string: suback.reason_string.map(Property::into_inner),
-                        user_properties: suback
-                            .user_properties
-                            .into_iter()
-                            .map(Property::into_inner)
-                            .collect(),
-                        reason_code: *r,
-                    })
-                } else {
-                    debug!("packet identifier {} in SUBACK not in use", pid);
-                    Event::Ignored
-                }
-            }
-            PacketType::Unsuback => {
-                // We only send UNSUBSCRIBE packets with exactly 1 topic
-                // -> Packets with more than 1 reason code are currently rejected by the RxPacket::receive implementation
-                //    with RxError::Protocol error. This is correct as long as we only send UNSUBSCRIBE packets with 1 topic.
-                let unsuback = self
-                    .raw
-                    .recv_body::<UnsubackPacket<1, MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (unsuback.reason_string.is_some() || !unsuback.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+        let packet = self
+            .raw
+            .recv_body::<Incoming<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>(&header)
+            .await?;
 
-                let pid = unsuback.packet_identifier;
-
-                if let Some(h) = self.session.unsub_handle(pid) {
-                    h.remove();
-
-                    // We only send UNSUBSCRIBE packets with exactly 1 topic
-                    let [r] = unsuback.reason_codes.as_slice() else {
-                        error!("received mismatched UNSUBACK");
-                        self.raw.close_with(Some(ReasonCode::ProtocolError));
-                        return Err(MqttError::Server);
-                    };
-
-                    Event::Unsuback(Suback {
-                        packet_identifier: pid,
-                        reason_string: unsuback.reason_string.map(Property::into_inner),
-                        user_properties: unsuback
-                            .user_properties
-                            .into_iter()
-                            .map(Property::into_inner)
-                            .collect(),
-                        reason_code: *r,
-                    })
-                } else {
-                    debug!("packet identifier {} in UNSUBACK not in use", pid);
-                    Event::Ignored
-                }
-            }
-            PacketType::Publish => {
-                let publish = self
-                    .raw
-                    .recv_body::<PublishPacket<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>(
-                        &header,
-                    )
-                    .await?;
-
-                // Our topic alias maximum is always 0, the moment we receive a topic alias, this is an error.
-                let TopicReference::Name(topic) = publish.topic else {
-                    error!("received disallowed topic alias");
-                    self.raw.close_with(Some(ReasonCode::TopicAliasInvalid));
-                    return Err(MqttError::Server);
-                };
-
-                let publish = Publish {
-                    ack_mode: AckMode::default(),
-                    dup: publish.dup,
-                    identified_qos: publish.identified_qos,
-                    retain: publish.retain,
-                    topic,
-                    payload_format_indicator: publish
-                        .payload_format_indicator
-                        .map(Property::into_inner),
-                    message_expiry_interval: publish
-                        .message_expiry_interval
-                        .map(Property::into_inner),
-                    response_topic: publish.response_topic.map(Property::into_inner),
-                    correlation_data: publish.correlation_data.map(Property::into_inner),
-                    user_properties: publish
-                        .user_properties
-                        .into_iter()
-                        .map(Property::into_inner)
-                        .collect(),
-                    subscription_identifiers: publish
-                        .subscription_identifiers
-                        .into_iter()
-                        .map(Property::into_inner)
-                        .collect(),
-                    content_type: publish.content_type.map(Property::into_inner),
-                    message: publish.message,
-                };
-
-                let ack_mode = if (self.manual_ack_when)(&publish) {
-                    AckMode::Manual
-                } else {
-                    AckMode::Automatic
-                };
-
-                let publish = Publish {
-                    ack_mode,
-                    ..publish
-                };
-
-                let (action, event) = self
-                    .session
-                    .inbound_publish(publish.identified_qos, publish.ack_mode);
-
-                match action {
-                    Response::Release(_) | Response::Complete(_) => unreachable!(),
-
-                    Response::None => {}
-                    Response::Acknowledge(reason_code) => {
-                        let puback = PubackPacket::<0>::minimal(
-                            publish.identified_qos.packet_identifier().unwrap(),
-                            reason_code,
-                        );
-                        debug!("sending PUBACK packet {}", puback.packet_identifier);
-
-                        // Don't check whether length exceeds servers maximum packet size because we don't
-                        // add properties to automatically sent PUBACK packets -> length is always minimal
-                        // at 6 bytes. The server really shouldn't reject this.
-                        self.raw.send(&puback).await?;
-                        self.raw.flush().await?;
-                    }
-                    Response::Receive(reason_code) => {
-                        let pubrec = PubrecPacket::<0>::minimal(
-                            publish.identified_qos.packet_identifier().unwrap(),
-                            reason_code,
-                        );
-                        debug!("sending PUBREC packet {}", pubrec.packet_identifier);
-
-                        // Don't check whether length exceeds servers maximum packet size because we don't
-                        // add properties to automatically sent PUBREC packets -> length is always minimal
-                        // at 6 bytes. The server really shouldn't reject this.
-                        self.raw.send(&pubrec).await?;
-                        self.raw.flush().await?;
-                    }
-                    Response::Disconnect(reason_code) => {
-                        error!("invalid PUBLISH packet rejected by state machine");
-                        self.raw.close_with(Some(reason_code));
-                    }
-                }
+        let (event, response) = self.protocol.receive(&mut self.raw, packet)?;
 
-                match event {
-                    SmEvent::Aborted
-                    | SmEvent::Rejected
-                    | SmEvent::Acknowledged
-                    | SmEvent::Received(_)
-                    | SmEvent::Released(_)
-                    | SmEvent::Completed => unreachable!(),
-
-                    SmEvent::Publish => Event::Publish(publish),
-                    SmEvent::Duplicate(ack_mode) => {
-                        let publish = Publish {
-                            ack_mode,
-                            ..publish
-                        };
-                        Event::Duplicate(publish)
-                    }
-                    SmEvent::Ignored => Event::Ignored,
-                    SmEvent::ServerError => return Err(MqttError::Server),
-                }
-            }
-            PacketType::Puback => {
-                let puback = self
-                    .raw
-                    .recv_body::<PubackPacket<MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (puback.reason_string.is_some() || !puback.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+        if let Some(packet) = response {
+            Self::respond(&mut self.raw, &packet).await?;
+        }
 
-                let (action, event) = self
-                    .session
-                    .inbound_puback(puback.packet_identifier, puback.reason_code);
-
-                match action {
-                    Response::Acknowledge(_)
-                    | Response::Receive(_)
-                    | Response::Release(_)
-                    | Response::Complete(_) => unreachable!(),
-
-                    Response::None => {}
-                    Response::Disconnect(reason_code) => {
-                        error!("invalid PUBACK packet rejected by state machine");
-                        self.raw.close_with(Some(reason_code));
-                    }
-                }
+        Ok(event)
+    }
 
-                match event {
-                    SmEvent::Publish
-                    | SmEvent::Duplicate(_)
-                    | SmEvent::Aborted
-                    | SmEvent::Received(_)
-                    | SmEvent::Released(_)
-                    | SmEvent::Completed => unreachable!(),
-
-                    SmEvent::Ignored => Event::Ignored,
-                    SmEvent::Rejected => Event::PublishRejected(Pubrej::from(puback)),
-                    SmEvent::Acknowledged => {
-                        Event::PublishAcknowledged(Puback::new(puback, AckMode::default()))
-                    }
-                    SmEvent::ServerError => return Err(MqttError::Server),
-                }
-            }
-            PacketType::Pubrec => {
-                let pubrec = self
-                    .raw
-                    .recv_body::<PubrecPacket<MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (pubrec.reason_string.is_some() || !pubrec.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+    /// Splits the connected client into a [`split::Receiver`] owning [`Self::poll`] over the read
+    /// half of the network connection and a [`split::Sender`] owning the methods sending packets
+    /// over its write half, so that packets can be sent while another task is waiting for
+    /// incoming packets.
+    ///
+    /// Both halves share the session state and configuration of the client through a lock which
+    /// is only held in between two awaits. The lock is not synchronized between threads because
+    /// the hooks of the client, such as the [`Authenticator`], are not required to be [`Send`].
+    /// The halves can be spawned as separate tasks of the same executor if the client and the
+    /// [`split::State`] live long enough, e.g. in a `static`, or be polled concurrently within
+    /// one task.
+    ///
+    /// The client can be used again once both halves have been dropped. After a half has returned
+    /// an unrecoverable error, both halves have to be dropped to [`Self::abort`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the client is not connected, if no reassembly buffer has been set
+    /// with [`Self::reassemble_packets_with`] or if the transmit buffer of the [`split::State`]
+    /// cannot hold the remainder of a response which [`Self::poll`] has not written completely.
+    pub fn split<'s>(
+        &'s mut self,
+        state: &'s mut split::State<
+            's,
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    ) -> (
+        split::Receiver<
+            's,
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        split::Sender<
+            's,
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    )
+    where
+        N: Split,
+    {
+        state.split(&mut self.protocol, &mut self.raw)
+    }
+}
 
-                let (action, event) = self
-                    .session
-                    .inbound_pubrec(pubrec.packet_identifier, pubrec.reason_code);
-
-                match action {
-                    Response::Acknowledge(_) | Response::Receive(_) | Response::Complete(_) => {
-                        unreachable!()
-                    }
-
-                    Response::None => {}
-                    Response::Release(reason_code) => {
-                        let pubrel =
-                            PubrelPacket::<0>::minimal(pubrec.packet_identifier, reason_code);
-
-                        debug!("sending PUBREL packet {}", pubrel.packet_identifier);
-
-                        // Don't check whether length exceeds servers maximum packet size because we don't
-                        // add properties to automatically sent PUBREL packets -> length is always minimal
-                        // at 6 bytes. The server really shouldn't reject this.
-                        self.raw.send(&pubrel).await?;
-                        self.raw.flush().await?;
-                    }
-                    Response::Disconnect(reason_code) => {
-                        error!("invalid PUBREC packet rejected by state machine");
-                        self.raw.close_with(Some(reason_code));
-                    }
-                }
+/// A value taken out of an optional field of the client, which is put back when this is dropped.
+/// This also happens if the future holding it is cancelled.
+#[cfg(feature = "v5")]
+struct Taken<'a, C, T> {
+    client: &'a mut C,
+    value: Option<T>,
+    field: fn(&mut C) -> &mut Option<T>,
+}
 
-                match event {
-                    SmEvent::Publish
-                    | SmEvent::Duplicate(_)
-                    | SmEvent::Aborted
-                    | SmEvent::Acknowledged
-                    | SmEvent::Released(_)
-                    | SmEvent::Completed => unreachable!(),
-
-                    SmEvent::Ignored => Event::Ignored,
-                    SmEvent::Rejected => Event::PublishRejected(Pubrej::from(pubrec)),
-                    SmEvent::Received(mode) => Event::PublishReceived(Puback::new(pubrec, mode)),
-                    SmEvent::ServerError => return Err(MqttError::Server),
-                }
-            }
-            PacketType::Pubrel => {
-                let pubrel = self
-                    .raw
-                    .recv_body::<PubrelPacket<MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (pubrel.reason_string.is_some() || !pubrel.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+#[cfg(feature = "v5")]
+impl<'a, C, T> Taken<'a, C, T> {
+    /// Takes the value out of the field, returning [`None`] if the field is empty.
+    fn new(client: &'a mut C, field: fn(&mut C) -> &mut Option<T>) -> Option<Self> {
+        let value = field(client).take()?;
+
+        Some(Self {
+            client,
+            value: Some(value),
+            field,
+        })
+    }
 
-                let (action, event) = self
-                    .session
-                    .inbound_pubrel(pubrel.packet_identifier, pubrel.reason_code);
-
-                match action {
-                    Response::Acknowledge(_) | Response::Receive(_) | Response::Release(_) => {
-                        unreachable!()
-                    }
-
-                    Response::None => {}
-                    Response::Complete(reason_code) => {
-                        let pubcomp =
-                            PubcompPacket::<0>::minimal(pubrel.packet_identifier, reason_code);
-
-                        debug!("sending PUBCOMP packet {}", pubcomp.packet_identifier);
-
-                        // Don't check whether length exceeds servers maximum packet size because we don't
-                        // add properties to automatically sent PUBCOMP packets -> length is always minimal
-                        // at 6 bytes. The server really shouldn't reject this.
-                        self.raw.send(&pubcomp).await?;
-                        self.raw.flush().await?;
-                    }
-                    Response::Disconnect(reason_code) => {
-                        error!("invalid PUBREL packet rejected by state machine");
-                        self.raw.close_with(Some(reason_code));
-                    }
-                }
+    /// Returns the client and the taken value.
+    fn split(&mut self) -> (&mut C, &mut T) {
+        let Some(value) = self.value.as_mut() else {
+            unreachable!()
+        };
 
-                match event {
-                    SmEvent::Publish
-                    | SmEvent::Duplicate(_)
-                    | SmEvent::Rejected
-                    | SmEvent::Acknowledged
-                    | SmEvent::Received(_)
-                    | SmEvent::Completed => unreachable!(),
-
-                    SmEvent::Ignored => Event::Ignored,
-                    SmEvent::Aborted => Event::PublishAborted(Pubrej::from(pubrel)),
-                    SmEvent::Released(mode) => Event::PublishReleased(Puback::new(pubrel, mode)),
-                    SmEvent::ServerError => return Err(MqttError::Server),
-                }
-            }
-            PacketType::Pubcomp => {
-                let pubcomp = self
-                    .raw
-                    .recv_body::<PubcompPacket<MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                if !self.client_config.request_problem_information
-                    && (pubcomp.reason_string.is_some() || !pubcomp.user_properties.is_empty())
-                {
-                    error!(
-                        "server sent reason string or user properties when request problem information was false"
-                    );
-                    self.raw.close_with(Some(ReasonCode::ProtocolError));
-                    return Err(MqttError::Server);
-                }
+        (self.client, value)
+    }
+}
 
-                let (action, event) = self
-                    .session
-                    .inbound_pubcomp(pubcomp.packet_identifier, pubcomp.reason_code);
-
-                match action {
-                    Response::Acknowledge(_)
-                    | Response::Receive(_)
-                    | Response::Release(_)
-                    | Response::Complete(_) => unreachable!(),
-
-                    Response::None => {}
-                    Response::Disconnect(reason_code) => {
-                        error!("invalid PUBCOMP packet rejected by state machine");
-                        self.raw.close_with(Some(reason_code));
-                    }
-                }
+#[cfg(feature = "v5")]
+impl<C, T> Drop for Taken<'_, C, T> {
+    fn drop(&mut self) {
+        *(self.field)(self.client) = self.value.take();
+    }
+}
 
-                match event {
-                    SmEvent::Publish
-                    | SmEvent::Duplicate(_)
-                    | SmEvent::Aborted
-                    | SmEvent::Rejected
-                    | SmEvent::Acknowledged
-                    | SmEvent::Received(_)
-                    | SmEvent::Released(_) => unreachable!(),
-
-                    SmEvent::Ignored => Event::Ignored,
-                    SmEvent::Completed => {
-                        Event::PublishComplete(Puback::new(pubcomp, AckMode::default()))
-                    }
-                    SmEvent::ServerError => return Err(MqttError::Server),
-                }
-            }
-            PacketType::Disconnect => {
-                let disconnect = self
-                    .raw
-                    .recv_body::<DisconnectPacket<MAX_USER_PROPERTIES>>(&header)
-                    .await?;
-
-                // The server initiated the disconnect. We must close the transport on our side
-                // as well so that subsequent error handling (e.g. `abort`) sees a non-Ok network state.
-                self.raw.close_with(None);
-
-                return Err(MqttError::Disconnect {
-                    reason: disconnect.reason_code,
-                    reason_string: disconnect.reason_string.map(Property::into_inner),
-                    user_properties: disconnect
-                        .user_properties
-                        .into_iter()
-                        .map(Property::into_inner)
-                        .collect(),
-                    server_reference: disconnect.server_reference.map(Property::into_inner),
-                });
-            }
-            t @ (PacketType::Connect
-            | PacketType::Subscribe
-            | PacketType::Unsubscribe
-            | PacketType::Pingreq) => {
-                error!(
-                    "received a packet that the server is not allowed to send: {:?}",
-                    t
-                );
+#[cfg(all(test, feature = "v5"))]
+mod unit {
+    use core::{cell::Cell, num::NonZero, time::Duration};
+
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, duplex},
+        join,
+        time::timeout,
+    };
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        client::{
+            Client, MqttError,
+            auth::Authenticator,
+            event::Event,
+            options::{ConnectOptions, PublicationOptions, TopicReference},
+        },
+        types::{IdentifiedQoS, MqttBinary, MqttString, ReasonCode, TopicName},
+    };
 
-                self.raw.close_with(Some(ReasonCode::ProtocolError));
-                return Err(MqttError::Server);
-            }
-            PacketType::Connack => {
-                error!("received unexpected CONNACK packet");
+    /// Answers the challenge "s" with "r" and expects the server to prove its identity with "f".
+    struct Challenge<'a> {
+        continued: &'a Cell<usize>,
+        completed: &'a Cell<bool>,
+    }
 
-                self.raw.close_with(Some(ReasonCode::ProtocolError));
-                return Err(MqttError::Server);
-            }
-            PacketType::Auth => {
-                error!("received unexpected AUTH packet");
-
-                // Receiving a AUTH packet is currently always a protocol error because we never send
-                // an Authentication Method property in the CONNECT packet.
-                // <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901217>
-                self.raw.close_with(Some(ReasonCode::ProtocolError));
-                return Err(MqttError::AuthPacketReceived);
+    impl Authenticator for Challenge<'_> {
+        fn continue_authentication(
+            &mut self,
+            data: Option<&[u8]>,
+        ) -> Result<Option<MqttBinary<'_>>, ReasonCode> {
+            assert_eq!(data, Some(&b"s"[..]));
+            self.continued.set(self.continued.get() + 1);
+            Ok(Some(MqttBinary::try_from(&b"r"[..]).unwrap()))
+        }
+
+        fn complete_authentication(&mut self, data: Option<&[u8]>) -> Result<(), ReasonCode> {
+            assert_eq!(data, Some(&b"f"[..]));
+            self.completed.set(true);
+            Ok(())
+        }
+    }
+
+    const CONTINUE: [u8; 12] = [
+        0xF0, 0x0A, 0x18, 0x08, 0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b's',
+    ];
+    const RESPONSE: [u8; 12] = [
+        0xF0, 0x0A, 0x18, 0x08, 0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b'r',
+    ];
+
+    fn options() -> ConnectOptions<'static> {
+        ConnectOptions::new()
+            .clean_start()
+            .authentication_method(MqttString::try_from("M").unwrap())
+            .authentication_data(MqttBinary::try_from(&b"i"[..]).unwrap())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn enhanced_authentication() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+        let continued = Cell::new(0);
+        let completed = Cell::new(false);
+        let mut authenticator = Challenge {
+            continued: &continued,
+            completed: &completed,
+        };
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.authenticate_with(&mut authenticator);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            let n = assert_ok!(s.read(&mut buf).await);
+            assert_eq!(buf[0], 0x10);
+            // The authentication method and data are sent in the CONNECT packet.
+            assert!(
+                buf[..n]
+                    .windows(8)
+                    .any(|w| w == [0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b'i'])
+            );
+
+            for _ in 0..2 {
+                assert_ok!(s.write_all(&CONTINUE).await);
+                assert_ok!(s.read_exact(&mut buf[..12]).await);
+                assert_eq!(buf[..12], RESPONSE);
             }
+
+            assert_ok!(
+                s.write_all(&[
+                    0x20, 0x0B, 0x00, 0x00, 0x08, 0x15, 0x00, 0x01, b'M', 0x16, 0x00, 0x01, b'f',
+                ])
+                .await
+            );
+        };
+        let client = async {
+            let id = MqttString::try_from("c").unwrap();
+            let connected = assert_ok!(
+                client
+                    .connect(FromTokio::new(c), &options(), Some(id))
+                    .await
+            );
+            assert!(!connected.session_present);
         };
 
-        Ok(event)
+        join!(server, client);
+
+        assert_eq!(continued.get(), 2);
+        assert!(completed.get());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn connack_with_other_authentication_method() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+        let continued = Cell::new(0);
+        let completed = Cell::new(false);
+        let mut authenticator = Challenge {
+            continued: &continued,
+            completed: &completed,
+        };
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.authenticate_with(&mut authenticator);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&CONTINUE).await);
+            assert_ok!(s.read_exact(&mut buf[..12]).await);
+            assert_eq!(buf[..12], RESPONSE);
+            assert_ok!(
+                s.write_all(&[0x20, 0x07, 0x00, 0x00, 0x04, 0x15, 0x00, 0x01, b'X'])
+                    .await
+            );
+
+            assert_ok!(s.read_exact(&mut buf[..4]).await);
+            assert_eq!(buf[..4], [0xE0, 0x02, 0x82, 0x00]);
+        };
+        let client = async {
+            let id = MqttString::try_from("c").unwrap();
+            let e = assert_err!(
+                client
+                    .connect(FromTokio::new(c), &options(), Some(id))
+                    .await
+            );
+            assert_eq!(e, MqttError::Server);
+            client.abort().await;
+        };
+
+        join!(server, client);
+
+        assert_eq!(continued.get(), 1);
+        assert!(!completed.get());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn auth_without_authenticator() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&CONTINUE).await);
+
+            assert_ok!(s.read_exact(&mut buf[..4]).await);
+            assert_eq!(buf[..4], [0xE0, 0x02, 0x83, 0x00]);
+        };
+        let client = async {
+            let id = MqttString::try_from("c").unwrap();
+            let e = assert_err!(
+                client
+                    .connect(FromTokio::new(c), &options(), Some(id))
+                    .await
+            );
+            assert_eq!(e, MqttError::AuthPacketReceived);
+            client.abort().await;
+        };
+
+        join!(server, client);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn cancelled_poll_keeps_response() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut reassembly = [0; 32];
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.reassemble_packets_with(&mut reassembly);
+
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new()
+                .clean_start()
+                .maximum_packet_size(NonZero::new(32).unwrap());
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+        };
+        join!(server, connect);
+
+        // Leaves 2 bytes of the transport free as the server does not read.
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let options = PublicationOptions::new(TopicReference::Name(topic));
+        assert_ok!(client.publish(&options, (&[0; 56][..]).into()).await);
+
+        assert_ok!(
+            s.write_all(&[0x32, 0x07, 0x00, 0x01, b't', 0x00, 0x01, 0x00, b'm'])
+                .await
+        );
+
+        // The PUBACK packet does not fit into the transport, yet the publication is emitted.
+        match assert_ok!(assert_ok!(
+            timeout(Duration::from_millis(10), client.poll()).await
+        )) {
+            Event::Publish(p) => assert!(matches!(p.identified_qos, IdentifiedQoS::AtLeastOnce(_))),
+            e => panic!("unexpected event {:?}", e),
+        }
+
+        // Cancelled while the PUBACK packet is being written.
+        assert_err!(timeout(Duration::from_millis(10), client.poll()).await);
+
+        let mut buf = [0; 64];
+        assert_ok!(s.read_exact(&mut buf).await);
+        assert_eq!(buf[62..], [0x40, 0x04]);
+
+        // The remainder of the PUBACK packet is written before the next packet is received.
+        assert_err!(timeout(Duration::from_millis(10), client.poll()).await);
+        assert_ok!(s.read_exact(&mut buf[..4]).await);
+        assert_eq!(buf[..4], [0x00, 0x01, 0x00, 0x00]);
     }
 }
@@ -7,8 +7,10 @@ use crate::types::{MqttString, MqttStringPair};
 /// Options for an acknowledgement to the server with a PUBACK, PUBREC, PUBREL or PUBCOMP packet.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'a> {
     /// The reason string property of the PUBACK, PUBREC, PUBREL or PUBCOMP packet.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub reason_string: Option<MqttString<'a>>,
 
     /// Arbitrary key-value pairs of strings sent as the user property entries of the PUBACK, PUBREC,
@@ -16,6 +18,7 @@ pub struct Options<'a> {
     /// generic parameter `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'a [MqttStringPair<'a>],
 }
 
@@ -60,6 +63,7 @@ impl<'d> Options<'d> {
 /// [`Client`]: crate::client::Client
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mode {
     /// All acknowledgements associated with a packet identifier of this mode are sent
     /// automatically by the client with [`ReasonCode::Success`]. In case of a
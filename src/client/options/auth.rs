@@ -0,0 +1,58 @@
+use const_fn::const_fn;
+
+use crate::types::{MqttBinary, MqttString, MqttStringPair};
+
+/// Options for a re-authentication.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options<'a> {
+    /// The name of the method used for enhanced authentication. Must be the same as the
+    /// authentication method in the [`ConnectOptions`] the connection was established with.
+    ///
+    /// [`ConnectOptions`]: crate::client::options::ConnectOptions
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub authentication_method: MqttString<'a>,
+
+    /// The initial data of the re-authentication exchange. The contents are defined by the
+    /// authentication method.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub authentication_data: Option<MqttBinary<'a>>,
+
+    /// Arbitrary key-value pairs of strings sent as the user property entries of the AUTH
+    /// packet. Note that this slice's length must be less than [`Client`]'s const generic
+    /// parameter `MAX_USER_PROPERTIES`.
+    ///
+    /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    pub user_properties: &'a [MqttStringPair<'a>],
+}
+
+impl<'a> Options<'a> {
+    /// Creates options with no authentication data and no user properties.
+    #[must_use]
+    pub const fn new(authentication_method: MqttString<'a>) -> Self {
+        Self {
+            authentication_method,
+            authentication_data: None,
+            user_properties: &[],
+        }
+    }
+
+    /// Sets the initial authentication data.
+    #[const_fn(cfg(not(feature = "alloc")))]
+    #[must_use]
+    pub const fn authentication_data(mut self, authentication_data: MqttBinary<'a>) -> Self {
+        self.authentication_data = Some(authentication_data);
+        self
+    }
+    /// Sets the user properties. Note that this slice's length must be less than [`Client`]'s
+    /// const generic parameter `MAX_USER_PROPERTIES`.
+    ///
+    /// [`Client`]: crate::client::Client
+    #[must_use]
+    pub const fn user_properties(mut self, user_properties: &'a [MqttStringPair<'a>]) -> Self {
+        self.user_properties = user_properties;
+        self
+    }
+}
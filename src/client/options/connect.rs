@@ -11,6 +11,7 @@ use crate::{
 /// Clientside options for establishing a connection to a broker.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'c> {
     /// If set to true, a new session is started. If set to false and the server has an existing session,
     /// it is continued.
@@ -28,6 +29,14 @@ pub struct Options<'c> {
     /// [`MaximumPacketSize::Unlimited`], the maximum packet size property is omitted on the network.
     pub maximum_packet_size: MaximumPacketSize,
 
+    /// The highest value of a topic alias the client accepts in PUBLISH packets sent by the server.
+    /// If set to 0, the server must not use topic aliases and the topic alias maximum property is
+    /// omitted on the network. Note that this value must be less than or equal to [`Client`]'s const
+    /// generic parameter `TOPIC_ALIAS_MAXIMUM`.
+    ///
+    /// [`Client`]: crate::client::Client
+    pub topic_alias_maximum: u16,
+
     /// When set to true, the server may return response information used to construct response topics.
     /// If set to false, the server must not return response information and the request response
     /// information property is omitted on the network.
@@ -45,14 +54,31 @@ pub struct Options<'c> {
     /// `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'c [MqttStringPair<'c>],
 
     /// The user name the client wishes to authenticate with.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub user_name: Option<MqttString<'c>>,
     /// The password the client wishes to perform basic authentication with.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub password: Option<MqttBinary<'c>>,
 
+    /// The name of the method used for enhanced authentication. If set, the server may continue the
+    /// authentication exchange with AUTH packets which are answered by the [`Authenticator`] set with
+    /// [`Client::authenticate_with`].
+    ///
+    /// [`Authenticator`]: crate::client::auth::Authenticator
+    /// [`Client::authenticate_with`]: crate::client::Client::authenticate_with
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub authentication_method: Option<MqttString<'c>>,
+    /// The initial data of the enhanced authentication exchange. The contents are defined by the
+    /// authentication method. Only sent if `authentication_method` is set.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub authentication_data: Option<MqttBinary<'c>>,
+
     /// The will configuration for the session of the connection.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub will: Option<WillOptions<'c>>,
 }
 
@@ -74,11 +100,14 @@ impl<'c> Options<'c> {
             keep_alive: KeepAlive::Infinite,
             session_expiry_interval: SessionExpiryInterval::EndOnDisconnect,
             maximum_packet_size: MaximumPacketSize::Unlimited,
+            topic_alias_maximum: 0,
             request_response_information: false,
             request_problem_information: false,
             user_properties: &[],
             user_name: None,
             password: None,
+            authentication_method: None,
+            authentication_data: None,
             will: None,
         }
     }
@@ -107,6 +136,15 @@ impl<'c> Options<'c> {
         self.maximum_packet_size = MaximumPacketSize::Limit(maximum_packet_size);
         self
     }
+    /// Sets the highest value of a topic alias the client accepts. Note that this value must be
+    /// less than or equal to [`Client`]'s const generic parameter `TOPIC_ALIAS_MAXIMUM`.
+    ///
+    /// [`Client`]: crate::client::Client
+    #[must_use]
+    pub const fn topic_alias_maximum(mut self, topic_alias_maximum: u16) -> Self {
+        self.topic_alias_maximum = topic_alias_maximum;
+        self
+    }
     /// Sets the request response information property to true prompting the server to return
     /// a response information property to construct response topics.
     #[must_use]
@@ -145,6 +183,20 @@ impl<'c> Options<'c> {
         self.password = Some(password);
         self
     }
+    /// Sets the authentication method used for enhanced authentication.
+    #[const_fn(cfg(not(feature = "alloc")))]
+    #[must_use]
+    pub const fn authentication_method(mut self, authentication_method: MqttString<'c>) -> Self {
+        self.authentication_method = Some(authentication_method);
+        self
+    }
+    /// Sets the initial authentication data. Only sent if an authentication method is set.
+    #[const_fn(cfg(not(feature = "alloc")))]
+    #[must_use]
+    pub const fn authentication_data(mut self, authentication_data: MqttBinary<'c>) -> Self {
+        self.authentication_data = Some(authentication_data);
+        self
+    }
     /// Sets the will.
     #[const_fn(cfg(not(feature = "alloc")))]
     #[must_use]
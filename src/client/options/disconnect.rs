@@ -8,6 +8,7 @@ use crate::{
 /// Options for a disconnection to the server with a DISCONNECT packet.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'d> {
     /// The [`ReasonCode`] of the DISCONNECT packet.
     ///
@@ -33,6 +34,7 @@ pub struct Options<'d> {
     pub session_expiry_interval: Option<SessionExpiryInterval>,
 
     /// The reason string property of the DISCONNECT packet.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub reason_string: Option<MqttString<'d>>,
 
     /// Arbitrary key-value pairs of strings sent as the user property entries of the DISCONNECT
@@ -40,6 +42,7 @@ pub struct Options<'d> {
     /// `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'d [MqttStringPair<'d>],
 }
 
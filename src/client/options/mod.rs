@@ -1,6 +1,7 @@
 //! Contains user-facing option types for configuring client actions.
 
 mod ack;
+mod auth;
 mod connect;
 mod disconnect;
 mod publish;
@@ -9,9 +10,12 @@ mod unsubscribe;
 mod will;
 
 pub use ack::{Mode as AckMode, Options as AckOptions};
+pub use auth::Options as AuthOptions;
 pub use connect::Options as ConnectOptions;
 pub use disconnect::Options as DisconnectOptions;
 pub use publish::{Options as PublicationOptions, TopicReference};
-pub use subscribe::{Options as SubscriptionOptions, RetainHandling};
+pub use subscribe::{
+    FilterOptions, Options as SubscriptionOptions, RetainHandling, SubscribeOptions,
+};
 pub use unsubscribe::Options as UnsubscriptionOptions;
 pub use will::Options as WillOptions;
@@ -3,13 +3,14 @@ use core::num::NonZero;
 use const_fn::const_fn;
 
 use crate::{
-    client::AckMode,
+    client::options::AckMode,
     types::{MqttBinary, MqttString, MqttStringPair, QoS, TopicName},
 };
 
 /// Options for a publication.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'p> {
     /// In case of [`QoS::ExactlyOnce`], whether the PUBREL acknowledgement
     /// packet of this flow is sent automatically by the client or must be
@@ -54,6 +55,7 @@ pub struct Options<'p> {
     /// if a violation occurs but prevent the protocol error and return an error.
     ///
     /// [`Client::server_config`]: crate::client::Client::server_config
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub topic: TopicReference<'p>,
 
     /// Indicates whether the message is valid UTF-8. If [`None`], there is no statement
@@ -67,19 +69,23 @@ pub struct Options<'p> {
     pub message_expiry_interval: Option<u32>,
 
     /// The topic on which the receiver should publish the response.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub response_topic: Option<TopicName<'p>>,
 
     /// Arbitrary binary data which the receiver should attach in the response to associate
     /// their response with this request.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub correlation_data: Option<MqttBinary<'p>>,
 
     /// Arbitrary key-value pairs of strings. Note that this slice's length must be less than
     /// [`Client`]'s const generic parameter `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'p [MqttStringPair<'p>],
 
     /// The custom content type of the message.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub content_type: Option<MqttString<'p>>,
 }
 
@@ -191,8 +197,10 @@ impl<'p> Options<'p> {
 /// Topic aliases must not be 0
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TopicReference<'t> {
     /// Publish to the inner topic name without creating an alias.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     Name(TopicName<'t>),
 
     /// Publish to an already mapped topic alias. The alias must have been defined earlier
@@ -201,10 +209,14 @@ pub enum TopicReference<'t> {
 
     /// Create a new topic alias or replace an existing topic alias.
     /// The alias lasts until the end of the network connection.
-    Mapping(TopicName<'t>, NonZero<u16>),
+    Mapping(
+        #[cfg_attr(feature = "serde", serde(borrow))] TopicName<'t>,
+        NonZero<u16>,
+    ),
 }
 
 impl<'t> TopicReference<'t> {
+    #[cfg(feature = "v5")]
     pub(crate) fn alias(&self) -> Option<NonZero<u16>> {
         match self {
             Self::Name(_) => None,
@@ -212,6 +224,7 @@ impl<'t> TopicReference<'t> {
             Self::Mapping(_, alias) => Some(*alias),
         }
     }
+    #[cfg(feature = "v5")]
     pub(crate) fn topic_name(&self) -> Option<&TopicName<'t>> {
         match self {
             Self::Name(topic_name) => Some(topic_name),
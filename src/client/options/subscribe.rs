@@ -3,6 +3,7 @@ use crate::types::{MqttStringPair, QoS, VarByteInt};
 /// Options for subscription included for every topic.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'s> {
     /// Server-side retain handling configuration for this subscription.
     pub retain_handling: RetainHandling,
@@ -41,6 +42,7 @@ pub struct Options<'s> {
     /// const generic parameter `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'s [MqttStringPair<'s>],
 }
 
@@ -116,11 +118,205 @@ impl<'s> Options<'s> {
         self.user_properties = user_properties;
         self
     }
+
+    /// Returns the options of the topic filter in the SUBSCRIBE packet.
+    pub(crate) const fn filter_options(&self) -> FilterOptions {
+        FilterOptions {
+            retain_handling: self.retain_handling,
+            retain_as_published: self.retain_as_published,
+            no_local: self.no_local,
+            qos: self.qos,
+        }
+    }
+
+    /// Returns the options of the SUBSCRIBE packet.
+    #[cfg(feature = "v5")]
+    pub(crate) const fn subscribe_options(&self) -> SubscribeOptions<'s> {
+        SubscribeOptions {
+            subscription_identifier: self.subscription_identifier,
+            user_properties: self.user_properties,
+        }
+    }
+
+    /// Combines the options of a topic filter with the subscription identifier of its SUBSCRIBE
+    /// packet.
+    #[cfg(feature = "v5")]
+    pub(crate) const fn from_filter_options(
+        filter_options: FilterOptions,
+        subscription_identifier: Option<VarByteInt>,
+    ) -> Options<'static> {
+        Options {
+            retain_handling: filter_options.retain_handling,
+            retain_as_published: filter_options.retain_as_published,
+            no_local: filter_options.no_local,
+            qos: filter_options.qos,
+            subscription_identifier,
+            user_properties: &[],
+        }
+    }
+}
+
+/// Options for a single topic filter of a SUBSCRIBE packet sent with
+/// [`Client::subscribe_many`].
+///
+/// [`Client::subscribe_many`]: crate::client::Client::subscribe_many
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterOptions {
+    /// Server-side retain handling configuration for this subscription.
+    pub retain_handling: RetainHandling,
+
+    /// Refer to [`Options::retain_as_published`].
+    pub retain_as_published: bool,
+
+    /// Refer to [`Options::no_local`]. Can not be set to true for a shared subscription.
+    pub no_local: bool,
+
+    /// The maximum quality of service that the server is allowed to forward publications
+    /// at matching this subscription with to the client.
+    pub qos: QoS,
+}
+
+impl Default for FilterOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FilterOptions {
+    /// Creates options with values coherent to the [`Default`] implementations of the fields and
+    /// [`QoS::AtMostOnce`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            retain_handling: RetainHandling::AlwaysSend,
+            retain_as_published: false,
+            no_local: false,
+            qos: QoS::AtMostOnce,
+        }
+    }
+
+    /// Sets the Quality of Service level.
+    #[must_use]
+    pub const fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+    /// Sets the Quality of Service level to 1 ([`QoS::AtLeastOnce`]).
+    #[must_use]
+    pub const fn at_least_once(self) -> Self {
+        self.qos(QoS::AtLeastOnce)
+    }
+    /// Sets the Quality of Service level to 2 ([`QoS::ExactlyOnce`]).
+    #[must_use]
+    pub const fn exactly_once(self) -> Self {
+        self.qos(QoS::ExactlyOnce)
+    }
+    /// Sets the server-side retain handling configuration for this subscription.
+    #[must_use]
+    pub const fn retain_handling(mut self, retain_handling: RetainHandling) -> Self {
+        self.retain_handling = retain_handling;
+        self
+    }
+    /// Sets the retain as published flag to true.
+    #[must_use]
+    pub const fn retain_as_published(mut self) -> Self {
+        self.retain_as_published = true;
+        self
+    }
+    /// Sets the no local flag to true.
+    #[must_use]
+    pub const fn no_local(mut self) -> Self {
+        self.no_local = true;
+        self
+    }
+
+    /// Returns the subscription options byte following the topic filter in a SUBSCRIBE packet.
+    #[cfg(feature = "v5")]
+    pub(crate) const fn into_bits(self) -> u8 {
+        let retain_handling_bits = match self.retain_handling {
+            RetainHandling::AlwaysSend => 0x00,
+            RetainHandling::SendIfNotSubscribedBefore => 0x10,
+            RetainHandling::NeverSend => 0x20,
+        };
+
+        let retain_as_published_bit = match self.retain_as_published {
+            true => 0x08,
+            false => 0x00,
+        };
+
+        let no_local_bit = match self.no_local {
+            true => 0x04,
+            false => 0x00,
+        };
+
+        let qos_bits = self.qos.into_bits(0);
+
+        retain_handling_bits | retain_as_published_bit | no_local_bit | qos_bits
+    }
+}
+
+/// Options for a SUBSCRIBE packet sent with [`Client::subscribe_many`] which apply to all of its
+/// topic filters.
+///
+/// [`Client::subscribe_many`]: crate::client::Client::subscribe_many
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubscribeOptions<'s> {
+    /// The subscription identifier of all subscriptions in the SUBSCRIBE packet. Refer to
+    /// [`Options::subscription_identifier`].
+    pub subscription_identifier: Option<VarByteInt>,
+
+    /// Arbitrary key-value pairs of strings sent as the user property entries of the
+    /// SUBSCRIBE packet. Note that this slice's length must be less than [`Client`]'s
+    /// const generic parameter `MAX_USER_PROPERTIES`.
+    ///
+    /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    pub user_properties: &'s [MqttStringPair<'s>],
+}
+
+impl Default for SubscribeOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'s> SubscribeOptions<'s> {
+    /// Creates options without subscription identifier and user properties.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            subscription_identifier: None,
+            user_properties: &[],
+        }
+    }
+
+    /// Sets the subscription identifier property.
+    ///
+    /// Note that this is only allowed if the server supports subscription identifiers.
+    #[must_use]
+    pub const fn subscription_identifier(mut self, subscription_identifier: VarByteInt) -> Self {
+        self.subscription_identifier = Some(subscription_identifier);
+        self
+    }
+    /// Sets the user properties. Note that this slice's length must be less than [`Client`]'s
+    /// const generic parameter `MAX_USER_PROPERTIES`.
+    ///
+    /// [`Client`]: crate::client::Client
+    #[must_use]
+    pub const fn user_properties(mut self, user_properties: &'s [MqttStringPair<'s>]) -> Self {
+        self.user_properties = user_properties;
+        self
+    }
 }
 
 /// Server-side retain handling configuration for a subscription.
 #[derive(Debug, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RetainHandling {
     /// Retained messages are always sent at the time of the subscribe.
     #[default]
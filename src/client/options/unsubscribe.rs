@@ -3,12 +3,14 @@ use crate::types::MqttStringPair;
 /// Options for unsubscription included for every topic.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'s> {
     /// Arbitrary key-value pairs of strings sent as the user property entries of the
     /// UNSUBSCRIBE packet. Note that this slice's length must be less than [`Client`]'s
     /// const generic parameter `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'s [MqttStringPair<'s>],
 }
 
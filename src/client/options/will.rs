@@ -1,9 +1,8 @@
 use const_fn::const_fn;
 
-use crate::{
-    types::{MqttBinary, MqttString, MqttStringPair, QoS, TopicName, Will},
-    v5::property::WillDelayInterval,
-};
+use crate::types::{MqttBinary, MqttString, MqttStringPair, QoS, TopicName};
+#[cfg(feature = "v5")]
+use crate::{types::Will, v5::property::WillDelayInterval};
 
 /// Options for configuring the client's will or last will in a session.
 /// The server can publish a single PUBLISH packet in place of the client.
@@ -13,6 +12,7 @@ use crate::{
 /// - The will delay interval passes.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options<'c> {
     /// The quality of service that the server publishes the will message with in place of the client.
     pub will_qos: QoS,
@@ -21,6 +21,7 @@ pub struct Options<'c> {
     pub will_retain: bool,
 
     /// The topic of the will publication.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub will_topic: TopicName<'c>,
 
     // Will properties starting here
@@ -41,23 +42,28 @@ pub struct Options<'c> {
 
     /// The content type property in the will publication. If set to [`None`], the property is omitted
     /// on the network.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub content_type: Option<MqttString<'c>>,
 
     /// The response topic property in the will publication. If set to [`None`], the property is omitted
     /// on the network.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub response_topic: Option<TopicName<'c>>,
 
     /// The correlation data property in the will publication. If set to [`None`], the property is omitted
     /// on the network.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub correlation_data: Option<MqttBinary<'c>>,
 
     /// The user properties in the will publication. Note that this slice's length must be less than
     /// [`Client`]'s const generic parameter `MAX_USER_PROPERTIES`.
     ///
     /// [`Client`]: crate::client::Client
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
     pub user_properties: &'c [MqttStringPair<'c>],
 
     /// The payload of the will publication.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub will_message: MqttBinary<'c>,
 }
 
@@ -154,6 +160,7 @@ impl<'c> Options<'c> {
     }
 }
 
+#[cfg(feature = "v5")]
 impl<'c> Options<'c> {
     pub(crate) fn as_borrowed_will<const MAX_USER_PROPERTIES: usize>(
         &'c self,
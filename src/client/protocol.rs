@@ -0,0 +1,2435 @@
+//! Implements the MQTT protocol without any I/O of its own. The [`Protocol`] builds the packets
+//! the client sends and turns the packets it receives into [`Event`]s and the packets responding
+//! to them. The async [`Client`](super::Client) and the sans-I/O
+//! [`Client`](super::sans_io::Client) only move these packets over their connection.
+
+use core::{matches, mem, num::NonZero, time::Duration};
+
+use heapless::Vec;
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError,
+        alias::{Alias, TopicAliasManager, TopicAliases},
+        auth::{Authenticator, MAX_AUTHENTICATION_METHOD_LEN, State as AuthState},
+        codec::EncodePayload,
+        event::{Auth, Connected, Disconnect, Event, Puback, Publish, Pubrej, Suback},
+        options::{
+            AckMode, AckOptions, AuthOptions, ConnectOptions, DisconnectOptions, FilterOptions,
+            PublicationOptions, SubscribeOptions, TopicReference, UnsubscriptionOptions,
+        },
+        queue::{PublishQueue, QueuedPublication},
+        raw::Raw,
+        registry::SubscriptionRegistry,
+        store::{MessageStore, StoredPublishPacket},
+    },
+    config::{ClientConfig, KeepAlive, ServerConfig, SessionExpiryInterval, SharedConfig},
+    eio::Read,
+    fmt::{assert, const_assert, debug, error, info, trace, unreachable, warn},
+    header::{FixedHeader, PacketType},
+    io::{Transport, read::BodyReader},
+    packet::{RxError, RxPacket, TxPacket, encode},
+    session::{Error as SmError, Event as SmEvent, LocalPublishState, Response, Session},
+    time::Instant,
+    types::{
+        IdentifiedQoS, MqttBinary, MqttString, MqttStringPair, PacketIdentifier, QoS, ReasonCode,
+        TopicFilter, TopicName,
+    },
+    v5::{
+        packet::{
+            AuthPacket, ConnackPacket, ConnectPacket, DisconnectPacket, PingrespPacket,
+            PubackPacket, PubcompPacket, PublishPacket, PubrecPacket, PubrelPacket, SubackPacket,
+            SubscribePacket, UnsubackPacket, UnsubscribePacket,
+        },
+        property::{AuthenticationMethod, Property},
+    },
+};
+
+/// The network connection of a client as far as the [`Protocol`] is concerned.
+pub(crate) trait Link<'c> {
+    type Buffer: BufferProvider<'c>;
+
+    /// Returns whether the connection is open and has not failed.
+    fn is_connected(&self) -> bool;
+
+    /// Closes the connection. With a reason code, the connection is only marked as failed so that
+    /// a DISCONNECT packet with this reason code can still be sent.
+    fn close_with(&mut self, reason_code: Option<ReasonCode>);
+
+    /// Returns the current time if a clock has been set.
+    fn now(&self) -> Option<Instant>;
+
+    /// Returns the point in time until which the PINGRESP packet must be received if a PINGREQ
+    /// packet is pending, or otherwise until which the next packet must be sent.
+    fn keep_alive_deadline(&self, interval: Duration) -> Option<Instant>;
+
+    /// Returns whether a PINGREQ packet has not been answered yet.
+    fn ping_pending(&self) -> bool;
+
+    /// Returns the buffer provider storing the dynamically sized fields of received packets.
+    fn buffer_mut(&mut self) -> &mut Self::Buffer;
+}
+
+impl<'c, N: Transport, B: BufferProvider<'c>> Link<'c> for Raw<'c, N, B> {
+    type Buffer = B;
+
+    fn is_connected(&self) -> bool {
+        Raw::is_connected(self)
+    }
+
+    fn close_with(&mut self, reason_code: Option<ReasonCode>) {
+        Raw::close_with(self, reason_code);
+    }
+
+    fn now(&self) -> Option<Instant> {
+        Raw::now(self)
+    }
+
+    fn keep_alive_deadline(&self, interval: Duration) -> Option<Instant> {
+        Raw::keep_alive_deadline(self, interval)
+    }
+
+    fn ping_pending(&self) -> bool {
+        Raw::ping_pending(self)
+    }
+
+    fn buffer_mut(&mut self) -> &mut B {
+        Raw::buffer_mut(self)
+    }
+}
+
+/// A packet the server sends once the connection has been established.
+pub(crate) enum Incoming<
+    'p,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> {
+    Pingresp,
+    Suback(SubackPacket<'p, MAX_USER_PROPERTIES>),
+    Unsuback(UnsubackPacket<'p, MAX_USER_PROPERTIES>),
+    Publish(PublishPacket<'p, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>),
+    Puback(PubackPacket<'p, MAX_USER_PROPERTIES>),
+    Pubrec(PubrecPacket<'p, MAX_USER_PROPERTIES>),
+    Pubrel(PubrelPacket<'p, MAX_USER_PROPERTIES>),
+    Pubcomp(PubcompPacket<'p, MAX_USER_PROPERTIES>),
+    Disconnect(DisconnectPacket<'p, MAX_USER_PROPERTIES>),
+    Auth(AuthPacket<'p, MAX_USER_PROPERTIES>),
+}
+
+impl<'p, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize> RxPacket<'p>
+    for Incoming<'p, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        match header.packet_type() {
+            Ok(PacketType::Pingresp) => PingrespPacket::receive(header, reader)
+                .await
+                .map(|_| Self::Pingresp),
+            Ok(PacketType::Suback) => SubackPacket::receive(header, reader)
+                .await
+                .map(Self::Suback),
+            Ok(PacketType::Unsuback) => UnsubackPacket::receive(header, reader)
+                .await
+                .map(Self::Unsuback),
+            Ok(PacketType::Publish) => PublishPacket::receive(header, reader)
+                .await
+                .map(Self::Publish),
+            Ok(PacketType::Puback) => PubackPacket::receive(header, reader)
+                .await
+                .map(Self::Puback),
+            Ok(PacketType::Pubrec) => PubrecPacket::receive(header, reader)
+                .await
+                .map(Self::Pubrec),
+            Ok(PacketType::Pubrel) => PubrelPacket::receive(header, reader)
+                .await
+                .map(Self::Pubrel),
+            Ok(PacketType::Pubcomp) => PubcompPacket::receive(header, reader)
+                .await
+                .map(Self::Pubcomp),
+            Ok(PacketType::Disconnect) => DisconnectPacket::receive(header, reader)
+                .await
+                .map(Self::Disconnect),
+            Ok(PacketType::Auth) => AuthPacket::receive(header, reader).await.map(Self::Auth),
+            _ => Err(RxError::ProtocolError),
+        }
+    }
+}
+
+/// A packet the client sends in response to a received packet or resends after a reconnection.
+pub(crate) enum Outgoing<'a> {
+    Puback(PubackPacket<'a, 0>),
+    Pubrec(PubrecPacket<'a, 0>),
+    Pubrel(PubrelPacket<'a, 0>),
+    Pubcomp(PubcompPacket<'a, 0>),
+    Auth(AuthPacket<'a, 0>),
+    Stored(StoredPublishPacket<'a>),
+}
+
+/// What happens to the record at the front of a [`PublishQueue`].
+pub(crate) enum Dequeued<'r, const MAX_USER_PROPERTIES: usize> {
+    /// The record leaves the queue without being published.
+    Drop,
+    /// The record stays in the queue until session state is free.
+    Wait,
+    /// The record is published and leaves the queue depending on [`leaves_queue`].
+    Publish(QueuedPublication<'r, MAX_USER_PROPERTIES>),
+}
+
+/// The state of an MQTT client apart from its network connection.
+pub(crate) struct Protocol<
+    'c,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    pub client_config: ClientConfig,
+    pub shared_config: SharedConfig,
+    pub server_config: ServerConfig,
+    pub session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+
+    pub manual_ack_when:
+        &'c dyn Fn(&Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>) -> bool,
+
+    pub authenticator: Option<&'c mut dyn Authenticator>,
+    pub auth_state: AuthState,
+    pub authentication_method: heapless::String<MAX_AUTHENTICATION_METHOD_LEN>,
+
+    pub topic_aliases: TopicAliases<TOPIC_ALIAS_MAXIMUM, MAX_ALIASED_TOPIC_LEN>,
+    pub topic_alias_manager: Option<&'c mut dyn TopicAliasManager>,
+
+    pub message_store: Option<&'c mut dyn MessageStore>,
+    pub publish_queue: Option<&'c mut dyn PublishQueue>,
+    /// Whether the publication at the front of the [`PublishQueue`] has been published by a drain
+    /// that was cancelled before the record left the queue.
+    pub queue_front_published: bool,
+    pub subscription_registry: Option<&'c mut dyn SubscriptionRegistry>,
+    pub payload_buffer: Option<&'c mut [u8]>,
+}
+
+impl<
+    'c,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Protocol<
+        'c,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates the state of a new, disconnected client with a new session.
+    pub fn new() -> Self {
+        const {
+            const_assert!(
+                SUBSCRIBE_MAXIMUM <= 65535,
+                "SUBSCRIBE_MAXIMUM must be less than or equal to 65535"
+            );
+            const_assert!(
+                RECEIVE_MAXIMUM <= 65535,
+                "RECEIVE_MAXIMUM must be less than or equal to 65535"
+            );
+            const_assert!(
+                RECEIVE_MAXIMUM > 0,
+                "RECEIVE_MAXIMUM must be greater than 0"
+            );
+            const_assert!(
+                MAX_USER_PROPERTIES <= 1021,
+                "MAX_USER_PROPERTIES must be less than or equal to 1021"
+            );
+            const_assert!(
+                TOPIC_ALIAS_MAXIMUM <= 65535,
+                "TOPIC_ALIAS_MAXIMUM must be less than or equal to 65535"
+            );
+            const_assert!(
+                TOPIC_ALIAS_MAXIMUM == 0 || MAX_ALIASED_TOPIC_LEN > 0,
+                "MAX_ALIASED_TOPIC_LEN must be greater than 0 if TOPIC_ALIAS_MAXIMUM is greater than 0"
+            );
+        }
+
+        Self {
+            client_config: ClientConfig::default(),
+            shared_config: SharedConfig::default(),
+            server_config: ServerConfig::default(),
+            session: Session::default(),
+
+            manual_ack_when: &|_| false,
+
+            authenticator: None,
+            auth_state: AuthState::Disabled,
+            authentication_method: heapless::String::new(),
+            topic_aliases: TopicAliases::new(),
+            topic_alias_manager: None,
+
+            message_store: None,
+            publish_queue: None,
+            queue_front_published: false,
+            subscription_registry: None,
+            payload_buffer: None,
+        }
+    }
+
+    /// Returns the amount of publications the client is allowed to make according to the server's
+    /// receive maximum. Does not account local space for storing publication state.
+    fn remaining_send_quota(&self) -> u16 {
+        self.server_config.receive_maximum.get() - self.session.active_outbound_publishes()
+    }
+
+    /// Prepares a new network connection and builds the CONNECT packet starting it.
+    pub fn connect<'p>(
+        &mut self,
+        options: &'p ConnectOptions<'_>,
+        client_identifier: Option<&'p MqttString<'_>>,
+    ) -> ConnectPacket<'p, MAX_USER_PROPERTIES> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send CONNECT with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+        if let Some(ref will) = options.will {
+            assert!(
+                will.user_properties.len() <= MAX_USER_PROPERTIES,
+                "attempted to send Will with {} > {} (MAX_USER_PROPERTIES) properties",
+                will.user_properties.len(),
+                MAX_USER_PROPERTIES
+            );
+        }
+
+        assert!(
+            options.topic_alias_maximum as usize <= TOPIC_ALIAS_MAXIMUM,
+            "attempted to send CONNECT with topic alias maximum {} > {} (TOPIC_ALIAS_MAXIMUM)",
+            options.topic_alias_maximum,
+            TOPIC_ALIAS_MAXIMUM
+        );
+
+        if let Some(ref authentication_method) = options.authentication_method {
+            assert!(
+                authentication_method.len() as usize <= MAX_AUTHENTICATION_METHOD_LEN,
+                "attempted to send CONNECT with an authentication method of {} > {} (MAX_AUTHENTICATION_METHOD_LEN) bytes",
+                authentication_method.len(),
+                MAX_AUTHENTICATION_METHOD_LEN
+            );
+        }
+
+        // A previous connection's authentication does not carry over to this connection.
+        self.auth_state = AuthState::Disabled;
+        self.authentication_method.clear();
+        if let Some(ref authentication_method) = options.authentication_method {
+            // Cannot fail as the length has been checked above.
+            let _ = self
+                .authentication_method
+                .push_str(authentication_method.as_str());
+        }
+
+        // Topic alias mappings are scoped to a single network connection.
+        self.client_config.topic_alias_maximum = options.topic_alias_maximum;
+        self.topic_aliases.clear();
+
+        // Set client session expiry interval because it is relevant to determine
+        // which session expiry interval can be sent in DISCONNECT packet.
+        self.client_config.session_expiry_interval = options.session_expiry_interval;
+
+        // Set request problem information because it is required to detect protocol
+        // errors when server sends a reason string or user properties in any packet
+        // other than PUBLISH, CONNACK, or DISCONNECT
+        self.client_config.request_problem_information = options.request_problem_information;
+
+        self.client_config.maximum_accepted_remaining_length =
+            options.maximum_packet_size.max_remaining_len();
+
+        trace!(
+            "maximum accepted remaining length set to {:?}",
+            self.client_config.maximum_accepted_remaining_length
+        );
+
+        let packet_client_identifier = client_identifier
+            .map(MqttString::as_borrowed)
+            .unwrap_or_default();
+
+        let mut packet = ConnectPacket::<MAX_USER_PROPERTIES>::new(
+            packet_client_identifier,
+            options.clean_start,
+            options.keep_alive,
+            options.maximum_packet_size,
+            options.session_expiry_interval,
+            // Safety: `Self::new` panics if `RECEIVE_MAXIMUM` is 0. Thus, this
+            // code is only reached when `RECEIVE_MAXIMUM` is greater than 0.
+            unsafe { NonZero::new_unchecked(RECEIVE_MAXIMUM as u16) },
+            options.request_response_information,
+            options.request_problem_information,
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        packet.add_topic_alias_maximum(options.topic_alias_maximum);
+        if let Some(ref user_name) = options.user_name {
+            packet.add_user_name(user_name.as_borrowed());
+        }
+        if let Some(ref password) = options.password {
+            packet.add_password(password.as_borrowed());
+        }
+        if let Some(ref authentication_method) = options.authentication_method {
+            packet.add_authentication(
+                authentication_method.as_borrowed(),
+                options
+                    .authentication_data
+                    .as_ref()
+                    .map(MqttBinary::as_borrowed),
+            );
+        }
+
+        if let Some(ref will) = options.will {
+            let will_qos = will.will_qos;
+            let will_retain = will.will_retain;
+
+            packet.add_will(will.as_borrowed_will(), will_qos, will_retain);
+        }
+
+        debug!("sending CONNECT packet");
+
+        packet
+    }
+
+    /// Checks a fixed header received while awaiting the CONNACK packet.
+    ///
+    /// # Returns:
+    /// [`PacketType::Connack`] or [`PacketType::Auth`].
+    pub fn handshake_header(
+        &self,
+        link: &mut impl Link<'c>,
+        header: &FixedHeader,
+        options: &ConnectOptions<'_>,
+    ) -> Result<PacketType, MqttError<'c, MAX_USER_PROPERTIES>> {
+        match header.packet_type() {
+            Ok(PacketType::Connack) => {
+                debug!(
+                    "received CONNACK packet header (remaining length: {})",
+                    header.remaining_len.value()
+                );
+                Ok(PacketType::Connack)
+            }
+            Ok(PacketType::Auth) => {
+                debug!(
+                    "received AUTH packet header (remaining length: {})",
+                    header.remaining_len.value()
+                );
+
+                // Protocol error according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901217>
+                if options.authentication_method.is_none() {
+                    error!("received AUTH packet without having sent an authentication method");
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    return Err(MqttError::Server);
+                }
+
+                Ok(PacketType::Auth)
+            }
+            Ok(t) => {
+                error!("received unexpected {:?} packet header", t);
+
+                link.close_with(Some(ReasonCode::ProtocolError));
+                Err(MqttError::Server)
+            }
+            Err(_) => {
+                error!("received invalid header {:?}", header);
+                link.close_with(Some(ReasonCode::MalformedPacket));
+                Err(MqttError::Server)
+            }
+        }
+    }
+
+    /// Processes an AUTH packet received before the CONNACK packet and builds the AUTH packet
+    /// answering it.
+    pub fn handshake_auth<'a>(
+        &'a mut self,
+        link: &mut impl Link<'c>,
+        auth: AuthPacket<'_, MAX_USER_PROPERTIES>,
+        options: &'a ConnectOptions<'_>,
+    ) -> Result<AuthPacket<'a, 0>, MqttError<'c, 0>> {
+        self.check_problem_information(
+            link,
+            auth.reason_string.is_some() || !auth.user_properties.is_empty(),
+        )?;
+
+        if auth.reason_code != ReasonCode::ContinueAuthentication {
+            error!(
+                "received AUTH packet with unexpected reason code {:?}",
+                auth.reason_code
+            );
+            link.close_with(Some(ReasonCode::ProtocolError));
+            return Err(MqttError::Server);
+        }
+
+        let Some(authentication_method) = options.authentication_method.as_ref().filter(
+            |m| matches!(auth.authentication_method, Some(AuthenticationMethod(ref a)) if a == *m),
+        ) else {
+            error!("server sent an authentication method other than the one in CONNECT");
+            link.close_with(Some(ReasonCode::ProtocolError));
+            return Err(MqttError::Server);
+        };
+
+        let data = auth.authentication_data.as_ref().map(|d| d.0.as_bytes());
+
+        self.continue_authentication(link, authentication_method.as_borrowed(), data)
+    }
+
+    /// Processes the CONNACK packet completing a connection.
+    pub fn connack<'d>(
+        &mut self,
+        link: &mut impl Link<'c>,
+        connack: ConnackPacket<'c, MAX_USER_PROPERTIES>,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Result<Connected<'d, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>>
+    where
+        'c: 'd,
+    {
+        let ConnackPacket::<MAX_USER_PROPERTIES> {
+            session_present,
+            reason_code,
+            session_expiry_interval,
+            receive_maximum,
+            maximum_qos,
+            retain_available,
+            maximum_packet_size,
+            assigned_client_identifier,
+            topic_alias_maximum,
+            reason_string,
+            user_properties,
+            wildcard_subscription_available,
+            subscription_identifier_available,
+            shared_subscription_available,
+            server_keep_alive,
+            response_information,
+            server_reference,
+            authentication_method,
+            authentication_data,
+        } = connack;
+
+        if !options.request_response_information && response_information.is_some() {
+            error!("server sent response information when request response information was false");
+            link.close_with(Some(ReasonCode::ProtocolError));
+            return Err(MqttError::Server);
+        }
+
+        if reason_code.is_success() {
+            debug!("CONNACK packet indicates success");
+
+            if authentication_method.is_some_and(|AuthenticationMethod(m)| {
+                options.authentication_method.as_ref() != Some(&m)
+            }) {
+                error!("server sent an authentication method other than the one in CONNECT");
+                link.close_with(Some(ReasonCode::ProtocolError));
+                return Err(MqttError::Server);
+            }
+
+            if options.authentication_method.is_some() {
+                let data = authentication_data.as_ref().map(|d| d.0.as_bytes());
+
+                self.complete_authentication(link, data)
+                    .map_err(MqttError::inflate)?;
+                self.auth_state = AuthState::Authenticated;
+            }
+
+            let client_identifier = assigned_client_identifier
+                .map(Property::into_inner)
+                .or(client_identifier)
+                .ok_or_else(|| {
+                    error!("server did not assign a client identifier when it was required.");
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    MqttError::Server
+                })?;
+
+            if session_present {
+                if options.clean_start {
+                    error!("server set the session present flag when clean start was set");
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    return Err(MqttError::Server);
+                } else {
+                    info!("connected to server and reconnected to session");
+                    self.session.reconnect();
+                    if let Some(registry) = self.subscription_registry.as_deref_mut() {
+                        registry.reconnect();
+                    }
+                }
+            } else {
+                #[allow(clippy::if_same_then_else)]
+                if options.clean_start {
+                    info!("connected to server");
+                } else {
+                    info!(
+                        "connected to server but server does not have the requested session present"
+                    );
+                }
+                self.session.clear();
+                if let Some(store) = self.message_store.as_deref_mut() {
+                    store.clear();
+                }
+                if let Some(registry) = self.subscription_registry.as_deref_mut() {
+                    registry.reset();
+                }
+            }
+
+            self.shared_config.session_expiry_interval =
+                session_expiry_interval.unwrap_or(options.session_expiry_interval);
+            self.shared_config.keep_alive =
+                server_keep_alive.map_or(options.keep_alive, Property::into_inner);
+
+            if let Some(r) = receive_maximum {
+                self.server_config.receive_maximum = r.into_inner();
+            }
+            if let Some(m) = maximum_qos {
+                self.server_config.maximum_qos = m.into_inner();
+            }
+            if let Some(r) = retain_available {
+                self.server_config.retain_supported = r.into_inner();
+            }
+            if let Some(m) = maximum_packet_size {
+                self.server_config.maximum_packet_size = m;
+            }
+            // Unlike the other values, the topic alias maximum has to be reset as the server
+            // does not support topic aliases if it omits the property.
+            self.server_config.topic_alias_maximum =
+                topic_alias_maximum.map_or(0, Property::into_inner);
+            if let Some(w) = wildcard_subscription_available {
+                self.server_config.wildcard_subscription_supported = w.into_inner();
+            }
+            if let Some(s) = subscription_identifier_available {
+                self.server_config.subscription_identifiers_supported = s.into_inner();
+            }
+            if let Some(s) = shared_subscription_available {
+                self.server_config.shared_subscription_supported = s.into_inner();
+            }
+
+            if let Some(manager) = self.topic_alias_manager.as_deref_mut() {
+                manager.reset(self.server_config.topic_alias_maximum);
+            }
+
+            Ok(Connected {
+                session_present,
+                client_identifier,
+                user_properties: user_properties
+                    .into_iter()
+                    .map(Property::into_inner)
+                    .collect(),
+                response_information: response_information.map(Property::into_inner),
+                server_reference: server_reference.map(Property::into_inner),
+            })
+        } else {
+            debug!("CONNACK packet indicates rejection");
+            info!("connection rejected by server (reason: {:?})", reason_code);
+
+            link.close_with(None);
+
+            info!("disconnected from server");
+
+            Err(MqttError::Disconnect {
+                reason: reason_code,
+                reason_string: reason_string.map(Property::into_inner),
+                user_properties: user_properties
+                    .into_iter()
+                    .map(Property::into_inner)
+                    .collect(),
+                server_reference: server_reference.map(Property::into_inner),
+            })
+        }
+    }
+
+    /// Closes the connection with a protocol error if the server sent a reason string or user
+    /// properties in a packet other than PUBLISH, CONNACK or DISCONNECT although the client has
+    /// not requested problem information.
+    fn check_problem_information(
+        &self,
+        link: &mut impl Link<'c>,
+        sent: bool,
+    ) -> Result<(), MqttError<'c, 0>> {
+        if !self.client_config.request_problem_information && sent {
+            error!(
+                "server sent reason string or user properties when request problem information was false"
+            );
+            link.close_with(Some(ReasonCode::ProtocolError));
+            return Err(MqttError::Server);
+        }
+
+        Ok(())
+    }
+
+    /// Builds the AUTH packet with [`ReasonCode::ContinueAuthentication`] answering an AUTH packet
+    /// with the authentication data computed by the [`Authenticator`].
+    fn continue_authentication<'a>(
+        &'a mut self,
+        link: &mut impl Link<'c>,
+        authentication_method: MqttString<'a>,
+        data: Option<&[u8]>,
+    ) -> Result<AuthPacket<'a, 0>, MqttError<'c, 0>> {
+        let Some(authenticator) = self.authenticator.as_deref_mut() else {
+            error!("received AUTH packet but no authenticator is set");
+            link.close_with(Some(ReasonCode::ImplementationSpecificError));
+            return Err(MqttError::AuthPacketReceived);
+        };
+
+        let response_data = match authenticator.continue_authentication(data) {
+            Ok(d) => d,
+            Err(r) => {
+                error!(
+                    "authenticator rejected the authentication exchange (reason: {:?})",
+                    r
+                );
+                link.close_with(Some(r));
+                return Err(MqttError::Authentication);
+            }
+        };
+
+        let packet = AuthPacket::<0>::new(
+            ReasonCode::ContinueAuthentication,
+            authentication_method.into(),
+            response_data.map(Into::into),
+            Vec::new(),
+        );
+
+        debug!("sending AUTH packet");
+
+        Ok(packet)
+    }
+
+    /// Passes the authentication data of the packet completing an authentication exchange to the
+    /// [`Authenticator`] if one is set.
+    fn complete_authentication(
+        &mut self,
+        link: &mut impl Link<'c>,
+        data: Option<&[u8]>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        if let Some(authenticator) = self.authenticator.as_deref_mut() {
+            if let Err(r) = authenticator.complete_authentication(data) {
+                error!(
+                    "authenticator rejected the completed authentication (reason: {:?})",
+                    r
+                );
+                link.close_with(Some(r));
+                return Err(MqttError::Authentication);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the AUTH packet starting a re-authentication.
+    pub fn reauthenticate<'p>(
+        &mut self,
+        options: &'p AuthOptions<'_>,
+    ) -> Result<AuthPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send AUTH with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        if self.auth_state != AuthState::Authenticated {
+            return Err(MqttError::IllegalReauthentication);
+        }
+
+        // The authentication method must not change during a network connection.
+        // <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901257>
+        if options.authentication_method.as_str() != self.authentication_method.as_str() {
+            return Err(MqttError::IllegalReauthentication);
+        }
+
+        let packet = AuthPacket::<MAX_USER_PROPERTIES>::new(
+            ReasonCode::ReAuthenticate,
+            options.authentication_method.as_borrowed().into(),
+            options
+                .authentication_data
+                .as_ref()
+                .map(MqttBinary::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        // If sending fails, the connection is closed, which ends the re-authentication as well.
+        self.auth_state = AuthState::Reauthenticating;
+
+        debug!("sending AUTH packet");
+
+        Ok(packet)
+    }
+
+    /// Returns the point in time at which the keep alive has to be checked next.
+    pub fn next_ping_deadline(&self, link: &impl Link<'c>) -> Option<Instant> {
+        match self.shared_config.keep_alive {
+            KeepAlive::Infinite => None,
+            KeepAlive::Seconds(s) => link.keep_alive_deadline(Duration::from_secs(s.get().into())),
+        }
+    }
+
+    /// Checks the keep alive and returns whether a PINGREQ packet has to be sent.
+    pub fn check_keep_alive(&self, link: &mut impl Link<'c>) -> Result<bool, MqttError<'c, 0>> {
+        let (Some(deadline), Some(now)) = (self.next_ping_deadline(link), link.now()) else {
+            return Ok(false);
+        };
+
+        if now < deadline {
+            return Ok(false);
+        }
+
+        if link.ping_pending() {
+            error!("no PINGRESP packet received within the keep alive interval");
+            link.close_with(None);
+            return Err(MqttError::KeepAliveTimeout);
+        }
+
+        Ok(true)
+    }
+
+    /// Tracks a subscription and builds its SUBSCRIBE packet.
+    pub fn subscribe_many<'p>(
+        &mut self,
+        subscriptions: &'p [(TopicFilter<'p>, FilterOptions)],
+        options: &'p SubscribeOptions<'_>,
+    ) -> Result<(PacketIdentifier, SubscribePacket<'p, MAX_USER_PROPERTIES>), MqttError<'c, 0>>
+    {
+        assert!(
+            !subscriptions.is_empty(),
+            "attempted to send SUBSCRIBE without topic filters"
+        );
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send SUBSCRIBE with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        if !self.server_config.subscription_identifiers_supported
+            && options.subscription_identifier.is_some()
+        {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        for (topic_filter, filter_options) in subscriptions {
+            if !self.server_config.wildcard_subscription_supported && topic_filter.has_wildcard() {
+                return Err(MqttError::UnsupportedByServer);
+            }
+
+            if !self.server_config.shared_subscription_supported && topic_filter.is_shared() {
+                return Err(MqttError::UnsupportedByServer);
+            }
+
+            // It is a Protocol Error to set the No Local bit to 1 on a Shared Subscription [MQTT-3.8.3-4].
+            if filter_options.no_local && topic_filter.is_shared() {
+                return Err(MqttError::IllegalNoLocalSharedSubscription);
+            }
+        }
+
+        let Some(handle) = self.session.free_handle() else {
+            info!("no free packet identifier");
+            return Err(MqttError::AllPacketIdentifiersUsed);
+        };
+        let pid = handle.packet_identifier;
+
+        let packet = SubscribePacket::<MAX_USER_PROPERTIES>::new(
+            pid,
+            options.subscription_identifier.map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+            subscriptions,
+        )
+        .map_err(|_| MqttError::PacketMaximumLengthExceeded)?;
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        let registry = self.subscription_registry.as_deref_mut();
+        let recorded = registry.as_ref().map_or(0, |r| r.len());
+
+        if let Some(registry) = registry {
+            if !registry.add(subscriptions, options.subscription_identifier) {
+                info!("subscription registry is full");
+                return Err(MqttError::SessionBuffer);
+            }
+
+            if handle.outbound_sub(subscriptions.len()).is_err() {
+                registry.truncate(recorded);
+                info!("maximum concurrent subscriptions reached");
+                return Err(MqttError::SessionBuffer);
+            }
+
+            registry.subscribing(subscriptions, options.subscription_identifier, pid);
+        } else {
+            handle.outbound_sub(subscriptions.len()).map_err(|_| {
+                info!("maximum concurrent subscriptions reached");
+                MqttError::SessionBuffer
+            })?;
+        }
+
+        debug!("sending SUBSCRIBE packet");
+
+        Ok((pid, packet))
+    }
+
+    /// Tracks an unsubscription and builds its UNSUBSCRIBE packet.
+    pub fn unsubscribe_many<'p>(
+        &mut self,
+        topic_filters: &'p [TopicFilter<'p>],
+        options: &'p UnsubscriptionOptions<'_>,
+    ) -> Result<(PacketIdentifier, UnsubscribePacket<'p, MAX_USER_PROPERTIES>), MqttError<'c, 0>>
+    {
+        assert!(
+            !topic_filters.is_empty(),
+            "attempted to send UNSUBSCRIBE without topic filters"
+        );
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send UNSUBSCRIBE with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        let Some(handle) = self.session.free_handle() else {
+            info!("no free packet identifier");
+            return Err(MqttError::AllPacketIdentifiersUsed);
+        };
+        let pid = handle.packet_identifier;
+
+        let packet = UnsubscribePacket::<MAX_USER_PROPERTIES>::new(
+            pid,
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+            topic_filters,
+        )
+        .map_err(|_| MqttError::PacketMaximumLengthExceeded)?;
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        handle.outbound_unsub(topic_filters.len()).map_err(|_| {
+            info!("maximum concurrent unsubscriptions reached");
+            MqttError::SessionBuffer
+        })?;
+
+        if let Some(registry) = self.subscription_registry.as_deref_mut() {
+            registry.unsubscribing(topic_filters, pid);
+        }
+
+        debug!("sending UNSUBSCRIBE packet");
+
+        Ok((pid, packet))
+    }
+
+    /// Tracks a publication and builds its PUBLISH packet.
+    ///
+    /// # Returns:
+    /// [`None`] if the publication has been pushed into the [`PublishQueue`].
+    pub fn publish<'p>(
+        &mut self,
+        link: &impl Link<'c>,
+        options: &'p PublicationOptions<'_>,
+        message: Bytes<'p>,
+    ) -> Result<Option<PublishPacket<'p, 0, MAX_USER_PROPERTIES>>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to publish {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+        assert!(
+            self.topic_alias_manager.is_none() || matches!(options.topic, TopicReference::Name(_)),
+            "attempted to publish with a topic alias while a topic alias manager is set"
+        );
+
+        if (matches!(options.qos, QoS::AtMostOnce | QoS::AtLeastOnce)
+            && options.ack_mode == AckMode::Manual)
+        {
+            return Err(MqttError::ManualAckNotAllowed);
+        }
+
+        if !link.is_connected() {
+            if let (Some(queue), Some(topic)) = (
+                self.publish_queue.as_deref_mut(),
+                options.topic.topic_name(),
+            ) {
+                let len = QueuedPublication::<MAX_USER_PROPERTIES>::encoded_len(
+                    options,
+                    topic,
+                    message.as_bytes(),
+                );
+                let Some(record) = queue.push(len) else {
+                    info!("publish queue is full");
+                    return Err(MqttError::SessionBuffer);
+                };
+
+                QueuedPublication::<MAX_USER_PROPERTIES>::encode(
+                    options,
+                    topic,
+                    message.as_bytes(),
+                    link.now(),
+                    record,
+                );
+
+                debug!("queued publication while not connected");
+                return Ok(None);
+            }
+        }
+
+        if options.qos > self.server_config.maximum_qos {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if !self.server_config.retain_supported && options.retain {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if options
+            .topic
+            .alias()
+            .map(NonZero::get)
+            .is_some_and(|a| a > self.server_config.topic_alias_maximum)
+        {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if options.qos > QoS::AtMostOnce
+            && self.message_store.is_some()
+            && matches!(options.topic, TopicReference::Alias(_))
+        {
+            return Err(MqttError::UnstorableTopicAlias);
+        }
+
+        let (identified_qos, handle) = if options.qos > QoS::AtMostOnce {
+            if self.remaining_send_quota() == 0 {
+                info!("server receive maximum reached");
+                return Err(MqttError::SendQuotaExceeded);
+            }
+
+            let Some(handle) = self.session.free_handle() else {
+                return Err(MqttError::AllPacketIdentifiersUsed);
+            };
+
+            match options.qos {
+                QoS::AtMostOnce => unreachable!(),
+                QoS::AtLeastOnce => (
+                    IdentifiedQoS::AtLeastOnce(handle.packet_identifier),
+                    Some(handle),
+                ),
+                QoS::ExactlyOnce => (
+                    IdentifiedQoS::ExactlyOnce(handle.packet_identifier),
+                    Some(handle),
+                ),
+            }
+        } else {
+            (IdentifiedQoS::AtMostOnce, None)
+        };
+
+        let mut packet = PublishPacket::<0, MAX_USER_PROPERTIES>::new(
+            false,
+            identified_qos,
+            options.retain,
+            options.topic.as_borrowed(),
+            options.payload_format_indicator.map(Into::into),
+            options.message_expiry_interval.map(Into::into),
+            options.response_topic.as_ref().map(TopicName::as_borrowed),
+            options
+                .correlation_data
+                .as_ref()
+                .map(MqttBinary::as_borrowed),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+            options
+                .content_type
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            message,
+        )?;
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        if let Some(pid) = identified_qos.packet_identifier() {
+            Self::store_message(self.message_store.as_deref_mut(), pid, &mut packet)?;
+        }
+
+        if let Some(handle) = handle {
+            let pid = handle.packet_identifier;
+
+            // Treat the packet as sent before successfully sending. In case of a network error,
+            // we have tracked the packet as in flight and can republish it.
+            if let Err(e) = handle.outbound_publish(options.qos, options.ack_mode) {
+                match e {
+                    SmError::NoCapacity => {
+                        self.forget_message(pid);
+                        return Err(MqttError::SessionBuffer);
+                    }
+                    SmError::PacketIdentifierUnused
+                    | SmError::QoSMismatched
+                    | SmError::HandshakeStateMismatched => unreachable!(),
+                }
+            }
+        }
+
+        self.alias_topic(&mut packet);
+
+        match identified_qos.packet_identifier() {
+            Some(pid) => debug!("sending PUBLISH packet with packet identifier {}", pid),
+            None => debug!("sending PUBLISH packet"),
+        }
+
+        Ok(Some(packet))
+    }
+
+    /// Builds the PUBLISH packet retransmitting a tracked publication.
+    pub fn republish<'p>(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &'p PublicationOptions<'_>,
+        message: Bytes<'p>,
+    ) -> Result<PublishPacket<'p, 0, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to publish {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+        assert!(
+            self.topic_alias_manager.is_none() || matches!(options.topic, TopicReference::Name(_)),
+            "attempted to publish with a topic alias while a topic alias manager is set"
+        );
+
+        assert_ne!(
+            options.qos,
+            QoS::AtMostOnce,
+            "QoS 0 packets cannot be republished"
+        );
+
+        if options.qos > self.server_config.maximum_qos {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if !self.server_config.retain_supported && options.retain {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if options
+            .topic
+            .alias()
+            .map(NonZero::get)
+            .is_some_and(|a| a > self.server_config.topic_alias_maximum)
+        {
+            return Err(MqttError::UnsupportedByServer);
+        }
+
+        if self.message_store.is_some() && matches!(options.topic, TopicReference::Alias(_)) {
+            return Err(MqttError::UnstorableTopicAlias);
+        }
+
+        let identified_qos = match options.qos {
+            QoS::AtMostOnce => unreachable!(),
+            QoS::AtLeastOnce => IdentifiedQoS::AtLeastOnce(packet_identifier),
+            QoS::ExactlyOnce => IdentifiedQoS::ExactlyOnce(packet_identifier),
+        };
+
+        let mut packet = PublishPacket::<0, MAX_USER_PROPERTIES>::new(
+            true,
+            identified_qos,
+            options.retain,
+            options.topic.as_borrowed(),
+            options.payload_format_indicator.map(Into::into),
+            options.message_expiry_interval.map(Into::into),
+            options.response_topic.as_ref().map(TopicName::as_borrowed),
+            options
+                .correlation_data
+                .as_ref()
+                .map(MqttBinary::as_borrowed),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+            options
+                .content_type
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            message,
+        )?;
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        if let Err(e) = self.session.outbound_republish(identified_qos) {
+            match e {
+                SmError::NoCapacity => {
+                    unreachable!("a republish can not fail due to missing capacity")
+                }
+                SmError::PacketIdentifierUnused => {
+                    return Err(MqttError::PacketIdentifierNotInFlight);
+                }
+                SmError::QoSMismatched => {
+                    return Err(MqttError::QoSMismatched);
+                }
+                SmError::HandshakeStateMismatched => {
+                    return Err(MqttError::HandshakeStateMismatched);
+                }
+            }
+        }
+
+        // The publication is already tracked, so a full store only prevents later retransmission
+        // by resuming.
+        if Self::store_message(
+            self.message_store.as_deref_mut(),
+            packet_identifier,
+            &mut packet,
+        )
+        .is_err()
+        {
+            warn!(
+                "message store is full, not storing PUBLISH packet {}",
+                packet_identifier
+            );
+        }
+
+        self.alias_topic(&mut packet);
+
+        debug!(
+            "resending PUBLISH packet with packet identifier {}",
+            packet_identifier
+        );
+
+        Ok(packet)
+    }
+
+    /// Writes an outgoing PUBLISH packet into the [`MessageStore`] if one is set.
+    fn store_message(
+        store: Option<&mut (dyn MessageStore + 'c)>,
+        packet_identifier: PacketIdentifier,
+        packet: &mut PublishPacket<'_, 0, MAX_USER_PROPERTIES>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let Some(store) = store else {
+            return Ok(());
+        };
+
+        // Topic alias mappings end with the network connection, so the packet is stored with the
+        // topic name to be resendable after a reconnection.
+        let mapping = match packet.topic {
+            TopicReference::Mapping(ref topic, _) => {
+                let name = TopicReference::Name(topic.clone());
+                Some(
+                    packet
+                        .replace_topic(name)
+                        .expect("the packet is shorter without the topic alias"),
+                )
+            }
+            TopicReference::Name(_) | TopicReference::Alias(_) => None,
+        };
+
+        let stored = match store.insert(packet_identifier, packet.encoded_len()) {
+            Some(record) => {
+                if encode(&*packet, record).is_err() {
+                    unreachable!("the record has the length of the encoded packet");
+                }
+                Ok(())
+            }
+            None => {
+                info!("message store is full");
+                Err(MqttError::SessionBuffer)
+            }
+        };
+
+        if let Some(mapping) = mapping {
+            packet
+                .replace_topic(mapping)
+                .expect("the packet could be encoded with the topic alias before");
+        }
+
+        stored
+    }
+
+    /// Removes an outgoing PUBLISH packet from the [`MessageStore`] if one is set.
+    fn forget_message(&mut self, packet_identifier: PacketIdentifier) {
+        if let Some(store) = self.message_store.as_deref_mut() {
+            store.remove(packet_identifier);
+        }
+    }
+
+    /// Replaces the topic name of an outgoing PUBLISH packet with the topic alias assigned by the
+    /// [`TopicAliasManager`] if one is set. The topic name is kept if the packet would exceed the
+    /// server's maximum packet size with the topic alias.
+    fn alias_topic<'p>(&mut self, packet: &mut PublishPacket<'p, 0, MAX_USER_PROPERTIES>) {
+        let Some(manager) = self.topic_alias_manager.as_deref_mut() else {
+            return;
+        };
+        let TopicReference::Name(ref topic) = packet.topic else {
+            return;
+        };
+        let Some(alias) = manager.alias(topic.as_ref().as_str()) else {
+            return;
+        };
+
+        let (reference, a) = match alias {
+            Alias::Existing(a) => (TopicReference::Alias(a), a),
+            Alias::New(a) => (TopicReference::Mapping(topic.clone(), a), a),
+        };
+
+        assert!(
+            a.get() <= self.server_config.topic_alias_maximum,
+            "topic alias manager assigned topic alias {} > {} (server's topic alias maximum)",
+            a.get(),
+            self.server_config.topic_alias_maximum
+        );
+
+        let fits = match packet.replace_topic(reference) {
+            Ok(name)
+                if self.server_config.maximum_packet_size.as_u32()
+                    < packet.encoded_len() as u32 =>
+            {
+                packet
+                    .replace_topic(name)
+                    .expect("the packet could be encoded with the topic name before");
+                false
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        };
+
+        if fits {
+            trace!("using topic alias {:?}", alias);
+        } else if let Alias::New(a) = alias {
+            manager.invalidate(a);
+        }
+    }
+
+    /// Builds the packet retransmitting the outgoing publication at index `i` of the session after
+    /// a reconnection. The stored PUBLISH packet is only resent if `publishes` is true, PUBREL
+    /// packets of [`AckMode::Automatic`] flows are always resent.
+    ///
+    /// # Returns:
+    /// [`None`] if there is nothing to resend for this publication.
+    pub fn resend(&mut self, i: usize, publishes: bool) -> Option<Outgoing<'_>> {
+        let (pid, state) = *self.session.outbound_publishes.get(i)?;
+
+        match state {
+            LocalPublishState::DuePublishAtLeastOnce
+            | LocalPublishState::DuePublishExactlyOnce(_)
+                if publishes =>
+            {
+                let Some(record) = self.message_store.as_deref().and_then(|s| s.get(pid)) else {
+                    warn!("no stored PUBLISH packet {} to resend", pid);
+                    return None;
+                };
+
+                let identified_qos = match QoS::from(state) {
+                    QoS::ExactlyOnce => IdentifiedQoS::ExactlyOnce(pid),
+                    _ => IdentifiedQoS::AtLeastOnce(pid),
+                };
+                self.session.outbound_republish(identified_qos).unwrap();
+
+                debug!("resending stored PUBLISH packet {}", pid);
+
+                Some(Outgoing::Stored(StoredPublishPacket::new(record)))
+            }
+            LocalPublishState::DueRel(AckMode::Automatic) => {
+                self.session.outbound_pubrel(pid).unwrap();
+
+                let pubrel = PubrelPacket::<0>::minimal(pid, ReasonCode::Success);
+
+                debug!("sending PUBREL packet {}", pubrel.packet_identifier);
+
+                // Don't check whether length exceeds servers maximum packet size because we don't
+                // add properties to automatically retransmitted PUBREL packets -> length is always
+                // minimal at 6 bytes. The server really shouldn't reject this.
+                Some(Outgoing::Pubrel(pubrel))
+            }
+            LocalPublishState::DuePublishAtLeastOnce
+            | LocalPublishState::DuePublishExactlyOnce(_)
+            | LocalPublishState::AwaitAck
+            | LocalPublishState::AwaitRec(_)
+            | LocalPublishState::DueRel(AckMode::Manual)
+            | LocalPublishState::AwaitComp(_) => None,
+        }
+    }
+
+    /// Decodes a record of a [`PublishQueue`] and decides whether it is published now.
+    pub fn dequeue<'r>(
+        &mut self,
+        link: &impl Link<'c>,
+        record: &'r [u8],
+    ) -> Dequeued<'r, MAX_USER_PROPERTIES> {
+        if mem::take(&mut self.queue_front_published) {
+            debug!("removing publication of a cancelled drain from publish queue");
+            return Dequeued::Drop;
+        }
+
+        let Some(mut publication) = QueuedPublication::<MAX_USER_PROPERTIES>::decode(record) else {
+            warn!("dropping malformed record from publish queue");
+            return Dequeued::Drop;
+        };
+
+        if let (Some(interval), Some(enqueued_at), Some(now)) = (
+            publication.message_expiry_interval,
+            publication.enqueued_at,
+            link.now(),
+        ) {
+            let queued = now.saturating_duration_since(enqueued_at).as_secs();
+
+            if queued >= u64::from(interval) {
+                info!("dropping queued publication as its message expiry interval has passed");
+                return Dequeued::Drop;
+            }
+
+            publication.message_expiry_interval = Some(interval - queued as u32);
+        }
+
+        if publication.qos > QoS::AtMostOnce && self.remaining_send_quota() == 0 {
+            info!("server receive maximum reached, pausing publish queue");
+            return Dequeued::Wait;
+        }
+
+        Dequeued::Publish(publication)
+    }
+
+    /// Builds a PUBACK packet acknowledging an incoming publication manually.
+    pub fn manual_acknowledge<'p>(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &'p AckOptions<'_>,
+    ) -> Result<PubackPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send PUBACK with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        // Not allowed:
+        // ReasonCode::NoMatchingSubscribers - only sent by the server
+        if !matches!(
+            reason_code,
+            ReasonCode::Success
+                | ReasonCode::UnspecifiedError
+                | ReasonCode::ImplementationSpecificError
+                | ReasonCode::NotAuthorized
+                | ReasonCode::TopicNameInvalid
+                | ReasonCode::PacketIdentifierInUse
+                | ReasonCode::QuotaExceeded
+                | ReasonCode::PayloadFormatInvalid
+        ) {
+            return Err(MqttError::IllegalReasonCode);
+        }
+
+        let packet = PubackPacket::<MAX_USER_PROPERTIES>::new(
+            packet_identifier,
+            reason_code,
+            options
+                .reason_string
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        self.session
+            .outbound_puback(packet_identifier)
+            .map_err(|e| match e {
+                SmError::NoCapacity => unreachable!(),
+                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
+                SmError::QoSMismatched => MqttError::QoSMismatched,
+                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
+            })?;
+
+        debug!("sending PUBACK packet {}", packet.packet_identifier);
+
+        Ok(packet)
+    }
+
+    /// Builds a PUBREC packet acknowledging an incoming publication manually.
+    pub fn manual_receive<'p>(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &'p AckOptions<'_>,
+    ) -> Result<PubrecPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send PUBREC with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        // Not allowed:
+        // ReasonCode::NoMatchingSubscribers - only sent by the server
+        if !matches!(
+            reason_code,
+            ReasonCode::Success
+                | ReasonCode::UnspecifiedError
+                | ReasonCode::ImplementationSpecificError
+                | ReasonCode::NotAuthorized
+                | ReasonCode::TopicNameInvalid
+                | ReasonCode::PacketIdentifierInUse
+                | ReasonCode::QuotaExceeded
+                | ReasonCode::PayloadFormatInvalid
+        ) {
+            return Err(MqttError::IllegalReasonCode);
+        }
+
+        let packet = PubrecPacket::<MAX_USER_PROPERTIES>::new(
+            packet_identifier,
+            reason_code,
+            options
+                .reason_string
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        self.session
+            .outbound_pubrec(packet_identifier, reason_code)
+            .map_err(|e| match e {
+                SmError::NoCapacity => unreachable!(),
+                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
+                SmError::QoSMismatched => MqttError::QoSMismatched,
+                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
+            })?;
+
+        debug!("sending PUBREC packet {}", packet.packet_identifier);
+
+        Ok(packet)
+    }
+
+    /// Builds a PUBREL packet continuing an outgoing publication manually.
+    pub fn manual_release<'p>(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &'p AckOptions<'_>,
+    ) -> Result<PubrelPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        const REASON_CODE: ReasonCode = ReasonCode::Success;
+
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send PUBREL with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        let packet = PubrelPacket::<MAX_USER_PROPERTIES>::new(
+            packet_identifier,
+            REASON_CODE,
+            options
+                .reason_string
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        self.session
+            .outbound_pubrel(packet_identifier)
+            .map_err(|e| match e {
+                SmError::NoCapacity => unreachable!(),
+                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
+                SmError::QoSMismatched => MqttError::QoSMismatched,
+                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
+            })?;
+
+        debug!("sending PUBREL packet {}", packet.packet_identifier);
+
+        Ok(packet)
+    }
+
+    /// Builds a PUBCOMP packet completing an incoming publication manually.
+    pub fn manual_complete<'p>(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &'p AckOptions<'_>,
+    ) -> Result<PubcompPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        const REASON_CODE: ReasonCode = ReasonCode::Success;
+
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send PUBCOMP with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        let packet = PubcompPacket::<MAX_USER_PROPERTIES>::new(
+            packet_identifier,
+            REASON_CODE,
+            options
+                .reason_string
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        self.session
+            .outbound_pubcomp(packet_identifier)
+            .map_err(|e| match e {
+                SmError::NoCapacity => unreachable!(),
+                SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
+                SmError::QoSMismatched => MqttError::QoSMismatched,
+                SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
+            })?;
+
+        debug!("sending PUBCOMP packet {}", packet.packet_identifier);
+
+        Ok(packet)
+    }
+
+    /// Builds the DISCONNECT packet of a graceful disconnection. The connection has to be closed
+    /// once it has been sent.
+    pub fn disconnect<'p>(
+        &self,
+        options: &'p DisconnectOptions<'_>,
+    ) -> Result<DisconnectPacket<'p, MAX_USER_PROPERTIES>, MqttError<'c, 0>> {
+        assert!(
+            options.user_properties.len() <= MAX_USER_PROPERTIES,
+            "attempted to send DISCONNECT with {} > {} (MAX_USER_PROPERTIES) properties",
+            options.user_properties.len(),
+            MAX_USER_PROPERTIES
+        );
+
+        // Not allowed:
+        // ReasonCode::NotAuthorized - only sent by the server
+        // ReasonCode::ServerBusy - only sent by the server
+        // ReasonCode::ServerShuttingDown - only sent by the server
+        // ReasonCode::KeepAliveTimeout - only sent by the server
+        // ReasonCode::SessionTakenOver - only sent by the server
+        // ReasonCode::TopicFilterInvalid - only sent by the server
+        // ReasonCode::RetainNotSupported - only sent by the server
+        // ReasonCode::QoSNotSupported - only sent by the server
+        // ReasonCode::UseAnotherServer - only sent by the server
+        // ReasonCode::ServerMoved - only sent by the server
+        // ReasonCode::SharedSubscriptionsNotSupported - only sent by the server
+        // ReasonCode::ConnectionRateExceeded - only sent by the server
+        // ReasonCode::MaximumConnectTime - only sent by the server
+        // ReasonCode::SubscriptionIdentifiersNotSupported - only sent by the server
+        // ReasonCode::WildcardSubscriptionsNotSupported - only sent by the server
+        if !matches!(
+            options.reason_code,
+            ReasonCode::Success
+                | ReasonCode::DisconnectWithWillMessage
+                | ReasonCode::UnspecifiedError
+                | ReasonCode::MalformedPacket
+                | ReasonCode::ProtocolError
+                | ReasonCode::ImplementationSpecificError
+                | ReasonCode::TopicNameInvalid
+                | ReasonCode::ReceiveMaximumExceeded
+                | ReasonCode::TopicAliasInvalid
+                | ReasonCode::PacketTooLarge
+                | ReasonCode::MessageRateTooHigh
+                | ReasonCode::QuotaExceeded
+                | ReasonCode::AdministrativeAction
+                | ReasonCode::PayloadFormatInvalid
+        ) {
+            return Err(MqttError::IllegalReasonCode);
+        }
+
+        let connect_session_expiry_interval_was_zero =
+            self.client_config.session_expiry_interval == SessionExpiryInterval::EndOnDisconnect;
+        let disconnect_session_expiry_interval_is_non_zero = options
+            .session_expiry_interval
+            .is_some_and(|s| s != SessionExpiryInterval::EndOnDisconnect);
+
+        if connect_session_expiry_interval_was_zero
+            && disconnect_session_expiry_interval_is_non_zero
+        {
+            return Err(MqttError::IllegalDisconnectSessionExpiryInterval);
+        }
+
+        let packet = DisconnectPacket::<MAX_USER_PROPERTIES>::new(
+            options.reason_code,
+            options.session_expiry_interval,
+            options
+                .reason_string
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .map(Into::into),
+            options
+                .user_properties
+                .iter()
+                .map(MqttStringPair::as_borrowed)
+                .map(Into::into)
+                .collect(),
+        );
+
+        if self.server_config.maximum_packet_size.as_u32() < packet.encoded_len() as u32 {
+            return Err(MqttError::ServerMaximumPacketSizeExceeded);
+        }
+
+        debug!("sending DISCONNECT packet");
+
+        Ok(packet)
+    }
+
+    /// Checks the packet type and the remaining length of a received fixed header.
+    pub fn check_header(
+        &self,
+        link: &mut impl Link<'c>,
+        header: FixedHeader,
+    ) -> Result<FixedHeader, MqttError<'c, 0>> {
+        if let Ok(p) = header.packet_type() {
+            debug!(
+                "received {:?} packet header (remaining length: {})",
+                p,
+                header.remaining_len.value()
+            );
+        } else {
+            error!("received invalid header {:?}", header);
+            link.close_with(Some(ReasonCode::MalformedPacket));
+            return Err(MqttError::Server);
+        }
+
+        if header.remaining_len.value() > self.client_config.maximum_accepted_remaining_length {
+            error!(
+                "received a packet exceeding maximum packet size, remaining length={:?}",
+                header.remaining_len.value()
+            );
+            link.close_with(Some(ReasonCode::PacketTooLarge));
+            return Err(MqttError::Server);
+        }
+
+        Ok(header)
+    }
+
+    /// Rejects the fixed header of a packet which the server is not allowed to send at this point
+    /// before its body is received.
+    pub fn check_packet_type(
+        &self,
+        link: &mut impl Link<'c>,
+        header: &FixedHeader,
+    ) -> Result<(), MqttError<'c, MAX_USER_PROPERTIES>> {
+        match header.packet_type()? {
+            t @ (PacketType::Connect
+            | PacketType::Subscribe
+            | PacketType::Unsubscribe
+            | PacketType::Pingreq) => {
+                error!(
+                    "received a packet that the server is not allowed to send: {:?}",
+                    t
+                );
+
+                link.close_with(Some(ReasonCode::ProtocolError));
+                Err(MqttError::Server)
+            }
+            PacketType::Connack => {
+                error!("received unexpected CONNACK packet");
+
+                link.close_with(Some(ReasonCode::ProtocolError));
+                Err(MqttError::Server)
+            }
+            PacketType::Auth if self.auth_state != AuthState::Reauthenticating => {
+                error!("received unexpected AUTH packet");
+
+                // Receiving an AUTH packet outside of CONNECT is a protocol error unless it is a response
+                // to a re-authentication initiated by the client.
+                // <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901257>
+                link.close_with(Some(ReasonCode::ProtocolError));
+                Err(MqttError::AuthPacketReceived)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Processes a packet received after the connection has been established.
+    ///
+    /// # Returns:
+    /// The [`Event`] to emit and the packet to respond with, which has to be sent before the
+    /// event is emitted.
+    pub fn receive<'a>(
+        &'a mut self,
+        link: &mut impl Link<'c>,
+        packet: Incoming<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<
+        (
+            Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+            Option<Outgoing<'a>>,
+        ),
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        let received = match packet {
+            Incoming::Pingresp => (Event::Pingresp, None),
+            Incoming::Suback(suback) => {
+                self.check_problem_information(
+                    link,
+                    suback.reason_string.is_some() || !suback.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let pid = suback.packet_identifier;
+
+                if let Some(h) = self.session.sub_handle(pid) {
+                    let topic_filters = h.topic_filters();
+                    h.remove();
+
+                    // The SUBACK packet contains a reason code for each topic filter in the SUBSCRIBE packet.
+                    if suback.reason_codes.len() != topic_filters {
+                        error!("received mismatched SUBACK");
+                        link.close_with(Some(ReasonCode::ProtocolError));
+                        return Err(MqttError::Server);
+                    }
+
+                    if let Some(registry) = self.subscription_registry.as_deref_mut() {
+                        registry.subacked(pid, &suback.reason_codes);
+                    }
+
+                    let event = Event::Suback(Suback {
+                        packet_identifier: pid,
+                        reason_string: suback.reason_string.map(Property::into_inner),
+                        user_properties: suback
+                            .user_properties
+                            .into_iter()
+                            .map(Property::into_inner)
+                            .collect(),
+                        reason_codes: suback.reason_codes,
+                    });
+
+                    (event, None)
+                } else {
+                    debug!("packet identifier {} in SUBACK not in use", pid);
+                    (Event::Ignored, None)
+                }
+            }
+            Incoming::Unsuback(unsuback) => {
+                self.check_problem_information(
+                    link,
+                    unsuback.reason_string.is_some() || !unsuback.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let pid = unsuback.packet_identifier;
+
+                if let Some(h) = self.session.unsub_handle(pid) {
+                    let topic_filters = h.topic_filters();
+                    h.remove();
+
+                    // The UNSUBACK packet contains a reason code for each topic filter in the UNSUBSCRIBE packet.
+                    if unsuback.reason_codes.len() != topic_filters {
+                        error!("received mismatched UNSUBACK");
+                        link.close_with(Some(ReasonCode::ProtocolError));
+                        return Err(MqttError::Server);
+                    }
+
+                    if let Some(registry) = self.subscription_registry.as_deref_mut() {
+                        registry.unsubacked(pid, &unsuback.reason_codes);
+                    }
+
+                    let event = Event::Unsuback(Suback {
+                        packet_identifier: pid,
+                        reason_string: unsuback.reason_string.map(Property::into_inner),
+                        user_properties: unsuback
+                            .user_properties
+                            .into_iter()
+                            .map(Property::into_inner)
+                            .collect(),
+                        reason_codes: unsuback.reason_codes,
+                    });
+
+                    (event, None)
+                } else {
+                    debug!("packet identifier {} in UNSUBACK not in use", pid);
+                    (Event::Ignored, None)
+                }
+            }
+            Incoming::Publish(publish) => self.receive_publish(link, publish)?,
+            Incoming::Puback(puback) => {
+                self.check_problem_information(
+                    link,
+                    puback.reason_string.is_some() || !puback.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let (action, event) = self
+                    .session
+                    .inbound_puback(puback.packet_identifier, puback.reason_code);
+
+                match action {
+                    Response::Acknowledge(_)
+                    | Response::Receive(_)
+                    | Response::Release(_)
+                    | Response::Complete(_) => unreachable!(),
+
+                    Response::None => {}
+                    Response::Disconnect(reason_code) => {
+                        error!("invalid PUBACK packet rejected by state machine");
+                        link.close_with(Some(reason_code));
+                    }
+                }
+
+                let event = match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Received(_)
+                    | SmEvent::Released(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Rejected => {
+                        self.forget_message(puback.packet_identifier);
+                        Event::PublishRejected(Pubrej::from(puback))
+                    }
+                    SmEvent::Acknowledged => {
+                        self.forget_message(puback.packet_identifier);
+                        Event::PublishAcknowledged(Puback::new(puback, AckMode::default()))
+                    }
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                };
+
+                (event, None)
+            }
+            Incoming::Pubrec(pubrec) => {
+                self.check_problem_information(
+                    link,
+                    pubrec.reason_string.is_some() || !pubrec.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let (action, event) = self
+                    .session
+                    .inbound_pubrec(pubrec.packet_identifier, pubrec.reason_code);
+
+                let response = match action {
+                    Response::Acknowledge(_) | Response::Receive(_) | Response::Complete(_) => {
+                        unreachable!()
+                    }
+
+                    Response::None => None,
+                    Response::Release(reason_code) => {
+                        let pubrel =
+                            PubrelPacket::<0>::minimal(pubrec.packet_identifier, reason_code);
+
+                        debug!("sending PUBREL packet {}", pubrel.packet_identifier);
+
+                        // Don't check whether length exceeds servers maximum packet size because we don't
+                        // add properties to automatically sent PUBREL packets -> length is always minimal
+                        // at 6 bytes. The server really shouldn't reject this.
+                        Some(Outgoing::Pubrel(pubrel))
+                    }
+                    Response::Disconnect(reason_code) => {
+                        error!("invalid PUBREC packet rejected by state machine");
+                        link.close_with(Some(reason_code));
+                        None
+                    }
+                };
+
+                let event = match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Acknowledged
+                    | SmEvent::Released(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Rejected => {
+                        self.forget_message(pubrec.packet_identifier);
+                        Event::PublishRejected(Pubrej::from(pubrec))
+                    }
+                    SmEvent::Received(mode) => {
+                        self.forget_message(pubrec.packet_identifier);
+                        Event::PublishReceived(Puback::new(pubrec, mode))
+                    }
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                };
+
+                (event, response)
+            }
+            Incoming::Pubrel(pubrel) => {
+                self.check_problem_information(
+                    link,
+                    pubrel.reason_string.is_some() || !pubrel.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let (action, event) = self
+                    .session
+                    .inbound_pubrel(pubrel.packet_identifier, pubrel.reason_code);
+
+                let response = match action {
+                    Response::Acknowledge(_) | Response::Receive(_) | Response::Release(_) => {
+                        unreachable!()
+                    }
+
+                    Response::None => None,
+                    Response::Complete(reason_code) => {
+                        let pubcomp =
+                            PubcompPacket::<0>::minimal(pubrel.packet_identifier, reason_code);
+
+                        debug!("sending PUBCOMP packet {}", pubcomp.packet_identifier);
+
+                        // Don't check whether length exceeds servers maximum packet size because we don't
+                        // add properties to automatically sent PUBCOMP packets -> length is always minimal
+                        // at 6 bytes. The server really shouldn't reject this.
+                        Some(Outgoing::Pubcomp(pubcomp))
+                    }
+                    Response::Disconnect(reason_code) => {
+                        error!("invalid PUBREL packet rejected by state machine");
+                        link.close_with(Some(reason_code));
+                        None
+                    }
+                };
+
+                let event = match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Received(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Aborted => Event::PublishAborted(Pubrej::from(pubrel)),
+                    SmEvent::Released(mode) => Event::PublishReleased(Puback::new(pubrel, mode)),
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                };
+
+                (event, response)
+            }
+            Incoming::Pubcomp(pubcomp) => {
+                self.check_problem_information(
+                    link,
+                    pubcomp.reason_string.is_some() || !pubcomp.user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let (action, event) = self
+                    .session
+                    .inbound_pubcomp(pubcomp.packet_identifier, pubcomp.reason_code);
+
+                match action {
+                    Response::Acknowledge(_)
+                    | Response::Receive(_)
+                    | Response::Release(_)
+                    | Response::Complete(_) => unreachable!(),
+
+                    Response::None => {}
+                    Response::Disconnect(reason_code) => {
+                        error!("invalid PUBCOMP packet rejected by state machine");
+                        link.close_with(Some(reason_code));
+                    }
+                }
+
+                let event = match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Received(_)
+                    | SmEvent::Released(_) => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Completed => {
+                        Event::PublishComplete(Puback::new(pubcomp, AckMode::default()))
+                    }
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                };
+
+                (event, None)
+            }
+            Incoming::Disconnect(disconnect) => {
+                // The server initiated the disconnect. We must close the transport on our side
+                // as well so that subsequent error handling (e.g. `abort`) sees a non-Ok network state.
+                link.close_with(None);
+
+                // The server rejects a re-authentication by disconnecting.
+                // <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901257>
+                if self.auth_state == AuthState::Reauthenticating {
+                    info!(
+                        "re-authentication rejected by server (reason: {:?})",
+                        disconnect.reason_code
+                    );
+
+                    let event = Event::ReauthenticationFailed(Disconnect {
+                        reason_code: disconnect.reason_code,
+                        reason_string: disconnect.reason_string.map(Property::into_inner),
+                        user_properties: disconnect
+                            .user_properties
+                            .into_iter()
+                            .map(Property::into_inner)
+                            .collect(),
+                        server_reference: disconnect.server_reference.map(Property::into_inner),
+                    });
+
+                    return Ok((event, None));
+                }
+
+                return Err(MqttError::Disconnect {
+                    reason: disconnect.reason_code,
+                    reason_string: disconnect.reason_string.map(Property::into_inner),
+                    user_properties: disconnect
+                        .user_properties
+                        .into_iter()
+                        .map(Property::into_inner)
+                        .collect(),
+                    server_reference: disconnect.server_reference.map(Property::into_inner),
+                });
+            }
+            Incoming::Auth(AuthPacket {
+                reason_code,
+                authentication_method,
+                authentication_data,
+                reason_string,
+                user_properties,
+            }) => {
+                self.check_problem_information(
+                    link,
+                    reason_string.is_some() || !user_properties.is_empty(),
+                )
+                .map_err(MqttError::inflate)?;
+
+                let Some(AuthenticationMethod(authentication_method)) = authentication_method
+                else {
+                    error!("server sent AUTH packet without an authentication method");
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    return Err(MqttError::Server);
+                };
+
+                if authentication_method.as_str() != self.authentication_method.as_str() {
+                    error!("server sent an authentication method other than the one in CONNECT");
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    return Err(MqttError::Server);
+                }
+
+                let data = authentication_data.as_ref().map(|d| d.0.as_bytes());
+                let auth = Auth {
+                    reason_string: reason_string.map(Property::into_inner),
+                    user_properties: user_properties
+                        .into_iter()
+                        .map(Property::into_inner)
+                        .collect(),
+                };
+
+                match reason_code {
+                    ReasonCode::ContinueAuthentication => {
+                        let packet = self
+                            .continue_authentication(link, authentication_method, data)
+                            .map_err(MqttError::inflate)?;
+
+                        (
+                            Event::ReauthenticationContinued(auth),
+                            Some(Outgoing::Auth(packet)),
+                        )
+                    }
+                    ReasonCode::Success => {
+                        self.complete_authentication(link, data)
+                            .map_err(MqttError::inflate)?;
+                        self.auth_state = AuthState::Authenticated;
+
+                        info!("re-authenticated with server");
+
+                        (Event::Reauthenticated(auth), None)
+                    }
+                    r => {
+                        error!("received AUTH packet with unexpected reason code {:?}", r);
+                        link.close_with(Some(ReasonCode::ProtocolError));
+                        return Err(MqttError::Server);
+                    }
+                }
+            }
+        };
+
+        Ok(received)
+    }
+
+    /// Processes a received PUBLISH packet.
+    fn receive_publish(
+        &mut self,
+        link: &mut impl Link<'c>,
+        publish: PublishPacket<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<
+        (
+            Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+            Option<Outgoing<'static>>,
+        ),
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        let topic = match publish.topic {
+            TopicReference::Name(topic) => topic,
+            TopicReference::Alias(alias) | TopicReference::Mapping(_, alias)
+                if alias.get() > self.client_config.topic_alias_maximum =>
+            {
+                error!("received topic alias {} exceeding maximum", alias.get());
+                link.close_with(Some(ReasonCode::TopicAliasInvalid));
+                return Err(MqttError::Server);
+            }
+            TopicReference::Alias(alias) => {
+                let Some(topic) = self.topic_aliases.get(alias) else {
+                    error!("received unmapped topic alias {}", alias.get());
+                    link.close_with(Some(ReasonCode::ProtocolError));
+                    return Err(MqttError::Server);
+                };
+
+                // The mapped topic name is copied into the buffer provider like the
+                // other contents of the received packet.
+                let mut buffer = match link.buffer_mut().provide_buffer(topic.len()) {
+                    Ok(b) => b,
+                    Err(e) => {
+                        error!("buffer provision failed: {:?}", e);
+                        link.close_with(Some(ReasonCode::ImplementationSpecificError));
+                        return Err(MqttError::Alloc);
+                    }
+                };
+                buffer.as_mut().copy_from_slice(topic.as_bytes());
+
+                let topic = MqttBinary::from_bytes_unchecked(buffer.into());
+                // Safety: The bytes are copied from a topic name which is valid UTF-8 and does
+                // not contain null characters.
+                let topic = unsafe { MqttString::from_utf8_binary_unchecked(topic) };
+                TopicName::new_unchecked(topic)
+            }
+            TopicReference::Mapping(topic, alias) => {
+                trace!("mapping topic alias {} to {:?}", alias.get(), topic);
+                if self
+                    .topic_aliases
+                    .insert(alias, topic.as_ref().as_str())
+                    .is_none()
+                {
+                    error!(
+                        "topic name mapped to topic alias {} exceeds {} (MAX_ALIASED_TOPIC_LEN) bytes",
+                        alias.get(),
+                        MAX_ALIASED_TOPIC_LEN
+                    );
+                    link.close_with(Some(ReasonCode::ImplementationSpecificError));
+                    return Err(MqttError::Alloc);
+                }
+                topic
+            }
+        };
+
+        let publish = Publish {
+            ack_mode: AckMode::default(),
+            dup: publish.dup,
+            identified_qos: publish.identified_qos,
+            retain: publish.retain,
+            topic,
+            payload_format_indicator: publish.payload_format_indicator.map(Property::into_inner),
+            message_expiry_interval: publish.message_expiry_interval.map(Property::into_inner),
+            response_topic: publish.response_topic.map(Property::into_inner),
+            correlation_data: publish.correlation_data.map(Property::into_inner),
+            user_properties: publish
+                .user_properties
+                .into_iter()
+                .map(Property::into_inner)
+                .collect(),
+            subscription_identifiers: publish
+                .subscription_identifiers
+                .into_iter()
+                .map(Property::into_inner)
+                .collect(),
+            content_type: publish.content_type.map(Property::into_inner),
+            message: publish.message,
+        };
+
+        let ack_mode = if (self.manual_ack_when)(&publish) {
+            AckMode::Manual
+        } else {
+            AckMode::Automatic
+        };
+
+        let publish = Publish {
+            ack_mode,
+            ..publish
+        };
+
+        let (action, event) = self
+            .session
+            .inbound_publish(publish.identified_qos, publish.ack_mode);
+
+        let response = match action {
+            Response::Release(_) | Response::Complete(_) => unreachable!(),
+
+            Response::None => None,
+            Response::Acknowledge(reason_code) => {
+                let puback = PubackPacket::<0>::minimal(
+                    publish.identified_qos.packet_identifier().unwrap(),
+                    reason_code,
+                );
+                debug!("sending PUBACK packet {}", puback.packet_identifier);
+
+                // Don't check whether length exceeds servers maximum packet size because we don't
+                // add properties to automatically sent PUBACK packets -> length is always minimal
+                // at 6 bytes. The server really shouldn't reject this.
+                Some(Outgoing::Puback(puback))
+            }
+            Response::Receive(reason_code) => {
+                let pubrec = PubrecPacket::<0>::minimal(
+                    publish.identified_qos.packet_identifier().unwrap(),
+                    reason_code,
+                );
+                debug!("sending PUBREC packet {}", pubrec.packet_identifier);
+
+                // Don't check whether length exceeds servers maximum packet size because we don't
+                // add properties to automatically sent PUBREC packets -> length is always minimal
+                // at 6 bytes. The server really shouldn't reject this.
+                Some(Outgoing::Pubrec(pubrec))
+            }
+            Response::Disconnect(reason_code) => {
+                error!("invalid PUBLISH packet rejected by state machine");
+                link.close_with(Some(reason_code));
+                None
+            }
+        };
+
+        let event = match event {
+            SmEvent::Aborted
+            | SmEvent::Rejected
+            | SmEvent::Acknowledged
+            | SmEvent::Received(_)
+            | SmEvent::Released(_)
+            | SmEvent::Completed => unreachable!(),
+
+            SmEvent::Publish => Event::Publish(publish),
+            SmEvent::Duplicate(ack_mode) => {
+                let publish = Publish {
+                    ack_mode,
+                    ..publish
+                };
+                Event::Duplicate(publish)
+            }
+            SmEvent::Ignored => Event::Ignored,
+            SmEvent::ServerError => return Err(MqttError::Server),
+        };
+
+        Ok((event, response))
+    }
+}
+
+/// Returns whether a publication leaves the [`PublishQueue`] given the result of publishing it,
+/// which it does unless it has to wait for free session state.
+///
+/// # Errors
+///
+/// Returns the errors of the connection, after which draining the queue stops.
+pub(crate) fn leaves_queue<'c>(
+    result: Result<Option<PacketIdentifier>, MqttError<'c, 0>>,
+) -> Result<bool, MqttError<'c, 0>> {
+    match result {
+        Ok(_) => Ok(true),
+        Err(
+            MqttError::SendQuotaExceeded
+            | MqttError::AllPacketIdentifiersUsed
+            | MqttError::SessionBuffer,
+        ) => {
+            info!("session state is exhausted, pausing publish queue");
+            Ok(false)
+        }
+        Err(e @ (MqttError::Network(_) | MqttError::RecoveryRequired)) => Err(e),
+        Err(e) => {
+            warn!("dropping queued publication: {:?}", e);
+            Ok(true)
+        }
+    }
+}
+
+/// Encodes `value` with the codec `C` into `buffer`.
+///
+/// # Returns:
+/// The options of the publication with the content type and payload format indicator of the
+/// codec and the length of the encoded message.
+///
+/// # Errors
+///
+/// Returns [`MqttError::PayloadEncoding`] if `value` cannot be encoded into `buffer`.
+pub(crate) fn encode_payload<'c, 'o, C: EncodePayload<T>, T: ?Sized>(
+    options: &PublicationOptions<'o>,
+    value: &T,
+    buffer: &mut [u8],
+) -> Result<(PublicationOptions<'o>, usize), MqttError<'c, 0>> {
+    let Ok(len) = C::encode(value, buffer) else {
+        info!("encoding application message failed");
+        return Err(MqttError::PayloadEncoding);
+    };
+
+    let options = PublicationOptions {
+        payload_format_indicator: Some(C::UTF8),
+        content_type: Some(MqttString::from_str_unchecked(C::CONTENT_TYPE)),
+        ..options.clone()
+    };
+
+    Ok((options, len))
+}
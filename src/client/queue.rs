@@ -0,0 +1,652 @@
+//! Contains the trait the client uses to hold back publications while it is not connected and
+//! basic implementations.
+
+#[cfg(feature = "alloc")]
+pub use alloc::AllocPublishQueue;
+
+use heapless::Vec;
+
+use crate::{
+    client::options::{AckMode, PublicationOptions, TopicReference},
+    time::Instant,
+    types::{MqttBinary, MqttString, MqttStringPair, QoS, TopicName},
+};
+
+/// A first-in-first-out queue of publications made while the client is not connected.
+///
+/// A [`Client`] with a queue set via [`Client::queue_publications_in`] pushes publications into
+/// the queue instead of failing while it is not connected. [`Client::drain_queue`] publishes
+/// them in order after the next connection has been established.
+///
+/// The client encodes each publication into a record of bytes. The queue only has to keep the
+/// records and their order.
+///
+/// [`Client`]: crate::client::Client
+/// [`Client::queue_publications_in`]: crate::client::Client::queue_publications_in
+/// [`Client::drain_queue`]: crate::client::Client::drain_queue
+pub trait PublishQueue {
+    /// Returns `len` bytes of memory for a new record at the back of the queue. The client writes
+    /// the record into the returned slice.
+    ///
+    /// Returns [`None`] if there is not enough space. The client then rejects the publication.
+    fn push(&mut self, len: usize) -> Option<&mut [u8]>;
+
+    /// Returns the record at the front of the queue.
+    fn front(&self) -> Option<&[u8]>;
+
+    /// Removes the record at the front of the queue if there is one.
+    fn pop(&mut self);
+
+    /// Returns the number of records in the queue.
+    fn len(&self) -> usize;
+
+    /// Returns whether the queue holds no records.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all records.
+    fn clear(&mut self);
+}
+
+/// A ring buffer of records in a caller-provided slice.
+///
+/// Every record occupies its length plus 4 bytes. Records are contiguous, so a record which does
+/// not fit in front of the end of the slice is placed at its start, leaving the remainder unused
+/// until the queue has been drained up to there.
+#[derive(Debug)]
+pub struct SlicePublishQueue<'b> {
+    buffer: &'b mut [u8],
+    head: usize,
+    tail: usize,
+    /// The end of the records behind `head` if the records wrap around the end of the buffer.
+    wrapped_at: Option<usize>,
+    len: usize,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SlicePublishQueue<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "SlicePublishQueue {{ capacity: {}, records: {} }}",
+            self.buffer.len(),
+            self.len
+        );
+    }
+}
+
+impl<'b> SlicePublishQueue<'b> {
+    /// The size of the length prefix of each record.
+    const PREFIX: usize = 4;
+
+    /// Creates an empty queue keeping its records in `buffer`.
+    pub fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            buffer,
+            head: 0,
+            tail: 0,
+            wrapped_at: None,
+            len: 0,
+        }
+    }
+
+    fn record_len(&self, at: usize) -> usize {
+        let mut prefix = [0; Self::PREFIX];
+        prefix.copy_from_slice(&self.buffer[at..at + Self::PREFIX]);
+        u32::from_be_bytes(prefix) as usize
+    }
+}
+
+impl PublishQueue for SlicePublishQueue<'_> {
+    fn push(&mut self, len: usize) -> Option<&mut [u8]> {
+        let size = Self::PREFIX.checked_add(len)?;
+        let prefix = u32::try_from(len).ok()?.to_be_bytes();
+
+        let start = match self.wrapped_at {
+            Some(_) if self.head - self.tail >= size => self.tail,
+            Some(_) => return None,
+            None if self.buffer.len() - self.tail >= size => self.tail,
+            None if self.head >= size => {
+                self.wrapped_at = Some(self.tail);
+                0
+            }
+            None => return None,
+        };
+
+        self.tail = start + size;
+        self.len += 1;
+
+        let record = &mut self.buffer[start..self.tail];
+        record[..Self::PREFIX].copy_from_slice(&prefix);
+        Some(&mut record[Self::PREFIX..])
+    }
+
+    fn front(&self) -> Option<&[u8]> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let start = self.head + Self::PREFIX;
+        Some(&self.buffer[start..start + self.record_len(self.head)])
+    }
+
+    fn pop(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+
+        self.head += Self::PREFIX + self.record_len(self.head);
+        self.len -= 1;
+
+        if self.len == 0 {
+            self.clear();
+        } else if self.wrapped_at == Some(self.head) {
+            self.head = 0;
+            self.wrapped_at = None;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn clear(&mut self) {
+        self.head = 0;
+        self.tail = 0;
+        self.wrapped_at = None;
+        self.len = 0;
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use alloc::{boxed::Box, collections::VecDeque, vec};
+
+    use crate::client::queue::PublishQueue;
+
+    /// Keeps up to a maximum number of records on the heap using the global allocator.
+    #[derive(Debug)]
+    pub struct AllocPublishQueue {
+        records: VecDeque<Box<[u8]>>,
+        maximum: usize,
+    }
+
+    #[cfg(feature = "defmt")]
+    impl defmt::Format for AllocPublishQueue {
+        fn format(&self, fmt: defmt::Formatter) {
+            defmt::write!(
+                fmt,
+                "AllocPublishQueue {{ records: {}, maximum: {} }}",
+                self.records.len(),
+                self.maximum
+            );
+        }
+    }
+
+    impl AllocPublishQueue {
+        /// Creates an empty queue holding at most `maximum` records.
+        #[must_use]
+        pub const fn new(maximum: usize) -> Self {
+            Self {
+                records: VecDeque::new(),
+                maximum,
+            }
+        }
+    }
+
+    impl PublishQueue for AllocPublishQueue {
+        fn push(&mut self, len: usize) -> Option<&mut [u8]> {
+            if self.records.len() >= self.maximum {
+                return None;
+            }
+
+            self.records.push_back(vec![0; len].into_boxed_slice());
+
+            self.records.back_mut().map(|r| &mut **r)
+        }
+
+        fn front(&self) -> Option<&[u8]> {
+            self.records.front().map(|r| &**r)
+        }
+
+        fn pop(&mut self) {
+            self.records.pop_front();
+        }
+
+        fn len(&self) -> usize {
+            self.records.len()
+        }
+
+        fn clear(&mut self) {
+            self.records.clear();
+        }
+    }
+}
+
+/// The bits of the two flags bytes of a record.
+mod flag {
+    pub const QOS: u8 = 0x03;
+    pub const RETAIN: u8 = 0x04;
+    pub const MANUAL_ACK: u8 = 0x08;
+    pub const ENQUEUED_AT: u8 = 0x10;
+    pub const PAYLOAD_FORMAT_INDICATOR: u8 = 0x20;
+    pub const PAYLOAD_IS_UTF8: u8 = 0x40;
+    pub const MESSAGE_EXPIRY_INTERVAL: u8 = 0x80;
+
+    pub const RESPONSE_TOPIC: u8 = 0x01;
+    pub const CORRELATION_DATA: u8 = 0x02;
+    pub const CONTENT_TYPE: u8 = 0x04;
+}
+
+/// A publication held back in a [`PublishQueue`].
+///
+/// Records start with a flags byte, followed by the time of enqueueing, the optional message
+/// expiry interval, the topic name, a second flags byte and the optional response topic,
+/// correlation data and content type, the user properties and finally the message. Strings and
+/// binary data are prefixed with their length as in MQTT.
+pub(crate) struct QueuedPublication<'r, const MAX_USER_PROPERTIES: usize> {
+    pub enqueued_at: Option<Instant>,
+    pub qos: QoS,
+    pub ack_mode: AckMode,
+    pub retain: bool,
+    pub topic: TopicName<'r>,
+    pub payload_format_indicator: Option<bool>,
+    pub message_expiry_interval: Option<u32>,
+    pub response_topic: Option<TopicName<'r>>,
+    pub correlation_data: Option<MqttBinary<'r>>,
+    pub user_properties: Vec<MqttStringPair<'r>, MAX_USER_PROPERTIES>,
+    pub content_type: Option<MqttString<'r>>,
+    pub message: &'r [u8],
+}
+
+impl<'r, const MAX_USER_PROPERTIES: usize> QueuedPublication<'r, MAX_USER_PROPERTIES> {
+    /// Returns the length of the record of a publication to `topic`.
+    pub fn encoded_len(
+        options: &PublicationOptions<'_>,
+        topic: &TopicName<'_>,
+        message: &[u8],
+    ) -> usize {
+        fn string_len(s: &MqttString<'_>) -> usize {
+            2 + s.len() as usize
+        }
+
+        // Flags, time of enqueueing, topic name, second flags, user property count and message.
+        let mut len = 1 + 8 + string_len(topic.as_ref()) + 1 + 2 + message.len();
+
+        if options.message_expiry_interval.is_some() {
+            len += 4;
+        }
+        if let Some(ref t) = options.response_topic {
+            len += string_len(t.as_ref());
+        }
+        if let Some(ref d) = options.correlation_data {
+            len += 2 + d.len() as usize;
+        }
+        if let Some(ref c) = options.content_type {
+            len += string_len(c);
+        }
+        for p in options.user_properties {
+            len += string_len(&p.name) + string_len(&p.value);
+        }
+
+        len
+    }
+
+    /// Writes the record of a publication to `topic` into `record`, which must have the length
+    /// returned by [`Self::encoded_len`].
+    pub fn encode(
+        options: &PublicationOptions<'_>,
+        topic: &TopicName<'_>,
+        message: &[u8],
+        enqueued_at: Option<Instant>,
+        record: &mut [u8],
+    ) {
+        let mut w = Writer { record, pos: 0 };
+
+        let mut flags = options.qos as u8;
+        if options.retain {
+            flags |= flag::RETAIN;
+        }
+        if options.ack_mode == AckMode::Manual {
+            flags |= flag::MANUAL_ACK;
+        }
+        if enqueued_at.is_some() {
+            flags |= flag::ENQUEUED_AT;
+        }
+        match options.payload_format_indicator {
+            Some(true) => flags |= flag::PAYLOAD_FORMAT_INDICATOR | flag::PAYLOAD_IS_UTF8,
+            Some(false) => flags |= flag::PAYLOAD_FORMAT_INDICATOR,
+            None => {}
+        }
+        if options.message_expiry_interval.is_some() {
+            flags |= flag::MESSAGE_EXPIRY_INTERVAL;
+        }
+        w.put(&[flags]);
+
+        // The time of enqueueing always takes up space so that the length of the record does
+        // not depend on whether a clock is set.
+        w.put(&enqueued_at.map_or(0, Instant::as_millis).to_be_bytes());
+        if let Some(i) = options.message_expiry_interval {
+            w.put(&i.to_be_bytes());
+        }
+        w.put_string(topic.as_ref());
+
+        let mut flags = 0;
+        if options.response_topic.is_some() {
+            flags |= flag::RESPONSE_TOPIC;
+        }
+        if options.correlation_data.is_some() {
+            flags |= flag::CORRELATION_DATA;
+        }
+        if options.content_type.is_some() {
+            flags |= flag::CONTENT_TYPE;
+        }
+        w.put(&[flags]);
+
+        if let Some(ref t) = options.response_topic {
+            w.put_string(t.as_ref());
+        }
+        if let Some(ref d) = options.correlation_data {
+            w.put_binary(d.as_bytes());
+        }
+        if let Some(ref c) = options.content_type {
+            w.put_string(c);
+        }
+
+        w.put(&(options.user_properties.len() as u16).to_be_bytes());
+        for p in options.user_properties {
+            w.put_string(&p.name);
+            w.put_string(&p.value);
+        }
+
+        w.put(message);
+    }
+
+    /// Reads a record. Returns [`None`] if it is malformed.
+    pub fn decode(record: &'r [u8]) -> Option<Self> {
+        let mut r = Reader(record);
+
+        let [flags] = r.take_array()?;
+        let qos = match flags & flag::QOS {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            _ => return None,
+        };
+
+        let enqueued_at = r
+            .take_array()
+            .map(u64::from_be_bytes)
+            .map(Instant::from_millis)?;
+        let message_expiry_interval = if flags & flag::MESSAGE_EXPIRY_INTERVAL != 0 {
+            Some(r.take_array().map(u32::from_be_bytes)?)
+        } else {
+            None
+        };
+        let topic = TopicName::new(r.take_string()?)?;
+
+        let [optional] = r.take_array()?;
+        let response_topic = if optional & flag::RESPONSE_TOPIC != 0 {
+            Some(TopicName::new(r.take_string()?)?)
+        } else {
+            None
+        };
+        let correlation_data = if optional & flag::CORRELATION_DATA != 0 {
+            Some(MqttBinary::from_slice(r.take_binary()?).ok()?)
+        } else {
+            None
+        };
+        let content_type = if optional & flag::CONTENT_TYPE != 0 {
+            Some(r.take_string()?)
+        } else {
+            None
+        };
+
+        let count = r.take_array().map(u16::from_be_bytes)?;
+        let mut user_properties = Vec::new();
+        for _ in 0..count {
+            let name = r.take_string()?;
+            let value = r.take_string()?;
+            user_properties
+                .push(MqttStringPair::new(name, value))
+                .ok()?;
+        }
+
+        Some(Self {
+            enqueued_at: (flags & flag::ENQUEUED_AT != 0).then_some(enqueued_at),
+            qos,
+            ack_mode: if flags & flag::MANUAL_ACK != 0 {
+                AckMode::Manual
+            } else {
+                AckMode::Automatic
+            },
+            retain: flags & flag::RETAIN != 0,
+            topic,
+            payload_format_indicator: (flags & flag::PAYLOAD_FORMAT_INDICATOR != 0)
+                .then_some(flags & flag::PAYLOAD_IS_UTF8 != 0),
+            message_expiry_interval,
+            response_topic,
+            correlation_data,
+            user_properties,
+            content_type,
+            message: r.0,
+        })
+    }
+
+    /// Returns the options to publish this publication with.
+    pub fn options(&self) -> PublicationOptions<'_> {
+        PublicationOptions {
+            ack_mode: self.ack_mode,
+            qos: self.qos,
+            retain: self.retain,
+            topic: TopicReference::Name(self.topic.as_borrowed()),
+            payload_format_indicator: self.payload_format_indicator,
+            message_expiry_interval: self.message_expiry_interval,
+            response_topic: self.response_topic.as_ref().map(TopicName::as_borrowed),
+            correlation_data: self.correlation_data.as_ref().map(MqttBinary::as_borrowed),
+            user_properties: &self.user_properties,
+            content_type: self.content_type.as_ref().map(MqttString::as_borrowed),
+        }
+    }
+}
+
+struct Writer<'w> {
+    record: &'w mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        self.record[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn put_binary(&mut self, bytes: &[u8]) {
+        self.put(&(bytes.len() as u16).to_be_bytes());
+        self.put(bytes);
+    }
+
+    fn put_string(&mut self, string: &MqttString<'_>) {
+        self.put_binary(string.as_str().as_bytes());
+    }
+}
+
+struct Reader<'r>(&'r [u8]);
+
+impl<'r> Reader<'r> {
+    fn take(&mut self, len: usize) -> Option<&'r [u8]> {
+        let (taken, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn take_binary(&mut self) -> Option<&'r [u8]> {
+        let len = self.take_array().map(u16::from_be_bytes)?;
+        self.take(len as usize)
+    }
+
+    fn take_string(&mut self) -> Option<MqttString<'r>> {
+        let binary = MqttBinary::from_slice(self.take_binary()?).ok()?;
+        MqttString::from_utf8_binary(binary).ok()
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use crate::{
+        client::{
+            options::{PublicationOptions, TopicReference},
+            queue::{PublishQueue, QueuedPublication, SlicePublishQueue},
+        },
+        time::Instant,
+        types::{MqttBinary, MqttString, MqttStringPair, QoS, TopicName},
+    };
+
+    #[test]
+    fn slice_queue_wraps() {
+        let mut b = [0; 16];
+        let mut q = SlicePublishQueue::new(&mut b);
+
+        q.push(4).unwrap().copy_from_slice(&[1; 4]);
+        q.push(2).unwrap().copy_from_slice(&[2; 2]);
+        assert!(q.push(1).is_none());
+        assert_eq!(q.len(), 2);
+
+        q.pop();
+        assert_eq!(q.front(), Some(&[2; 2][..]));
+
+        // Placed at the start as it does not fit in front of the end of the buffer.
+        q.push(3).unwrap().copy_from_slice(&[3; 3]);
+        assert!(q.push(0).is_none());
+
+        q.pop();
+        assert_eq!(q.front(), Some(&[3; 3][..]));
+        q.push(5).unwrap().copy_from_slice(&[4; 5]);
+        assert!(q.push(0).is_none());
+
+        q.pop();
+        assert_eq!(q.front(), Some(&[4; 5][..]));
+        q.pop();
+        assert!(q.is_empty());
+        assert_eq!(q.front(), None);
+
+        assert!(q.push(12).is_some());
+        assert!(q.push(13).is_none());
+    }
+
+    #[test]
+    fn record_roundtrip() {
+        let topic = TopicName::new(MqttString::try_from("a/b").unwrap()).unwrap();
+        let response_topic = TopicName::new(MqttString::try_from("c").unwrap()).unwrap();
+        let user_properties = [MqttStringPair::new(
+            MqttString::try_from("k").unwrap(),
+            MqttString::try_from("v").unwrap(),
+        )];
+        let options = PublicationOptions::new(TopicReference::Name(topic.clone()))
+            .exactly_once()
+            .ack_manually()
+            .retain()
+            .payload_format_indicator(false)
+            .message_expiry_interval(60)
+            .response_topic(response_topic.clone())
+            .correlation_data(MqttBinary::try_from(&[7, 8][..]).unwrap())
+            .user_properties(&user_properties);
+
+        let len = QueuedPublication::<1>::encoded_len(&options, &topic, b"msg");
+        let mut record = [0; 64];
+        let record = &mut record[..len];
+        QueuedPublication::<1>::encode(
+            &options,
+            &topic,
+            b"msg",
+            Some(Instant::from_millis(5)),
+            record,
+        );
+
+        let p = QueuedPublication::<1>::decode(record).unwrap();
+        assert_eq!(p.enqueued_at, Some(Instant::from_millis(5)));
+        assert_eq!(p.qos, QoS::ExactlyOnce);
+        assert_eq!(p.ack_mode, options.ack_mode);
+        assert!(p.retain);
+        assert_eq!(p.topic, topic);
+        assert_eq!(p.payload_format_indicator, Some(false));
+        assert_eq!(p.message_expiry_interval, Some(60));
+        assert_eq!(p.response_topic, Some(response_topic));
+        assert_eq!(p.correlation_data.unwrap().as_bytes(), &[7, 8]);
+        assert_eq!(p.user_properties.as_slice(), &user_properties);
+        assert_eq!(p.content_type, None);
+        assert_eq!(p.message, b"msg");
+
+        // Too many user properties.
+        assert!(QueuedPublication::<0>::decode(record).is_none());
+        assert!(QueuedPublication::<1>::decode(&record[..5]).is_none());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn cancelled_drain_does_not_republish() {
+        use core::time::Duration;
+
+        use embedded_io_adapters::tokio_1::FromTokio;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt, duplex},
+            join,
+            time::timeout,
+        };
+        use tokio_test::{assert_err, assert_ok};
+
+        #[cfg(feature = "alloc")]
+        use crate::buffer::AllocBuffer;
+        #[cfg(feature = "bump")]
+        use crate::buffer::BumpBuffer;
+        use crate::client::{Client, options::ConnectOptions};
+
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut q = [0; 256];
+        let mut q = SlicePublishQueue::new(&mut q);
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.queue_publications_in(&mut q);
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let options = PublicationOptions::new(TopicReference::Name(topic));
+        assert_ok!(client.publish(&options, (&[0; 128][..]).into()).await);
+        assert_eq!(client.queued_publications(), 1);
+
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new().clean_start();
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+        };
+        join!(server, connect);
+
+        // The server does not read, so the PUBLISH packet does not fit into the transport.
+        assert_err!(timeout(Duration::from_millis(10), client.drain_queue()).await);
+        assert_eq!(client.queued_publications(), 1);
+
+        // The publication has been partially sent and leaves the queue without being sent again.
+        assert_ok!(client.drain_queue().await);
+        assert_eq!(client.queued_publications(), 0);
+
+        let mut buf = [0; 256];
+        let n = assert_ok!(s.read(&mut buf).await);
+        assert!(n < 128);
+        assert_err!(timeout(Duration::from_millis(10), s.read(&mut buf)).await);
+    }
+}
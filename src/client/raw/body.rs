@@ -0,0 +1,264 @@
+use crate::{
+    eio::Read,
+    fmt::{debug_assert, verbose},
+    header::FixedHeader,
+    io::err::ReadError,
+};
+#[cfg(feature = "v5")]
+use crate::{
+    eio::Write,
+    io::err::WriteError,
+    packet::{TxPacket, encode},
+};
+
+/// Reassembles the variable header and payload of a packet in a buffer so that receiving them can
+/// be resumed after the receiving future has been dropped.
+///
+/// In between two packets, the buffer holds the encoded response to the last packet until it has
+/// been written completely, so that writing it can be resumed as well.
+pub struct BodyState<'b> {
+    buffer: &'b mut [u8],
+    header: Option<FixedHeader>,
+    read: usize,
+
+    /// The length of the encoded response and the amount of its bytes written so far.
+    response: Option<(usize, usize)>,
+}
+
+impl core::fmt::Debug for BodyState<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BodyState")
+            .field("capacity", &self.buffer.len())
+            .field("header", &self.header)
+            .field("read", &self.read)
+            .field("response", &self.response)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for BodyState<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "BodyState {{ capacity: {}, header: {:?}, read: {}, response: {:?} }}",
+            self.buffer.len(),
+            self.header,
+            self.read,
+            self.response
+        );
+    }
+}
+
+impl<'b> BodyState<'b> {
+    pub fn new(buffer: &'b mut [u8]) -> Self {
+        Self {
+            buffer,
+            header: None,
+            read: 0,
+            response: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[cfg(all(feature = "v3", feature = "v5"))]
+    pub fn into_buffer(self) -> &'b mut [u8] {
+        self.buffer
+    }
+
+    /// Discards the packet that is currently being received.
+    pub fn reset(&mut self) {
+        self.header = None;
+        self.read = 0;
+    }
+
+    /// Discards the packet that is currently being received and the response that is currently
+    /// being written.
+    pub fn clear(&mut self) {
+        self.reset();
+        self.response = None;
+    }
+
+    /// Returns the fixed header of the packet whose body has not been received completely yet.
+    pub fn pending_header(&self) -> Option<FixedHeader> {
+        self.header
+    }
+
+    /// Starts receiving the body following `header`.
+    pub fn start(&mut self, header: FixedHeader) {
+        debug_assert!(
+            self.response.is_none(),
+            "the response to the last packet must be written before receiving the next one"
+        );
+
+        self.header = Some(header);
+        self.read = 0;
+    }
+
+    /// Cancel-safe if `R::read()` is cancel-safe
+    ///
+    /// Attempts to complete the body by reading once. Returns whether the body is complete.
+    ///
+    /// The remaining length of the pending header must not exceed the capacity of the buffer.
+    pub async fn update<R: Read>(&mut self, r: &mut R) -> Result<bool, ReadError<R::Error>> {
+        let len = self.pending_len();
+
+        if self.read == len {
+            return Ok(true);
+        }
+
+        verbose!("receiving bytes {}..{} of body", self.read, len);
+
+        let read = r
+            .read(&mut self.buffer[self.read..len])
+            .await
+            .map_err(ReadError::Read)?;
+
+        if read == 0 {
+            return Err(ReadError::UnexpectedEOF);
+        }
+
+        self.read += read;
+
+        Ok(self.read == len)
+    }
+
+    /// Returns the completely received body and resets the state.
+    pub fn take(&mut self) -> &[u8] {
+        let len = self.pending_len();
+        self.reset();
+        &self.buffer[..len]
+    }
+
+    /// Returns whether a response has not been written completely yet.
+    #[cfg(feature = "v5")]
+    pub fn response_pending(&self) -> bool {
+        self.response.is_some()
+    }
+
+    /// Encodes the response to the packet taken last into the buffer. Returns whether it fits.
+    #[cfg(feature = "v5")]
+    pub fn respond<P: TxPacket>(&mut self, packet: &P) -> bool {
+        debug_assert!(
+            self.header.is_none() && self.response.is_none(),
+            "a response must only be encoded in between two packets"
+        );
+
+        match encode(packet, self.buffer) {
+            Ok(len) => {
+                self.response = Some((len, 0));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Cancel-safe if `W::write()` is cancel-safe
+    ///
+    /// Writes the remainder of the encoded response, if any.
+    #[cfg(feature = "v5")]
+    pub async fn write_response<W: Write>(
+        &mut self,
+        w: &mut W,
+    ) -> Result<(), WriteError<W::Error>> {
+        while let Some((len, written)) = self.response {
+            if written == len {
+                self.response = None;
+                break;
+            }
+
+            verbose!("writing bytes {}..{} of response", written, len);
+
+            let n = w
+                .write(&self.buffer[written..len])
+                .await
+                .map_err(WriteError::Write)?;
+
+            if n == 0 {
+                return Err(WriteError::WriteZero);
+            }
+
+            self.response = Some((len, written + n));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the remainder of the encoded response which has not been written yet and forgets
+    /// the response.
+    #[cfg(feature = "v5")]
+    pub fn take_response(&mut self) -> &[u8] {
+        let (len, written) = self.response.take().unwrap_or_default();
+        &self.buffer[written..len]
+    }
+
+    fn pending_len(&self) -> usize {
+        self.header.map_or(0, |h| h.remaining_len.size())
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use tokio_test::{assert_err, assert_ok};
+
+    use crate::{
+        client::raw::body::BodyState,
+        header::{FixedHeader, PacketType},
+        io::err::ReadError,
+        test::read::SliceReader,
+        types::VarByteInt,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn resume_after_partial_reads() {
+        let mut buffer = [0; 8];
+        let mut s = BodyState::new(&mut buffer);
+        let header = FixedHeader::new(PacketType::Puback, 0x00, VarByteInt::from(4u8));
+        s.start(header);
+
+        let mut r = SliceReader::new(&[0x00, 0x01]);
+        assert!(!assert_ok!(s.update(&mut r).await));
+        assert_eq!(s.pending_header(), Some(header));
+
+        let mut r = SliceReader::new(&[0x00, 0x00, 0xFF]);
+        assert!(assert_ok!(s.update(&mut r).await));
+        assert_eq!(s.take(), &[0x00, 0x01, 0x00, 0x00]);
+        assert_eq!(s.pending_header(), None);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn empty_body() {
+        let mut buffer = [0; 8];
+        let mut s = BodyState::new(&mut buffer);
+        s.start(FixedHeader::new(
+            PacketType::Pingresp,
+            0x00,
+            VarByteInt::from(0u8),
+        ));
+
+        let mut r = SliceReader::new(&[]);
+        assert!(assert_ok!(s.update(&mut r).await));
+        assert!(s.take().is_empty());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn eof() {
+        let mut buffer = [0; 8];
+        let mut s = BodyState::new(&mut buffer);
+        s.start(FixedHeader::new(
+            PacketType::Puback,
+            0x00,
+            VarByteInt::from(2u8),
+        ));
+
+        let mut r = SliceReader::new(&[]);
+        let e = assert_err!(s.update(&mut r).await);
+        assert_eq!(e, ReadError::UnexpectedEOF);
+    }
+}
@@ -30,28 +30,33 @@ impl HeaderState {
         &mut self,
         r: &mut R,
     ) -> Result<Option<FixedHeader>, ReadError<R::Error>> {
-        // `i` is always in the 0..=4 range.
-        // After reading, `i` is always the value of `self.read` + 1. Once 5 bytes have been read,
-        // `self.read` is always reset to 0, never bringing `i` above 4
-        let i = self.read as usize;
-
-        // Since i is in the 0..=4 range, we can expect a value of the current header when indexing into `self.buffer`
-
-        verbose!("receiving byte {} of header", i);
+        verbose!("receiving byte {} of header", self.read);
 
-        let read = r
-            .read(&mut self.buffer[i..=i])
-            .await
-            .map_err(ReadError::Read)?;
+        let mut byte = [0];
+        let read = r.read(&mut byte).await.map_err(ReadError::Read)?;
 
         match read {
-            0 => return Err(ReadError::UnexpectedEOF),
-            1 => self.read += 1,
+            0 => Err(ReadError::UnexpectedEOF),
+            1 => self.push(byte[0]),
             n @ 2.. => unreachable!(
                 "Incorrect Read impl: {} bytes returned when reading into 1-byte buffer",
                 n
             ),
         }
+    }
+
+    /// Attempts to complete the header with a single received byte
+    ///
+    /// Resets the state before returning
+    pub fn push<E>(&mut self, byte: u8) -> Result<Option<FixedHeader>, ReadError<E>> {
+        // `i` is always in the 0..=4 range.
+        // After pushing, `i` is always the value of `self.read` + 1. Once 5 bytes have been pushed,
+        // `self.read` is always reset to 0, never bringing `i` above 4
+        let i = self.read as usize;
+
+        // Since i is in the 0..=4 range, we can expect a value of the current header when indexing into `self.buffer`
+        self.buffer[i] = byte;
+        self.read += 1;
 
         verbose!("received {} byte(s) in total", self.read);
 
@@ -0,0 +1,147 @@
+use core::time::Duration;
+
+use crate::{
+    header::{FixedHeader, PacketType},
+    time::{Clock, Instant},
+};
+
+/// Tracks the times of sent packets and of unanswered PINGREQ packets.
+pub struct KeepAliveState<'b> {
+    clock: Option<&'b dyn Clock>,
+    last_sent: Option<Instant>,
+    ping_sent: Option<Instant>,
+}
+
+impl core::fmt::Debug for KeepAliveState<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeepAliveState")
+            .field("clock", &self.clock.is_some())
+            .field("last_sent", &self.last_sent)
+            .field("ping_sent", &self.ping_sent)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for KeepAliveState<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "KeepAliveState {{ clock: {}, last_sent: {:?}, ping_sent: {:?} }}",
+            self.clock.is_some(),
+            self.last_sent,
+            self.ping_sent
+        );
+    }
+}
+
+impl<'b> KeepAliveState<'b> {
+    pub fn new() -> Self {
+        Self {
+            clock: None,
+            last_sent: None,
+            ping_sent: None,
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: &'b dyn Clock) {
+        self.clock = Some(clock);
+        self.reset();
+    }
+
+    /// Returns the current time if a clock has been set.
+    pub fn now(&self) -> Option<Instant> {
+        self.clock.map(Clock::now)
+    }
+
+    /// Forgets the times of the previous connection.
+    pub fn reset(&mut self) {
+        self.last_sent = None;
+        self.ping_sent = None;
+    }
+
+    /// Records the time at which a packet of the given type has been sent.
+    pub fn sent(&mut self, packet_type: PacketType) {
+        let Some(now) = self.now() else {
+            return;
+        };
+
+        self.last_sent = Some(now);
+
+        // The PINGRESP deadline runs from the first unanswered PINGREQ packet.
+        if packet_type == PacketType::Pingreq && self.ping_sent.is_none() {
+            self.ping_sent = Some(now);
+        }
+    }
+
+    /// Records the reception of a packet with the given fixed header.
+    pub fn received(&mut self, header: &FixedHeader) {
+        if matches!(header.packet_type(), Ok(PacketType::Pingresp)) {
+            self.ping_sent = None;
+        }
+    }
+
+    /// Returns whether a PINGREQ packet has not been answered yet.
+    pub fn ping_pending(&self) -> bool {
+        self.ping_sent.is_some()
+    }
+
+    /// Returns the point in time until which the PINGRESP packet must arrive if a PINGREQ packet
+    /// is pending, or otherwise until which the next packet must be sent.
+    pub fn deadline(&self, interval: Duration) -> Option<Instant> {
+        self.ping_sent.or(self.last_sent).map(|t| t + interval)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::{cell::Cell, time::Duration};
+
+    use crate::{
+        client::raw::keep_alive::KeepAliveState,
+        header::{FixedHeader, PacketType},
+        time::{Clock, Instant},
+        types::VarByteInt,
+    };
+
+    struct TestClock(Cell<u64>);
+
+    impl Clock for TestClock {
+        fn now(&self) -> Instant {
+            Instant::from_millis(self.0.get())
+        }
+    }
+
+    #[test]
+    fn deadlines() {
+        let clock = TestClock(Cell::new(0));
+        let interval = Duration::from_secs(10);
+        let mut s = KeepAliveState::new();
+
+        s.sent(PacketType::Connect);
+        assert_eq!(s.deadline(interval), None);
+
+        s.set_clock(&clock);
+        s.sent(PacketType::Connect);
+        assert_eq!(s.deadline(interval), Some(Instant::from_millis(10_000)));
+
+        clock.0.set(10_000);
+        s.sent(PacketType::Pingreq);
+        assert!(s.ping_pending());
+        assert_eq!(s.deadline(interval), Some(Instant::from_millis(20_000)));
+
+        // The deadline of an unanswered PINGREQ is not extended by sending other packets.
+        clock.0.set(15_000);
+        s.sent(PacketType::Publish);
+        s.sent(PacketType::Pingreq);
+        assert_eq!(s.deadline(interval), Some(Instant::from_millis(20_000)));
+
+        s.received(&FixedHeader::new(
+            PacketType::Pingresp,
+            0x00,
+            VarByteInt::from(0u8),
+        ));
+        assert!(!s.ping_pending());
+        assert_eq!(s.deadline(interval), Some(Instant::from_millis(25_000)));
+    }
+}
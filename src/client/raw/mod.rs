@@ -1,38 +1,67 @@
 //! Implements primitives for handling connections along with sending and receiving packets.
 
+mod body;
 mod err;
 mod header;
+mod keep_alive;
 mod net;
 
+pub(crate) use body::BodyState;
 pub(crate) use err::Error as RawError;
+pub(crate) use header::HeaderState;
+pub(crate) use keep_alive::KeepAliveState;
 pub(crate) use net::Error as NetStateError;
+#[cfg(feature = "v5")]
+pub(crate) use net::Status as NetStatus;
 
+use core::time::Duration;
+
+#[cfg(feature = "v5")]
 use heapless::Vec;
 
 #[cfg(debug_assertions)]
 use crate::fmt::unreachable;
+#[cfg(feature = "v5")]
+use crate::v5::packet::DisconnectPacket;
 use crate::{
     buffer::BufferProvider,
-    client::raw::{header::HeaderState, net::NetState},
+    client::raw::net::NetState,
+    eio::ReadReady,
     fmt::{debug, debug_assert, error, warn},
     header::FixedHeader,
     io::{Transport, err::WriteError, read::BodyReader},
     packet::{RxError, RxPacket, TxError, TxPacket},
+    time::{Clock, Instant},
     types::ReasonCode,
-    v5::packet::DisconnectPacket,
 };
 
+/// The network connection of a [`Raw`] client and the state of receiving and sending packets over
+/// it, borrowed separately by [`Raw::split`].
+#[cfg(feature = "v5")]
+pub(crate) struct Parts<'a, 'b, N, B> {
+    pub net: &'a mut N,
+    pub status: &'a mut NetStatus,
+    pub buf: &'a mut B,
+    pub header: &'a mut HeaderState,
+    pub body: &'a mut BodyState<'b>,
+    pub keep_alive: &'a mut KeepAliveState<'b>,
+}
+
 /// An MQTT Client offering a low level api for sending and receiving packets
 pub(crate) struct Raw<'b, N: Transport, B: BufferProvider<'b>> {
     n: NetState<N>,
     buf: &'b mut B,
     header: HeaderState,
+    body: Option<BodyState<'b>>,
+    keep_alive: KeepAliveState<'b>,
 }
 
 impl<'b, N: Transport, B: BufferProvider<'b>> core::fmt::Debug for Raw<'b, N, B> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Raw")
             .field("header", &self.header)
+            .field("body", &self.body)
+            .field("keep_alive", &self.keep_alive)
             .finish_non_exhaustive()
     }
 }
@@ -40,25 +69,83 @@ impl<'b, N: Transport, B: BufferProvider<'b>> core::fmt::Debug for Raw<'b, N, B>
 #[cfg(feature = "defmt")]
 impl<'b, N: Transport, B: BufferProvider<'b>> defmt::Format for Raw<'b, N, B> {
     fn format(&self, fmt: defmt::Formatter) {
-        defmt::write!(fmt, "Raw {{ header: {:?}, .. }}", self.header);
+        defmt::write!(
+            fmt,
+            "Raw {{ header: {:?}, body: {:?}, keep_alive: {:?}, .. }}",
+            self.header,
+            self.body,
+            self.keep_alive
+        );
     }
 }
 
 impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
     pub fn new_disconnected(buf: &'b mut B) -> Self {
         Self {
-            n: NetState::Terminated,
+            n: NetState::new(),
             buf,
             header: HeaderState::new(),
+            body: None,
+            keep_alive: KeepAliveState::new(),
+        }
+    }
+
+    /// Reassembles packet bodies in `buffer` from now on, making `recv_header` and `recv_body`
+    /// cancel-safe together.
+    pub fn reassemble_with(&mut self, buffer: &'b mut [u8]) {
+        self.body = Some(BodyState::new(buffer));
+    }
+
+    /// Returns the size of the buffer set with `reassemble_with`.
+    pub fn reassembly_capacity(&self) -> Option<usize> {
+        self.body.as_ref().map(BodyState::capacity)
+    }
+
+    /// Records the times of sent packets and unanswered PINGREQ packets with `clock` from now on.
+    pub fn keep_alive_with(&mut self, clock: &'b dyn Clock) {
+        self.keep_alive.set_clock(clock);
+    }
+
+    /// Returns the current time if a clock has been set with `keep_alive_with`.
+    pub fn now(&self) -> Option<Instant> {
+        self.keep_alive.now()
+    }
+
+    /// Returns the point in time until which the PINGRESP packet must be received if a PINGREQ
+    /// packet is pending, or otherwise until which the next packet must be sent. Returns `None` if
+    /// no clock has been set or the network connection is not open.
+    pub fn keep_alive_deadline(&self, interval: Duration) -> Option<Instant> {
+        if self.n.is_ok() {
+            self.keep_alive.deadline(interval)
+        } else {
+            None
         }
     }
 
+    /// Returns whether a PINGREQ packet has not been answered yet.
+    pub fn ping_pending(&self) -> bool {
+        self.keep_alive.ping_pending()
+    }
+
+    /// Returns whether the network connection is open and has not failed.
+    #[cfg(feature = "v5")]
+    pub fn is_connected(&self) -> bool {
+        self.n.is_ok()
+    }
+
     pub fn set_net(&mut self, net: N) {
         debug_assert!(
             !self.n.is_ok(),
             "network must not be in Ok() state to replace it."
         );
         self.n.replace(net);
+
+        // A packet partially received or a response partially written over a previous connection
+        // is never completed.
+        if let Some(b) = &mut self.body {
+            b.clear();
+        }
+        self.keep_alive.reset();
     }
 
     pub fn buffer(&self) -> &B {
@@ -69,6 +156,31 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
         self.buf
     }
 
+    /// Drops the network connection and returns the buffer provider and the buffer set with
+    /// `reassemble_with`.
+    #[cfg(all(feature = "v3", feature = "v5"))]
+    pub fn into_buffers(self) -> (&'b mut B, Option<&'b mut [u8]>) {
+        (self.buf, self.body.map(BodyState::into_buffer))
+    }
+
+    /// Borrows the network connection and the state of receiving and sending packets separately,
+    /// so that packets can be received and sent over the halves of the connection concurrently.
+    /// Returns `None` if the network connection is not open or bodies are not reassembled.
+    #[cfg(feature = "v5")]
+    pub fn split(&mut self) -> Option<Parts<'_, 'b, N, B>> {
+        let (net, status) = self.n.split()?;
+        let body = self.body.as_mut()?;
+
+        Some(Parts {
+            net,
+            status,
+            buf: self.buf,
+            header: &mut self.header,
+            body,
+            keep_alive: &mut self.keep_alive,
+        })
+    }
+
     pub fn close_with(&mut self, reason_code: Option<ReasonCode>) {
         match reason_code {
             Some(r) => self.n.fail(r),
@@ -82,37 +194,46 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
     /// Disconnect handler after an error occured.
     ///
     /// This expects the network to not be in `Ok()` state
+    #[cfg(feature = "v5")]
     pub async fn abort(&mut self) -> Result<(), RawError<B::ProvisionError>> {
         debug_assert!(
             !self.n.is_ok(),
             "network must not be in Ok() state to disconnect due to an error."
         );
 
-        match &mut self.n {
-            NetState::Faulted(n, r) => {
-                let packet = DisconnectPacket::<0>::new(*r, None, None, Vec::new());
-
-                debug!("sending DISCONNECT packet with reason code: {:?}", r);
+        let Some((n, r)) = self.n.faulted() else {
+            // Also drops a connection whose status has been terminated while it was split.
+            if self.n.is_ok() {
+                self.close_with(None);
+            } else {
+                drop(self.n.terminate());
+            }
+            return Ok(());
+        };
 
-                // Don't check whether length exceeds servers maximum packet size because we don't
-                // add properties to the DISCONNECT packet -> length is always in the 4..=6 range in bytes.
-                // The server really shouldn't reject this.
-                let r = packet
-                    .send(n)
-                    .await
-                    .map_err(Into::into)
-                    .inspect_err(|e| error!("I/O error during send: {:?}", e));
+        let packet = DisconnectPacket::<0>::new(r, None, None, Vec::new());
 
-                self.close_with(None);
+        debug!("sending DISCONNECT packet with reason code: {:?}", r);
 
-                r
-            }
-            NetState::Ok(_) => {
-                self.close_with(None);
-                Ok(())
-            }
-            NetState::Terminated => Ok(()),
+        // Don't check whether length exceeds servers maximum packet size because we don't
+        // add properties to the DISCONNECT packet -> length is always in the 4..=6 range in bytes.
+        // The server really shouldn't reject this.
+        // A partially written response is completed so that the DISCONNECT packet follows
+        // a complete packet.
+        let r = match &mut self.body {
+            Some(b) => b.write_response(n).await.map_err(TxError::from),
+            None => Ok(()),
+        };
+        let r = match r {
+            Ok(()) => packet.send(n).await,
+            Err(e) => Err(e),
         }
+        .map_err(Into::into)
+        .inspect_err(|e| error!("I/O error during send: {:?}", e));
+
+        self.close_with(None);
+
+        r
     }
 
     fn handle_rx<E: Into<(RawError<B::ProvisionError>, Option<ReasonCode>)>>(
@@ -181,7 +302,14 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
     }
 
     /// Cancel-safe method to receive the fixed header of a packet
+    ///
+    /// If bodies are reassembled, the header of a packet whose body has not been received
+    /// completely is returned again and a response that has not been written completely is written
+    /// first.
     pub async fn recv_header(&mut self) -> Result<FixedHeader, RawError<B::ProvisionError>> {
+        #[cfg(feature = "v5")]
+        self.flush_response().await?;
+
         let net = self.n.get().inspect_err(|e| match e {
             NetStateError::Faulted => {
                 warn!("attempted to receive from a faulted mqtt connection")
@@ -191,10 +319,20 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
             }
         })?;
 
+        if let Some(h) = self.body.as_ref().and_then(BodyState::pending_header) {
+            debug!("resuming reception of packet body");
+            return Ok(h);
+        }
+
         loop {
             match self.header.update(net).await {
                 Ok(None) => {}
-                Ok(Some(h)) => return Ok(h),
+                Ok(Some(h)) => {
+                    if let Some(b) = &mut self.body {
+                        b.start(h);
+                    }
+                    return Ok(h);
+                }
                 Err(e) => {
                     let e: RxError<_, _> = e.into();
                     return Err(self.handle_rx(e));
@@ -203,7 +341,60 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
         }
     }
 
-    /// Not cancel-safe
+    /// Cancel-safe method to receive the fixed header of a packet without waiting for data
+    ///
+    /// Returns `None` as soon as the network has no data ready to be read before the header is
+    /// complete. The bytes received so far are kept for the next call.
+    pub async fn try_recv_header(
+        &mut self,
+    ) -> Result<Option<FixedHeader>, RawError<B::ProvisionError>>
+    where
+        N: ReadReady,
+    {
+        #[cfg(feature = "v5")]
+        self.flush_response().await?;
+
+        let net = self.n.get().inspect_err(|e| match e {
+            NetStateError::Faulted => {
+                warn!("attempted to receive from a faulted mqtt connection")
+            }
+            NetStateError::Terminated => {
+                warn!("attempted to receive from a closed network connection")
+            }
+        })?;
+
+        if let Some(h) = self.body.as_ref().and_then(BodyState::pending_header) {
+            debug!("resuming reception of packet body");
+            return Ok(Some(h));
+        }
+
+        loop {
+            match net.read_ready() {
+                Ok(true) => {}
+                Ok(false) => return Ok(None),
+                Err(e) => {
+                    let e: RxError<_, _> = RxError::Read(e);
+                    return Err(self.handle_rx(e));
+                }
+            }
+
+            match self.header.update(net).await {
+                Ok(None) => {}
+                Ok(Some(h)) => {
+                    if let Some(b) = &mut self.body {
+                        b.start(h);
+                    }
+                    return Ok(Some(h));
+                }
+                Err(e) => {
+                    let e: RxError<_, _> = e.into();
+                    return Err(self.handle_rx(e));
+                }
+            }
+        }
+    }
+
+    /// Cancel-safe if bodies are reassembled and `N::read()` is cancel-safe
     ///
     /// Does not perform a check on headers packet type
     /// => Assumes you call this only for correct packet headers
@@ -217,11 +408,39 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
                 warn!("attempted to receive from a closed network connection")
             }
         })?;
-        let reader = BodyReader::new(net, self.buf, header.remaining_len.size());
+
+        let Some(body) = &mut self.body else {
+            let reader = BodyReader::new(net, self.buf, header.remaining_len.size());
+
+            return P::receive(header, reader)
+                .await
+                .map_err(|e| self.handle_rx(e))
+                .inspect(|_| self.keep_alive.received(header));
+        };
+
+        debug_assert!(
+            body.pending_header() == Some(*header),
+            "the body must follow the header received last"
+        );
+
+        loop {
+            match body.update(net).await {
+                Ok(false) => {}
+                Ok(true) => break,
+                Err(e) => {
+                    let e: RxError<_, _> = e.into();
+                    return Err(self.handle_rx(e));
+                }
+            }
+        }
+
+        let mut bytes = body.take();
+        let reader = BodyReader::new(&mut bytes, self.buf, header.remaining_len.size());
 
         P::receive(header, reader)
             .await
             .map_err(|e| self.handle_rx(e))
+            .inspect(|_| self.keep_alive.received(header))
     }
 
     // pub async fn recv_full<P: RxPacket<'b>>(&mut self) -> Result<P, RawError<B::ProvisionError>> {
@@ -238,15 +457,68 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
     //     self.recv_body(&header).await
     // }
 
+    /// Writes a response kept by `respond` first, so that packets are not interleaved.
     pub async fn send<P: TxPacket>(
         &mut self,
         packet: &P,
     ) -> Result<(), RawError<B::ProvisionError>> {
+        #[cfg(feature = "v5")]
+        self.flush_response().await?;
+
+        let net = self.n.get().inspect_err(|e| match e {
+            NetStateError::Faulted => warn!("attempted to send on a faulted mqtt connection"),
+            NetStateError::Terminated => warn!("attempted to send on a closed network connection"),
+        })?;
+        packet
+            .send(net)
+            .await
+            .map_err(|e| self.handle_tx(e))
+            .inspect(|()| self.keep_alive.sent(P::PACKET_TYPE))
+    }
+
+    /// Sends and flushes the response to the packet received last.
+    ///
+    /// If bodies are reassembled and the response fits into the reassembly buffer, it is only
+    /// encoded and written by the next `recv_header`, `try_recv_header` or `send`, so that
+    /// receiving a packet and responding to it are cancel-safe together.
+    #[cfg(feature = "v5")]
+    pub async fn respond<P: TxPacket>(
+        &mut self,
+        packet: &P,
+    ) -> Result<(), RawError<B::ProvisionError>> {
+        let kept = match &mut self.body {
+            Some(b) if self.n.is_ok() => b.respond(packet),
+            _ => false,
+        };
+
+        if kept {
+            self.keep_alive.sent(P::PACKET_TYPE);
+            Ok(())
+        } else {
+            self.send(packet).await?;
+            self.flush().await
+        }
+    }
+
+    /// Cancel-safe if `N::write()` and `N::flush()` are cancel-safe
+    ///
+    /// Writes and flushes the remainder of a response kept by `respond`, if any.
+    #[cfg(feature = "v5")]
+    async fn flush_response(&mut self) -> Result<(), RawError<B::ProvisionError>> {
+        let Some(body) = self.body.as_mut().filter(|b| b.response_pending()) else {
+            return Ok(());
+        };
         let net = self.n.get().inspect_err(|e| match e {
             NetStateError::Faulted => warn!("attempted to send on a faulted mqtt connection"),
             NetStateError::Terminated => warn!("attempted to send on a closed network connection"),
         })?;
-        packet.send(net).await.map_err(|e| self.handle_tx(e))
+
+        if let Err(e) = body.write_response(net).await {
+            let e: TxError<_> = e.into();
+            return Err(self.handle_tx(e));
+        }
+
+        self.flush().await
     }
 
     /// Cancel-safe if `N::flush()` is cancel-safe
@@ -264,9 +536,9 @@ impl<'b, N: Transport, B: BufferProvider<'b>> Raw<'b, N, B> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "v5"))]
 mod unit {
-    use core::time::Duration;
+    use core::{cell::Cell, time::Duration};
 
     use embedded_io_adapters::tokio_1::FromTokio;
     use tokio::{
@@ -283,10 +555,45 @@ mod unit {
     use crate::buffer::BumpBuffer;
     use crate::{
         client::raw::Raw,
+        eio::{ErrorType, Read, ReadReady, Write},
         header::{FixedHeader, PacketType},
-        types::VarByteInt,
+        test::read::{SliceReader, SliceReaderError},
+        types::{ReasonCode, VarByteInt},
+        v5::packet::PubackPacket,
     };
 
+    /// Reads from a slice of which only `ready` bytes are ready to be read.
+    struct ReadyReader<'a> {
+        r: SliceReader<'a>,
+        ready: &'a Cell<usize>,
+    }
+
+    impl ErrorType for ReadyReader<'_> {
+        type Error = SliceReaderError;
+    }
+    impl Read for ReadyReader<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = buf.len().min(self.ready.get());
+            let n = self.r.read(&mut buf[..len]).await?;
+            self.ready.set(self.ready.get() - n);
+            Ok(n)
+        }
+    }
+    impl ReadReady for ReadyReader<'_> {
+        fn read_ready(&mut self) -> Result<bool, Self::Error> {
+            Ok(self.ready.get() > 0)
+        }
+    }
+    impl Write for ReadyReader<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
     #[tokio::test]
     #[test_log::test]
     async fn recv_header_simple() {
@@ -511,4 +818,89 @@ mod unit {
 
         join!(rx, tx);
     }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn recv_body_cancel_reassembled() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut reassembly = [0; 16];
+        let (c, mut s) = duplex(64);
+        let r = FromTokio::new(c);
+        let (rx_ready, tx_ready) = channel();
+
+        let mut c = Raw::new_disconnected(&mut b);
+        c.reassemble_with(&mut reassembly);
+        c.set_net(r);
+
+        let tx = async {
+            assert_ok!(s.write_all(&[0x40, 0x04, 0x12]).await);
+            assert_ok!(tx_ready.await);
+            assert_ok!(s.write_all(&[0x34, 0x10, 0x00]).await);
+        };
+        let rx = async {
+            let h = assert_ok!(c.recv_header().await);
+            assert_err!(
+                timeout(
+                    Duration::from_millis(100),
+                    c.recv_body::<PubackPacket<'_, 0>>(&h)
+                )
+                .await
+            );
+
+            // The header of the partially received packet is returned again.
+            let h = assert_ok!(c.recv_header().await);
+            assert_eq!(
+                h,
+                FixedHeader::new(PacketType::Puback, 0x00, VarByteInt::from(4u8))
+            );
+            assert_ok!(rx_ready.send(()));
+
+            let p = assert_ok!(c.recv_body::<PubackPacket<'_, 0>>(&h).await);
+            assert_eq!(p.packet_identifier.get().get(), 0x1234);
+            assert_eq!(p.reason_code, ReasonCode::NoMatchingSubscribers);
+        };
+
+        join!(rx, tx);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn try_recv_header_only_reads_ready_bytes() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let ready = Cell::new(0);
+        let r = ReadyReader {
+            r: SliceReader::new(&[0xD0, 0x80, 0x01]),
+            ready: &ready,
+        };
+
+        let mut c = Raw::new_disconnected(&mut b);
+        c.set_net(r);
+
+        assert_eq!(assert_ok!(c.try_recv_header().await), None);
+
+        ready.set(2);
+        assert_eq!(assert_ok!(c.try_recv_header().await), None);
+        assert_eq!(ready.get(), 0);
+
+        ready.set(1);
+        let h = assert_ok!(c.try_recv_header().await);
+        assert_eq!(
+            h,
+            Some(FixedHeader::new(
+                PacketType::Pingresp,
+                0x00,
+                VarByteInt::from(128u8)
+            ))
+        );
+    }
 }
@@ -1,20 +1,26 @@
-use core::mem;
-
 use crate::{io::Transport, types::ReasonCode};
 
-/// Represents a network connection with different variants for handling failures in the connection gracefully.
-#[derive(Debug, Default)]
-pub(crate) enum NetState<N: Transport> {
+/// Represents a network connection with different states for handling failures in the connection gracefully.
+#[derive(Debug)]
+pub(crate) struct NetState<N: Transport> {
+    net: Option<N>,
+    status: Status,
+}
+
+/// The state of the dialogue with the server over a network connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub(crate) enum Status {
     /// The connection and dialogue with the server is ok
-    Ok(N),
+    Ok,
 
     /// The connection is ok but some protocol specific error (e.g. `MalformedPacket` or `ProtocolError` occured)
     ///
     /// Sending messages can be considered, but the session should ultimately be closed according to spec
-    Faulted(N, ReasonCode),
+    #[cfg_attr(not(feature = "v5"), allow(dead_code))]
+    Faulted(ReasonCode),
 
     /// The connection is closed
-    #[default]
     Terminated,
 }
 
@@ -29,33 +35,69 @@ pub enum Error {
     Terminated,
 }
 
+impl Status {
+    pub fn fail(&mut self, reason_code: ReasonCode) {
+        if *self != Self::Terminated {
+            *self = Self::Faulted(reason_code);
+        }
+    }
+
+    pub fn check(self) -> Result<(), Error> {
+        match self {
+            Self::Ok => Ok(()),
+            Self::Faulted(_) => Err(Error::Faulted),
+            Self::Terminated => Err(Error::Terminated),
+        }
+    }
+}
+
 impl<N: Transport> NetState<N> {
+    pub const fn new() -> Self {
+        Self {
+            net: None,
+            status: Status::Terminated,
+        }
+    }
+
     pub fn is_ok(&self) -> bool {
-        matches!(self, Self::Ok(_))
+        self.status == Status::Ok
     }
     pub fn replace(&mut self, net: N) {
-        *self = Self::Ok(net);
+        self.net = Some(net);
+        self.status = Status::Ok;
     }
     pub fn get(&mut self) -> Result<&mut N, Error> {
-        match self {
-            Self::Ok(n) => Ok(n),
-            Self::Faulted(_, _) => Err(Error::Faulted),
-            Self::Terminated => Err(Error::Terminated),
+        self.status.check()?;
+        self.net.as_mut().ok_or(Error::Terminated)
+    }
+
+    /// Returns the network connection and the reason code if the connection has failed.
+    #[cfg(feature = "v5")]
+    pub fn faulted(&mut self) -> Option<(&mut N, ReasonCode)> {
+        match (self.status, &mut self.net) {
+            (Status::Faulted(r), Some(n)) => Some((n, r)),
+            _ => None,
         }
     }
 
-    pub fn fail(&mut self, reason_code: ReasonCode) {
-        *self = match mem::take(self) {
-            Self::Ok(n) | Self::Faulted(n, _) => Self::Faulted(n, reason_code),
-            Self::Terminated => Self::Terminated,
+    /// Returns the network connection and its status separately if the connection is ok, so that
+    /// the status can be changed while the connection is in use.
+    #[cfg(feature = "v5")]
+    pub fn split(&mut self) -> Option<(&mut N, &mut Status)> {
+        match (&mut self.net, &mut self.status) {
+            (Some(n), s @ Status::Ok) => Some((n, s)),
+            _ => None,
         }
     }
 
+    pub fn fail(&mut self, reason_code: ReasonCode) {
+        self.status.fail(reason_code);
+    }
+
+    /// Closes the connection and returns it. The connection is also returned if its status has
+    /// already been terminated while it was split.
     pub fn terminate(&mut self) -> Option<N> {
-        match mem::take(self) {
-            Self::Ok(n) => Some(n),
-            Self::Faulted(n, _) => Some(n),
-            Self::Terminated => None,
-        }
+        self.status = Status::Terminated;
+        self.net.take()
     }
 }
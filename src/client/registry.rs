@@ -0,0 +1,780 @@
+//! Contains the trait the client uses to record its subscriptions and basic implementations.
+
+#[cfg(feature = "alloc")]
+pub use alloc::AllocSubscriptionRegistry;
+
+use heapless::{String, Vec};
+
+use crate::{
+    client::options::{FilterOptions, SubscriptionOptions},
+    types::{MqttString, PacketIdentifier, QoS, ReasonCode, ReasonCodes, TopicFilter, VarByteInt},
+};
+
+/// The state of a recorded subscription.
+///
+/// The options are the ones of the latest SUBSCRIBE packet for the topic filter, without the
+/// user properties.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Subscription {
+    /// The options the topic filter has been subscribed to with.
+    pub options: SubscriptionOptions<'static>,
+
+    /// The maximum quality of service the server granted in its SUBACK packet. [`None`] if the
+    /// server has not granted the subscription in the current session yet.
+    pub granted_qos: Option<QoS>,
+
+    /// The SUBSCRIBE or UNSUBSCRIBE packet awaiting its acknowledgement.
+    pub pending: Option<Pending>,
+}
+
+/// A SUBSCRIBE or UNSUBSCRIBE packet containing a recorded topic filter which the server has not
+/// acknowledged yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pending {
+    /// A SUBSCRIBE packet awaiting its SUBACK packet.
+    Subscribe {
+        /// The packet identifier of the SUBSCRIBE packet.
+        packet_identifier: PacketIdentifier,
+        /// The position of the topic filter in the SUBSCRIBE packet.
+        index: u16,
+    },
+
+    /// An UNSUBSCRIBE packet awaiting its UNSUBACK packet.
+    Unsubscribe {
+        /// The packet identifier of the UNSUBSCRIBE packet.
+        packet_identifier: PacketIdentifier,
+        /// The position of the topic filter in the UNSUBSCRIBE packet.
+        index: u16,
+    },
+}
+
+/// Storage for the subscriptions of a [`Client`].
+///
+/// A [`Client`] with a registry set via [`Client::track_subscriptions_in`] records the topic
+/// filter of every SUBSCRIBE packet with its options and the quality of service granted in the
+/// SUBACK packet and removes it once an UNSUBACK packet confirms the unsubscription. After
+/// reconnecting, [`Client::resubscribe_all`] subscribes again to the recorded topic filters the
+/// server has not granted in the current session.
+///
+/// The subscriptions are addressed by their position, which may change when a subscription is
+/// removed.
+///
+/// [`Client`]: crate::client::Client
+/// [`Client::track_subscriptions_in`]: crate::client::Client::track_subscriptions_in
+/// [`Client::resubscribe_all`]: crate::client::Client::resubscribe_all
+pub trait SubscriptionRegistry {
+    /// Returns the number of subscriptions.
+    fn len(&self) -> usize;
+
+    /// Returns whether there are no subscriptions.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the topic filter and state of the subscription at this position.
+    fn get(&self, index: usize) -> Option<(TopicFilter<'_>, Subscription)>;
+
+    /// Returns the position of the subscription to this topic filter.
+    fn find(&self, topic_filter: &TopicFilter<'_>) -> Option<usize> {
+        (0..self.len()).find(|i| self.get(*i).is_some_and(|(f, _)| f == *topic_filter))
+    }
+
+    /// Adds a subscription to a topic filter which has not been recorded yet.
+    ///
+    /// Returns `false` if there is not enough space. The client then does not subscribe.
+    fn push(&mut self, topic_filter: &TopicFilter<'_>, subscription: Subscription) -> bool;
+
+    /// Replaces the state of the subscription at this position.
+    fn set(&mut self, index: usize, subscription: Subscription);
+
+    /// Removes the subscription at this position.
+    fn remove(&mut self, index: usize);
+
+    /// Removes all subscriptions.
+    fn clear(&mut self);
+}
+
+impl dyn SubscriptionRegistry + '_ {
+    /// Adds the topic filters of a SUBSCRIBE packet which are not recorded yet.
+    ///
+    /// Returns `false` if not all of them could be added, in which case the registry is left
+    /// unchanged.
+    pub(crate) fn add(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        subscription_identifier: Option<VarByteInt>,
+    ) -> bool {
+        let len = self.len();
+
+        for (topic_filter, options) in subscriptions {
+            if self.find(topic_filter).is_none() {
+                let subscription = Subscription {
+                    options: SubscriptionOptions::from_filter_options(
+                        *options,
+                        subscription_identifier,
+                    ),
+                    granted_qos: None,
+                    pending: None,
+                };
+
+                if !self.push(topic_filter, subscription) {
+                    self.truncate(len);
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Removes the subscriptions at and after this position.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        while self.len() > len {
+            self.remove(self.len() - 1);
+        }
+    }
+
+    /// Marks the topic filters of a sent SUBSCRIBE packet as pending. The topic filters must have
+    /// been added with [`Self::add`].
+    pub(crate) fn subscribing(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        subscription_identifier: Option<VarByteInt>,
+        packet_identifier: PacketIdentifier,
+    ) {
+        for (index, (topic_filter, options)) in subscriptions.iter().enumerate() {
+            if let Some(i) = self.find(topic_filter) {
+                let granted_qos = self.get(i).and_then(|(_, s)| s.granted_qos);
+
+                self.set(
+                    i,
+                    Subscription {
+                        options: SubscriptionOptions::from_filter_options(
+                            *options,
+                            subscription_identifier,
+                        ),
+                        granted_qos,
+                        pending: Some(Pending::Subscribe {
+                            packet_identifier,
+                            index: index as u16,
+                        }),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Marks the recorded topic filters of a sent UNSUBSCRIBE packet as pending.
+    pub(crate) fn unsubscribing(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        packet_identifier: PacketIdentifier,
+    ) {
+        for (index, topic_filter) in topic_filters.iter().enumerate() {
+            if let Some(i) = self.find(topic_filter) {
+                if let Some((_, subscription)) = self.get(i) {
+                    self.set(
+                        i,
+                        Subscription {
+                            pending: Some(Pending::Unsubscribe {
+                                packet_identifier,
+                                index: index as u16,
+                            }),
+                            ..subscription
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Applies the reason codes of a SUBACK packet to the subscriptions pending with its packet
+    /// identifier. Refused subscriptions which have not been granted before are removed.
+    pub(crate) fn subacked(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        codes: &ReasonCodes<'_>,
+    ) {
+        let mut i = 0;
+
+        while let Some((_, subscription)) = self.get(i) {
+            let Some(Pending::Subscribe {
+                packet_identifier: pid,
+                index,
+            }) = subscription.pending
+            else {
+                i += 1;
+                continue;
+            };
+
+            if pid != packet_identifier {
+                i += 1;
+                continue;
+            }
+
+            let granted_qos = match codes.get(index.into()) {
+                Some(ReasonCode::Success) => Some(QoS::AtMostOnce),
+                Some(ReasonCode::GrantedQoS1) => Some(QoS::AtLeastOnce),
+                Some(ReasonCode::GrantedQoS2) => Some(QoS::ExactlyOnce),
+                _ => subscription.granted_qos,
+            };
+
+            if granted_qos.is_some() {
+                self.set(
+                    i,
+                    Subscription {
+                        granted_qos,
+                        pending: None,
+                        ..subscription
+                    },
+                );
+                i += 1;
+            } else {
+                self.remove(i);
+            }
+        }
+    }
+
+    /// Applies the reason codes of an UNSUBACK packet to the subscriptions pending with its
+    /// packet identifier. Subscriptions are removed if the server confirms that it does not have
+    /// them (anymore).
+    pub(crate) fn unsubacked(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        codes: &ReasonCodes<'_>,
+    ) {
+        let mut i = 0;
+
+        while let Some((_, subscription)) = self.get(i) {
+            let Some(Pending::Unsubscribe {
+                packet_identifier: pid,
+                index,
+            }) = subscription.pending
+            else {
+                i += 1;
+                continue;
+            };
+
+            if pid != packet_identifier {
+                i += 1;
+                continue;
+            }
+
+            if codes.get(index.into()).is_some_and(|c| c.is_success()) {
+                self.remove(i);
+            } else {
+                self.set(
+                    i,
+                    Subscription {
+                        pending: None,
+                        ..subscription
+                    },
+                );
+                i += 1;
+            }
+        }
+    }
+
+    /// Forgets the SUBSCRIBE and UNSUBSCRIBE packets in flight when the connection was lost,
+    /// which the client does not resend after reconnecting to a present session. The subscriptions
+    /// keep their granted quality of service.
+    pub(crate) fn reconnect(&mut self) {
+        for i in 0..self.len() {
+            if let Some((_, subscription)) = self.get(i) {
+                if subscription.pending.is_some() {
+                    self.set(
+                        i,
+                        Subscription {
+                            pending: None,
+                            ..subscription
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Forgets the state of the previous session. Subscriptions pending to be unsubscribed are
+    /// removed, all others are marked as not granted.
+    pub(crate) fn reset(&mut self) {
+        let mut i = 0;
+
+        while let Some((_, subscription)) = self.get(i) {
+            if matches!(subscription.pending, Some(Pending::Unsubscribe { .. })) {
+                self.remove(i);
+            } else {
+                self.set(
+                    i,
+                    Subscription {
+                        granted_qos: None,
+                        pending: None,
+                        ..subscription
+                    },
+                );
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Records up to `SUBSCRIPTIONS` subscriptions to topic filters of up to `FILTER_SIZE` bytes in
+/// place.
+#[derive(Debug, Default)]
+pub struct FixedSubscriptionRegistry<const SUBSCRIPTIONS: usize, const FILTER_SIZE: usize> {
+    subscriptions: Vec<(String<FILTER_SIZE>, Subscription), SUBSCRIPTIONS>,
+}
+
+#[cfg(feature = "defmt")]
+impl<const SUBSCRIPTIONS: usize, const FILTER_SIZE: usize> defmt::Format
+    for FixedSubscriptionRegistry<SUBSCRIPTIONS, FILTER_SIZE>
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "FixedSubscriptionRegistry {{ subscriptions: {} }}",
+            self.subscriptions.len()
+        );
+    }
+}
+
+impl<const SUBSCRIPTIONS: usize, const FILTER_SIZE: usize>
+    FixedSubscriptionRegistry<SUBSCRIPTIONS, FILTER_SIZE>
+{
+    /// Creates an empty registry.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            subscriptions: Vec::new(),
+        }
+    }
+}
+
+impl<const SUBSCRIPTIONS: usize, const FILTER_SIZE: usize> SubscriptionRegistry
+    for FixedSubscriptionRegistry<SUBSCRIPTIONS, FILTER_SIZE>
+{
+    fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    fn get(&self, index: usize) -> Option<(TopicFilter<'_>, Subscription)> {
+        self.subscriptions
+            .get(index)
+            .map(|(f, s)| (filter(f.as_str()), *s))
+    }
+
+    fn push(&mut self, topic_filter: &TopicFilter<'_>, subscription: Subscription) -> bool {
+        let mut f = String::new();
+
+        f.push_str(topic_filter.as_ref().as_str()).is_ok()
+            && self.subscriptions.push((f, subscription)).is_ok()
+    }
+
+    fn set(&mut self, index: usize, subscription: Subscription) {
+        if let Some((_, s)) = self.subscriptions.get_mut(index) {
+            *s = subscription;
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        if index < self.subscriptions.len() {
+            self.subscriptions.remove(index);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.subscriptions.clear();
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use alloc::{boxed::Box, vec::Vec};
+
+    use crate::{
+        client::registry::{Subscription, SubscriptionRegistry, filter},
+        types::TopicFilter,
+    };
+
+    /// Records subscriptions on the heap using the global allocator.
+    #[derive(Debug, Default)]
+    pub struct AllocSubscriptionRegistry {
+        subscriptions: Vec<(Box<str>, Subscription)>,
+    }
+
+    #[cfg(feature = "defmt")]
+    impl defmt::Format for AllocSubscriptionRegistry {
+        fn format(&self, fmt: defmt::Formatter) {
+            defmt::write!(
+                fmt,
+                "AllocSubscriptionRegistry {{ subscriptions: {} }}",
+                self.subscriptions.len()
+            );
+        }
+    }
+
+    impl AllocSubscriptionRegistry {
+        /// Creates an empty registry.
+        #[must_use]
+        pub const fn new() -> Self {
+            Self {
+                subscriptions: Vec::new(),
+            }
+        }
+    }
+
+    impl SubscriptionRegistry for AllocSubscriptionRegistry {
+        fn len(&self) -> usize {
+            self.subscriptions.len()
+        }
+
+        fn get(&self, index: usize) -> Option<(TopicFilter<'_>, Subscription)> {
+            self.subscriptions.get(index).map(|(f, s)| (filter(f), *s))
+        }
+
+        fn push(&mut self, topic_filter: &TopicFilter<'_>, subscription: Subscription) -> bool {
+            self.subscriptions
+                .push((topic_filter.as_ref().as_str().into(), subscription));
+            true
+        }
+
+        fn set(&mut self, index: usize, subscription: Subscription) {
+            if let Some((_, s)) = self.subscriptions.get_mut(index) {
+                *s = subscription;
+            }
+        }
+
+        fn remove(&mut self, index: usize) {
+            if index < self.subscriptions.len() {
+                self.subscriptions.remove(index);
+            }
+        }
+
+        fn clear(&mut self) {
+            self.subscriptions.clear();
+        }
+    }
+}
+
+/// Restores a recorded topic filter.
+fn filter(s: &str) -> TopicFilter<'_> {
+    // Invariant: Only valid topic filters are recorded.
+    TopicFilter::new_unchecked(MqttString::from_str_unchecked(s))
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{
+        bytes::Bytes,
+        client::{
+            options::{FilterOptions, SubscriptionOptions},
+            registry::{FixedSubscriptionRegistry, Pending, Subscription, SubscriptionRegistry},
+        },
+        types::{MqttString, PacketIdentifier, QoS, ReasonCodes, TopicFilter},
+    };
+
+    fn topic_filter(s: &str) -> TopicFilter<'_> {
+        TopicFilter::new(MqttString::try_from(s).unwrap()).unwrap()
+    }
+
+    fn pid(pid: u16) -> PacketIdentifier {
+        PacketIdentifier::new(NonZero::new(pid).unwrap())
+    }
+
+    fn codes(codes: &[u8]) -> ReasonCodes<'_> {
+        ReasonCodes::new_unchecked(Bytes::Borrowed(codes))
+    }
+
+    /// Subscribes to the topic filters in one SUBSCRIBE packet.
+    fn subscribe(r: &mut dyn SubscriptionRegistry, filters: &[&str], packet_identifier: u16) {
+        let mut subscriptions = heapless::Vec::<_, 4>::new();
+        for f in filters {
+            subscriptions
+                .push((topic_filter(f), FilterOptions::new().at_least_once()))
+                .unwrap();
+        }
+
+        assert!(r.add(&subscriptions, None));
+        r.subscribing(&subscriptions, None, pid(packet_identifier));
+    }
+
+    fn state(r: &dyn SubscriptionRegistry, f: &str) -> Option<(Option<QoS>, Option<Pending>)> {
+        let i = r.find(&topic_filter(f))?;
+        r.get(i).map(|(_, s)| (s.granted_qos, s.pending))
+    }
+
+    #[test]
+    fn fixed_registry_capacity() {
+        let subscription = Subscription {
+            options: SubscriptionOptions::new(),
+            granted_qos: Some(QoS::AtMostOnce),
+            pending: None,
+        };
+        let a = TopicFilter::new(MqttString::try_from("a/+").unwrap()).unwrap();
+        let b = TopicFilter::new(MqttString::try_from("b").unwrap()).unwrap();
+        let long = TopicFilter::new(MqttString::try_from("a/b/c/d").unwrap()).unwrap();
+
+        let mut r = FixedSubscriptionRegistry::<2, 4>::new();
+        assert!(r.push(&a, subscription));
+        assert!(!r.push(&long, subscription));
+        assert!(r.push(&b, subscription));
+        assert!(!r.push(&a, subscription));
+
+        assert_eq!(r.find(&b), Some(1));
+        assert_eq!(r.get(0).unwrap().0, a);
+
+        r.set(
+            1,
+            Subscription {
+                granted_qos: Some(QoS::ExactlyOnce),
+                ..subscription
+            },
+        );
+        assert_eq!(r.get(1).unwrap().1.granted_qos, Some(QoS::ExactlyOnce));
+
+        r.remove(0);
+        assert_eq!(r.find(&a), None);
+        assert_eq!(r.find(&b), Some(0));
+        r.clear();
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn add_rolls_back_when_full() {
+        let mut r = FixedSubscriptionRegistry::<2, 8>::new();
+        let r: &mut dyn SubscriptionRegistry = &mut r;
+
+        subscribe(r, &["a"], 1);
+
+        let options = FilterOptions::new();
+        assert!(!r.add(
+            &[
+                (topic_filter("a"), options),
+                (topic_filter("b"), options),
+                (topic_filter("c"), options),
+            ],
+            None
+        ));
+        assert_eq!(r.len(), 1);
+        assert!(state(r, "b").is_none());
+
+        assert!(r.add(
+            &[(topic_filter("a"), options), (topic_filter("b"), options)],
+            None
+        ));
+        assert_eq!(r.len(), 2);
+
+        r.truncate(1);
+        assert_eq!(r.len(), 1);
+        assert!(state(r, "a").is_some());
+        assert!(state(r, "b").is_none());
+    }
+
+    #[test]
+    fn subacked() {
+        let mut r = FixedSubscriptionRegistry::<4, 8>::new();
+        let r: &mut dyn SubscriptionRegistry = &mut r;
+
+        subscribe(r, &["a", "b", "c"], 1);
+        subscribe(r, &["d"], 2);
+        assert_eq!(
+            state(r, "b"),
+            Some((
+                None,
+                Some(Pending::Subscribe {
+                    packet_identifier: pid(1),
+                    index: 1
+                })
+            ))
+        );
+
+        // Granted, refused and missing reason codes.
+        r.subacked(pid(1), &codes(&[0x02, 0x80]));
+        assert_eq!(state(r, "a"), Some((Some(QoS::ExactlyOnce), None)));
+        assert!(state(r, "b").is_none());
+        assert!(state(r, "c").is_none());
+        assert!(state(r, "d").unwrap().1.is_some());
+
+        // A refused subscription which was granted before keeps its granted QoS.
+        subscribe(r, &["a"], 3);
+        r.subacked(pid(3), &codes(&[0x87]));
+        assert_eq!(state(r, "a"), Some((Some(QoS::ExactlyOnce), None)));
+
+        r.subacked(pid(2), &codes(&[0x00]));
+        assert_eq!(state(r, "d"), Some((Some(QoS::AtMostOnce), None)));
+        assert_eq!(r.len(), 2);
+    }
+
+    #[test]
+    fn unsubacked() {
+        let mut r = FixedSubscriptionRegistry::<4, 8>::new();
+        let r: &mut dyn SubscriptionRegistry = &mut r;
+
+        subscribe(r, &["a", "b", "c"], 1);
+        r.subacked(pid(1), &codes(&[0x01, 0x01, 0x01]));
+
+        r.unsubscribing(&[topic_filter("a"), topic_filter("b")], pid(2));
+        assert_eq!(
+            state(r, "b"),
+            Some((
+                Some(QoS::AtLeastOnce),
+                Some(Pending::Unsubscribe {
+                    packet_identifier: pid(2),
+                    index: 1
+                })
+            ))
+        );
+
+        r.unsubacked(pid(2), &codes(&[0x00, 0x80]));
+        assert!(state(r, "a").is_none());
+        assert_eq!(state(r, "b"), Some((Some(QoS::AtLeastOnce), None)));
+        assert_eq!(state(r, "c"), Some((Some(QoS::AtLeastOnce), None)));
+    }
+
+    #[test]
+    fn reset() {
+        let mut r = FixedSubscriptionRegistry::<4, 8>::new();
+        let r: &mut dyn SubscriptionRegistry = &mut r;
+
+        subscribe(r, &["a", "b"], 1);
+        r.subacked(pid(1), &codes(&[0x01, 0x01]));
+        subscribe(r, &["c"], 2);
+        r.unsubscribing(&[topic_filter("b")], pid(3));
+
+        r.reset();
+        assert_eq!(r.len(), 2);
+        assert_eq!(state(r, "a"), Some((None, None)));
+        assert!(state(r, "b").is_none());
+        assert_eq!(state(r, "c"), Some((None, None)));
+    }
+
+    #[test]
+    fn reconnect() {
+        let mut r = FixedSubscriptionRegistry::<4, 8>::new();
+        let r: &mut dyn SubscriptionRegistry = &mut r;
+
+        subscribe(r, &["a", "b"], 1);
+        r.subacked(pid(1), &codes(&[0x01, 0x01]));
+        subscribe(r, &["c"], 2);
+        subscribe(r, &["a"], 3);
+        r.unsubscribing(&[topic_filter("b")], pid(4));
+
+        r.reconnect();
+        assert_eq!(r.len(), 3);
+        assert_eq!(state(r, "a"), Some((Some(QoS::AtLeastOnce), None)));
+        assert_eq!(state(r, "b"), Some((Some(QoS::AtLeastOnce), None)));
+        assert_eq!(state(r, "c"), Some((None, None)));
+
+        // Acknowledgements of the previous connection's packet identifiers are ignored.
+        r.subacked(pid(2), &codes(&[0x80]));
+        r.unsubacked(pid(4), &codes(&[0x00]));
+        assert_eq!(r.len(), 3);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn session_present_forgets_pending() {
+        use core::time::Duration;
+
+        use embedded_io_adapters::tokio_1::FromTokio;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt, duplex},
+            join,
+            time::timeout,
+        };
+        use tokio_test::{assert_err, assert_ok};
+
+        #[cfg(feature = "alloc")]
+        use crate::buffer::AllocBuffer;
+        #[cfg(feature = "bump")]
+        use crate::buffer::BumpBuffer;
+        use crate::client::{Client, options::ConnectOptions};
+
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut r = FixedSubscriptionRegistry::<1, 128>::new();
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.track_subscriptions_in(&mut r);
+
+        let (c, mut s) = duplex(64);
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new();
+            let id = MqttString::try_from("c").unwrap();
+            assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+        };
+        join!(server, connect);
+
+        // The server does not read, so the SUBSCRIBE packet does not fit into the transport.
+        let long = [b'a'; 100];
+        let long = core::str::from_utf8(&long).unwrap();
+        let long = TopicFilter::new(MqttString::try_from(long).unwrap()).unwrap();
+        let options = SubscriptionOptions::new();
+        assert_err!(timeout(Duration::from_millis(10), client.subscribe(long, &options)).await);
+        assert!(
+            client
+                .subscriptions()
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .1
+                .pending
+                .is_some()
+        );
+
+        drop(s);
+        assert_err!(client.poll().await);
+        client.abort().await;
+
+        let (c, mut s) = duplex(64);
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x01, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new();
+            let id = MqttString::try_from("c").unwrap();
+            let connected = assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+            assert!(connected.session_present);
+        };
+        join!(server, connect);
+        assert!(
+            client
+                .subscriptions()
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .1
+                .pending
+                .is_none()
+        );
+
+        // The registry is kept when resubscribing is cancelled and the subscription is sent again
+        // by the next call.
+        assert_err!(timeout(Duration::from_millis(10), client.resubscribe_all()).await);
+        assert!(client.subscriptions().is_some());
+        assert!(
+            client
+                .subscriptions()
+                .unwrap()
+                .get(0)
+                .unwrap()
+                .1
+                .pending
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,839 @@
+//! Implements the requesting and the responding side of the request/response pattern on top of
+//! response topics and correlation data.
+
+use core::time::Duration;
+
+use heapless::{String, Vec};
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        Client, MqttError,
+        event::{Connected, Publish, Suback},
+        options::{PublicationOptions, SubscriptionOptions, TopicReference},
+    },
+    fmt::{const_assert, panic},
+    io::Transport,
+    time::{Clock, Instant},
+    types::{
+        MqttBinary, MqttString, MqttStringPair, PacketIdentifier, QoS, TopicFilter, TopicName,
+    },
+};
+
+/// The name of the user property which marks a reply as an error reply. Its value describes the
+/// error.
+pub const ERROR_PROPERTY: &str = "error";
+
+/// Identifies a request made with a [`Requester`]. Its big-endian bytes are sent as the
+/// correlation data of the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestId(u64);
+
+impl RequestId {
+    /// Returns the correlation data identifying this request.
+    #[must_use]
+    pub const fn correlation_data(self) -> [u8; 8] {
+        self.0.to_be_bytes()
+    }
+
+    /// Returns the request identified by correlation data or [`None`] if the correlation data has
+    /// not been created by a [`Requester`].
+    #[must_use]
+    pub fn from_correlation_data(correlation_data: &[u8]) -> Option<Self> {
+        correlation_data
+            .try_into()
+            .ok()
+            .map(|bytes| Self(u64::from_be_bytes(bytes)))
+    }
+}
+
+/// Error returned by [`Requester::connected`] if the derived response topic does not fit into
+/// `TOPIC_SIZE` bytes or is not a valid topic name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidResponseTopic;
+
+/// Makes requests and matches the responses to them.
+///
+/// After every connection, [`Self::connected`] derives the response topic from the
+/// [`Connected::response_information`] and [`Self::subscribe`] subscribes to it if the
+/// subscription has not been granted in the current session yet. The server's SUBACK packet is
+/// passed to [`Self::subacked`]. [`Self::request`] publishes a request with this response topic
+/// and correlation data which is unique for this requester.
+///
+/// Incoming publications are passed to [`Self::accept`], which returns the [`RequestId`] of the
+/// request they respond to. Requests which are not responded to within their timeout are
+/// returned by [`Self::expire`], which is due at [`Self::next_deadline`].
+///
+/// Up to `REQUESTS` requests can be pending at a time. The response topic is at most
+/// `TOPIC_SIZE` bytes long.
+pub struct Requester<'r, const REQUESTS: usize, const TOPIC_SIZE: usize> {
+    clock: &'r dyn Clock,
+    suffix: &'r str,
+
+    response_topic: String<TOPIC_SIZE>,
+    subscribed: bool,
+    subscribing: Option<PacketIdentifier>,
+
+    next_id: u64,
+    pending: Vec<(RequestId, Instant), REQUESTS>,
+}
+
+impl<const REQUESTS: usize, const TOPIC_SIZE: usize> core::fmt::Debug
+    for Requester<'_, REQUESTS, TOPIC_SIZE>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Requester")
+            .field("suffix", &self.suffix)
+            .field("response_topic", &self.response_topic)
+            .field("subscribed", &self.subscribed)
+            .field("subscribing", &self.subscribing)
+            .field("next_id", &self.next_id)
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const REQUESTS: usize, const TOPIC_SIZE: usize> defmt::Format
+    for Requester<'_, REQUESTS, TOPIC_SIZE>
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Requester {{ response_topic: {}, subscribed: {}, subscribing: {}, next_id: {}, pending: {}, .. }}",
+            self.response_topic.as_str(),
+            self.subscribed,
+            self.subscribing,
+            self.next_id,
+            self.pending.len()
+        );
+    }
+}
+
+impl<'r, const REQUESTS: usize, const TOPIC_SIZE: usize> Requester<'r, REQUESTS, TOPIC_SIZE> {
+    /// Creates a requester whose response topic is `suffix` appended as a separate topic level to
+    /// the response information or the client identifier.
+    ///
+    /// The request identifiers count up from `seed`. A restarted requester reuses the correlation
+    /// data of its earlier requests unless it is given a different seed, e.g. a random number or
+    /// a counter persisted across restarts.
+    pub fn new(clock: &'r dyn Clock, suffix: &'r str, seed: u64) -> Self {
+        Self {
+            clock,
+            suffix,
+            response_topic: String::new(),
+            subscribed: false,
+            subscribing: None,
+            next_id: seed,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Derives the response topic for a new connection. It is made of the
+    /// [`Connected::response_information`] if the server has sent it and otherwise of the
+    /// [`Connected::client_identifier`], followed by the suffix of this requester. The response
+    /// information is only sent by the server if `request_response_information` is set in the
+    /// [`ConnectOptions`](crate::client::options::ConnectOptions).
+    ///
+    /// If the session is not present or the response topic has changed, [`Self::subscribe`]
+    /// subscribes again.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidResponseTopic`] if the response topic is longer than `TOPIC_SIZE` bytes or not a
+    /// valid topic name.
+    pub fn connected<const MAX_USER_PROPERTIES: usize>(
+        &mut self,
+        connected: &Connected<'_, MAX_USER_PROPERTIES>,
+    ) -> Result<(), InvalidResponseTopic> {
+        let base = match &connected.response_information {
+            Some(i) if !i.is_empty() => i,
+            _ => &connected.client_identifier,
+        };
+
+        let mut topic = String::<TOPIC_SIZE>::new();
+        topic
+            .push_str(base.as_ref())
+            .map_err(|_| InvalidResponseTopic)?;
+        if !self.suffix.is_empty() {
+            if !topic.ends_with('/') {
+                topic.push('/').map_err(|_| InvalidResponseTopic)?;
+            }
+            topic
+                .push_str(self.suffix)
+                .map_err(|_| InvalidResponseTopic)?;
+        }
+
+        MqttString::try_from(topic.as_str())
+            .ok()
+            .and_then(TopicName::new)
+            .ok_or(InvalidResponseTopic)?;
+
+        if !connected.session_present || topic != self.response_topic {
+            self.subscribed = false;
+        }
+        // The SUBACK packet of a previous connection is not received.
+        self.subscribing = None;
+        self.response_topic = topic;
+
+        Ok(())
+    }
+
+    /// Returns the response topic derived by [`Self::connected`].
+    #[must_use]
+    pub fn response_topic(&self) -> Option<TopicName<'_>> {
+        if self.response_topic.is_empty() {
+            None
+        } else {
+            // Invariant: The response topic has been validated in `Self::connected`.
+            Some(TopicName::new_unchecked(MqttString::from_str_unchecked(
+                self.response_topic.as_str(),
+            )))
+        }
+    }
+
+    /// Returns the number of requests awaiting their response.
+    #[inline]
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Subscribes to the response topic unless the subscription has already been granted in the
+    /// current session or is awaiting its SUBACK packet, which has to be passed to
+    /// [`Self::subacked`].
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent SUBSCRIBE packet or [`None`] if no subscription was
+    /// necessary.
+    ///
+    /// # Errors
+    ///
+    /// The errors of [`Client::subscribe`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no response topic has been derived with [`Self::connected`].
+    pub async fn subscribe<
+        'c,
+        N: Transport,
+        B: BufferProvider<'c>,
+        const SUBSCRIBE_MAXIMUM: usize,
+        const RECEIVE_MAXIMUM: usize,
+        const SEND_MAXIMUM: usize,
+        const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+        const MAX_USER_PROPERTIES: usize,
+        const TOPIC_ALIAS_MAXIMUM: usize,
+        const MAX_ALIASED_TOPIC_LEN: usize,
+    >(
+        &mut self,
+        client: &mut Client<
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        if self.subscribed || self.subscribing.is_some() {
+            return Ok(None);
+        }
+
+        let Some(topic) = self.response_topic() else {
+            panic!("attempted to subscribe to the response topic before connecting");
+        };
+
+        // A valid topic name is a valid topic filter.
+        let topic_filter = TopicFilter::new_unchecked(topic.into());
+        let pid = client.subscribe(topic_filter, options).await?;
+        self.subscribing = Some(pid);
+
+        Ok(Some(pid))
+    }
+
+    /// Applies a SUBACK packet to the subscription made by [`Self::subscribe`]. The response topic
+    /// is subscribed to once the server has granted the subscription. If the server has refused
+    /// it, [`Self::subscribe`] subscribes again.
+    ///
+    /// # Returns:
+    /// Whether the SUBACK packet acknowledges the subscription to the response topic.
+    pub fn subacked<const MAX_USER_PROPERTIES: usize>(
+        &mut self,
+        suback: &Suback<'_, MAX_USER_PROPERTIES>,
+    ) -> bool {
+        if self.subscribing != Some(suback.packet_identifier) {
+            return false;
+        }
+
+        self.subscribing = None;
+        self.subscribed = suback.reason_codes.get(0).is_some_and(|c| c.is_success());
+
+        true
+    }
+
+    /// Publishes a request with `options`, replacing their response topic and correlation data.
+    /// The request is pending until [`Self::accept`] receives its response or [`Self::expire`]
+    /// returns it after `timeout` has passed.
+    ///
+    /// # Returns:
+    /// The [`RequestId`] matching the response to the request.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::SessionBuffer`] if `REQUESTS` requests are pending
+    /// * the errors of [`Client::publish`]
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no response topic has been derived with [`Self::connected`] and
+    /// in the cases listed in [`Client::publish`].
+    pub async fn request<
+        'c,
+        N: Transport,
+        B: BufferProvider<'c>,
+        const SUBSCRIBE_MAXIMUM: usize,
+        const RECEIVE_MAXIMUM: usize,
+        const SEND_MAXIMUM: usize,
+        const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+        const MAX_USER_PROPERTIES: usize,
+        const TOPIC_ALIAS_MAXIMUM: usize,
+        const MAX_ALIASED_TOPIC_LEN: usize,
+    >(
+        &mut self,
+        client: &mut Client<
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+        timeout: Duration,
+    ) -> Result<RequestId, MqttError<'c, 0>> {
+        if self.pending.is_full() {
+            return Err(MqttError::SessionBuffer);
+        }
+
+        let Some(response_topic) = self.response_topic() else {
+            panic!("attempted to make a request before connecting");
+        };
+
+        let id = RequestId(self.next_id);
+        let correlation_data = id.correlation_data();
+
+        let options = PublicationOptions {
+            response_topic: Some(response_topic),
+            correlation_data: Some(MqttBinary::from_slice_unchecked(&correlation_data)),
+            ..options.clone()
+        };
+
+        client.publish(&options, message).await?;
+
+        self.next_id = self.next_id.wrapping_add(1);
+        // Invariant: There is space for the request as checked above.
+        let _ = self.pending.push((id, self.clock.now() + timeout));
+
+        Ok(id)
+    }
+
+    /// Matches an incoming publication to a pending request. The publication is the response to
+    /// the request if it is published to the response topic with the correlation data of the
+    /// request. The request is then no longer pending.
+    ///
+    /// # Returns:
+    /// The [`RequestId`] of the request or [`None`] if the publication is not a response to a
+    /// pending request.
+    pub fn accept<const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize>(
+        &mut self,
+        publish: &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Option<RequestId> {
+        if publish.topic.as_ref().as_str() != self.response_topic.as_str() {
+            return None;
+        }
+
+        let id = RequestId::from_correlation_data(publish.correlation_data.as_ref()?.as_bytes())?;
+        self.cancel(id).then_some(id)
+    }
+
+    /// Stops waiting for the response to a request.
+    ///
+    /// Returns whether the request was pending.
+    pub fn cancel(&mut self, id: RequestId) -> bool {
+        if let Some(i) = self.pending.iter().position(|(p, _)| *p == id) {
+            self.pending.swap_remove(i);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the earliest deadline of the pending requests, at which [`Self::expire`] should be
+    /// called.
+    #[must_use]
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.iter().map(|(_, d)| *d).min()
+    }
+
+    /// Removes a pending request whose timeout has passed.
+    ///
+    /// # Returns:
+    /// The [`RequestId`] of the expired request or [`None`] if no request has expired. Should be
+    /// called until it returns [`None`].
+    pub fn expire(&mut self) -> Option<RequestId> {
+        let now = self.clock.now();
+        let i = self.pending.iter().position(|(_, d)| *d <= now)?;
+
+        Some(self.pending.swap_remove(i).0)
+    }
+}
+
+/// Error returned by [`Reply::to`] and [`Reply::echo`] if the request has no response topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NoResponseTopic;
+
+/// The reply to a request, which is published to the response topic of the request with its
+/// correlation data.
+///
+/// An error reply carries the [`ERROR_PROPERTY`] user property, which [`error_of`] reads on the
+/// requesting side.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Reply<'r, const MAX_USER_PROPERTIES: usize> {
+    topic: TopicName<'r>,
+    qos: QoS,
+    correlation_data: Option<MqttBinary<'r>>,
+    content_type: Option<MqttString<'r>>,
+    user_properties: Vec<MqttStringPair<'r>, MAX_USER_PROPERTIES>,
+}
+
+impl<'r, const MAX_USER_PROPERTIES: usize> Reply<'r, MAX_USER_PROPERTIES> {
+    /// Creates a reply to `request` which copies its correlation data.
+    ///
+    /// # Errors
+    ///
+    /// [`NoResponseTopic`] if the request has no response topic and cannot be replied to.
+    pub fn to<const MAX_SUBSCRIPTION_IDENTIFIERS: usize>(
+        request: &'r Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<Self, NoResponseTopic> {
+        let topic = request.response_topic.as_ref().ok_or(NoResponseTopic)?;
+
+        Ok(Self {
+            topic: topic.as_borrowed(),
+            qos: QoS::AtMostOnce,
+            correlation_data: request
+                .correlation_data
+                .as_ref()
+                .map(MqttBinary::as_borrowed),
+            content_type: None,
+            user_properties: Vec::new(),
+        })
+    }
+
+    /// Creates a reply to `request` which copies its correlation data, content type and user
+    /// properties.
+    ///
+    /// # Errors
+    ///
+    /// [`NoResponseTopic`] if the request has no response topic and cannot be replied to.
+    pub fn echo<const MAX_SUBSCRIPTION_IDENTIFIERS: usize>(
+        request: &'r Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<Self, NoResponseTopic> {
+        let mut reply = Self::to(request)?;
+        reply.content_type = request.content_type.as_ref().map(MqttString::as_borrowed);
+        reply.user_properties = request
+            .user_properties
+            .iter()
+            .map(MqttStringPair::as_borrowed)
+            .collect();
+
+        Ok(reply)
+    }
+
+    /// Sets the quality of service of the reply.
+    #[must_use]
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Sets the content type of the reply.
+    #[must_use]
+    pub fn content_type(mut self, content_type: MqttString<'r>) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// Turns the reply into an error reply. Its user properties are replaced by the
+    /// [`ERROR_PROPERTY`] with `message` as its value.
+    #[must_use]
+    pub fn error(mut self, message: MqttString<'r>) -> Self {
+        const {
+            const_assert!(
+                MAX_USER_PROPERTIES > 0,
+                "MAX_USER_PROPERTIES must be greater than 0 to send error replies"
+            );
+        }
+
+        self.user_properties.clear();
+        // Invariant: The vector has been cleared and has a capacity greater than 0.
+        let _ = self.user_properties.push(MqttStringPair::new(
+            MqttString::from_str_unchecked(ERROR_PROPERTY),
+            message,
+        ));
+        self
+    }
+
+    /// Returns whether this is an error reply.
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.user_properties
+            .iter()
+            .any(|p| p.name.as_ref() == ERROR_PROPERTY)
+    }
+
+    /// Returns the options with which to publish the reply.
+    #[must_use]
+    pub fn options(&self) -> PublicationOptions<'_> {
+        PublicationOptions {
+            qos: self.qos,
+            correlation_data: self.correlation_data.as_ref().map(MqttBinary::as_borrowed),
+            user_properties: &self.user_properties,
+            content_type: self.content_type.as_ref().map(MqttString::as_borrowed),
+            ..PublicationOptions::new(TopicReference::Name(self.topic.as_borrowed()))
+        }
+    }
+}
+
+/// Returns the error message of an error reply, which carries the [`ERROR_PROPERTY`] user
+/// property, or [`None`] if the publication is not an error reply.
+#[must_use]
+pub fn error_of<'p, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize>(
+    reply: &'p Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+) -> Option<&'p str> {
+    reply
+        .user_properties
+        .iter()
+        .find(|p| p.name.as_ref() == ERROR_PROPERTY)
+        .map(|p| p.value.as_ref())
+}
+
+#[cfg(test)]
+mod unit {
+    use core::{cell::Cell, num::NonZero, time::Duration};
+
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use heapless::Vec;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, duplex},
+        join,
+    };
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        bytes::Bytes,
+        client::{
+            Client,
+            event::{Connected, Event, Publish, Suback},
+            options::{
+                AckMode, ConnectOptions, PublicationOptions, SubscriptionOptions, TopicReference,
+            },
+            request::{
+                ERROR_PROPERTY, InvalidResponseTopic, NoResponseTopic, Reply, RequestId, Requester,
+                error_of,
+            },
+        },
+        time::{Clock, Instant},
+        types::{
+            IdentifiedQoS, MqttBinary, MqttString, MqttStringPair, PacketIdentifier, QoS,
+            ReasonCodes, TopicName,
+        },
+    };
+
+    struct TestClock(Cell<u64>);
+
+    impl Clock for TestClock {
+        fn now(&self) -> Instant {
+            Instant::from_millis(self.0.get())
+        }
+    }
+
+    fn connected(
+        session_present: bool,
+        response_information: Option<&'static str>,
+    ) -> Connected<'static, 0> {
+        Connected {
+            session_present,
+            client_identifier: MqttString::try_from("c").unwrap(),
+            user_properties: Vec::new(),
+            response_information: response_information.map(|i| MqttString::try_from(i).unwrap()),
+            server_reference: None,
+        }
+    }
+
+    #[test]
+    fn response_topic() {
+        let clock = TestClock(Cell::new(0));
+        let mut r = Requester::<'_, 1, 8>::new(&clock, "res", 0);
+        assert!(r.response_topic().is_none());
+
+        assert_ok!(r.connected(&connected(false, None)));
+        assert_eq!(r.response_topic().unwrap().as_ref().as_str(), "c/res");
+
+        assert_ok!(r.connected(&connected(true, Some("r/"))));
+        assert_eq!(r.response_topic().unwrap().as_ref().as_str(), "r/res");
+
+        assert_eq!(
+            r.connected(&connected(true, Some("r/+"))),
+            Err(InvalidResponseTopic)
+        );
+        assert_eq!(
+            r.connected(&connected(true, Some("abcdef"))),
+            Err(InvalidResponseTopic)
+        );
+        assert_eq!(r.response_topic().unwrap().as_ref().as_str(), "r/res");
+    }
+
+    fn suback(packet_identifier: u16, reason_code: &'static [u8]) -> Suback<'static, 0> {
+        Suback {
+            packet_identifier: PacketIdentifier::new(NonZero::new(packet_identifier).unwrap()),
+            reason_string: None,
+            user_properties: Vec::new(),
+            reason_codes: ReasonCodes::new_unchecked(Bytes::Borrowed(reason_code)),
+        }
+    }
+
+    #[test]
+    fn refused_subscription() {
+        let clock = TestClock(Cell::new(0));
+        let mut r = Requester::<'_, 1, 8>::new(&clock, "res", 0);
+        assert_ok!(r.connected(&connected(false, None)));
+
+        r.subscribing = Some(PacketIdentifier::new(NonZero::new(1).unwrap()));
+        assert!(!r.subacked(&suback(2, &[0x00])));
+        assert!(r.subacked(&suback(1, &[0x87])));
+        assert!(!r.subscribed);
+        assert!(r.subscribing.is_none());
+
+        r.subscribing = Some(PacketIdentifier::new(NonZero::new(3).unwrap()));
+        assert!(r.subacked(&suback(3, &[0x01])));
+        assert!(r.subscribed);
+
+        // A lost SUBACK packet does not block the subscription after reconnecting.
+        r.subscribed = false;
+        r.subscribing = Some(PacketIdentifier::new(NonZero::new(4).unwrap()));
+        assert_ok!(r.connected(&connected(true, None)));
+        assert!(r.subscribing.is_none());
+    }
+
+    fn string(s: &'static str) -> MqttString<'static> {
+        MqttString::try_from(s).unwrap()
+    }
+
+    fn publish(
+        response_topic: Option<&'static str>,
+        user_properties: &[(&'static str, &'static str)],
+    ) -> Publish<'static, 0, 2> {
+        Publish {
+            ack_mode: AckMode::Automatic,
+            dup: false,
+            identified_qos: IdentifiedQoS::AtMostOnce,
+            retain: false,
+            topic: TopicName::new(string("req")).unwrap(),
+            payload_format_indicator: None,
+            message_expiry_interval: None,
+            response_topic: response_topic.map(|t| TopicName::new(string(t)).unwrap()),
+            correlation_data: Some(MqttBinary::try_from("c").unwrap()),
+            user_properties: user_properties
+                .iter()
+                .map(|(n, v)| MqttStringPair::new(string(n), string(v)))
+                .collect(),
+            subscription_identifiers: Vec::new(),
+            content_type: Some(string("text/plain")),
+            message: Bytes::Borrowed(b"m"),
+        }
+    }
+
+    #[test]
+    fn reply() {
+        assert_eq!(Reply::to(&publish(None, &[])).unwrap_err(), NoResponseTopic);
+
+        let request = publish(Some("res"), &[("k", "v")]);
+
+        let reply = assert_ok!(Reply::to(&request)).qos(QoS::AtLeastOnce);
+        let options = reply.options();
+        assert!(matches!(&options.topic, TopicReference::Name(t) if t.as_ref().as_str() == "res"));
+        assert_eq!(options.qos, QoS::AtLeastOnce);
+        assert_eq!(options.correlation_data, request.correlation_data);
+        assert!(options.content_type.is_none());
+        assert!(options.user_properties.is_empty());
+
+        let reply = assert_ok!(Reply::echo(&request));
+        assert!(!reply.is_error());
+        let options = reply.options();
+        assert_eq!(options.content_type, request.content_type);
+        assert_eq!(options.user_properties, &request.user_properties[..]);
+
+        let reply = reply.error(string("unknown command"));
+        assert!(reply.is_error());
+        let options = reply.options();
+        assert_eq!(options.correlation_data, request.correlation_data);
+        assert_eq!(options.user_properties.len(), 1);
+        assert_eq!(options.user_properties[0].name.as_ref(), ERROR_PROPERTY);
+    }
+
+    #[test]
+    fn error_reply() {
+        assert_eq!(error_of(&publish(None, &[("k", "v")])), None);
+        assert_eq!(
+            error_of(&publish(None, &[("k", "v"), (ERROR_PROPERTY, "busy")])),
+            Some("busy")
+        );
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn request_response() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c, mut s) = duplex(64);
+        let clock = TestClock(Cell::new(0));
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        let mut r = Requester::<'_, 2, 8>::new(&clock, "res", 0x0102);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s.read(&mut buf).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+
+            // SUBSCRIBE to "c/res"
+            assert_ok!(s.read_exact(&mut buf[..13]).await);
+            assert_eq!(buf[..2], [0x82, 0x0B]);
+            assert_eq!(
+                buf[4..13],
+                [0x00, 0x00, 0x05, b'c', b'/', b'r', b'e', b's', 0x00]
+            );
+            let [_, _, msb, lsb, ..] = buf;
+            assert_ok!(s.write_all(&[0x90, 0x04, msb, lsb, 0x00, 0x00]).await);
+
+            // PUBLISH with response topic and correlation data
+            assert_ok!(s.read_exact(&mut buf[..26]).await);
+            assert_eq!(buf[..5], [0x30, 0x18, 0x00, 0x01, b't']);
+            assert_eq!(
+                buf[5..26],
+                [
+                    0x13, 0x08, 0x00, 0x05, b'c', b'/', b'r', b'e', b's', 0x09, 0x00, 0x08, 0, 0,
+                    0, 0, 0, 0, 0x01, 0x02, b'm',
+                ]
+            );
+
+            assert_ok!(s.read_exact(&mut buf[..26]).await);
+            assert_eq!(buf[23..26], [0x01, 0x03, b'n']);
+
+            // Response to the second request
+            assert_ok!(
+                s.write_all(&[
+                    0x30, 0x14, 0x00, 0x05, b'c', b'/', b'r', b'e', b's', 0x0B, 0x09, 0x00, 0x08,
+                    0, 0, 0, 0, 0, 0, 0x01, 0x03, b'o',
+                ])
+                .await
+            );
+        };
+        let requester = async {
+            let options = ConnectOptions::new().clean_start();
+            let id = MqttString::try_from("c").unwrap();
+            let connected = assert_ok!(client.connect(FromTokio::new(c), &options, Some(id)).await);
+            assert_ok!(r.connected(&connected));
+
+            assert!(
+                assert_ok!(r.subscribe(&mut client, &SubscriptionOptions::new()).await).is_some()
+            );
+            assert!(
+                assert_ok!(r.subscribe(&mut client, &SubscriptionOptions::new()).await).is_none()
+            );
+            let Event::Suback(suback) = assert_ok!(client.poll().await) else {
+                panic!("expected SUBACK");
+            };
+            assert!(r.subacked(&suback));
+            assert!(
+                assert_ok!(r.subscribe(&mut client, &SubscriptionOptions::new()).await).is_none()
+            );
+
+            let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+            let options = PublicationOptions::new(TopicReference::Name(topic));
+            let first = assert_ok!(
+                r.request(
+                    &mut client,
+                    &options,
+                    "m".as_bytes().into(),
+                    Duration::from_secs(1)
+                )
+                .await
+            );
+            assert_eq!(first.correlation_data(), [0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+            let second = assert_ok!(
+                r.request(
+                    &mut client,
+                    &options,
+                    "n".as_bytes().into(),
+                    Duration::from_secs(2)
+                )
+                .await
+            );
+            assert_err!(
+                r.request(
+                    &mut client,
+                    &options,
+                    "o".as_bytes().into(),
+                    Duration::from_secs(2)
+                )
+                .await
+            );
+            assert_eq!(r.pending(), 2);
+
+            let Event::Publish(publish) = assert_ok!(client.poll().await) else {
+                panic!("expected PUBLISH");
+            };
+            assert_eq!(r.accept(&publish), Some(second));
+            assert_eq!(r.accept(&publish), None);
+
+            assert_eq!(r.next_deadline(), Some(Instant::from_millis(1_000)));
+            assert_eq!(r.expire(), None);
+            clock.0.set(1_000);
+            assert_eq!(r.expire(), Some(first));
+            assert_eq!(r.pending(), 0);
+        };
+
+        join!(server, requester);
+
+        assert_eq!(
+            RequestId::from_correlation_data(&[0, 0, 0, 0, 0, 0, 0x01, 0x02]),
+            Some(RequestId(0x0102))
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Implements a router which dispatches incoming publications to the handlers of matching topic
+//! filters or subscription identifiers.
+
+use heapless::Vec;
+
+use crate::{
+    client::event::{Event, Publish},
+    types::{TopicFilter, VarByteInt},
+};
+
+/// Handles the incoming publications dispatched to it by a [`Router`].
+///
+/// Implemented for all closures taking a [`Publish`].
+pub trait Handler<const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize> {
+    /// Handles an incoming publication.
+    fn handle(&mut self, publish: &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>);
+}
+
+impl<
+    F: FnMut(&Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>),
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES> for F
+{
+    fn handle(&mut self, publish: &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>) {
+        self(publish);
+    }
+}
+
+/// Selects the publications a [`Router`] dispatches to a handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Route<'r> {
+    /// Publications whose topic name matches the topic filter as determined by
+    /// [`TopicFilter::matches`].
+    Filter(TopicFilter<'r>),
+
+    /// Publications which carry the subscription identifier, i.e. which the server forwards
+    /// because of a subscription made with this subscription identifier.
+    SubscriptionIdentifier(VarByteInt),
+}
+
+impl Route<'_> {
+    /// Returns whether the publication is selected by this route.
+    pub fn matches<const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize>(
+        &self,
+        publish: &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> bool {
+        match self {
+            Self::Filter(f) => f.matches(&publish.topic),
+            Self::SubscriptionIdentifier(i) => publish.subscription_identifiers.contains(i),
+        }
+    }
+}
+
+/// Error returned when adding a route failed because the router already holds `ROUTES` routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoutesFull;
+
+/// Dispatches incoming publications to the handlers of all matching [`Route`]s.
+///
+/// The router holds up to `ROUTES` routes and does not allocate. Handlers are called in the
+/// order in which their routes were added.
+///
+/// Subscription identifiers are only present in publications if the server supports them, which
+/// can be checked via [`Client::server_config`]. Routing by topic filter works with any server.
+///
+/// [`Client::server_config`]: crate::client::Client::server_config
+pub struct Router<
+    'r,
+    const ROUTES: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> {
+    routes: Vec<
+        (
+            Route<'r>,
+            &'r mut dyn Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        ),
+        ROUTES,
+    >,
+}
+
+impl<
+    const ROUTES: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> core::fmt::Debug for Router<'_, ROUTES, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Router")
+            .field("routes", &self.routes().collect::<Vec<_, ROUTES>>())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    const ROUTES: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> defmt::Format for Router<'_, ROUTES, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Router {{ routes: {:?}, .. }}",
+            self.routes().collect::<Vec<_, ROUTES>>().as_slice()
+        );
+    }
+}
+
+impl<
+    const ROUTES: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> Default for Router<'_, ROUTES, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<
+    'r,
+    const ROUTES: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+> Router<'r, ROUTES, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>
+{
+    /// Creates a router without routes.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Adds a route dispatching the publications selected by `route` to `handler`.
+    ///
+    /// # Errors
+    ///
+    /// [`RoutesFull`] if the router already holds `ROUTES` routes.
+    pub fn route(
+        &mut self,
+        route: Route<'r>,
+        handler: &'r mut dyn Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<(), RoutesFull> {
+        self.routes.push((route, handler)).map_err(|_| RoutesFull)
+    }
+
+    /// Adds a route dispatching the publications matching `filter` to `handler`.
+    ///
+    /// # Errors
+    ///
+    /// [`RoutesFull`] if the router already holds `ROUTES` routes.
+    pub fn route_filter(
+        &mut self,
+        filter: TopicFilter<'r>,
+        handler: &'r mut dyn Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<(), RoutesFull> {
+        self.route(Route::Filter(filter), handler)
+    }
+
+    /// Adds a route dispatching the publications carrying `subscription_identifier` to
+    /// `handler`.
+    ///
+    /// # Errors
+    ///
+    /// [`RoutesFull`] if the router already holds `ROUTES` routes.
+    pub fn route_subscription_identifier(
+        &mut self,
+        subscription_identifier: VarByteInt,
+        handler: &'r mut dyn Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> Result<(), RoutesFull> {
+        self.route(
+            Route::SubscriptionIdentifier(subscription_identifier),
+            handler,
+        )
+    }
+
+    /// Removes all routes equal to `route` and returns their handlers in the order in which they
+    /// were added.
+    pub fn remove(
+        &mut self,
+        route: &Route<'_>,
+    ) -> Vec<&'r mut dyn Handler<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>, ROUTES> {
+        let mut removed = Vec::new();
+        let mut i = 0;
+
+        while i < self.routes.len() {
+            if self.routes[i].0 == *route {
+                let (_, handler) = self.routes.remove(i);
+                // Invariant: At most `ROUTES` routes are removed.
+                let _ = removed.push(handler);
+            } else {
+                i += 1;
+            }
+        }
+
+        removed
+    }
+
+    /// Removes all routes.
+    pub fn clear(&mut self) {
+        self.routes.clear();
+    }
+
+    /// Returns the routes in the order in which they were added.
+    pub fn routes(&self) -> impl Iterator<Item = &Route<'r>> {
+        self.routes.iter().map(|(r, _)| r)
+    }
+
+    /// Passes the publication to the handlers of all matching routes.
+    ///
+    /// # Returns:
+    /// The number of handlers the publication has been passed to.
+    pub fn dispatch(
+        &mut self,
+        publish: &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> usize {
+        let mut dispatched = 0;
+
+        for (route, handler) in &mut self.routes {
+            if route.matches(publish) {
+                handler.handle(publish);
+                dispatched += 1;
+            }
+        }
+
+        dispatched
+    }
+
+    /// Dispatches the publication of an [`Event::Publish`] like [`Self::dispatch`]. Other events,
+    /// including [`Event::Duplicate`], are not dispatched.
+    ///
+    /// # Returns:
+    /// The number of handlers the publication has been passed to.
+    pub fn dispatch_event(
+        &mut self,
+        event: &Event<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+    ) -> usize {
+        match event {
+            Event::Publish(p) => self.dispatch(p),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::cell::Cell;
+
+    use heapless::Vec;
+
+    use crate::{
+        bytes::Bytes,
+        client::{
+            event::{Event, Publish},
+            options::AckMode,
+            router::{Route, Router, RoutesFull},
+        },
+        types::{IdentifiedQoS, MqttString, TopicFilter, TopicName, VarByteInt},
+    };
+
+    fn publish(topic: &'static str, subscription_identifiers: &[u32]) -> Publish<'static, 2, 0> {
+        Publish {
+            ack_mode: AckMode::Automatic,
+            dup: false,
+            identified_qos: IdentifiedQoS::AtMostOnce,
+            retain: false,
+            topic: TopicName::new(MqttString::try_from(topic).unwrap()).unwrap(),
+            payload_format_indicator: None,
+            message_expiry_interval: None,
+            response_topic: None,
+            correlation_data: None,
+            user_properties: Vec::new(),
+            subscription_identifiers: subscription_identifiers
+                .iter()
+                .map(|i| VarByteInt::new(*i).unwrap())
+                .collect(),
+            content_type: None,
+            message: Bytes::Borrowed(b"m"),
+        }
+    }
+
+    fn filter(filter: &'static str) -> TopicFilter<'static> {
+        TopicFilter::new(MqttString::try_from(filter).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn dispatch() {
+        let sport = Cell::new(0);
+        let tennis = Cell::new(0);
+        let identified = Cell::new(0);
+        let mut sport_handler = |_: &Publish<'_, 2, 0>| sport.set(sport.get() + 1);
+        let mut tennis_handler = |p: &Publish<'_, 2, 0>| {
+            assert_eq!(p.topic.as_ref().as_str(), "sport/tennis");
+            tennis.set(tennis.get() + 1);
+        };
+        let mut identified_handler = |_: &Publish<'_, 2, 0>| identified.set(identified.get() + 1);
+
+        let mut r = Router::<'_, 3, 2, 0>::new();
+        r.route_filter(filter("sport/#"), &mut sport_handler)
+            .unwrap();
+        r.route_filter(filter("$share/g/+/tennis"), &mut tennis_handler)
+            .unwrap();
+        r.route_subscription_identifier(VarByteInt::new(7).unwrap(), &mut identified_handler)
+            .unwrap();
+
+        assert_eq!(r.dispatch(&publish("sport/tennis", &[])), 2);
+        assert_eq!(r.dispatch(&publish("sport", &[3, 7])), 2);
+        assert_eq!(r.dispatch(&publish("$SYS/sport", &[])), 0);
+        assert_eq!(
+            r.dispatch_event(&Event::Duplicate(publish("sport", &[]))),
+            0
+        );
+        assert_eq!(r.dispatch_event(&Event::Publish(publish("a", &[7]))), 1);
+
+        assert_eq!(sport.get(), 2);
+        assert_eq!(tennis.get(), 1);
+        assert_eq!(identified.get(), 2);
+
+        assert_eq!(r.remove(&Route::Filter(filter("sport/#"))).len(), 1);
+        assert_eq!(r.routes().count(), 2);
+        assert_eq!(r.dispatch(&publish("sport", &[])), 0);
+    }
+
+    #[test]
+    fn routes_full() {
+        let mut h = |_: &Publish<'_, 0, 0>| {};
+        let mut g = |_: &Publish<'_, 0, 0>| {};
+
+        let mut r = Router::<'_, 1, 0, 0>::new();
+        r.route_filter(filter("#"), &mut h).unwrap();
+        assert_eq!(r.route_filter(filter("a"), &mut g), Err(RoutesFull));
+
+        r.clear();
+        assert_eq!(r.routes().count(), 0);
+    }
+}
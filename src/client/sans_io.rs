@@ -0,0 +1,1800 @@
+//! Implements a client without any I/O of its own. Received bytes are fed into the client and bytes
+//! to transmit are taken out of it, allowing the protocol to be driven by any kind of I/O, such as
+//! DMA transfers, interrupt handlers or non-async event loops.
+
+use core::{cmp::min, convert::Infallible};
+
+use heapless::Vec;
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError, Taken,
+        alias::TopicAliasManager,
+        auth::Authenticator,
+        codec::EncodePayload,
+        event::{Connected, Event, Publish},
+        options::{
+            AckOptions, AuthOptions, ConnectOptions, DisconnectOptions, FilterOptions,
+            PublicationOptions, SubscribeOptions, SubscriptionOptions, UnsubscriptionOptions,
+        },
+        protocol::{Dequeued, Incoming, Link, Outgoing, Protocol, encode_payload, leaves_queue},
+        queue::PublishQueue,
+        raw::{HeaderState, KeepAliveState, RawError},
+        registry::{Pending, SubscriptionRegistry},
+        store::MessageStore,
+    },
+    config::{ClientConfig, MaximumPacketSize, ServerConfig, SharedConfig},
+    eio::ErrorKind,
+    fmt::{assert, debug, debug_assert, error, info, panic, warn},
+    header::{FixedHeader, PacketType},
+    packet::{RxError, RxPacket, TxPacket, decode, encode},
+    session::Session,
+    time::{Clock, Instant},
+    types::{MqttString, PacketIdentifier, ReasonCode, TopicFilter},
+    v5::packet::{AuthPacket, DisconnectPacket, PingreqPacket},
+};
+
+struct Queue<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Queue<'b> {
+    fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> usize {
+        let n = min(self.buf.len() - self.len, bytes.len());
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+        n
+    }
+
+    fn pop(&mut self, bytes: &mut [u8]) -> usize {
+        let n = min(self.len, bytes.len());
+        bytes[..n].copy_from_slice(&self.buf[..n]);
+        self.consume(n);
+        n
+    }
+
+    /// Removes the first `n` bytes.
+    fn consume(&mut self, n: usize) {
+        self.buf.copy_within(n..self.len, 0);
+        self.len -= n;
+    }
+}
+
+/// The receive and transmit buffers of a sans-I/O [`Client`].
+///
+/// The receive buffer must be able to hold the largest packet the client accepts, which is
+/// configured with the maximum packet size in [`ConnectOptions`]. The transmit buffer must be able
+/// to hold all packets sent by the client between two calls to [`Client::transmit`].
+pub struct Pipe<'b> {
+    rx: Queue<'b>,
+    tx: Queue<'b>,
+}
+
+impl core::fmt::Debug for Pipe<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Pipe")
+            .field("received", &self.rx.len)
+            .field("transmittable", &self.tx.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Pipe<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Pipe {{ received: {}, transmittable: {}, .. }}",
+            self.rx.len,
+            self.tx.len
+        );
+    }
+}
+
+impl<'b> Pipe<'b> {
+    /// Creates a pipe using `rx` as receive and `tx` as transmit buffer.
+    pub fn new(rx: &'b mut [u8], tx: &'b mut [u8]) -> Self {
+        Self {
+            rx: Queue::new(rx),
+            tx: Queue::new(tx),
+        }
+    }
+}
+
+/// The state of the connection whose bytes are exchanged through the [`Pipe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum State {
+    /// The connection and dialogue with the server is ok.
+    Open,
+
+    /// A protocol error occured. A DISCONNECT packet with the reason code is sent on abort.
+    Faulted(ReasonCode),
+
+    /// The connection is closed.
+    Closed,
+}
+
+/// The connection of a sans-I/O [`Client`]. Packets are encoded into the transmit buffer of the
+/// [`Pipe`] and decoded once they have been received completely.
+struct Connection<'c, B: BufferProvider<'c>> {
+    pipe: Pipe<'c>,
+    buf: &'c mut B,
+    state: State,
+    header: HeaderState,
+
+    /// The fixed header of the packet whose body has not been received completely yet.
+    pending: Option<FixedHeader>,
+    keep_alive: KeepAliveState<'c>,
+}
+
+impl<'c, B: BufferProvider<'c>> core::fmt::Debug for Connection<'c, B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Connection")
+            .field("pipe", &self.pipe)
+            .field("state", &self.state)
+            .field("header", &self.header)
+            .field("pending", &self.pending)
+            .field("keep_alive", &self.keep_alive)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'c, B: BufferProvider<'c>> defmt::Format for Connection<'c, B> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Connection {{ pipe: {:?}, state: {:?}, header: {:?}, pending: {:?}, keep_alive: {:?}, .. }}",
+            self.pipe,
+            self.state,
+            self.header,
+            self.pending,
+            self.keep_alive
+        );
+    }
+}
+
+impl<'c, B: BufferProvider<'c>> Connection<'c, B> {
+    fn new(pipe: Pipe<'c>, buf: &'c mut B) -> Self {
+        Self {
+            pipe,
+            buf,
+            state: State::Closed,
+            header: HeaderState::new(),
+            pending: None,
+            keep_alive: KeepAliveState::new(),
+        }
+    }
+
+    /// Starts a new connection. Bytes left in the buffers belong to the previous connection and
+    /// are discarded.
+    fn open(&mut self) {
+        debug_assert!(
+            self.state != State::Open,
+            "connection must not be open to start a new one."
+        );
+
+        self.pipe.rx.len = 0;
+        self.pipe.tx.len = 0;
+        self.header = HeaderState::new();
+        self.pending = None;
+        self.keep_alive.reset();
+        self.state = State::Open;
+    }
+
+    /// Returns an error if the connection is not open.
+    fn check_open(&self) -> Result<(), RawError<B::ProvisionError>> {
+        match self.state {
+            State::Open => Ok(()),
+            State::Faulted(_) | State::Closed => Err(RawError::Disconnected),
+        }
+    }
+
+    /// Encodes a packet into the transmit buffer.
+    fn send<P: TxPacket>(&mut self, packet: &P) -> Result<(), RawError<B::ProvisionError>> {
+        self.check_open().inspect_err(|_| match self.state {
+            State::Faulted(_) => warn!("attempted to send on a faulted mqtt connection"),
+            _ => warn!("attempted to send on a closed network connection"),
+        })?;
+
+        let tx = &mut self.pipe.tx;
+        match encode(packet, &mut tx.buf[tx.len..]) {
+            Ok(n) => {
+                tx.len += n;
+                self.keep_alive.sent(P::PACKET_TYPE);
+                Ok(())
+            }
+            Err(_) => {
+                error!("transmit buffer cannot hold the packet");
+
+                // Close right away because the packets that follow would be sent out of order.
+                self.close_with(None);
+                Err(RawError::Network(ErrorKind::OutOfMemory))
+            }
+        }
+    }
+
+    /// Takes the fixed header of the next packet out of the receive buffer.
+    ///
+    /// The header of a packet whose body has not been received completely is returned again.
+    ///
+    /// # Returns:
+    /// [`None`] if the fixed header has not been received completely yet.
+    fn recv_header(&mut self) -> Result<Option<FixedHeader>, RawError<B::ProvisionError>> {
+        self.check_open().inspect_err(|_| match self.state {
+            State::Faulted(_) => warn!("attempted to receive from a faulted mqtt connection"),
+            _ => warn!("attempted to receive from a closed network connection"),
+        })?;
+
+        if self.pending.is_some() {
+            debug!("resuming reception of packet body");
+            return Ok(self.pending);
+        }
+
+        let rx = &mut self.pipe.rx;
+        let mut header = Ok(None);
+        let mut read = 0;
+
+        while let (Ok(None), Some(&byte)) = (&header, rx.buf[..rx.len].get(read)) {
+            header = self.header.push::<Infallible>(byte);
+            read += 1;
+        }
+
+        rx.consume(read);
+
+        match header {
+            Ok(h) => {
+                self.pending = h;
+                Ok(h)
+            }
+            Err(e) => Err(self.handle_rx(e.into())),
+        }
+    }
+
+    /// Takes the body of the packet with the given fixed header out of the receive buffer and
+    /// decodes it.
+    ///
+    /// # Returns:
+    /// [`None`] if the body has not been received completely yet.
+    fn recv_body<P: RxPacket<'c>>(
+        &mut self,
+        header: &FixedHeader,
+    ) -> Result<Option<P>, RawError<B::ProvisionError>> {
+        self.check_open()?;
+
+        let len = header.remaining_len.size();
+
+        if len > self.pipe.rx.buf.len() {
+            error!(
+                "received a packet exceeding the receive buffer, remaining length={:?}",
+                len
+            );
+            self.close_with(Some(ReasonCode::PacketTooLarge));
+            return Err(RawError::Server);
+        }
+
+        if self.pipe.rx.len < len {
+            return Ok(None);
+        }
+
+        let packet = decode(header, &self.pipe.rx.buf[..len], self.buf);
+
+        self.pipe.rx.consume(len);
+        self.pending = None;
+
+        match packet {
+            Ok(p) => {
+                self.keep_alive.received(header);
+                Ok(Some(p))
+            }
+            // The body has been received completely, so it is too short for its packet.
+            Err(RxError::UnexpectedEOF) => Err(self.handle_rx(RxError::MalformedPacket)),
+            Err(e) => Err(self.handle_rx(e)),
+        }
+    }
+
+    fn handle_rx(
+        &mut self,
+        e: RxError<Infallible, B::ProvisionError>,
+    ) -> RawError<B::ProvisionError> {
+        let (e, r) = e.into();
+
+        match e {
+            RawError::Alloc(ref e) => error!("buffer provision failed: {:?}", e),
+            _ => error!("server protocol violation"),
+        }
+
+        self.close_with(r);
+
+        e
+    }
+
+    /// Disconnect handler after an error occured. Encodes a DISCONNECT packet with the reason code
+    /// of a faulted connection into the transmit buffer.
+    ///
+    /// This expects the connection to not be open.
+    fn abort(&mut self) -> Result<(), RawError<B::ProvisionError>> {
+        debug_assert!(
+            self.state != State::Open,
+            "connection must not be open to disconnect due to an error."
+        );
+
+        let State::Faulted(r) = self.state else {
+            self.close_with(None);
+            return Ok(());
+        };
+
+        let packet = DisconnectPacket::<0>::new(r, None, None, Vec::new());
+
+        debug!("sending DISCONNECT packet with reason code: {:?}", r);
+
+        // Encode regardless of the state and don't check whether length exceeds servers maximum
+        // packet size because we don't add properties to the DISCONNECT packet -> length is always
+        // in the 4..=6 range in bytes. The server really shouldn't reject this.
+        let tx = &mut self.pipe.tx;
+        let r = match encode(&packet, &mut tx.buf[tx.len..]) {
+            Ok(n) => {
+                tx.len += n;
+                Ok(())
+            }
+            Err(_) => {
+                error!("transmit buffer cannot hold the packet");
+                Err(RawError::Network(ErrorKind::OutOfMemory))
+            }
+        };
+
+        self.close_with(None);
+
+        r
+    }
+}
+
+impl<'c, B: BufferProvider<'c>> Link<'c> for Connection<'c, B> {
+    type Buffer = B;
+
+    fn is_connected(&self) -> bool {
+        self.state == State::Open
+    }
+
+    fn close_with(&mut self, reason_code: Option<ReasonCode>) {
+        self.state = match (self.state, reason_code) {
+            (State::Closed, _) => State::Closed,
+            (_, Some(r)) => State::Faulted(r),
+            (_, None) => {
+                debug!("closed network connection");
+                State::Closed
+            }
+        };
+    }
+
+    fn now(&self) -> Option<Instant> {
+        self.keep_alive.now()
+    }
+
+    fn keep_alive_deadline(&self, interval: core::time::Duration) -> Option<Instant> {
+        if self.is_connected() {
+            self.keep_alive.deadline(interval)
+        } else {
+            None
+        }
+    }
+
+    fn ping_pending(&self) -> bool {
+        self.keep_alive.ping_pending()
+    }
+
+    fn buffer_mut(&mut self) -> &mut B {
+        self.buf
+    }
+}
+
+/// An MQTT client without any I/O of its own.
+///
+/// The client runs the same protocol implementation as the async [`Client`](super::Client):
+/// Bytes received from the server are passed to [`Self::receive`] and decoded by
+/// [`Self::poll_connack`] and [`Self::poll`] once a packet is complete. Every method sending
+/// packets encodes them into the transmit buffer of the [`Pipe`], from which [`Self::transmit`]
+/// takes the bytes to send to the server. None of the methods ever wait, so the client can be
+/// driven from any context.
+///
+/// The const parameters, the session handling and the acknowledgement modes are the ones of the
+/// async [`Client`](super::Client). Before a method sending a packet is called, the caller should
+/// take out the pending bytes with [`Self::transmit`] if the transmit buffer may not be able to
+/// hold the packet. Otherwise, the method returns [`MqttError::Network`] with
+/// [`ErrorKind::OutOfMemory`] and the connection has to be aborted.
+pub struct Client<
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
+> {
+    protocol: Protocol<
+        'c,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+    conn: Connection<'c, B>,
+}
+
+impl<
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Client<
+        'c,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("client_config", &self.protocol.client_config)
+            .field("shared_config", &self.protocol.shared_config)
+            .field("server_config", &self.protocol.server_config)
+            .field("session", &self.protocol.session)
+            .field("conn", &self.conn)
+            .field("auth_state", &self.protocol.auth_state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Client<
+        'c,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Client {{ client_config: {:?}, shared_config: {:?}, server_config: {:?}, session: {:?}, conn: {:?}, auth_state: {:?}, .. }}",
+            self.protocol.client_config,
+            self.protocol.shared_config,
+            self.protocol.server_config,
+            self.protocol.session,
+            self.conn,
+            self.protocol.auth_state,
+        );
+    }
+}
+
+impl<
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Client<
+        'c,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates a new, disconnected MQTT client exchanging bytes through `pipe` and using a buffer
+    /// provider to store dynamically sized fields of received packets.
+    /// The session state is initialised as a new session. If you want to start the
+    /// client with an existing session, use [`Self::with_session`].
+    pub fn new(pipe: Pipe<'c>, buffer: &'c mut B) -> Self {
+        Self {
+            protocol: Protocol::new(),
+            conn: Connection::new(pipe, buffer),
+        }
+    }
+
+    /// Creates a new, disconnected MQTT client exchanging bytes through `pipe` and using a buffer
+    /// provider to store dynamically sized fields of received packets.
+    pub fn with_session(
+        pipe: Pipe<'c>,
+        session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        buffer: &'c mut B,
+    ) -> Self {
+        let mut s = Self::new(pipe, buffer);
+        s.protocol.session = session;
+        s
+    }
+
+    /// Refer to [`Client::ack_manually_when`](super::Client::ack_manually_when).
+    pub fn ack_manually_when(
+        &mut self,
+        predicate: &'c dyn Fn(
+            &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        ) -> bool,
+    ) {
+        self.protocol.manual_ack_when = predicate;
+    }
+
+    /// Refer to [`Client::authenticate_with`](super::Client::authenticate_with).
+    pub fn authenticate_with(&mut self, authenticator: &'c mut dyn Authenticator) {
+        self.protocol.authenticator = Some(authenticator);
+    }
+
+    /// Refer to [`Client::manage_topic_aliases_with`](super::Client::manage_topic_aliases_with).
+    pub fn manage_topic_aliases_with(&mut self, manager: &'c mut dyn TopicAliasManager) {
+        self.protocol.topic_alias_manager = Some(manager);
+    }
+
+    /// Refer to [`Client::keep_alive_with`](super::Client::keep_alive_with).
+    pub fn keep_alive_with(&mut self, clock: &'c dyn Clock) {
+        self.conn.keep_alive.set_clock(clock);
+    }
+
+    /// Refer to [`Client::store_messages_in`](super::Client::store_messages_in).
+    pub fn store_messages_in(&mut self, store: &'c mut dyn MessageStore) {
+        self.protocol.message_store = Some(store);
+    }
+
+    /// Refer to [`Client::queue_publications_in`](super::Client::queue_publications_in).
+    pub fn queue_publications_in(&mut self, queue: &'c mut dyn PublishQueue) {
+        self.protocol.publish_queue = Some(queue);
+    }
+
+    /// Refer to [`Client::track_subscriptions_in`](super::Client::track_subscriptions_in).
+    pub fn track_subscriptions_in(&mut self, registry: &'c mut dyn SubscriptionRegistry) {
+        self.protocol.subscription_registry = Some(registry);
+    }
+
+    /// Refer to [`Client::encode_payloads_with`](super::Client::encode_payloads_with).
+    pub fn encode_payloads_with(&mut self, buffer: &'c mut [u8]) {
+        self.protocol.payload_buffer = Some(buffer);
+    }
+
+    /// Refer to [`Client::queued_publications`](super::Client::queued_publications).
+    #[inline]
+    pub fn queued_publications(&self) -> usize {
+        self.protocol
+            .publish_queue
+            .as_deref()
+            .map_or(0, PublishQueue::len)
+    }
+
+    /// Refer to [`Client::subscriptions`](super::Client::subscriptions).
+    #[inline]
+    pub fn subscriptions(&self) -> Option<&dyn SubscriptionRegistry> {
+        self.protocol.subscription_registry.as_deref()
+    }
+
+    /// Returns configuration for this client.
+    #[inline]
+    pub fn client_config(&self) -> &ClientConfig {
+        &self.protocol.client_config
+    }
+
+    /// Returns configuration for this client that was negotiated with the server.
+    #[inline]
+    pub fn server_config(&self) -> &ServerConfig {
+        &self.protocol.server_config
+    }
+
+    /// Returns configuration for the current connection that was negotiated with the server.
+    #[inline]
+    pub fn shared_config(&self) -> &SharedConfig {
+        &self.protocol.shared_config
+    }
+
+    /// Returns session related configuration and tracking information.
+    #[inline]
+    pub fn session(&self) -> &Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM> {
+        &self.protocol.session
+    }
+
+    /// Returns an immutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer(&self) -> &B {
+        self.conn.buf
+    }
+
+    /// Returns a mutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut B {
+        self.conn.buf
+    }
+
+    /// Appends bytes received from the server to the receive buffer.
+    ///
+    /// # Returns:
+    /// The amount of bytes taken from `bytes`. This is less than the length of `bytes` if the
+    /// receive buffer is full, in which case the packets in it have to be processed with
+    /// [`Self::poll`] or [`Self::poll_connack`] before the remaining bytes can be received.
+    pub fn receive(&mut self, bytes: &[u8]) -> usize {
+        self.conn.pipe.rx.push(bytes)
+    }
+
+    /// Returns the amount of bytes that the receive buffer can currently take.
+    pub fn receive_capacity(&self) -> usize {
+        let rx = &self.conn.pipe.rx;
+        rx.buf.len() - rx.len
+    }
+
+    /// Moves bytes to transmit to the server from the transmit buffer into `bytes`.
+    ///
+    /// # Returns:
+    /// The amount of bytes written to `bytes`. This is 0 if there is nothing to transmit.
+    pub fn transmit(&mut self, bytes: &mut [u8]) -> usize {
+        self.conn.pipe.tx.pop(bytes)
+    }
+
+    /// Reads bytes from `net` into the free space of the receive buffer.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn receive_from<R: embedded_io::Read>(
+        &mut self,
+        net: &mut R,
+    ) -> Result<usize, R::Error> {
+        let rx = &mut self.conn.pipe.rx;
+        let n = net.read(&mut rx.buf[rx.len..])?;
+        rx.len += n;
+        Ok(n)
+    }
+
+    /// Writes all bytes of the transmit buffer to `net` and flushes it.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn transmit_to<W: embedded_io::Write>(
+        &mut self,
+        net: &mut W,
+    ) -> Result<(), W::Error> {
+        let tx = &mut self.conn.pipe.tx;
+        net.write_all(&tx.buf[..tx.len])?;
+        tx.len = 0;
+        net.flush()
+    }
+
+    /// Starts connecting to an MQTT server by encoding a CONNECT packet. The connection is
+    /// established once [`Self::poll_connack`] returns the CONNACK packet.
+    ///
+    /// The receive and transmit buffers are cleared as this must be called for a fresh network
+    /// connection. Only call this when
+    /// - the client is newly constructed.
+    /// - a non-recoverable error has occured and [`Self::abort`] has been called.
+    /// - [`Self::disconnect`] has been called.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::Network`] if the transmit buffer cannot hold the CONNECT packet
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the maximum packet size in the [`ConnectOptions`] is
+    /// [`MaximumPacketSize::Unlimited`] or greater than the receive buffer of the [`Pipe`],
+    /// or in the cases that [`Client::connect`](super::Client::connect) panics.
+    pub fn connect(
+        &mut self,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<&MqttString<'_>>,
+    ) -> Result<(), MqttError<'c, MAX_USER_PROPERTIES>> {
+        let rx_capacity = self.conn.pipe.rx.buf.len();
+        assert!(
+            matches!(options.maximum_packet_size, MaximumPacketSize::Limit(l) if l.get() as usize <= rx_capacity),
+            "the maximum packet size must be limited to the receive buffer size {}",
+            rx_capacity
+        );
+
+        let packet = self.protocol.connect(options, client_identifier);
+
+        self.conn.open();
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Processes the packets in the receive buffer until the CONNACK packet has been received.
+    /// AUTH packets of an enhanced authentication exchange are answered on the way. The
+    /// arguments must be the same as the ones passed to [`Self::connect`].
+    ///
+    /// # Returns:
+    /// - [`None`] if the CONNACK packet has not been received completely yet.
+    /// - [`Some`] with the result of [`Client::connect`](super::Client::connect) otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::connect`](super::Client::connect) returns.
+    pub fn poll_connack<'d>(
+        &mut self,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Option<Result<Connected<'d, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>>>
+    where
+        'c: 'd,
+    {
+        self.recv_connack(options, client_identifier).transpose()
+    }
+
+    fn recv_connack<'d>(
+        &mut self,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Result<Option<Connected<'d, MAX_USER_PROPERTIES>>, MqttError<'c, MAX_USER_PROPERTIES>>
+    where
+        'c: 'd,
+    {
+        // AUTH packets received before the CONNACK packet are answered until it arrives.
+        loop {
+            let Some(header) = self.conn.recv_header()? else {
+                return Ok(None);
+            };
+
+            match self
+                .protocol
+                .handshake_header(&mut self.conn, &header, options)?
+            {
+                PacketType::Auth => {
+                    let Some(auth) = self
+                        .conn
+                        .recv_body::<AuthPacket<MAX_USER_PROPERTIES>>(&header)?
+                    else {
+                        return Ok(None);
+                    };
+                    let packet = self
+                        .protocol
+                        .handshake_auth(&mut self.conn, auth, options)
+                        .map_err(MqttError::inflate)?;
+
+                    self.conn.send(&packet)?;
+                }
+                _ => {
+                    let Some(connack) = self.conn.recv_body(&header)? else {
+                        return Ok(None);
+                    };
+
+                    return self
+                        .protocol
+                        .connack(&mut self.conn, connack, options, client_identifier)
+                        .map(Some);
+                }
+            }
+        }
+    }
+
+    /// Processes the next packet in the receive buffer.
+    ///
+    /// # Returns:
+    /// - [`None`] if there is no complete packet in the receive buffer.
+    /// - [`Some`] with the result of [`Client::poll`](super::Client::poll) otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::poll`](super::Client::poll) returns.
+    pub fn poll(
+        &mut self,
+    ) -> Option<
+        Result<
+            Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+            MqttError<'c, MAX_USER_PROPERTIES>,
+        >,
+    > {
+        self.recv_packet().transpose()
+    }
+
+    fn recv_packet(
+        &mut self,
+    ) -> Result<
+        Option<Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        let Some(header) = self.conn.recv_header()? else {
+            return Ok(None);
+        };
+
+        let header = self
+            .protocol
+            .check_header(&mut self.conn, header)
+            .map_err(MqttError::inflate)?;
+        self.protocol.check_packet_type(&mut self.conn, &header)?;
+
+        let Some(packet) = self
+            .conn
+            .recv_body::<Incoming<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>>(&header)?
+        else {
+            return Ok(None);
+        };
+
+        let (event, response) = self.protocol.receive(&mut self.conn, packet)?;
+
+        if let Some(packet) = response {
+            Self::send_outgoing(&mut self.conn, &packet)?;
+        }
+
+        Ok(Some(event))
+    }
+
+    /// Encodes a packet built by the [`Protocol`] into the transmit buffer.
+    fn send_outgoing(
+        conn: &mut Connection<'c, B>,
+        packet: &Outgoing<'_>,
+    ) -> Result<(), RawError<B::ProvisionError>> {
+        match packet {
+            Outgoing::Puback(p) => conn.send(p),
+            Outgoing::Pubrec(p) => conn.send(p),
+            Outgoing::Pubrel(p) => conn.send(p),
+            Outgoing::Pubcomp(p) => conn.send(p),
+            Outgoing::Auth(p) => conn.send(p),
+            Outgoing::Stored(p) => conn.send(p),
+        }
+    }
+
+    /// Refer to [`Client::reauthenticate`](super::Client::reauthenticate).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::reauthenticate`](super::Client::reauthenticate) returns.
+    pub fn reauthenticate(&mut self, options: &AuthOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.reauthenticate(options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::ping`](super::Client::ping).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::ping`](super::Client::ping) returns.
+    pub fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        debug!("sending PINGREQ packet");
+
+        self.conn.send(&PingreqPacket::new())?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::next_ping_deadline`](super::Client::next_ping_deadline).
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        self.protocol.next_ping_deadline(&self.conn)
+    }
+
+    /// Refer to [`Client::check_keep_alive`](super::Client::check_keep_alive). The PINGREQ
+    /// packet, if any, is left in the transmit buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::check_keep_alive`](super::Client::check_keep_alive)
+    /// returns.
+    pub fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        if self.protocol.check_keep_alive(&mut self.conn)? {
+            self.ping()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Refer to [`Client::subscribe`](super::Client::subscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe`](super::Client::subscribe) returns.
+    pub fn subscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.subscribe_many(
+            &[(topic_filter, options.filter_options())],
+            &options.subscribe_options(),
+        )
+    }
+
+    /// Refer to [`Client::subscribe_many`](super::Client::subscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe_many`](super::Client::subscribe_many) returns.
+    pub fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.subscribe_many(subscriptions, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(pid)
+    }
+
+    /// Refer to [`Client::unsubscribe`](super::Client::unsubscribe).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe`](super::Client::unsubscribe) returns.
+    pub fn unsubscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.unsubscribe_many(&[topic_filter], options)
+    }
+
+    /// Refer to [`Client::unsubscribe_many`](super::Client::unsubscribe_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe_many`](super::Client::unsubscribe_many)
+    /// returns.
+    pub fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.unsubscribe_many(topic_filters, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(pid)
+    }
+
+    /// Refer to [`Client::publish`](super::Client::publish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish`](super::Client::publish) returns.
+    pub fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(packet) = self.protocol.publish(&self.conn, options, message)? else {
+            return Ok(None);
+        };
+
+        self.conn.send(&packet)?;
+
+        Ok(packet.identified_qos.packet_identifier())
+    }
+
+    /// Refer to [`Client::publish_typed`](super::Client::publish_typed).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish_typed`](super::Client::publish_typed) returns.
+    pub fn publish_typed<C: EncodePayload<T>, T: ?Sized>(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        value: &T,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.payload_buffer) else {
+            panic!("attempted to publish a typed value without a payload buffer");
+        };
+        let (client, buffer) = taken.split();
+
+        let (options, len) = encode_payload::<C, T>(options, value, buffer)?;
+
+        client.publish(&options, Bytes::Borrowed(&buffer[..len]))
+    }
+
+    /// Refer to [`Client::republish`](super::Client::republish).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::republish`](super::Client::republish) returns.
+    pub fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .republish(packet_identifier, options, message)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::rerelease`](super::Client::rerelease).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::rerelease`](super::Client::rerelease) returns.
+    pub fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
+        self.resend(false)
+    }
+
+    /// Refer to [`Client::resume`](super::Client::resume).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resume`](super::Client::resume) returns.
+    pub fn resume(&mut self) -> Result<(), MqttError<'c, 0>> {
+        self.resend(true)
+    }
+
+    /// Encodes the packets retransmitting the outgoing publications of the session in the order
+    /// in which they were started. Stored PUBLISH packets are only sent if `publishes` is true.
+    fn resend(&mut self, publishes: bool) -> Result<(), MqttError<'c, 0>> {
+        for i in 0..self.protocol.session.outbound_publishes.len() {
+            if let Some(packet) = self.protocol.resend(i, publishes) {
+                Self::send_outgoing(&mut self.conn, &packet)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::drain_queue`](super::Client::drain_queue).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::drain_queue`](super::Client::drain_queue) returns.
+    pub fn drain_queue(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.publish_queue) else {
+            return Ok(());
+        };
+        let (client, queue) = taken.split();
+
+        while let Some(record) = queue.front() {
+            match client.publish_queued(record) {
+                Ok(true) => queue.pop(),
+                Ok(false) => return Ok(()),
+                Err(e) => {
+                    queue.pop();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publishes a record of a [`PublishQueue`]. Returns whether the record leaves the queue,
+    /// which it does unless the publication has to wait for free session state.
+    fn publish_queued(&mut self, record: &[u8]) -> Result<bool, MqttError<'c, 0>> {
+        match self.protocol.dequeue(&self.conn, record) {
+            Dequeued::Drop => Ok(true),
+            Dequeued::Wait => Ok(false),
+            Dequeued::Publish(publication) => leaves_queue(
+                self.publish(&publication.options(), Bytes::Borrowed(publication.message)),
+            ),
+        }
+    }
+
+    /// Refer to [`Client::resubscribe_all`](super::Client::resubscribe_all).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resubscribe_all`](super::Client::resubscribe_all) returns.
+    pub fn resubscribe_all(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.subscription_registry) else {
+            return Ok(());
+        };
+        let (client, registry) = taken.split();
+
+        for i in 0..registry.len() {
+            let Some((topic_filter, mut subscription)) = registry.get(i) else {
+                break;
+            };
+
+            if subscription.granted_qos.is_some() || subscription.pending.is_some() {
+                continue;
+            }
+
+            debug!("resubscribing to recorded topic filter");
+
+            let pid = client.subscribe(topic_filter, &subscription.options)?;
+
+            subscription.pending = Some(Pending::Subscribe {
+                packet_identifier: pid,
+                index: 0,
+            });
+            registry.set(i, subscription);
+        }
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::manual_acknowledge`](super::Client::manual_acknowledge).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_acknowledge`](super::Client::manual_acknowledge)
+    /// returns.
+    pub fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .manual_acknowledge(packet_identifier, reason_code, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::manual_receive`](super::Client::manual_receive).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_receive`](super::Client::manual_receive) returns.
+    pub fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .manual_receive(packet_identifier, reason_code, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::manual_release`](super::Client::manual_release).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_release`](super::Client::manual_release) returns.
+    pub fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.manual_release(packet_identifier, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::manual_complete`](super::Client::manual_complete).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_complete`](super::Client::manual_complete) returns.
+    pub fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.manual_complete(packet_identifier, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    /// Refer to [`Client::abort`](super::Client::abort). The DISCONNECT packet, if any, is
+    /// left in the transmit buffer.
+    pub fn abort(&mut self) {
+        match self.conn.abort() {
+            Ok(()) => info!("connection aborted"),
+            Err(e) => warn!("connection abort failed: {:?}", e),
+        }
+    }
+
+    /// Closes the connection without sending anything after the I/O driving the client failed.
+    #[cfg(any(feature = "blocking", test))]
+    pub(crate) fn terminate(&mut self) {
+        self.conn.close_with(None);
+    }
+
+    /// Refer to [`Client::disconnect`](super::Client::disconnect). The DISCONNECT packet is left
+    /// in the transmit buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::disconnect`](super::Client::disconnect) returns.
+    pub fn disconnect(&mut self, options: &DisconnectOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.disconnect(options)?;
+
+        self.conn.send(&packet)?;
+
+        // Terminates (closes) the connection
+        self.conn.close_with(None);
+
+        info!("disconnected from server");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::{cell::Cell, num::NonZero};
+
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        client::{
+            MqttError,
+            event::Event,
+            options::{
+                ConnectOptions, FilterOptions, PublicationOptions, SubscribeOptions,
+                SubscriptionOptions, TopicReference,
+            },
+            queue::SlicePublishQueue,
+            registry::FixedSubscriptionRegistry,
+            sans_io::{Client, Pipe},
+            store::FixedMessageStore,
+        },
+        config::KeepAlive,
+        time::{Clock, Instant},
+        types::{MqttString, QoS, TopicFilter, TopicName},
+    };
+
+    #[test_log::test]
+    fn connect_publish_puback() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+
+        let mut c = Client::<'_, _, 1, 1, 1, 0, 0>::new(pipe, &mut b);
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        let n = c.transmit(&mut out);
+        assert_eq!(out[0], 0x10);
+        assert_eq!(usize::from(out[1]) + 2, n);
+        assert_eq!(c.transmit(&mut out), 0);
+
+        // The CONNACK packet arrives in two parts.
+        assert_eq!(c.receive(&[0x20, 0x03]), 2);
+        assert!(
+            c.poll_connack(&options, Some(client_identifier.clone()))
+                .is_none()
+        );
+        assert_eq!(c.receive(&[0x00, 0x00, 0x00]), 3);
+        let connected = assert_ok!(
+            c.poll_connack(&options, Some(client_identifier.clone()))
+                .unwrap()
+        );
+        assert!(!connected.session_present);
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let pid = assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Name(topic)).at_least_once(),
+            "m".as_bytes().into(),
+        ))
+        .unwrap();
+        let n = c.transmit(&mut out);
+        assert_eq!(out[0], 0x32);
+        assert_eq!(usize::from(out[1]) + 2, n);
+
+        let [msb, lsb] = pid.get().get().to_be_bytes();
+        assert_eq!(c.receive(&[0x40, 0x02, msb, lsb, 0xD0]), 5);
+        let event = assert_ok!(c.poll().unwrap());
+        assert!(matches!(event, Event::PublishAcknowledged(p) if p.packet_identifier == pid));
+
+        // A partial packet is not processed.
+        assert!(c.poll().is_none());
+        assert_eq!(c.receive(&[0x00]), 1);
+        let event = assert_ok!(c.poll().unwrap());
+        assert!(matches!(event, Event::Pingresp));
+    }
+
+    struct TestClock(Cell<u64>);
+
+    impl Clock for TestClock {
+        fn now(&self) -> Instant {
+            Instant::from_millis(self.0.get())
+        }
+    }
+
+    #[test_log::test]
+    fn keep_alive() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let clock = TestClock(Cell::new(1_000));
+
+        let mut c = Client::<'_, _, 1, 1, 1, 0, 0>::new(pipe, &mut b);
+        c.keep_alive_with(&clock);
+        let options = ConnectOptions::new()
+            .clean_start()
+            .keep_alive(KeepAlive::Seconds(NonZero::new(10).unwrap()))
+            .maximum_packet_size(NonZero::new(64).unwrap());
+
+        let mut out = [0; 64];
+
+        assert_eq!(c.next_ping_deadline(), None);
+        let client_identifier = MqttString::try_from("a").unwrap();
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x00, 0x00, 0x00]), 5);
+        assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+        assert_eq!(c.next_ping_deadline(), Some(Instant::from_millis(11_000)));
+
+        // Nothing is sent before the deadline.
+        clock.0.set(10_999);
+        assert_ok!(c.check_keep_alive());
+        assert_eq!(c.transmit(&mut out), 0);
+
+        clock.0.set(11_000);
+        assert_ok!(c.check_keep_alive());
+        assert_eq!(c.transmit(&mut out), 2);
+        assert_eq!(out[..2], [0xC0, 0x00]);
+        assert_eq!(c.next_ping_deadline(), Some(Instant::from_millis(21_000)));
+
+        // The PINGRESP packet restarts the keep alive interval from the last sent packet.
+        assert_eq!(c.receive(&[0xD0, 0x00]), 2);
+        assert!(matches!(assert_ok!(c.poll().unwrap()), Event::Pingresp));
+        assert_eq!(c.next_ping_deadline(), Some(Instant::from_millis(21_000)));
+
+        clock.0.set(21_000);
+        assert_ok!(c.check_keep_alive());
+        assert_eq!(c.transmit(&mut out), 2);
+
+        clock.0.set(31_000);
+        let e = assert_err!(c.check_keep_alive());
+        assert_eq!(e, MqttError::KeepAliveTimeout);
+        assert_eq!(c.transmit(&mut out), 0);
+        assert_eq!(c.next_ping_deadline(), None);
+        assert_eq!(assert_err!(c.ping()), MqttError::RecoveryRequired);
+    }
+
+    #[test_log::test]
+    fn resume() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let mut store = FixedMessageStore::<2, 16>::new();
+
+        let mut c = Client::<'_, _, 1, 1, 2, 0, 0>::new(pipe, &mut b);
+        c.store_messages_in(&mut store);
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x00, 0x00, 0x00]), 5);
+        assert_ok!(
+            c.poll_connack(&options, Some(client_identifier.clone()))
+                .unwrap()
+        );
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let first = assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Name(topic.clone())).at_least_once(),
+            "m".as_bytes().into(),
+        ))
+        .unwrap();
+        let n = c.transmit(&mut out);
+        let mut publish = [0; 64];
+        publish[..n].copy_from_slice(&out[..n]);
+        let publish = &publish[..n];
+
+        let second = assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Name(topic)).exactly_once(),
+            "n".as_bytes().into(),
+        ))
+        .unwrap();
+        c.transmit(&mut out);
+
+        let [msb, lsb] = second.get().get().to_be_bytes();
+        assert_eq!(c.receive(&[0x50, 0x02, msb, lsb]), 4);
+        assert!(matches!(
+            assert_ok!(c.poll().unwrap()),
+            Event::PublishReceived(_)
+        ));
+        assert_eq!(c.transmit(&mut out), 6);
+
+        c.terminate();
+
+        let options = ConnectOptions::new().maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x01, 0x00, 0x00]), 5);
+        let connected = assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+        assert!(connected.session_present);
+
+        // The unacknowledged PUBLISH packet is resent with the DUP flag, followed by the PUBREL
+        // packet of the publication whose PUBLISH packet has been received by the server.
+        assert_ok!(c.resume());
+        let n = c.transmit(&mut out);
+        assert_eq!(n, publish.len() + 6);
+        assert_eq!(out[0], publish[0] | 0x08);
+        assert_eq!(out[1..publish.len()], publish[1..]);
+        assert_eq!(out[publish.len()..n], [0x62, 0x04, msb, lsb, 0x00, 0x00]);
+
+        let [msb, lsb] = first.get().get().to_be_bytes();
+        assert_eq!(c.receive(&[0x40, 0x02, msb, lsb]), 4);
+        assert!(matches!(
+            assert_ok!(c.poll().unwrap()),
+            Event::PublishAcknowledged(_)
+        ));
+
+        // Nothing is resent twice.
+        assert_ok!(c.resume());
+        assert_eq!(c.transmit(&mut out), 0);
+    }
+
+    #[test_log::test]
+    fn resume_aliased() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let mut store = FixedMessageStore::<2, 16>::new();
+
+        let mut c = Client::<'_, _, 1, 1, 2, 0, 0>::new(pipe, &mut b);
+        c.store_messages_in(&mut store);
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        // The server allows topic aliases up to 5.
+        const CONNACK: [u8; 8] = [0x20, 0x06, 0x00, 0x00, 0x03, 0x22, 0x00, 0x05];
+
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&CONNACK), 8);
+        assert_ok!(
+            c.poll_connack(&options, Some(client_identifier.clone()))
+                .unwrap()
+        );
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let alias = NonZero::new(1).unwrap();
+        let pid = assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Mapping(topic, alias)).at_least_once(),
+            "m".as_bytes().into(),
+        ))
+        .unwrap();
+        let n = c.transmit(&mut out);
+        // The topic alias property is sent on this connection.
+        assert_eq!(out[..2], [0x32, 0x0A]);
+        assert_eq!(out[7..n], [0x03, 0x23, 0x00, 0x01, b'm']);
+
+        // A publication to the bare topic alias could not be resent after a reconnection.
+        assert_eq!(
+            c.publish(
+                &PublicationOptions::new(TopicReference::Alias(alias)).at_least_once(),
+                "n".as_bytes().into(),
+            ),
+            Err(MqttError::UnstorableTopicAlias)
+        );
+        assert_eq!(c.transmit(&mut out), 0);
+        assert_ok!(c.publish(
+            &PublicationOptions::new(TopicReference::Alias(alias)),
+            "n".as_bytes().into(),
+        ));
+        c.transmit(&mut out);
+
+        c.terminate();
+
+        let options = ConnectOptions::new().maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x01, 0x00, 0x00]), 5);
+        assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+
+        // The mapping does not exist on the new connection, so the topic name is resent.
+        assert_ok!(c.resume());
+        let n = c.transmit(&mut out);
+        let [msb, lsb] = pid.get().get().to_be_bytes();
+        assert_eq!(
+            out[..n],
+            [0x3A, 0x07, 0x00, 0x01, b't', msb, lsb, 0x00, b'm']
+        );
+    }
+
+    #[test_log::test]
+    fn drain_queue() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let clock = TestClock(Cell::new(1_000));
+        let mut queue = [0; 128];
+        let mut queue = SlicePublishQueue::new(&mut queue);
+
+        let mut c = Client::<'_, _, 1, 1, 2, 0, 0>::new(pipe, &mut b);
+        c.keep_alive_with(&clock);
+        c.queue_publications_in(&mut queue);
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        let topic =
+            TopicReference::Name(TopicName::new(MqttString::try_from("t").unwrap()).unwrap());
+        for (qos, message_expiry_interval, message) in [
+            (QoS::AtLeastOnce, 10, "m"),
+            (QoS::AtLeastOnce, 60, "n"),
+            (QoS::AtMostOnce, 1, "o"),
+        ] {
+            let options = PublicationOptions::new(topic.clone())
+                .qos(qos)
+                .message_expiry_interval(message_expiry_interval);
+            assert_eq!(
+                assert_ok!(c.publish(&options, message.as_bytes().into())),
+                None
+            );
+        }
+        assert_eq!(c.queued_publications(), 3);
+        assert_eq!(c.transmit(&mut out), 0);
+
+        clock.0.set(6_000);
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        // The server's receive maximum is 1.
+        assert_eq!(
+            c.receive(&[0x20, 0x06, 0x00, 0x00, 0x03, 0x21, 0x00, 0x01]),
+            8
+        );
+        assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+
+        // The message expiry interval is reduced by the time spent in the queue.
+        assert_ok!(c.drain_queue());
+        let n = c.transmit(&mut out);
+        assert_eq!(n, 14);
+        assert_eq!(out[..5], [0x32, 0x0C, 0x00, 0x01, b't']);
+        assert_eq!(out[7..n], [0x05, 0x02, 0x00, 0x00, 0x00, 0x05, b'm']);
+        assert_eq!(c.queued_publications(), 2);
+
+        let [msb, lsb] = [out[5], out[6]];
+        assert_eq!(c.receive(&[0x40, 0x02, msb, lsb]), 4);
+        assert!(matches!(
+            assert_ok!(c.poll().unwrap()),
+            Event::PublishAcknowledged(_)
+        ));
+
+        // The QoS 0 publication has expired.
+        clock.0.set(7_000);
+        assert_ok!(c.drain_queue());
+        let n = c.transmit(&mut out);
+        assert_eq!(n, 14);
+        assert_eq!(out[7..n], [0x05, 0x02, 0x00, 0x00, 0x00, 0x36, b'n']);
+        assert_eq!(c.queued_publications(), 0);
+    }
+
+    #[test_log::test]
+    fn resubscribe_all() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let mut registry = FixedSubscriptionRegistry::<4, 8>::new();
+
+        let mut c = Client::<'_, _, 2, 1, 1, 0, 0>::new(pipe, &mut b);
+        c.track_subscriptions_in(&mut registry);
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x00, 0x00, 0x00]), 5);
+        assert_ok!(
+            c.poll_connack(&options, Some(client_identifier.clone()))
+                .unwrap()
+        );
+
+        let filter = |f| TopicFilter::new(MqttString::try_from(f).unwrap()).unwrap();
+
+        let pid = assert_ok!(c.subscribe_many(
+            &[
+                (filter("a"), FilterOptions::new().at_least_once()),
+                (filter("b"), FilterOptions::new()),
+            ],
+            &SubscribeOptions::new()
+        ));
+        c.transmit(&mut out);
+
+        // The subscription to "b" is refused.
+        let [msb, lsb] = pid.get().get().to_be_bytes();
+        assert_eq!(c.receive(&[0x90, 0x05, msb, lsb, 0x00, 0x01, 0x80]), 7);
+        assert!(matches!(assert_ok!(c.poll().unwrap()), Event::Suback(_)));
+
+        let registry = c.subscriptions().unwrap();
+        assert_eq!(registry.len(), 1);
+        let (topic_filter, subscription) = registry.get(0).unwrap();
+        assert_eq!(topic_filter, filter("a"));
+        assert_eq!(subscription.granted_qos, Some(QoS::AtLeastOnce));
+
+        // The subscription to "c" is not acknowledged before the connection is lost.
+        assert_ok!(c.subscribe(filter("c"), &SubscriptionOptions::new()));
+        c.transmit(&mut out);
+        c.terminate();
+
+        let options = ConnectOptions::new().maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x00, 0x00, 0x00]), 5);
+        let connected = assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+        assert!(!connected.session_present);
+
+        assert_ok!(c.resubscribe_all());
+        let n = c.transmit(&mut out);
+        assert_eq!(n, 18);
+        assert_eq!(out[..2], [0x82, 0x07]);
+        assert_eq!(out[4..9], [0x00, 0x00, 0x01, b'a', 0x01]);
+        assert_eq!(out[9..11], [0x82, 0x07]);
+        assert_eq!(out[13..18], [0x00, 0x00, 0x01, b'c', 0x00]);
+
+        // Nothing is resubscribed twice.
+        assert_ok!(c.resubscribe_all());
+        assert_eq!(c.transmit(&mut out), 0);
+
+        let acks = [([out[2], out[3]], 0x01), ([out[11], out[12]], 0x00)];
+        for ([msb, lsb], code) in acks {
+            assert_eq!(c.receive(&[0x90, 0x04, msb, lsb, 0x00, code]), 6);
+            assert!(matches!(assert_ok!(c.poll().unwrap()), Event::Suback(_)));
+        }
+
+        let registry = c.subscriptions().unwrap();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(
+            registry.get(0).unwrap().1.granted_qos,
+            Some(QoS::AtLeastOnce)
+        );
+        assert_eq!(
+            registry.get(1).unwrap().1.granted_qos,
+            Some(QoS::AtMostOnce)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test_log::test]
+    fn publish_typed() {
+        use crate::client::codec::Json;
+
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut rx = [0; 64];
+        let mut tx = [0; 64];
+        let pipe = Pipe::new(&mut rx, &mut tx);
+        let mut payload = [0; 8];
+
+        let mut c = Client::<'_, _, 1, 1, 1, 0, 0>::new(pipe, &mut b);
+        c.encode_payloads_with(&mut payload);
+        let client_identifier = MqttString::try_from("a").unwrap();
+
+        let mut out = [0; 64];
+
+        let options = ConnectOptions::new()
+            .clean_start()
+            .maximum_packet_size(NonZero::new(64).unwrap());
+        assert_ok!(c.connect(&options, Some(&client_identifier)));
+        c.transmit(&mut out);
+        assert_eq!(c.receive(&[0x20, 0x03, 0x00, 0x00, 0x00]), 5);
+        assert_ok!(c.poll_connack(&options, Some(client_identifier)).unwrap());
+
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let options = PublicationOptions::new(TopicReference::Name(topic));
+        assert_ok!(c.publish_typed::<Json, _>(&options, &[1u8, 2]));
+
+        // The content type and payload format indicator are set.
+        let n = c.transmit(&mut out);
+        assert_eq!(n, 32);
+        assert_eq!(out[..6], [0x30, 0x1E, 0x00, 0x01, b't', 0x15]);
+        assert_eq!(out[n - 5..n], *b"[1,2]");
+
+        // The buffer is too small.
+        assert_eq!(
+            c.publish_typed::<Json, _>(&options, &[100u8, 200, 255]),
+            Err(MqttError::PayloadEncoding)
+        );
+        assert_eq!(c.transmit(&mut out), 0);
+
+        // The publication is received back.
+        let mut publish = [0; 64];
+        publish[..n].copy_from_slice(&out[..n]);
+        assert_eq!(c.receive(&publish[..n]), n);
+        let Event::Publish(p) = assert_ok!(c.poll().unwrap()) else {
+            panic!("expected PUBLISH");
+        };
+        assert_eq!(p.payload_format_indicator, Some(true));
+        assert_eq!(p.decode::<Json, [u8; 2]>(), Ok([1, 2]));
+    }
+}
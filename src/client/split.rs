@@ -0,0 +1,1860 @@
+//! Implements the halves into which [`Client::split`] splits a connected client, so that packets
+//! can be sent by one task while another one is waiting for incoming packets.
+
+use core::{
+    cell::{Cell, RefCell},
+    cmp::min,
+    convert::Infallible,
+    mem,
+    time::Duration,
+};
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError, Taken,
+        codec::EncodePayload,
+        event::Event,
+        options::{
+            AckOptions, AuthOptions, DisconnectOptions, FilterOptions, PublicationOptions,
+            SubscribeOptions, SubscriptionOptions, UnsubscriptionOptions,
+        },
+        protocol::{Dequeued, Incoming, Link, Outgoing, Protocol, encode_payload, leaves_queue},
+        raw::{
+            BodyState, HeaderState, KeepAliveState, NetStateError, NetStatus, Parts, Raw, RawError,
+        },
+        registry::Pending,
+    },
+    eio::{Error as _, ErrorKind, Write},
+    fmt::{assert, debug, error, info, panic, warn},
+    header::FixedHeader,
+    io::Split,
+    packet::{RxError, TxPacket, decode, encode},
+    time::Instant,
+    types::{PacketIdentifier, ReasonCode, TopicFilter},
+    v5::packet::PingreqPacket,
+};
+
+#[cfg(doc)]
+use crate::client::Client;
+
+/// The size of the chunks in which bytes are moved from the transmit buffer to the write half of
+/// the network connection.
+const CHUNK_SIZE: usize = 64;
+
+/// The write half of the network connection with the bytes taken out of the transmit buffer which
+/// have not been written to it yet.
+struct Transmitter<W> {
+    writer: W,
+    chunk: [u8; CHUNK_SIZE],
+    written: usize,
+    len: usize,
+}
+
+impl<W> Transmitter<W> {
+    const fn new(writer: W) -> Self {
+        Self {
+            writer,
+            chunk: [0; CHUNK_SIZE],
+            written: 0,
+            len: 0,
+        }
+    }
+
+    fn pending(&self) -> bool {
+        self.written < self.len
+    }
+}
+
+/// The state of the network connection shared by the halves of a split [`Client`] with the
+/// transmit buffer into which both halves encode their packets.
+struct Connection<'s, 'c, B: BufferProvider<'c>> {
+    status: &'s mut NetStatus,
+    buf: &'s mut B,
+    keep_alive: &'s mut KeepAliveState<'c>,
+    tx: &'s mut [u8],
+    len: usize,
+}
+
+impl<'c, B: BufferProvider<'c>> Connection<'_, 'c, B> {
+    fn check_open(&self, direction: &str) -> Result<(), RawError<B::ProvisionError>> {
+        self.status.check().inspect_err(|e| match e {
+            NetStateError::Faulted => {
+                warn!("attempted to {} a faulted mqtt connection", direction)
+            }
+            NetStateError::Terminated => {
+                warn!("attempted to {} a closed network connection", direction)
+            }
+        })?;
+
+        Ok(())
+    }
+
+    fn send<P: TxPacket>(&mut self, packet: &P) -> Result<(), RawError<B::ProvisionError>> {
+        self.check_open("send on")?;
+
+        match encode(packet, &mut self.tx[self.len..]) {
+            Ok(n) => {
+                self.len += n;
+                self.keep_alive.sent(P::PACKET_TYPE);
+                Ok(())
+            }
+            Err(_) => {
+                error!("transmit buffer cannot hold the packet");
+
+                // Close right away because the packets that follow would be sent out of order.
+                self.close_with(None);
+                Err(RawError::Network(ErrorKind::OutOfMemory))
+            }
+        }
+    }
+
+    fn send_outgoing(&mut self, packet: &Outgoing<'_>) -> Result<(), RawError<B::ProvisionError>> {
+        match packet {
+            Outgoing::Puback(p) => self.send(p),
+            Outgoing::Pubrec(p) => self.send(p),
+            Outgoing::Pubrel(p) => self.send(p),
+            Outgoing::Pubcomp(p) => self.send(p),
+            Outgoing::Auth(p) => self.send(p),
+            Outgoing::Stored(p) => self.send(p),
+        }
+    }
+
+    /// Appends encoded bytes to the transmit buffer. Returns whether they fit.
+    fn push(&mut self, bytes: &[u8]) -> bool {
+        let Some(tx) = self.tx.get_mut(self.len..self.len + bytes.len()) else {
+            return false;
+        };
+
+        tx.copy_from_slice(bytes);
+        self.len += bytes.len();
+        true
+    }
+
+    /// Moves bytes from the front of the transmit buffer into `chunk` and returns their amount.
+    fn transmit(&mut self, chunk: &mut [u8]) -> usize {
+        let n = min(self.len, chunk.len());
+        chunk[..n].copy_from_slice(&self.tx[..n]);
+        self.tx.copy_within(n..self.len, 0);
+        self.len -= n;
+        n
+    }
+
+    fn handle_rx<E: Into<(RawError<B::ProvisionError>, Option<ReasonCode>)>>(
+        &mut self,
+        e: E,
+    ) -> RawError<B::ProvisionError> {
+        let (e, r) = e.into();
+
+        match e {
+            RawError::Network(ref e) => error!("I/O error during receive: {:?}", e),
+            RawError::Alloc(ref e) => error!("buffer provision failed: {:?}", e),
+            _ => error!("server protocol violation"),
+        }
+
+        self.close_with(r);
+
+        e
+    }
+}
+
+impl<'c, B: BufferProvider<'c>> Link<'c> for Connection<'_, 'c, B> {
+    type Buffer = B;
+
+    fn is_connected(&self) -> bool {
+        *self.status == NetStatus::Ok
+    }
+
+    fn close_with(&mut self, reason_code: Option<ReasonCode>) {
+        match reason_code {
+            Some(r) => self.status.fail(r),
+            None if *self.status != NetStatus::Terminated => {
+                // The halves of the network connection are dropped by the client once both halves
+                // of the client have been dropped.
+                *self.status = NetStatus::Terminated;
+                debug!("closed network connection");
+            }
+            None => {}
+        }
+    }
+
+    fn now(&self) -> Option<Instant> {
+        self.keep_alive.now()
+    }
+
+    fn keep_alive_deadline(&self, interval: Duration) -> Option<Instant> {
+        if self.is_connected() {
+            self.keep_alive.deadline(interval)
+        } else {
+            None
+        }
+    }
+
+    fn ping_pending(&self) -> bool {
+        self.keep_alive.ping_pending()
+    }
+
+    fn buffer_mut(&mut self) -> &mut B {
+        self.buf
+    }
+}
+
+/// The protocol state of a split [`Client`] together with the state of its network connection.
+/// The methods encode the packets they send into the transmit buffer.
+struct Core<
+    's,
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    protocol: &'s mut Protocol<
+        'c,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+    conn: Connection<'s, 'c, B>,
+}
+
+impl<
+    'c,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Core<
+        '_,
+        'c,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn reauthenticate(&mut self, options: &AuthOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.reauthenticate(options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        debug!("sending PINGREQ packet");
+
+        self.conn.send(&PingreqPacket::new())?;
+
+        Ok(())
+    }
+
+    fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        if self.protocol.check_keep_alive(&mut self.conn)? {
+            self.ping()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.subscribe_many(subscriptions, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(pid)
+    }
+
+    fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let (pid, packet) = self.protocol.unsubscribe_many(topic_filters, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(pid)
+    }
+
+    fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(packet) = self.protocol.publish(&self.conn, options, message)? else {
+            return Ok(None);
+        };
+
+        self.conn.send(&packet)?;
+
+        Ok(packet.identified_qos.packet_identifier())
+    }
+
+    fn publish_typed<C: EncodePayload<T>, T: ?Sized>(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        value: &T,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.payload_buffer) else {
+            panic!("attempted to publish a typed value without a payload buffer");
+        };
+        let (core, buffer) = taken.split();
+
+        let (options, len) = encode_payload::<C, T>(options, value, buffer)?;
+
+        core.publish(&options, Bytes::Borrowed(&buffer[..len]))
+    }
+
+    fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .republish(packet_identifier, options, message)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn resend(&mut self, publishes: bool) -> Result<(), MqttError<'c, 0>> {
+        for i in 0..self.protocol.session.outbound_publishes.len() {
+            if let Some(packet) = self.protocol.resend(i, publishes) {
+                self.conn.send_outgoing(&packet)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn drain_queue(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.publish_queue) else {
+            return Ok(());
+        };
+        let (core, queue) = taken.split();
+
+        while let Some(record) = queue.front() {
+            match core.publish_queued(record) {
+                Ok(true) => queue.pop(),
+                Ok(false) => return Ok(()),
+                Err(e) => {
+                    queue.pop();
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn publish_queued(&mut self, record: &[u8]) -> Result<bool, MqttError<'c, 0>> {
+        match self.protocol.dequeue(&self.conn, record) {
+            Dequeued::Drop => Ok(true),
+            Dequeued::Wait => Ok(false),
+            Dequeued::Publish(publication) => leaves_queue(
+                self.publish(&publication.options(), Bytes::Borrowed(publication.message)),
+            ),
+        }
+    }
+
+    fn resubscribe_all(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut taken) = Taken::new(self, |c| &mut c.protocol.subscription_registry) else {
+            return Ok(());
+        };
+        let (core, registry) = taken.split();
+
+        for i in 0..registry.len() {
+            let Some((topic_filter, mut subscription)) = registry.get(i) else {
+                break;
+            };
+
+            if subscription.granted_qos.is_some() || subscription.pending.is_some() {
+                continue;
+            }
+
+            debug!("resubscribing to recorded topic filter");
+
+            let pid = core.subscribe_many(
+                &[(topic_filter, subscription.options.filter_options())],
+                &subscription.options.subscribe_options(),
+            )?;
+
+            subscription.pending = Some(Pending::Subscribe {
+                packet_identifier: pid,
+                index: 0,
+            });
+            registry.set(i, subscription);
+        }
+
+        Ok(())
+    }
+
+    fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .manual_acknowledge(packet_identifier, reason_code, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self
+            .protocol
+            .manual_receive(packet_identifier, reason_code, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.manual_release(packet_identifier, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.manual_complete(packet_identifier, options)?;
+
+        self.conn.send(&packet)?;
+
+        Ok(())
+    }
+
+    fn disconnect(&mut self, options: &DisconnectOptions<'_>) -> Result<(), MqttError<'c, 0>> {
+        let packet = self.protocol.disconnect(options)?;
+
+        self.conn.send(&packet)?;
+
+        // Terminates (closes) the connection. The DISCONNECT packet is still transmitted.
+        self.conn.close_with(None);
+
+        info!("disconnected from server");
+
+        Ok(())
+    }
+}
+
+/// The state shared by the halves of a split [`Client`].
+struct Shared<
+    's,
+    'c,
+    W: Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    /// Only ever borrowed in between two awaits, so that neither half waits for the other one
+    /// to release it.
+    core: RefCell<
+        Core<
+            's,
+            'c,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    >,
+
+    /// Borrowed across awaits by the half which is currently transmitting, so it stays in place
+    /// if that half is cancelled. The other half does not transmit while it is borrowed.
+    tx: RefCell<Transmitter<W>>,
+
+    /// The amount of halves which have not been dropped yet.
+    halves: Cell<u8>,
+}
+
+impl<
+    'c,
+    W: Write,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Shared<
+        '_,
+        'c,
+        W,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Writes the pending bytes of the transmit buffer to the network connection.
+    ///
+    /// If the other half is transmitting at the moment, it also writes the bytes which are added to
+    /// the transmit buffer in the meantime, so this returns right away. If the returned future is
+    /// cancelled, the bytes which have not been written are written by the next call.
+    // The borrow marks this half as transmitting. Everything else only tries to borrow `tx`.
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn transmit(&self) -> Result<(), MqttError<'c, 0>> {
+        let Ok(mut tx) = self.tx.try_borrow_mut() else {
+            return Ok(());
+        };
+        let Transmitter {
+            writer,
+            chunk,
+            written,
+            len,
+        } = &mut *tx;
+
+        let r = loop {
+            if written == len {
+                *written = 0;
+                *len = self.core.borrow_mut().conn.transmit(chunk);
+            }
+
+            if *len == 0 {
+                if let Err(e) = writer.flush().await {
+                    break Err(e.kind());
+                }
+
+                // Bytes may have been added while flushing.
+                *len = self.core.borrow_mut().conn.transmit(chunk);
+                if *len == 0 {
+                    break Ok(());
+                }
+            }
+
+            match writer.write(&chunk[*written..*len]).await {
+                Ok(0) => break Err(ErrorKind::WriteZero),
+                Ok(n) => *written += n,
+                Err(e) => break Err(e.kind()),
+            }
+        };
+
+        drop(tx);
+        r.map_err(|kind| {
+            error!("I/O error during send: {:?}", kind);
+
+            // Terminate right away because if writing fails, writing another (DISCONNECT) packet
+            // doesn't make sense.
+            self.core.borrow_mut().conn.close_with(None);
+            MqttError::Network(kind)
+        })
+    }
+
+    /// Transmits the packets encoded by a sending method if it was successful.
+    async fn sent<T>(&self, r: Result<T, MqttError<'c, 0>>) -> Result<T, MqttError<'c, 0>> {
+        let t = r?;
+        self.transmit().await?;
+        Ok(t)
+    }
+
+    /// Closes the network connection once both halves have been dropped if bytes have not been
+    /// written to it completely, as the client can not continue writing them.
+    fn leave(&self) {
+        let halves = self.halves.get() - 1;
+        self.halves.set(halves);
+
+        if halves > 0 {
+            return;
+        }
+
+        let unsent = self.tx.borrow().pending();
+        let conn = &mut self.core.borrow_mut().conn;
+
+        if (unsent || conn.len > 0) && *conn.status != NetStatus::Terminated {
+            warn!("split client dropped before all packets have been written");
+            conn.close_with(None);
+        }
+    }
+}
+
+/// The state shared by the [`Receiver`] and the [`Sender`] of a split [`Client`], which has to
+/// outlive both halves.
+///
+/// The buffer passed to [`Self::new`] is the transmit buffer into which both halves encode the
+/// packets they send before these are written to the network connection. It must be able to hold
+/// all packets which are sent by both halves while one of them is writing to the network
+/// connection. A state can be used to split a client once.
+pub struct State<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
+> {
+    buffer: &'s mut [u8],
+    shared: Option<
+        Shared<
+            's,
+            'c,
+            N::Writer<'s>,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    >,
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for State<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("State")
+            .field("split", &self.shared.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for State<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "State {{ split: {}, .. }}", self.shared.is_some());
+    }
+}
+
+impl<
+    's,
+    'c: 's,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    State<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates the state for splitting a client with `buffer` as the transmit buffer.
+    pub fn new(buffer: &'s mut [u8]) -> Self {
+        Self {
+            buffer,
+            shared: None,
+        }
+    }
+
+    /// Implements [`Client::split`].
+    pub(super) fn split(
+        &'s mut self,
+        protocol: &'s mut Protocol<
+            'c,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        raw: &'s mut Raw<'c, N, B>,
+    ) -> (
+        Receiver<
+            's,
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        Sender<
+            's,
+            'c,
+            N,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+    ) {
+        let Some(Parts {
+            net,
+            status,
+            buf,
+            header,
+            body,
+            keep_alive,
+        }) = raw.split()
+        else {
+            panic!("the client must be connected and reassemble packets to be split");
+        };
+
+        let mut conn = Connection {
+            status,
+            buf,
+            keep_alive,
+            tx: mem::take(&mut self.buffer),
+            len: 0,
+        };
+
+        // The remainder of a response which the client has not written completely is written
+        // before any other packet.
+        assert!(
+            conn.push(body.take_response()),
+            "the transmit buffer must be able to hold the response to the last packet"
+        );
+
+        let (reader, writer) = net.split();
+
+        let shared = &*self.shared.insert(Shared {
+            core: RefCell::new(Core { protocol, conn }),
+            tx: RefCell::new(Transmitter::new(writer)),
+            halves: Cell::new(2),
+        });
+
+        (
+            Receiver {
+                reader,
+                header,
+                body,
+                shared,
+            },
+            Sender { shared },
+        )
+    }
+}
+
+/// The receive half of a split [`Client`] which owns [`Receiver::poll`] and reads from the read
+/// half of the network connection.
+pub struct Receiver<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    reader: N::Reader<'s>,
+    header: &'s mut HeaderState,
+    body: &'s mut BodyState<'c>,
+    shared: &'s Shared<
+        's,
+        'c,
+        N::Writer<'s>,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Receiver<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Receiver")
+            .field("header", &self.header)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Receiver<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Receiver {{ header: {:?}, body: {:?}, .. }}",
+            self.header,
+            self.body
+        );
+    }
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> Drop
+    for Receiver<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn drop(&mut self) {
+        self.shared.leave();
+    }
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Receiver<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Writes the responses to the packets received before, then waits for the next packet and
+    /// processes it. Responses to this packet, such as the PUBACK packet of an incoming
+    /// [`QoS::AtLeastOnce`](crate::types::QoS::AtLeastOnce) publication, are encoded into the
+    /// transmit buffer and written by the next call or the next method of the [`Sender`] sending a
+    /// packet, whichever comes first.
+    ///
+    /// This is cancel-safe if `N::Reader::read()`, `N::Writer::write()` and `N::Writer::flush()`
+    /// are cancel-safe: If the future is dropped, the next call continues where it stopped and
+    /// no event is lost.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::poll`] returns.
+    pub async fn poll(
+        &mut self,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        self.shared.transmit().await.map_err(MqttError::inflate)?;
+
+        let header = self.poll_header().await.map_err(MqttError::inflate)?;
+        self.poll_body(header).await
+    }
+
+    async fn poll_header(&mut self) -> Result<FixedHeader, MqttError<'c, 0>> {
+        self.shared.core.borrow().conn.check_open("receive from")?;
+
+        if let Some(h) = self.body.pending_header() {
+            debug!("resuming reception of packet body");
+            return Ok(h);
+        }
+
+        loop {
+            let r = self.header.update(&mut self.reader).await;
+
+            let mut core = self.shared.core.borrow_mut();
+            let Core { protocol, conn } = &mut *core;
+
+            match r {
+                Ok(None) => {}
+                Ok(Some(h)) => {
+                    self.body.start(h);
+                    return protocol.check_header(conn, h);
+                }
+                Err(e) => {
+                    let e: RxError<_, _> = e.into();
+                    return Err(conn.handle_rx(e).into());
+                }
+            }
+        }
+    }
+
+    async fn poll_body(
+        &mut self,
+        header: FixedHeader,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        {
+            let mut core = self.shared.core.borrow_mut();
+            let Core { protocol, conn } = &mut *core;
+            protocol.check_packet_type(conn, &header)?;
+        }
+
+        loop {
+            match self.body.update(&mut self.reader).await {
+                Ok(false) => {}
+                Ok(true) => break,
+                Err(e) => {
+                    let e: RxError<_, _> = e.into();
+                    return Err(self.shared.core.borrow_mut().conn.handle_rx(e).into());
+                }
+            }
+        }
+
+        let mut core = self.shared.core.borrow_mut();
+        let Core { protocol, conn } = &mut *core;
+
+        let packet = match decode::<Incoming<MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>, _>(
+            &header,
+            self.body.take(),
+            conn.buf,
+        ) {
+            Ok(p) => p,
+            // The body has been received completely, so it is too short for its packet.
+            Err(RxError::UnexpectedEOF) => {
+                return Err(conn
+                    .handle_rx(RxError::<Infallible, _>::MalformedPacket)
+                    .into());
+            }
+            Err(e) => return Err(conn.handle_rx(e).into()),
+        };
+
+        conn.keep_alive.received(&header);
+
+        let (event, response) = protocol.receive(conn, packet)?;
+
+        if let Some(packet) = response {
+            conn.send_outgoing(&packet)?;
+        }
+
+        Ok(event)
+    }
+}
+
+/// The send half of a split [`Client`] which owns the methods sending packets and writes to the
+/// write half of the network connection.
+///
+/// The methods sending packets return once the packets have been written to the network
+/// connection. If the [`Receiver`] is writing to the network connection at the moment, they
+/// return right after the packets have been added to the transmit buffer, from which the
+/// [`Receiver`] writes them. If a returned future is dropped while packets are being written, the
+/// remaining bytes are written by the next method sending a packet or the next call to
+/// [`Receiver::poll`].
+pub struct Sender<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> {
+    shared: &'s Shared<
+        's,
+        'c,
+        N::Writer<'s>,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Sender<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Sender<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "Sender {{ .. }}");
+    }
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> Drop
+    for Sender<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn drop(&mut self) {
+        self.shared.leave();
+    }
+}
+
+impl<
+    's,
+    'c,
+    N: Split + 's,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Sender<
+        's,
+        'c,
+        N,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Refer to [`Client::reauthenticate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::reauthenticate`] returns.
+    pub async fn reauthenticate(
+        &mut self,
+        options: &AuthOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().reauthenticate(options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::ping`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::ping`] returns.
+    pub async fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().ping();
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::next_ping_deadline`].
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        let core = self.shared.core.borrow();
+        core.protocol.next_ping_deadline(&core.conn)
+    }
+
+    /// Refer to [`Client::check_keep_alive`]. After [`MqttError::KeepAliveTimeout`], both halves
+    /// have to be dropped to [`Client::abort`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::check_keep_alive`] returns.
+    pub async fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().check_keep_alive();
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::subscribe`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe`] returns.
+    pub async fn subscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.subscribe_many(
+            &[(topic_filter, options.filter_options())],
+            &options.subscribe_options(),
+        )
+        .await
+    }
+
+    /// Refer to [`Client::subscribe_many`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::subscribe_many`] returns.
+    pub async fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+        options: &SubscribeOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .subscribe_many(subscriptions, options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::unsubscribe`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe`] returns.
+    pub async fn unsubscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.unsubscribe_many(&[topic_filter], options).await
+    }
+
+    /// Refer to [`Client::unsubscribe_many`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::unsubscribe_many`] returns.
+    pub async fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+        options: &UnsubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .unsubscribe_many(topic_filters, options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::publish`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish`] returns.
+    pub async fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().publish(options, message);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::publish_typed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::publish_typed`] returns.
+    pub async fn publish_typed<C: EncodePayload<T>, T: ?Sized>(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        value: &T,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .publish_typed::<C, T>(options, value);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::republish`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::republish`] returns.
+    pub async fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .republish(packet_identifier, options, message);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::rerelease`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::rerelease`] returns.
+    pub async fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().resend(false);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::resume`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resume`] returns.
+    pub async fn resume(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().resend(true);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::drain_queue`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::drain_queue`] returns.
+    pub async fn drain_queue(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().drain_queue();
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::resubscribe_all`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::resubscribe_all`] returns.
+    pub async fn resubscribe_all(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().resubscribe_all();
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::manual_acknowledge`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_acknowledge`] returns.
+    pub async fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().manual_acknowledge(
+            packet_identifier,
+            reason_code,
+            options,
+        );
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::manual_receive`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_receive`] returns.
+    pub async fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        reason_code: ReasonCode,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r =
+            self.shared
+                .core
+                .borrow_mut()
+                .manual_receive(packet_identifier, reason_code, options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::manual_release`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_release`] returns.
+    pub async fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .manual_release(packet_identifier, options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::manual_complete`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::manual_complete`] returns.
+    pub async fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &AckOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self
+            .shared
+            .core
+            .borrow_mut()
+            .manual_complete(packet_identifier, options);
+        self.shared.sent(r).await
+    }
+
+    /// Refer to [`Client::disconnect`]. The [`Receiver`] returns an error once the server has
+    /// closed the network connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::disconnect`] returns.
+    pub async fn disconnect(
+        &mut self,
+        options: &DisconnectOptions<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let r = self.shared.core.borrow_mut().disconnect(options);
+        self.shared.sent(r).await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::{num::NonZero, time::Duration};
+    use std::boxed::Box;
+
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf, duplex, split},
+        join,
+        sync::oneshot::channel,
+        task::{LocalSet, spawn_local},
+        time::timeout,
+    };
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        buffer::BufferProvider,
+        client::{
+            Client,
+            event::Event,
+            options::{ConnectOptions, DisconnectOptions, PublicationOptions, TopicReference},
+            split::State,
+        },
+        eio::{ErrorType, Read, Write},
+        io::Split,
+        types::{MqttString, TopicName},
+    };
+
+    /// A duplex stream split into its halves.
+    struct SplitDuplex(
+        FromTokio<ReadHalf<DuplexStream>>,
+        FromTokio<WriteHalf<DuplexStream>>,
+    );
+
+    impl ErrorType for SplitDuplex {
+        type Error = std::io::Error;
+    }
+    impl Read for SplitDuplex {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.0.read(buf).await
+        }
+    }
+    impl Write for SplitDuplex {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.1.write(buf).await
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.1.flush().await
+        }
+    }
+    impl Split for SplitDuplex {
+        type Reader<'a> = &'a mut FromTokio<ReadHalf<DuplexStream>>;
+        type Writer<'a> = &'a mut FromTokio<WriteHalf<DuplexStream>>;
+
+        fn split(&mut self) -> (Self::Reader<'_>, Self::Writer<'_>) {
+            (&mut self.0, &mut self.1)
+        }
+    }
+
+    /// Connects the client over `c` while the server answers over `s`.
+    async fn connect<'c, B: BufferProvider<'c>>(
+        client: &mut Client<'c, SplitDuplex, B, 1, 1, 1, 0, 0>,
+        c: DuplexStream,
+        s: &mut DuplexStream,
+    ) {
+        let (r, w) = split(c);
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read_exact(&mut buf[..2]).await);
+            assert_eq!(buf[0], 0x10);
+            let len = buf[1].into();
+            assert_ok!(s.read_exact(&mut buf[..len]).await);
+            assert_ok!(s.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+        };
+        let connect = async {
+            let options = ConnectOptions::new()
+                .clean_start()
+                .maximum_packet_size(NonZero::new(64).unwrap());
+            let id = MqttString::try_from("a").unwrap();
+            let net = SplitDuplex(FromTokio::new(r), FromTokio::new(w));
+            assert_ok!(client.connect(net, &options, Some(id)).await);
+        };
+        join!(server, connect);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn publish_while_polling() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut reassembly = [0; 64];
+        let mut tx = [0; 64];
+        let (c, mut s) = duplex(64);
+        let (published, publish_seen) = channel();
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.reassemble_packets_with(&mut reassembly);
+        connect(&mut client, c, &mut s).await;
+
+        let mut state = State::new(&mut tx);
+        let (mut receiver, mut sender) = client.split(&mut state);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(publish_seen.await);
+            let n = assert_ok!(s.read(&mut buf).await);
+            assert_eq!(buf[0], 0x32);
+            assert_eq!(usize::from(buf[1]) + 2, n);
+            assert_ok!(s.write_all(&[0x40, 0x02, 0x00, 0x01]).await);
+        };
+        let poll = async {
+            let event = assert_ok!(receiver.poll().await);
+            assert!(
+                matches!(event, Event::PublishAcknowledged(p) if p.packet_identifier.get().get() == 1)
+            );
+        };
+        let publish = async {
+            let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+            let pid = assert_ok!(
+                sender
+                    .publish(
+                        &PublicationOptions::new(TopicReference::Name(topic)).at_least_once(),
+                        "m".as_bytes().into(),
+                    )
+                    .await
+            );
+            assert_eq!(pid.unwrap().get().get(), 1);
+            assert_ok!(published.send(()));
+        };
+        join!(server, poll, publish);
+
+        // The client continues on its own once both halves have been dropped.
+        drop((receiver, sender));
+
+        let server = async {
+            let mut buf = [0; 4];
+            assert_ok!(s.read_exact(&mut buf).await);
+            assert_eq!(buf, [0xE0, 0x02, 0x00, 0x00]);
+        };
+        let disconnect = async {
+            assert_ok!(client.disconnect(&DisconnectOptions::new()).await);
+        };
+        join!(server, disconnect);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn cancelled_transmission_resumes() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut reassembly = [0; 64];
+        let mut tx = [0; 64];
+        let (c, mut s) = duplex(16);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.reassemble_packets_with(&mut reassembly);
+        connect(&mut client, c, &mut s).await;
+
+        let mut state = State::new(&mut tx);
+        let (_, mut sender) = client.split(&mut state);
+        let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+        let options = PublicationOptions::new(TopicReference::Name(topic));
+
+        // The server does not read, so the PUBLISH packet does not fit into the transport.
+        let message = [b'm'; 32];
+        assert_err!(
+            timeout(
+                Duration::from_millis(10),
+                sender.publish(&options, (&message[..]).into())
+            )
+            .await
+        );
+
+        let server = async {
+            let mut buf = [0; 64];
+            assert_ok!(s.read_exact(&mut buf[..38]).await);
+            assert_eq!(buf[..6], [0x30, 0x24, 0x00, 0x01, b't', 0x00]);
+            assert_eq!(buf[6..38], [b'm'; 32]);
+            assert_ok!(s.read_exact(&mut buf[..7]).await);
+            assert_eq!(buf[..7], [0x30, 0x05, 0x00, 0x01, b't', 0x00, b'n']);
+        };
+        let publish = async {
+            assert_ok!(sender.publish(&options, "n".as_bytes().into()).await);
+        };
+        join!(server, publish);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn sender_writes_pending_response() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let mut reassembly = [0; 64];
+        let mut tx = [0; 64];
+        let (c, mut s) = duplex(64);
+
+        let mut client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        client.reassemble_packets_with(&mut reassembly);
+        connect(&mut client, c, &mut s).await;
+
+        let mut state = State::new(&mut tx);
+        let (mut receiver, mut sender) = client.split(&mut state);
+
+        assert_ok!(
+            s.write_all(&[0x32, 0x07, 0x00, 0x01, b't', 0x00, 0x01, 0x00, b'm'])
+                .await
+        );
+        let event = assert_ok!(receiver.poll().await);
+        assert!(matches!(event, Event::Publish(_)));
+
+        // The PUBACK packet is written before the packet sent next.
+        assert_ok!(sender.ping().await);
+
+        let mut buf = [0; 8];
+        assert_ok!(s.read_exact(&mut buf).await);
+        assert_eq!(buf, [0x40, 0x04, 0x00, 0x01, 0x00, 0x00, 0xC0, 0x00]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn halves_run_as_separate_tasks() {
+        #[cfg(feature = "alloc")]
+        let b = Box::leak(Box::new(AllocBuffer));
+        #[cfg(feature = "bump")]
+        let b = Box::leak(Box::new(BumpBuffer::new(Box::leak(Box::new([0; 64])))));
+        let reassembly = Box::leak(Box::new([0; 64]));
+        let tx = Box::leak(Box::new([0; 64]));
+        let (c, mut s) = duplex(64);
+
+        let client = Box::leak(Box::new(Client::<'_, _, _, 1, 1, 1, 0, 0>::new(b)));
+        client.reassemble_packets_with(reassembly);
+        connect(client, c, &mut s).await;
+
+        let state = Box::leak(Box::new(State::new(tx)));
+        let (mut receiver, mut sender) = client.split(state);
+
+        LocalSet::new()
+            .run_until(async move {
+                let poll = spawn_local(async move {
+                    let event = assert_ok!(receiver.poll().await);
+                    assert!(
+                        matches!(event, Event::PublishAcknowledged(p) if p.packet_identifier.get().get() == 1)
+                    );
+                });
+                let publish = spawn_local(async move {
+                    let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+                    let options = PublicationOptions::new(TopicReference::Name(topic)).at_least_once();
+                    assert_ok!(sender.publish(&options, "m".as_bytes().into()).await);
+                });
+                let server = async {
+                    let mut buf = [0; 9];
+                    assert_ok!(s.read_exact(&mut buf).await);
+                    assert_eq!(buf, [0x32, 0x07, 0x00, 0x01, b't', 0x00, 0x01, 0x00, b'm']);
+                    assert_ok!(s.write_all(&[0x40, 0x02, 0x00, 0x01]).await);
+                };
+
+                let (poll, publish, ()) = join!(poll, publish, server);
+                assert_ok!(poll);
+                assert_ok!(publish);
+            })
+            .await;
+    }
+}
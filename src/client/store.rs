@@ -0,0 +1,247 @@
+//! Contains the trait the client uses to keep outgoing publications for retransmission and basic
+//! implementations.
+
+#[cfg(feature = "alloc")]
+pub use alloc::AllocMessageStore;
+
+use heapless::Vec;
+
+use crate::{
+    eio::Write,
+    header::PacketType,
+    io::write::Writable,
+    packet::{Packet, TxError, TxPacket},
+    types::{PacketIdentifier, VarByteInt},
+};
+
+/// Storage for the encoded PUBLISH packets of outgoing [`QoS::AtLeastOnce`] and
+/// [`QoS::ExactlyOnce`] publications.
+///
+/// A [`Client`] with a message store set via [`Client::store_messages_in`] inserts every such
+/// PUBLISH packet before sending it and removes it once the server has acknowledged it with a
+/// PUBACK or PUBREC packet. After reconnecting to a present session, [`Client::resume`] resends
+/// the stored packets.
+///
+/// [`QoS::AtLeastOnce`]: crate::types::QoS::AtLeastOnce
+/// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
+/// [`Client`]: crate::client::Client
+/// [`Client::store_messages_in`]: crate::client::Client::store_messages_in
+/// [`Client::resume`]: crate::client::Client::resume
+pub trait MessageStore {
+    /// Returns `len` bytes of memory for the packet with this packet identifier, replacing a
+    /// previously inserted packet with the same packet identifier. The client writes the packet
+    /// into the returned slice.
+    ///
+    /// Returns [`None`] if there is not enough space. The client then does not send the packet.
+    fn insert(&mut self, packet_identifier: PacketIdentifier, len: usize) -> Option<&mut [u8]>;
+
+    /// Returns the packet with this packet identifier.
+    fn get(&self, packet_identifier: PacketIdentifier) -> Option<&[u8]>;
+
+    /// Removes the packet with this packet identifier if there is one.
+    fn remove(&mut self, packet_identifier: PacketIdentifier);
+
+    /// Removes all packets.
+    fn clear(&mut self);
+}
+
+/// Stores up to `MESSAGES` packets of up to `SIZE` bytes each in place.
+#[derive(Debug, Default)]
+pub struct FixedMessageStore<const MESSAGES: usize, const SIZE: usize> {
+    packets: Vec<(PacketIdentifier, Vec<u8, SIZE>), MESSAGES>,
+}
+
+#[cfg(feature = "defmt")]
+impl<const MESSAGES: usize, const SIZE: usize> defmt::Format for FixedMessageStore<MESSAGES, SIZE> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "FixedMessageStore {{ packets: {} }}",
+            self.packets.len()
+        );
+    }
+}
+
+impl<const MESSAGES: usize, const SIZE: usize> FixedMessageStore<MESSAGES, SIZE> {
+    /// Creates an empty store.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            packets: Vec::new(),
+        }
+    }
+}
+
+impl<const MESSAGES: usize, const SIZE: usize> MessageStore for FixedMessageStore<MESSAGES, SIZE> {
+    fn insert(&mut self, packet_identifier: PacketIdentifier, len: usize) -> Option<&mut [u8]> {
+        self.remove(packet_identifier);
+
+        let mut packet = Vec::new();
+        packet.resize(len, 0).ok()?;
+        self.packets.push((packet_identifier, packet)).ok()?;
+
+        self.packets.last_mut().map(|(_, p)| p.as_mut_slice())
+    }
+
+    fn get(&self, packet_identifier: PacketIdentifier) -> Option<&[u8]> {
+        self.packets
+            .iter()
+            .find(|(p, _)| *p == packet_identifier)
+            .map(|(_, p)| p.as_slice())
+    }
+
+    fn remove(&mut self, packet_identifier: PacketIdentifier) {
+        self.packets.retain(|(p, _)| *p != packet_identifier);
+    }
+
+    fn clear(&mut self) {
+        self.packets.clear();
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod alloc {
+    use alloc::{boxed::Box, vec, vec::Vec};
+
+    use crate::{client::store::MessageStore, types::PacketIdentifier};
+
+    /// Stores packets on the heap using the global allocator.
+    #[derive(Debug, Default)]
+    pub struct AllocMessageStore {
+        packets: Vec<(PacketIdentifier, Box<[u8]>)>,
+    }
+
+    #[cfg(feature = "defmt")]
+    impl defmt::Format for AllocMessageStore {
+        fn format(&self, fmt: defmt::Formatter) {
+            defmt::write!(
+                fmt,
+                "AllocMessageStore {{ packets: {} }}",
+                self.packets.len()
+            );
+        }
+    }
+
+    impl AllocMessageStore {
+        /// Creates an empty store.
+        #[must_use]
+        pub const fn new() -> Self {
+            Self {
+                packets: Vec::new(),
+            }
+        }
+    }
+
+    impl MessageStore for AllocMessageStore {
+        fn insert(&mut self, packet_identifier: PacketIdentifier, len: usize) -> Option<&mut [u8]> {
+            self.remove(packet_identifier);
+
+            self.packets
+                .push((packet_identifier, vec![0; len].into_boxed_slice()));
+
+            self.packets.last_mut().map(|(_, p)| &mut **p)
+        }
+
+        fn get(&self, packet_identifier: PacketIdentifier) -> Option<&[u8]> {
+            self.packets
+                .iter()
+                .find(|(p, _)| *p == packet_identifier)
+                .map(|(_, p)| &**p)
+        }
+
+        fn remove(&mut self, packet_identifier: PacketIdentifier) {
+            self.packets.retain(|(p, _)| *p != packet_identifier);
+        }
+
+        fn clear(&mut self) {
+            self.packets.clear();
+        }
+    }
+}
+
+/// A stored PUBLISH packet which is resent with the DUP flag set.
+pub(crate) struct StoredPublishPacket<'p> {
+    bytes: &'p [u8],
+    header_len: usize,
+}
+
+impl<'p> StoredPublishPacket<'p> {
+    /// The DUP flag in the first byte of the fixed header.
+    const DUP: u8 = 0x08;
+
+    pub fn new(bytes: &'p [u8]) -> Self {
+        // The fixed header consists of the type and flags byte and the variable byte integer
+        // whose last byte has the continuation bit cleared.
+        let header_len = bytes
+            .iter()
+            .skip(1)
+            .position(|b| b & 0x80 == 0)
+            .map_or(bytes.len(), |i| i + 2);
+
+        Self { bytes, header_len }
+    }
+}
+
+impl Packet for StoredPublishPacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Publish;
+}
+
+impl TxPacket for StoredPublishPacket<'_> {
+    fn remaining_len(&self) -> VarByteInt {
+        // Invariant: The packet has been encoded with this remaining length before.
+        VarByteInt::new_unchecked((self.bytes.len() - self.header_len) as u32)
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        let Some((first, rest)) = self.bytes.split_first() else {
+            return Ok(());
+        };
+
+        (first | Self::DUP).write(write).await?;
+        rest.write(write).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use crate::{
+        client::store::{FixedMessageStore, MessageStore, StoredPublishPacket},
+        packet::TxPacket,
+        test::tx::encode,
+        types::PacketIdentifier,
+    };
+
+    #[test]
+    fn fixed_store_capacity() {
+        let mut s = FixedMessageStore::<2, 4>::new();
+        let one = PacketIdentifier::ONE;
+        let two = one.next();
+
+        s.insert(one, 4).unwrap().copy_from_slice(&[1, 2, 3, 4]);
+        assert!(s.insert(two, 5).is_none());
+        s.insert(two, 1).unwrap()[0] = 5;
+        assert!(s.insert(two.next(), 1).is_none());
+
+        // Replacing a packet does not need additional space.
+        s.insert(one, 2).unwrap().copy_from_slice(&[6, 7]);
+        assert_eq!(s.get(one), Some(&[6, 7][..]));
+        assert_eq!(s.get(two), Some(&[5][..]));
+
+        s.remove(one);
+        assert_eq!(s.get(one), None);
+        s.clear();
+        assert_eq!(s.get(two), None);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn stored_packet_sets_dup() {
+        let bytes = [0x32, 0x06, 0x00, 0x01, b't', 0x00, 0x01, b'm'];
+        let packet = StoredPublishPacket::new(&bytes);
+        assert_eq!(packet.encoded_len(), bytes.len());
+
+        encode!(packet, [0x3A, 0x06, 0x00, 0x01, b't', 0x00, 0x01, b'm']);
+    }
+}
@@ -0,0 +1,818 @@
+//! Implements an opt-in supervisor which reconnects a [`Client`] after its connection failed.
+//!
+//! The [`Supervisor`] owns a [`Client`] and opens network connections through a [`Connector`].
+//! Whenever polling fails because the connection is lost, the supervisor aborts the connection,
+//! reconnects with exponential backoff and continues the session by retransmitting the due
+//! PUBLISH and PUBREL packets and publishing the publications queued while it was disconnected.
+
+use core::time::Duration;
+
+use heapless::String;
+
+use crate::{
+    Bytes,
+    buffer::BufferProvider,
+    client::{
+        Client, MqttError,
+        event::{Connected, Event as ClientEvent},
+        options::{ConnectOptions, PublicationOptions},
+    },
+    eio::{Error as _, ErrorType},
+    fmt::{assert, error, info, warn},
+    io::Transport,
+    session::LocalPublishState,
+    types::{MqttBinary, MqttString, PacketIdentifier},
+};
+
+/// The maximum length in bytes of the client identifier a [`Supervisor`] reconnects with. Servers
+/// accept client identifiers of up to 23 bytes but may assign longer ones.
+pub const MAX_CLIENT_IDENTIFIER_LEN: usize = 64;
+
+/// Opens network connections to the server for a [`Supervisor`] and waits in between attempts.
+#[allow(async_fn_in_trait)]
+pub trait Connector {
+    /// The network connection type.
+    type Transport: Transport;
+
+    /// Opens a new network connection to the server.
+    async fn connect(&mut self) -> Result<Self::Transport, <Self::Transport as ErrorType>::Error>;
+
+    /// Waits for `duration` before the next connection attempt, e.g. with a timer of the
+    /// executor.
+    async fn delay(&mut self, duration: Duration);
+}
+
+/// Provides the publications of due [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`] flows so that a
+/// [`Supervisor`] can republish them after reconnecting.
+///
+/// [`QoS::AtLeastOnce`]: crate::types::QoS::AtLeastOnce
+/// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
+pub trait Outbox {
+    /// Returns the options and message of the publication which was sent with this packet
+    /// identifier, or [`None`] if it is not available anymore.
+    fn publication(
+        &self,
+        packet_identifier: PacketIdentifier,
+    ) -> Option<(PublicationOptions<'_>, Bytes<'_>)>;
+}
+
+/// Exponential backoff with jitter between connection attempts.
+///
+/// The delay starts at the initial delay and doubles after every failed attempt until it reaches
+/// the maximum delay. The actual delay is chosen randomly between half the delay and the full
+/// delay, so that many clients losing their connections at the same time do not reconnect in
+/// lockstep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Backoff {
+    initial: Duration,
+    maximum: Duration,
+    current: Duration,
+    random: u32,
+}
+
+impl Default for Backoff {
+    /// Starts at 1 second and grows up to 1 minute.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60), 0)
+    }
+}
+
+impl Backoff {
+    /// Creates a backoff starting at `initial` and growing up to `maximum`. The jitter is derived
+    /// from `seed`, which should differ between devices, e.g. by taking it from a hardware random
+    /// number generator or a serial number.
+    #[must_use]
+    pub const fn new(initial: Duration, maximum: Duration, seed: u32) -> Self {
+        Self {
+            initial,
+            maximum,
+            current: initial,
+            // The xorshift generator gets stuck at 0.
+            random: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    /// Starts over at the initial delay.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+
+    /// Returns the delay before the next connection attempt and increases the delay of the
+    /// following one.
+    pub fn next_delay(&mut self) -> Duration {
+        let half = self.current / 2;
+        let jitter = half
+            .checked_mul(self.next_random() % 1024)
+            .map_or(half, |d| d / 1024);
+
+        self.current = self.current.saturating_mul(2).min(self.maximum);
+
+        half + jitter
+    }
+
+    fn next_random(&mut self) -> u32 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random = x;
+        x
+    }
+}
+
+/// The result of polling a [`Supervisor`].
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event<'e, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: usize> {
+    /// The client received a packet.
+    Client(ClientEvent<'e, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>),
+
+    /// The connection was lost and the supervisor has reconnected. If the session is present,
+    /// the due PUBLISH and PUBREL packets have been retransmitted. Otherwise, the session state
+    /// has been cleared and subscriptions have to be made again, unless they are recorded in a
+    /// [`SubscriptionRegistry`](crate::client::registry::SubscriptionRegistry) and have been
+    /// restored. Afterwards, the publications queued in the meantime have been published.
+    Reconnected(Connected<'e, MAX_USER_PROPERTIES>),
+}
+
+/// Reconnects a [`Client`] after its network connection failed.
+///
+/// After [`Self::connect`], [`Self::poll`] polls the client. If polling fails with
+/// [`MqttError::Network`], [`MqttError::RecoveryRequired`] or [`MqttError::KeepAliveTimeout`],
+/// the supervisor
+/// 1. calls [`Client::abort`],
+/// 2. waits for the next delay of its [`Backoff`],
+/// 3. opens a new network connection with its [`Connector`],
+/// 4. connects with the initial [`ConnectOptions`] but without clean start and with the client
+///    identifier of the previous connection and
+/// 5. republishes every due publication provided by the [`Outbox`] set with
+///    [`Self::republish_from`], resumes the remaining flows with [`Client::resume`], restores
+///    the recorded subscriptions with [`Client::resubscribe_all`] and publishes the queued
+///    publications with [`Client::drain_queue`],
+///
+/// retrying from step 1 as long as these steps fail with one of the errors above.
+///
+/// Publications and other packets are sent through [`Self::client_mut`]. If sending fails
+/// because the connection is lost, the next call to [`Self::poll`] reconnects. A
+/// [`PublishQueue`](crate::client::queue::PublishQueue) set with
+/// [`Client::queue_publications_in`] holds back the publications made in the meantime. If the
+/// server's receive maximum pauses the queue, it is continued by calling [`Client::drain_queue`]
+/// once publications have been acknowledged.
+pub struct Supervisor<
+    'c,
+    C: Connector,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize = 0,
+    const MAX_ALIASED_TOPIC_LEN: usize = 0,
+> {
+    client: Client<
+        'c,
+        C::Transport,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >,
+    connector: C,
+    backoff: Backoff,
+    outbox: Option<&'c dyn Outbox>,
+
+    options: ConnectOptions<'c>,
+    client_identifier: String<MAX_CLIENT_IDENTIFIER_LEN>,
+}
+
+impl<
+    'c,
+    C: Connector,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> core::fmt::Debug
+    for Supervisor<
+        'c,
+        C,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("client", &self.client)
+            .field("backoff", &self.backoff)
+            .field("options", &self.options)
+            .field("client_identifier", &self.client_identifier)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    'c,
+    C: Connector,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+> defmt::Format
+    for Supervisor<
+        'c,
+        C,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Supervisor {{ client: {:?}, backoff: {:?}, options: {:?}, client_identifier: {:?}, .. }}",
+            self.client,
+            self.backoff,
+            self.options,
+            self.client_identifier
+        );
+    }
+}
+
+impl<
+    'c,
+    C: Connector,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+    const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+    const MAX_USER_PROPERTIES: usize,
+    const TOPIC_ALIAS_MAXIMUM: usize,
+    const MAX_ALIASED_TOPIC_LEN: usize,
+>
+    Supervisor<
+        'c,
+        C,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    >
+{
+    /// Creates a supervisor for a disconnected client which connects with `options` and
+    /// `client_identifier` through `connector`. The [`Backoff`] is the default one.
+    ///
+    /// The client identifier is copied into the supervisor so that it outlives resets of the
+    /// [`BufferProvider`].
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the client identifier is longer than
+    /// [`MAX_CLIENT_IDENTIFIER_LEN`].
+    pub fn new(
+        client: Client<
+            'c,
+            C::Transport,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        connector: C,
+        options: ConnectOptions<'c>,
+        client_identifier: Option<MqttString<'_>>,
+    ) -> Self {
+        let mut s = Self {
+            client,
+            connector,
+            backoff: Backoff::default(),
+            outbox: None,
+            options,
+            client_identifier: String::new(),
+        };
+
+        if let Some(client_identifier) = client_identifier {
+            assert!(
+                s.client_identifier
+                    .push_str(client_identifier.as_str())
+                    .is_ok(),
+                "the client identifier must not be longer than {} bytes",
+                MAX_CLIENT_IDENTIFIER_LEN
+            );
+        }
+
+        s
+    }
+
+    /// Sets the [`Backoff`] between connection attempts.
+    pub fn back_off_with(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    /// Sets the [`Outbox`] which provides the publications to republish after reconnecting.
+    /// Without an outbox, due publications are only retransmitted if the client stores them in a
+    /// [`MessageStore`](crate::client::store::MessageStore).
+    pub fn republish_from(&mut self, outbox: &'c dyn Outbox) {
+        self.outbox = Some(outbox);
+    }
+
+    /// Returns the supervised client.
+    #[inline]
+    pub fn client(
+        &self,
+    ) -> &Client<
+        'c,
+        C::Transport,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    > {
+        &self.client
+    }
+
+    /// Returns the supervised client, e.g. to send publications.
+    #[inline]
+    pub fn client_mut(
+        &mut self,
+    ) -> &mut Client<
+        'c,
+        C::Transport,
+        B,
+        SUBSCRIBE_MAXIMUM,
+        RECEIVE_MAXIMUM,
+        SEND_MAXIMUM,
+        MAX_SUBSCRIPTION_IDENTIFIERS,
+        MAX_USER_PROPERTIES,
+        TOPIC_ALIAS_MAXIMUM,
+        MAX_ALIASED_TOPIC_LEN,
+    > {
+        &mut self.client
+    }
+
+    /// Returns the connector.
+    #[inline]
+    pub fn connector_mut(&mut self) -> &mut C {
+        &mut self.connector
+    }
+
+    /// Returns the supervised client and the connector.
+    pub fn into_parts(
+        self,
+    ) -> (
+        Client<
+            'c,
+            C::Transport,
+            B,
+            SUBSCRIBE_MAXIMUM,
+            RECEIVE_MAXIMUM,
+            SEND_MAXIMUM,
+            MAX_SUBSCRIPTION_IDENTIFIERS,
+            MAX_USER_PROPERTIES,
+            TOPIC_ALIAS_MAXIMUM,
+            MAX_ALIASED_TOPIC_LEN,
+        >,
+        C,
+    ) {
+        (self.client, self.connector)
+    }
+
+    /// Connects the client, retrying with backoff as long as the connection fails with
+    /// [`MqttError::Network`] or [`MqttError::RecoveryRequired`]. Due publications of a present
+    /// session are retransmitted and queued publications are published before returning.
+    ///
+    /// Only call this when the client is not connected.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::Alloc`] if the underlying [`BufferProvider`] returned an error while
+    ///   providing the client identifier for the CONNECT packet or if the client identifier
+    ///   assigned by the server is longer than [`MAX_CLIENT_IDENTIFIER_LEN`]. In the latter case,
+    ///   the connection is closed.
+    /// * The errors that [`Client::connect`] and [`Client::republish`] return, except for the ones
+    ///   that are retried.
+    ///
+    /// # Panics
+    ///
+    /// This function panics in the cases that [`Client::connect`] and [`Client::republish`]
+    /// panic.
+    pub async fn connect(
+        &mut self,
+    ) -> Result<Connected<'c, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>> {
+        self.establish(false).await
+    }
+
+    /// Polls the client for the next packet like [`Client::poll`] and reconnects if the
+    /// connection has been lost.
+    ///
+    /// # Returns:
+    /// - [`Event::Client`] with the event of the received packet.
+    /// - [`Event::Reconnected`] after reconnecting.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Client::poll`] and [`Self::connect`] return, except for the ones
+    /// that cause a reconnection.
+    pub async fn poll(
+        &mut self,
+    ) -> Result<
+        Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        MqttError<'c, MAX_USER_PROPERTIES>,
+    > {
+        match self.client.poll().await {
+            Ok(e) => Ok(Event::Client(e)),
+            Err(e) if Self::is_connection_lost(&e) => {
+                warn!("connection lost: {:?}", e);
+                self.establish(true).await.map(Event::Reconnected)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn is_connection_lost<const N: usize>(e: &MqttError<'_, N>) -> bool {
+        matches!(
+            e,
+            MqttError::Network(_) | MqttError::RecoveryRequired | MqttError::KeepAliveTimeout
+        )
+    }
+
+    async fn establish(
+        &mut self,
+        mut reconnecting: bool,
+    ) -> Result<Connected<'c, MAX_USER_PROPERTIES>, MqttError<'c, MAX_USER_PROPERTIES>> {
+        loop {
+            if reconnecting {
+                self.client.abort().await;
+
+                let delay = self.backoff.next_delay();
+                info!("reconnecting in {} ms", delay.as_millis() as u64);
+                self.connector.delay(delay).await;
+            }
+            reconnecting = true;
+
+            let net = match self.connector.connect().await {
+                Ok(n) => n,
+                Err(e) => {
+                    warn!("opening network connection failed: {:?}", e.kind());
+                    continue;
+                }
+            };
+
+            let client_identifier = self.provide_client_identifier()?;
+
+            let connected = match self
+                .client
+                .connect(net, &self.options, client_identifier)
+                .await
+            {
+                Ok(c) => c,
+                Err(e) if Self::is_connection_lost(&e) => {
+                    warn!("connecting failed: {:?}", e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            // Later connections continue the session of this one.
+            self.options.clean_start = false;
+            self.client_identifier.clear();
+            if self
+                .client_identifier
+                .push_str(connected.client_identifier.as_str())
+                .is_err()
+            {
+                error!(
+                    "client identifier is longer than {} bytes",
+                    MAX_CLIENT_IDENTIFIER_LEN
+                );
+                self.client.abort().await;
+                return Err(MqttError::Alloc);
+            }
+
+            match self.resume().await {
+                Ok(()) => {}
+                Err(e) if Self::is_connection_lost(&e) => {
+                    warn!("retransmitting failed: {:?}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.inflate()),
+            }
+
+            self.backoff.reset();
+
+            return Ok(connected);
+        }
+    }
+
+    /// Copies the client identifier into the buffer provider, from which the client borrows the
+    /// contents of the connection.
+    fn provide_client_identifier(
+        &mut self,
+    ) -> Result<Option<MqttString<'c>>, MqttError<'c, MAX_USER_PROPERTIES>> {
+        if self.client_identifier.is_empty() {
+            return Ok(None);
+        }
+
+        let mut buffer = self
+            .client
+            .buffer_mut()
+            .provide_buffer(self.client_identifier.len())
+            .map_err(|e| {
+                error!("buffer provision failed: {:?}", e);
+                MqttError::Alloc
+            })?;
+        buffer
+            .as_mut()
+            .copy_from_slice(self.client_identifier.as_bytes());
+
+        let client_identifier = MqttBinary::from_bytes_unchecked(buffer.into());
+        // Safety: The bytes are copied from a client identifier which is valid UTF-8 and does not
+        // contain null characters.
+        Ok(Some(unsafe {
+            MqttString::from_utf8_binary_unchecked(client_identifier)
+        }))
+    }
+
+    /// Retransmits the due PUBLISH and PUBREL packets, restores the recorded subscriptions and
+    /// publishes the queued publications.
+    async fn resume(&mut self) -> Result<(), MqttError<'c, 0>> {
+        // Republishing changes the states, but not the order of the entries.
+        for i in 0..self.client.session().outbound_publishes.len() {
+            let (pid, state) = self.client.session().outbound_publishes[i];
+
+            if !matches!(
+                state,
+                LocalPublishState::DuePublishAtLeastOnce
+                    | LocalPublishState::DuePublishExactlyOnce(_)
+            ) {
+                continue;
+            }
+
+            if let Some((options, message)) = self.outbox.and_then(|o| o.publication(pid)) {
+                self.client.republish(pid, &options, message).await?;
+            }
+        }
+
+        // Publications missing from the outbox are resent from the client's message store.
+        self.client.resume().await?;
+
+        // Subscriptions lost with the session are restored as far as packet identifiers allow.
+        match self.client.resubscribe_all().await {
+            Ok(()) => {}
+            Err(MqttError::AllPacketIdentifiersUsed | MqttError::SessionBuffer) => {
+                warn!("not all recorded subscriptions could be restored");
+            }
+            Err(e) => return Err(e),
+        }
+
+        // Publications made while disconnected follow the retransmitted ones.
+        self.client.drain_queue().await
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::time::Duration;
+    use std::vec::Vec;
+
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex},
+        join,
+    };
+    use tokio_test::assert_ok;
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        Bytes,
+        client::{
+            Client,
+            event::Event as ClientEvent,
+            options::{ConnectOptions, PublicationOptions, TopicReference},
+            supervisor::{Backoff, Connector, Event, Outbox, Supervisor},
+        },
+        types::{MqttString, PacketIdentifier, TopicName},
+    };
+
+    struct TestConnector {
+        nets: std::vec::IntoIter<DuplexStream>,
+        delays: Vec<Duration>,
+    }
+
+    impl Connector for TestConnector {
+        type Transport = FromTokio<DuplexStream>;
+
+        async fn connect(&mut self) -> Result<Self::Transport, std::io::Error> {
+            self.nets
+                .next()
+                .map(FromTokio::new)
+                .ok_or(std::io::ErrorKind::ConnectionRefused.into())
+        }
+
+        async fn delay(&mut self, duration: Duration) {
+            self.delays.push(duration);
+        }
+    }
+
+    struct TestOutbox;
+
+    impl Outbox for TestOutbox {
+        fn publication(
+            &self,
+            _packet_identifier: PacketIdentifier,
+        ) -> Option<(PublicationOptions<'_>, Bytes<'_>)> {
+            let topic = TopicName::new(MqttString::try_from("t").unwrap()).unwrap();
+            Some((
+                PublicationOptions::new(TopicReference::Name(topic)).at_least_once(),
+                "m".as_bytes().into(),
+            ))
+        }
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter() {
+        let mut b = Backoff::new(Duration::from_secs(1), Duration::from_secs(4), 7);
+
+        for maximum in [1, 2, 4, 4] {
+            let d = b.next_delay();
+            let maximum = Duration::from_secs(maximum);
+            assert!(d >= maximum / 2 && d <= maximum, "{d:?}");
+        }
+
+        b.reset();
+        assert!(b.next_delay() <= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn reconnect_and_republish() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 128];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (c1, mut s1) = duplex(64);
+        let (c2, mut s2) = duplex(64);
+        let connector = TestConnector {
+            nets: std::vec![c1, c2].into_iter(),
+            delays: Vec::new(),
+        };
+        let outbox = TestOutbox;
+
+        let client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        let mut s = Supervisor::new(
+            client,
+            connector,
+            ConnectOptions::new().clean_start(),
+            Some(MqttString::try_from("a").unwrap()),
+        );
+        s.republish_from(&outbox);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            let n = assert_ok!(s1.read(&mut buf).await);
+            assert_eq!(buf[0], 0x10);
+            assert_eq!(usize::from(buf[1]) + 2, n);
+            // Connect flags with clean start
+            assert_eq!(buf[9], 0x02);
+            assert_ok!(s1.write_all(&[0x20, 0x03, 0x00, 0x00, 0x00]).await);
+
+            let n = assert_ok!(s1.read(&mut buf).await);
+            assert_eq!(buf[0], 0x32);
+            assert_eq!(usize::from(buf[1]) + 2, n);
+            drop(s1);
+
+            let n = assert_ok!(s2.read(&mut buf).await);
+            assert_eq!(buf[0], 0x10);
+            assert_eq!(usize::from(buf[1]) + 2, n);
+            // Connect flags without clean start
+            assert_eq!(buf[9], 0x00);
+            assert_ok!(s2.write_all(&[0x20, 0x03, 0x01, 0x00, 0x00]).await);
+
+            // The PUBLISH packet is retransmitted with the DUP flag.
+            let n = assert_ok!(s2.read(&mut buf).await);
+            assert_eq!(buf[0], 0x3A);
+            assert_eq!(usize::from(buf[1]) + 2, n);
+            assert_ok!(s2.write_all(&[0x40, 0x02, 0x00, 0x01]).await);
+        };
+        let client = async {
+            let connected = assert_ok!(s.connect().await);
+            assert!(!connected.session_present);
+
+            let (options, message) = TestOutbox.publication(PacketIdentifier::ONE).unwrap();
+            assert_ok!(s.client_mut().publish(&options, message).await);
+
+            let event = assert_ok!(s.poll().await);
+            assert!(matches!(event, Event::Reconnected(c) if c.session_present));
+
+            let event = assert_ok!(s.poll().await);
+            assert!(matches!(
+                event,
+                Event::Client(ClientEvent::PublishAcknowledged(_))
+            ));
+        };
+
+        join!(server, client);
+
+        assert_eq!(s.connector_mut().delays.len(), 1);
+    }
+
+    #[cfg(feature = "bump")]
+    #[tokio::test]
+    #[test_log::test]
+    async fn client_identifier_outlives_buffer_reset() {
+        use crate::buffer::BufferProvider;
+
+        let mut b = [0; 64];
+        let mut b = BumpBuffer::new(&mut b);
+        let (c1, mut s1) = duplex(64);
+        let (c2, mut s2) = duplex(64);
+        let connector = TestConnector {
+            nets: std::vec![c1, c2].into_iter(),
+            delays: Vec::new(),
+        };
+
+        let client = Client::<'_, _, _, 1, 1, 1, 0, 0>::new(&mut b);
+        let mut s = Supervisor::new(client, connector, ConnectOptions::new(), None);
+
+        let server = async {
+            let mut buf = [0; 64];
+
+            assert_ok!(s1.read(&mut buf).await);
+            // CONNACK assigning the client identifier "xyz"
+            assert_ok!(
+                s1.write_all(&[
+                    0x20, 0x09, 0x00, 0x00, 0x06, 0x12, 0x00, 0x03, b'x', b'y', b'z',
+                ])
+                .await
+            );
+            drop(s1);
+
+            let n = assert_ok!(s2.read(&mut buf).await);
+            assert_eq!(buf[0], 0x10);
+            assert_eq!(buf[n - 5..n], [0x00, 0x03, b'x', b'y', b'z']);
+            assert_ok!(s2.write_all(&[0x20, 0x03, 0x01, 0x00, 0x00]).await);
+        };
+        let client = async {
+            let connected = assert_ok!(s.connect().await);
+            assert_eq!(connected.client_identifier.as_str(), "xyz");
+            drop(connected);
+
+            // Safety: No value returned by the client is alive.
+            unsafe { s.client_mut().buffer_mut().reset() };
+            assert_ok!(s.client_mut().buffer_mut().provide_buffer(64)).fill(b'#');
+            // Safety: No value returned by the client is alive.
+            unsafe { s.client_mut().buffer_mut().reset() };
+
+            let event = assert_ok!(s.poll().await);
+            assert!(
+                matches!(event, Event::Reconnected(c) if c.client_identifier.as_str() == "xyz")
+            );
+        };
+
+        join!(server, client);
+    }
+}
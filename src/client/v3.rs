@@ -0,0 +1,1759 @@
+//! Implements an MQTT version 3.1.1 client on top of the session handling, configuration and
+//! Quality of Service flows shared with the MQTT version 5.0 [`Client`].
+//!
+//! [`Client`]: crate::client::Client
+
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    client::{
+        MqttError,
+        event::{Connected, Event, Puback, Publish, Suback},
+        options::{
+            AckMode, ConnectOptions, FilterOptions, PublicationOptions, SubscriptionOptions,
+            TopicReference,
+        },
+        raw::Raw,
+    },
+    config::{ClientConfig, KeepAlive, SessionExpiryInterval, SharedConfig},
+    eio::ReadReady,
+    fmt::{assert, const_assert, debug, error, info, panic, trace, unreachable},
+    header::{FixedHeader, PacketType},
+    io::Transport,
+    packet::Packet,
+    session::{Error as SmError, Event as SmEvent, LocalPublishState, Response, Session},
+    time::{Clock, Instant},
+    types::{
+        IdentifiedQoS, MqttString, PacketIdentifier, QoS, ReasonCode, ReasonCodes, TopicFilter,
+        TopicName,
+    },
+    v3::packet::{
+        ConnackPacket, ConnectPacket, ConnectReturnCode, DisconnectPacket, PingreqPacket,
+        PingrespPacket, PubackPacket, PubcompPacket, PublishPacket, PubrecPacket, PubrelPacket,
+        SubackPacket, SubscribePacket, UnsubackPacket, UnsubscribePacket,
+    },
+};
+
+/// An MQTT version 3.1.1 client.
+///
+/// The client emits the same [`Event`]s and returns the same [`MqttError`]s as the MQTT version 5.0
+/// [`Client`](crate::client::Client) with the const parameters `MAX_SUBSCRIPTION_IDENTIFIERS` and
+/// `MAX_USER_PROPERTIES` being 0. The acknowledgement modes and the state machine behind the
+/// publication mechanism are identical as well.
+///
+/// Because MQTT version 3.1.1 packets contain neither properties nor reason codes (except for the
+/// return codes in CONNACK and SUBACK packets), the following differences apply:
+///
+/// - Only the clean start, keep alive, maximum packet size, user name, password and will fields of
+///   [`ConnectOptions`] are used. Of the will, only the quality of service, retain flag, topic and
+///   message are sent. The maximum packet size is not sent to the server, the client only enforces
+///   it on received packets.
+/// - Only the quality of service of [`SubscriptionOptions`] is sent.
+/// - Only the acknowledgement mode, quality of service, retain flag and topic of
+///   [`PublicationOptions`] are used. The topic must be a [`TopicReference::Name`].
+/// - The reason codes of [`Event::Suback`] are [`ReasonCode::Success`],
+///   [`ReasonCode::GrantedQoS1`], [`ReasonCode::GrantedQoS2`] or [`ReasonCode::UnspecifiedError`]
+///   (failure). The reason codes of [`Event::Unsuback`] are always empty.
+/// - A refused connection is returned as [`MqttError::Disconnect`] with the CONNACK return code
+///   mapped to the equivalent [`ReasonCode`].
+/// - [`Event::PublishRejected`], [`Event::PublishAborted`] and the authentication events are never
+///   emitted.
+/// - [`Self::abort`] never sends a DISCONNECT packet.
+///
+/// Configuration via const parameters:
+///
+/// - `SUBSCRIBE_MAXIMUM`: The maximum amount of in-flight/unacknowledged packets allowed for SUBSCRIBE and UNSUBSCRIBE packets
+///   each. Must not be greater than 65535.
+/// - `RECEIVE_MAXIMUM`: The maximum amount of incoming [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`] publications
+///   (accumulated). As MQTT version 3.1.1 has no flow control, this value is not communicated to the server. Must not be 0
+///   and must not be greater than 65535.
+/// - `SEND_MAXIMUM`: The maximum amount of outgoing [`QoS::AtLeastOnce`] and [`QoS::ExactlyOnce`] publications.
+pub struct Client<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+> {
+    client_config: ClientConfig,
+    shared_config: SharedConfig,
+    session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+
+    raw: Raw<'c, N, B>,
+
+    manual_ack_when: &'c dyn Fn(&Publish<'_, 0, 0>) -> bool,
+}
+
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+> core::fmt::Debug for Client<'c, N, B, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("client_config", &self.client_config)
+            .field("shared_config", &self.shared_config)
+            .field("session", &self.session)
+            .field("raw", &self.raw)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+> defmt::Format for Client<'c, N, B, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>
+{
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(
+            fmt,
+            "Client {{ client_config: {:?}, shared_config: {:?}, session: {:?}, raw: {:?}, .. }}",
+            self.client_config,
+            self.shared_config,
+            self.session,
+            self.raw,
+        );
+    }
+}
+
+impl<
+    'c,
+    N: Transport,
+    B: BufferProvider<'c>,
+    const SUBSCRIBE_MAXIMUM: usize,
+    const RECEIVE_MAXIMUM: usize,
+    const SEND_MAXIMUM: usize,
+> Client<'c, N, B, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>
+{
+    /// Creates a new, disconnected MQTT client using a buffer provider to store
+    /// dynamically sized fields of received packets.
+    /// The session state is initialised as a new session. If you want to start the
+    /// client with an existing session, use [`Self::with_session`].
+    /// All publications and acknowledgements will be acknowledged automatically.
+    pub fn new(buffer: &'c mut B) -> Self {
+        const {
+            const_assert!(
+                SUBSCRIBE_MAXIMUM <= 65535,
+                "SUBSCRIBE_MAXIMUM must be less than or equal to 65535"
+            );
+            const_assert!(
+                RECEIVE_MAXIMUM <= 65535,
+                "RECEIVE_MAXIMUM must be less than or equal to 65535"
+            );
+            const_assert!(
+                RECEIVE_MAXIMUM > 0,
+                "RECEIVE_MAXIMUM must be greater than 0"
+            );
+        }
+
+        Self {
+            client_config: ClientConfig::default(),
+            shared_config: SharedConfig::default(),
+            session: Session::default(),
+            raw: Raw::new_disconnected(buffer),
+
+            manual_ack_when: &|_| false,
+        }
+    }
+
+    /// Creates a new, disconnected MQTT client using a buffer provider to store
+    /// dynamically sized fields of received packets.
+    pub fn with_session(
+        session: Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        buffer: &'c mut B,
+    ) -> Self {
+        let mut s = Self::new(buffer);
+        s.session = session;
+        s
+    }
+
+    /// Sets the predicate which selects whether the quality of service handshakes of an incoming
+    /// publication are executed automatically by the client or manually by the user. Refer to
+    /// [`Client::ack_manually_when`](crate::client::Client::ack_manually_when).
+    pub fn ack_manually_when(&mut self, predicate: &'c dyn Fn(&Publish<'_, 0, 0>) -> bool) {
+        self.manual_ack_when = predicate;
+    }
+
+    /// Sets a buffer in which received packets are reassembled before they are processed, making
+    /// [`Self::poll`] cancel-safe. Refer to
+    /// [`Client::reassemble_packets_with`](crate::client::Client::reassemble_packets_with).
+    pub fn reassemble_packets_with(&mut self, buffer: &'c mut [u8]) {
+        self.raw.reassemble_with(buffer);
+    }
+
+    /// Sets the [`Clock`] with which the client tracks the keep alive. Refer to
+    /// [`Client::keep_alive_with`](crate::client::Client::keep_alive_with).
+    pub fn keep_alive_with(&mut self, clock: &'c dyn Clock) {
+        self.raw.keep_alive_with(clock);
+    }
+
+    /// Returns configuration for this client.
+    #[inline]
+    pub fn client_config(&self) -> &ClientConfig {
+        &self.client_config
+    }
+
+    /// Returns the configuration in force for the current or last connection. The session expiry
+    /// interval reflects the clean session flag: [`SessionExpiryInterval::EndOnDisconnect`] if
+    /// it was set, [`SessionExpiryInterval::NeverEnd`] otherwise.
+    #[inline]
+    pub fn shared_config(&self) -> &SharedConfig {
+        &self.shared_config
+    }
+
+    /// Returns session related configuration and tracking information.
+    #[inline]
+    pub fn session(&self) -> &Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM> {
+        &self.session
+    }
+
+    /// Returns an immutable reference to the supplied [`BufferProvider`] implementation.
+    #[inline]
+    pub fn buffer(&self) -> &B {
+        self.raw.buffer()
+    }
+
+    /// Returns a mutable reference to the supplied [`BufferProvider`] implementation.
+    ///
+    /// This can for example be used to reset the underlying buffer if using `BumpBuffer`.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut B {
+        self.raw.buffer_mut()
+    }
+
+    /// Drops the network connection and returns the session state, the buffer provider and the
+    /// buffer set with [`Self::reassemble_packets_with`].
+    #[cfg(feature = "v5")]
+    pub(crate) fn into_parts(
+        self,
+    ) -> (
+        Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>,
+        &'c mut B,
+        Option<&'c mut [u8]>,
+    ) {
+        let (buffer, reassembly_buffer) = self.raw.into_buffers();
+        (self.session, buffer, reassembly_buffer)
+    }
+
+    /// Connect the client to an MQTT server on the other end of the `net` argument.
+    /// Sends a CONNECT message with protocol level 4 and awaits the CONNACK response by the server.
+    ///
+    /// Only call this when
+    /// - the client is newly constructed.
+    /// - a non-recoverable error has occured and [`Self::abort`] has been called.
+    /// - [`Self::disconnect`] has been called.
+    ///
+    /// The clean start field of [`ConnectOptions`] is sent as the clean session flag.
+    ///
+    /// If the server does not have a session present, the client's session is cleared. In case you would want
+    /// to keep the session state, you can call [`Self::session`] and clone the session before.
+    ///
+    /// # Returns:
+    /// Information about the session/connection. As MQTT version 3.1.1 servers don't assign client
+    /// identifiers, the client identifier is the one passed to this function or empty.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::Server`] if:
+    ///   * the server sends a malformed packet
+    ///   * the first received packet is something other than a CONNACK packet
+    ///   * the server causes a protocol error
+    /// * [`MqttError::Disconnect`] if the CONNACK packet's return code is not 0. The reason code is
+    ///   the MQTT version 5.0 equivalent of the return code.
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::Alloc`] if the underlying [`BufferProvider`] returned an error
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the [`ConnectOptions`] contain a password but no user name or if a
+    /// reassembly buffer has been set with [`Self::reassemble_packets_with`] that cannot hold a
+    /// packet of the maximum packet size in the [`ConnectOptions`].
+    pub async fn connect<'d>(
+        &mut self,
+        net: N,
+        options: &ConnectOptions<'_>,
+        client_identifier: Option<MqttString<'d>>,
+    ) -> Result<Connected<'d, 0>, MqttError<'c, 0>>
+    where
+        'c: 'd,
+    {
+        assert!(
+            options.password.is_none() || options.user_name.is_some(),
+            "attempted to send CONNECT with a password but without a user name"
+        );
+
+        self.raw.set_net(net);
+
+        self.client_config.maximum_accepted_remaining_length =
+            options.maximum_packet_size.max_remaining_len();
+
+        trace!(
+            "maximum accepted remaining length set to {:?}",
+            self.client_config.maximum_accepted_remaining_length
+        );
+
+        if let Some(capacity) = self.raw.reassembly_capacity() {
+            assert!(
+                self.client_config.maximum_accepted_remaining_length as usize <= capacity,
+                "the maximum packet size must be limited to fit into the reassembly buffer of {} bytes",
+                capacity
+            );
+        }
+
+        {
+            let packet_client_identifier = client_identifier
+                .as_ref()
+                .map(MqttString::as_borrowed)
+                .unwrap_or_default();
+
+            let mut packet = ConnectPacket::new(
+                packet_client_identifier,
+                options.clean_start,
+                options.keep_alive,
+            );
+
+            if let Some(ref user_name) = options.user_name {
+                packet.add_user_name(user_name.as_borrowed());
+            }
+            if let Some(ref password) = options.password {
+                packet.add_password(password.as_borrowed());
+            }
+            if let Some(ref will) = options.will {
+                packet.add_will(
+                    will.will_topic.as_borrowed(),
+                    will.will_message.as_borrowed(),
+                    will.will_qos,
+                    will.will_retain,
+                );
+            }
+
+            debug!("sending CONNECT packet");
+            self.raw.send(&packet).await?;
+            self.raw.flush().await?;
+        }
+
+        let header = self.raw.recv_header().await?;
+
+        match header.packet_type() {
+            Ok(ConnackPacket::PACKET_TYPE) => {
+                debug!(
+                    "received CONNACK packet header (remaining length: {})",
+                    header.remaining_len.value()
+                );
+            }
+            Ok(t) => {
+                error!("received unexpected {:?} packet header", t);
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            }
+            Err(_) => {
+                error!("received invalid header {:?}", header);
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            }
+        }
+
+        let ConnackPacket {
+            session_present,
+            return_code,
+        } = self.raw.recv_body(&header).await?;
+
+        if return_code != ConnectReturnCode::Accepted {
+            debug!("CONNACK packet indicates rejection");
+            info!(
+                "connection rejected by server (return code: {:?})",
+                return_code
+            );
+
+            self.raw.close_with(None);
+
+            info!("disconnected from server");
+
+            return Err(MqttError::Disconnect {
+                reason: return_code.into(),
+                reason_string: None,
+                user_properties: Vec::new(),
+                server_reference: None,
+            });
+        }
+
+        debug!("CONNACK packet indicates success");
+
+        if session_present {
+            if options.clean_start {
+                error!("server set the session present flag when clean session was set");
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            } else {
+                info!("connected to server and reconnected to session");
+                self.session.reconnect();
+            }
+        } else {
+            #[allow(clippy::if_same_then_else)]
+            if options.clean_start {
+                info!("connected to server");
+            } else {
+                info!("connected to server but server does not have the requested session present");
+            }
+            self.session.clear();
+        }
+
+        self.shared_config.keep_alive = options.keep_alive;
+        self.shared_config.session_expiry_interval = if options.clean_start {
+            SessionExpiryInterval::EndOnDisconnect
+        } else {
+            SessionExpiryInterval::NeverEnd
+        };
+
+        Ok(Connected {
+            session_present,
+            client_identifier: client_identifier.unwrap_or_default(),
+            user_properties: Vec::new(),
+            response_information: None,
+            server_reference: None,
+        })
+    }
+
+    /// Start a ping handshake by sending a PINGREQ packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    pub async fn ping(&mut self) -> Result<(), MqttError<'c, 0>> {
+        debug!("sending PINGREQ packet");
+
+        self.raw.send(&PingreqPacket::new()).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Returns the point in time at which [`Self::check_keep_alive`] has to be called next. Refer to
+    /// [`Client::next_ping_deadline`](crate::client::Client::next_ping_deadline).
+    pub fn next_ping_deadline(&self) -> Option<Instant> {
+        match self.shared_config.keep_alive {
+            KeepAlive::Infinite => None,
+            KeepAlive::Seconds(s) => self
+                .raw
+                .keep_alive_deadline(Duration::from_secs(s.get().into())),
+        }
+    }
+
+    /// Maintains the keep alive once [`Self::next_ping_deadline`] has passed. Refer to
+    /// [`Client::check_keep_alive`](crate::client::Client::check_keep_alive).
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::KeepAliveTimeout`] if no PINGRESP packet has been received in time
+    /// * The errors that [`Self::ping`] returns
+    pub async fn check_keep_alive(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let (Some(deadline), Some(now)) = (self.next_ping_deadline(), self.raw.now()) else {
+            return Ok(());
+        };
+
+        if now < deadline {
+            return Ok(());
+        }
+
+        if self.raw.ping_pending() {
+            error!("no PINGRESP packet received within the keep alive interval");
+            self.raw.close_with(None);
+            return Err(MqttError::KeepAliveTimeout);
+        }
+
+        self.ping().await
+    }
+
+    /// Subscribes to a single topic with the quality of service of the given options.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent SUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::subscribe_many`] returns.
+    pub async fn subscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+        options: &SubscriptionOptions<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.subscribe_many(&[(topic_filter, options.filter_options())])
+            .await
+    }
+
+    /// Subscribes to multiple topics with a single SUBSCRIBE packet. Each topic filter is
+    /// subscribed to with the quality of service of the [`FilterOptions`] it is paired with.
+    ///
+    /// The [`Event::Suback`] contains one reason code per topic filter in the order of
+    /// `subscriptions`.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent SUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing SUBSCRIBE packet identifiers is full
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the SUBSCRIBE packet is too long to be encoded
+    /// * [`MqttError::AllPacketIdentifiersUsed`] if there are no free packet identifiers
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `subscriptions` is empty.
+    pub async fn subscribe_many(
+        &mut self,
+        subscriptions: &[(TopicFilter<'_>, FilterOptions)],
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        assert!(
+            !subscriptions.is_empty(),
+            "attempted to send SUBSCRIBE without topic filters"
+        );
+
+        let Some(handle) = self.session.free_handle() else {
+            info!("no free packet identifier");
+            return Err(MqttError::AllPacketIdentifiersUsed);
+        };
+        let pid = handle.packet_identifier;
+
+        let packet = SubscribePacket::new(pid, subscriptions)
+            .map_err(|_| MqttError::PacketMaximumLengthExceeded)?;
+
+        handle.outbound_sub(subscriptions.len()).map_err(|_| {
+            info!("maximum concurrent subscriptions reached");
+            MqttError::SessionBuffer
+        })?;
+
+        debug!("sending SUBSCRIBE packet");
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(pid)
+    }
+
+    /// Unsubscribes from a single topic filter.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent UNSUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::unsubscribe_many`] returns.
+    pub async fn unsubscribe(
+        &mut self,
+        topic_filter: TopicFilter<'_>,
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        self.unsubscribe_many(&[topic_filter]).await
+    }
+
+    /// Unsubscribes from multiple topic filters with a single UNSUBSCRIBE packet.
+    ///
+    /// # Returns:
+    /// The packet identifier of the sent UNSUBSCRIBE packet.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing UNSUBSCRIBE packet identifiers is full
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the UNSUBSCRIBE packet is too long to be encoded
+    /// * [`MqttError::AllPacketIdentifiersUsed`] if there are no free packet identifiers
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `topic_filters` is empty.
+    pub async fn unsubscribe_many(
+        &mut self,
+        topic_filters: &[TopicFilter<'_>],
+    ) -> Result<PacketIdentifier, MqttError<'c, 0>> {
+        assert!(
+            !topic_filters.is_empty(),
+            "attempted to send UNSUBSCRIBE without topic filters"
+        );
+
+        let Some(handle) = self.session.free_handle() else {
+            info!("no free packet identifier");
+            return Err(MqttError::AllPacketIdentifiersUsed);
+        };
+        let pid = handle.packet_identifier;
+
+        let packet = UnsubscribePacket::new(pid, topic_filters)
+            .map_err(|_| MqttError::PacketMaximumLengthExceeded)?;
+
+        handle.outbound_unsub(topic_filters.len()).map_err(|_| {
+            info!("maximum concurrent unsubscriptions reached");
+            MqttError::SessionBuffer
+        })?;
+
+        debug!("sending UNSUBSCRIBE packet");
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(pid)
+    }
+
+    /// Start the publication of a message. If [`QoS`] is greater than [`QoS::AtMostOnce`], the
+    /// packet identifier is also kept track of by the client and further steps are necessary
+    /// by the server and may be necessary by the client.
+    ///
+    /// # Returns:
+    /// - In case of [`QoS::AtMostOnce`]: [`None`]
+    /// - In case of [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`]: [`Some`] with the packet identifier
+    ///   of the published packet. This value is required in case of a republication attempt.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::SessionBuffer`] if the buffer for outgoing PUBLISH packet identifiers is full
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the PUBLISH packet is too long to be encoded
+    /// * [`MqttError::AllPacketIdentifiersUsed`] if there are no free packet identifiers in the pool
+    ///   of outgoing publications, SUBSCRIBEs and UNSUBSCRIBEs
+    /// * [`MqttError::ManualAckNotAllowed`] if the [`QoS`] is [`QoS::AtMostOnce`] or
+    ///   [`QoS::AtLeastOnce`] and [`PublicationOptions::ack_mode`] is [`AckMode::Manual`]
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the topic of the [`PublicationOptions`] is not a
+    /// [`TopicReference::Name`].
+    pub async fn publish(
+        &mut self,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<Option<PacketIdentifier>, MqttError<'c, 0>> {
+        let topic = Self::topic_name(options);
+
+        if (matches!(options.qos, QoS::AtMostOnce | QoS::AtLeastOnce)
+            && options.ack_mode == AckMode::Manual)
+        {
+            return Err(MqttError::ManualAckNotAllowed);
+        }
+
+        let (identified_qos, handle) = if options.qos > QoS::AtMostOnce {
+            let Some(handle) = self.session.free_handle() else {
+                return Err(MqttError::AllPacketIdentifiersUsed);
+            };
+
+            match options.qos {
+                QoS::AtMostOnce => unreachable!(),
+                QoS::AtLeastOnce => (
+                    IdentifiedQoS::AtLeastOnce(handle.packet_identifier),
+                    Some(handle),
+                ),
+                QoS::ExactlyOnce => (
+                    IdentifiedQoS::ExactlyOnce(handle.packet_identifier),
+                    Some(handle),
+                ),
+            }
+        } else {
+            (IdentifiedQoS::AtMostOnce, None)
+        };
+
+        let packet = PublishPacket::new(false, identified_qos, options.retain, topic, message)?;
+
+        if let Some(handle) = handle {
+            // Treat the packet as sent before successfully sending. In case of a network error,
+            // we have tracked the packet as in flight and can republish it.
+            if let Err(e) = handle.outbound_publish(options.qos, options.ack_mode) {
+                match e {
+                    SmError::NoCapacity => return Err(MqttError::SessionBuffer),
+                    SmError::PacketIdentifierUnused
+                    | SmError::QoSMismatched
+                    | SmError::HandshakeStateMismatched => unreachable!(),
+                }
+            }
+        }
+
+        match identified_qos.packet_identifier() {
+            Some(pid) => debug!("sending PUBLISH packet with packet identifier {}", pid),
+            None => debug!("sending PUBLISH packet"),
+        }
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(identified_qos.packet_identifier())
+    }
+
+    /// Resends a PUBLISH packet with DUP flag set.
+    ///
+    /// This method must be called and must only be called after a reconnection with clean session set to 0,
+    /// for every unacknowledged PUBLISH packet. The preconditions of
+    /// [`Client::republish`](crate::client::Client::republish) apply.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::QoSMismatched`] if the [`QoS`] of this republish does not match the
+    ///   [`QoS`] that this packet identifier was originally published with
+    /// * [`MqttError::HandshakeStateMismatched`]
+    ///   * if a PUBREC packet with this packet identifier has already been received and the server
+    ///     has therefore already received the PUBLISH. Our next action is sending a PUBREL packet
+    ///   * if the previous PUBLISH packet was sent in the same network connection
+    /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
+    ///   an outgoing publication in the client's session
+    /// * [`MqttError::PacketMaximumLengthExceeded`] if the PUBLISH packet is too long to be encoded
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if the [`QoS`] in the `options` is [`QoS::AtMostOnce`].
+    /// This function panics if the topic of the [`PublicationOptions`] is not a
+    /// [`TopicReference::Name`].
+    pub async fn republish(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+        options: &PublicationOptions<'_>,
+        message: Bytes<'_>,
+    ) -> Result<(), MqttError<'c, 0>> {
+        let topic = Self::topic_name(options);
+
+        assert_ne!(
+            options.qos,
+            QoS::AtMostOnce,
+            "QoS 0 packets cannot be republished"
+        );
+
+        let identified_qos = match options.qos {
+            QoS::AtMostOnce => unreachable!(),
+            QoS::AtLeastOnce => IdentifiedQoS::AtLeastOnce(packet_identifier),
+            QoS::ExactlyOnce => IdentifiedQoS::ExactlyOnce(packet_identifier),
+        };
+
+        let packet = PublishPacket::new(true, identified_qos, options.retain, topic, message)?;
+
+        if let Err(e) = self.session.outbound_republish(identified_qos) {
+            match e {
+                SmError::NoCapacity => {
+                    unreachable!("a republish can not fail due to missing capacity")
+                }
+                SmError::PacketIdentifierUnused => {
+                    return Err(MqttError::PacketIdentifierNotInFlight);
+                }
+                SmError::QoSMismatched => {
+                    return Err(MqttError::QoSMismatched);
+                }
+                SmError::HandshakeStateMismatched => {
+                    return Err(MqttError::HandshakeStateMismatched);
+                }
+            }
+        }
+
+        debug!(
+            "resending PUBLISH packet with packet identifier {}",
+            packet_identifier
+        );
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Returns the topic name of publication options as MQTT version 3.1.1 has no topic aliases.
+    fn topic_name<'p>(options: &'p PublicationOptions<'_>) -> TopicName<'p> {
+        match options.topic {
+            TopicReference::Name(ref topic) => topic.as_borrowed(),
+            TopicReference::Alias(_) | TopicReference::Mapping(_, _) => {
+                panic!("attempted to publish with a topic alias in MQTT version 3.1.1")
+            }
+        }
+    }
+
+    /// Resends all pending PUBREL packets that belong to publication flows started with the default
+    /// [`AckMode::Automatic`]. PUBREL packets associated with [`AckMode::Manual`] flows must be resent
+    /// manually on a per-packet basis with [`Self::manual_release`].
+    ///
+    /// This method should only be called once after a reconnection with clean session set to 0.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    pub async fn rerelease(&mut self) -> Result<(), MqttError<'c, 0>> {
+        let Some(mut handle) = self.session.outbound_iter() else {
+            return Ok(());
+        };
+
+        loop {
+            if handle.state == LocalPublishState::DueRel(AckMode::Automatic) {
+                handle.outbound_pubrel().unwrap();
+
+                let pubrel = PubrelPacket::new(handle.packet_identifier());
+
+                debug!("sending PUBREL packet {}", pubrel.packet_identifier);
+
+                self.raw.send(&pubrel).await?;
+            }
+            if let Some(next) = handle.next() {
+                handle = next;
+            } else {
+                break;
+            }
+        }
+
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Sends a PUBACK packet responding to an incoming [`QoS::AtLeastOnce`] PUBLISH packet, that
+    /// was marked with [`AckMode::Manual`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::QoSMismatched`] if this packet identifier is actually part of an incoming
+    ///   [`QoS::ExactlyOnce`] publication
+    /// * [`MqttError::HandshakeStateMismatched`] if a reconnection occured and this
+    ///   the server has not resent the PUBLISH in this network connection.
+    /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
+    ///   an incoming publication in the client's session
+    pub async fn manual_acknowledge(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+    ) -> Result<(), MqttError<'c, 0>> {
+        self.session
+            .outbound_puback(packet_identifier)
+            .map_err(Self::map_sm_error)?;
+
+        let packet = PubackPacket::new(packet_identifier);
+
+        debug!("sending PUBACK packet {}", packet.packet_identifier);
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Sends a PUBREC packet responding to an incoming [`QoS::ExactlyOnce`] PUBLISH packet, that
+    /// was marked with [`AckMode::Manual`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::QoSMismatched`] if this packet identifier is actually part of an incoming
+    ///   [`QoS::AtLeastOnce`] publication
+    /// * [`MqttError::HandshakeStateMismatched`]
+    ///   * if a PUBREC has already been sent for this packet identifier in this network connection
+    ///   * if a reconnection occured and the server has not resent the PUBLISH in this network
+    ///     connection
+    /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
+    ///   an incoming publication in the client's session
+    pub async fn manual_receive(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+    ) -> Result<(), MqttError<'c, 0>> {
+        self.session
+            .outbound_pubrec(packet_identifier, ReasonCode::Success)
+            .map_err(Self::map_sm_error)?;
+
+        let packet = PubrecPacket::new(packet_identifier);
+
+        debug!("sending PUBREC packet {}", packet.packet_identifier);
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Sends a PUBREL packet responding to an incoming PUBREC packet belonging to an
+    /// outgoing publication flow configured with [`AckMode::Manual`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::QoSMismatched`] if this packet identifier is actually part of an outgoing
+    ///   [`QoS::AtLeastOnce`] publication
+    /// * [`MqttError::HandshakeStateMismatched`]
+    ///   * if the client hasn't yet received a PUBREC from the server for this packet identifier
+    ///   * if a PUBREL has already been sent for this packet identifier in this network connection
+    /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
+    ///   an incoming publication in the client's session
+    pub async fn manual_release(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+    ) -> Result<(), MqttError<'c, 0>> {
+        self.session
+            .outbound_pubrel(packet_identifier)
+            .map_err(Self::map_sm_error)?;
+
+        let packet = PubrelPacket::new(packet_identifier);
+
+        debug!("sending PUBREL packet {}", packet.packet_identifier);
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    /// Sends a PUBCOMP packet responding to an incoming PUBREL packet belonging to an
+    /// incoming publication flow, that was marked with [`AckMode::Manual`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::QoSMismatched`] if this packet identifier is actually part of an outgoing
+    ///   [`QoS::AtLeastOnce`] publication
+    /// * [`MqttError::HandshakeStateMismatched`] if the client hasn't yet received a PUBREL from
+    ///   the server for this packet identifier in this network connection
+    /// * [`MqttError::PacketIdentifierNotInFlight`] if this packet identifier is not tracked in
+    ///   an incoming publication in the client's session
+    pub async fn manual_complete(
+        &mut self,
+        packet_identifier: PacketIdentifier,
+    ) -> Result<(), MqttError<'c, 0>> {
+        self.session
+            .outbound_pubcomp(packet_identifier)
+            .map_err(Self::map_sm_error)?;
+
+        let packet = PubcompPacket::new(packet_identifier);
+
+        debug!("sending PUBCOMP packet {}", packet.packet_identifier);
+
+        self.raw.send(&packet).await?;
+        self.raw.flush().await?;
+
+        Ok(())
+    }
+
+    fn map_sm_error(e: SmError) -> MqttError<'c, 0> {
+        match e {
+            SmError::NoCapacity => unreachable!(),
+            SmError::PacketIdentifierUnused => MqttError::PacketIdentifierNotInFlight,
+            SmError::QoSMismatched => MqttError::QoSMismatched,
+            SmError::HandshakeStateMismatched => MqttError::HandshakeStateMismatched,
+        }
+    }
+
+    /// Closes the network connection after an error occured. MQTT version 3.1.1 does not allow the
+    /// client to report errors to the server, therefore no DISCONNECT packet is sent.
+    ///
+    /// After this, the session can be recovered by calling [`Self::connect`] again without clean
+    /// start.
+    #[inline]
+    pub fn abort(&mut self) {
+        self.raw.close_with(None);
+        info!("connection aborted");
+    }
+
+    /// Disconnects gracefully from the server by sending a DISCONNECT packet. The server discards
+    /// the will message.
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    pub async fn disconnect(&mut self) -> Result<(), MqttError<'c, 0>> {
+        debug!("sending DISCONNECT packet");
+
+        self.raw.send(&DisconnectPacket).await?;
+        self.raw.flush().await?;
+
+        // Terminates (closes) the connection by dropping it
+        self.raw.close_with(None);
+
+        info!("disconnected from server");
+
+        Ok(())
+    }
+
+    /// Combines [`Self::poll_header`] and [`Self::poll_body`].
+    ///
+    /// Polls the network for a full packet. Not cancel-safe unless a reassembly buffer has been set
+    /// with [`Self::reassemble_packets_with`].
+    ///
+    /// # Preconditions:
+    /// - The last MQTT packet was received completely
+    /// - The client did not return a non-recoverable Error before
+    ///
+    /// # Returns:
+    /// MQTT Events. Their further meaning is documented in [`Event`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::poll_header`] and [`Self::poll_body`] return.
+    pub async fn poll(&mut self) -> Result<Event<'c, 0, 0>, MqttError<'c, 0>> {
+        let header = self.poll_header().await?;
+        self.poll_body(header).await
+    }
+
+    /// Combines [`Self::try_poll_header`] and [`Self::poll_body`].
+    ///
+    /// Polls the network for a full packet if data is ready to be read. Refer to
+    /// [`Client::try_poll`](crate::client::Client::try_poll).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::try_poll_header`] and [`Self::poll_body`] return.
+    pub async fn try_poll(&mut self) -> Result<Option<Event<'c, 0, 0>>, MqttError<'c, 0>>
+    where
+        N: ReadReady,
+    {
+        match self.try_poll_header().await? {
+            Some(header) => self.poll_body(header).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Polls the network for a fixed header in a cancel-safe way.
+    ///
+    /// # Preconditions:
+    /// - The last MQTT packet was received completely
+    /// - The client did not return a non-recoverable Error before
+    ///
+    /// # Returns:
+    /// The received fixed header with a valid packet type. It can be used to call [`Self::poll_body`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::Server`] if:
+    ///   * the server sends a malformed packet header
+    ///   * the packet following this header exceeds the client's maximum packet size
+    pub async fn poll_header(&mut self) -> Result<FixedHeader, MqttError<'c, 0>> {
+        let header = self.raw.recv_header().await?;
+        self.check_header(header)
+    }
+
+    /// Polls the network for a fixed header in a cancel-safe way without waiting for data that is
+    /// not ready to be read yet.
+    ///
+    /// Bytes of the fixed header are only read while [`ReadReady::read_ready`] returns [`true`].
+    /// They are kept across calls until the fixed header is complete.
+    ///
+    /// # Returns:
+    /// The received fixed header with a valid packet type like [`Self::poll_header`] or [`None`] if the
+    /// network has no data ready to be read before the fixed header is complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors that [`Self::poll_header`] returns.
+    pub async fn try_poll_header(&mut self) -> Result<Option<FixedHeader>, MqttError<'c, 0>>
+    where
+        N: ReadReady,
+    {
+        match self.raw.try_recv_header().await? {
+            Some(header) => self.check_header(header).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks the packet type and the remaining length of a received fixed header.
+    fn check_header(&mut self, header: FixedHeader) -> Result<FixedHeader, MqttError<'c, 0>> {
+        if let Ok(p) = header.packet_type() {
+            debug!(
+                "received {:?} packet header (remaining length: {})",
+                p,
+                header.remaining_len.value()
+            );
+        } else {
+            error!("received invalid header {:?}", header);
+            self.raw.close_with(None);
+            return Err(MqttError::Server);
+        }
+
+        if header.remaining_len.value() > self.client_config.maximum_accepted_remaining_length {
+            error!(
+                "received a packet exceeding maximum packet size, remaining length={:?}",
+                header.remaining_len.value()
+            );
+            self.raw.close_with(None);
+            return Err(MqttError::Server);
+        }
+
+        Ok(header)
+    }
+
+    /// Polls the network for the variable header and payload of a packet. Not cancel-safe unless a
+    /// reassembly buffer has been set with [`Self::reassemble_packets_with`].
+    ///
+    /// # Preconditions:
+    /// - The [`FixedHeader`] argument was received from the network right before.
+    /// - The client did not return a non-recoverable [`MqttError`] before
+    ///
+    /// # Returns:
+    /// MQTT Events for regular communication. Their further meaning is documented in [`Event`].
+    ///
+    /// # Errors
+    ///
+    /// * [`MqttError::RecoveryRequired`] if an unrecoverable error occured previously
+    /// * [`MqttError::Network`] if the underlying [`Transport`] returned an error
+    /// * [`MqttError::Alloc`] if the underlying [`BufferProvider`] returned an error
+    /// * [`MqttError::Server`] if:
+    ///   * the server sends a malformed packet
+    ///   * the server causes a protocol error
+    ///   * the server exceeded the client's receive maximum with a new [`QoS::ExactlyOnce`]
+    ///     PUBLISH
+    ///   * the server sends a PUBACK/PUBREC/PUBREL/PUBCOMP packet which mismatches what
+    ///     the client expects for this packet identifier from its session state
+    ///   * the fixed header has a packet type other than PUBLISH, PUBACK, PUBREC, PUBREL,
+    ///     PUBCOMP, SUBACK, UNSUBACK or PINGRESP
+    pub async fn poll_body(
+        &mut self,
+        header: FixedHeader,
+    ) -> Result<Event<'c, 0, 0>, MqttError<'c, 0>> {
+        let manual_ack_when = self.manual_ack_when;
+        self.recv_body(header, manual_ack_when).await
+    }
+
+    /// Implements [`Self::poll_body`] for any event capacities as MQTT version 3.1.1 packets never
+    /// contain subscription identifiers or user properties.
+    pub(crate) async fn recv_body<
+        const MAX_SUBSCRIPTION_IDENTIFIERS: usize,
+        const MAX_USER_PROPERTIES: usize,
+    >(
+        &mut self,
+        header: FixedHeader,
+        manual_ack_when: &dyn Fn(
+            &Publish<'_, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>,
+        ) -> bool,
+    ) -> Result<Event<'c, MAX_SUBSCRIPTION_IDENTIFIERS, MAX_USER_PROPERTIES>, MqttError<'c, 0>>
+    {
+        let event = match header.packet_type()? {
+            PacketType::Pingresp => {
+                self.raw.recv_body::<PingrespPacket>(&header).await?;
+                Event::Pingresp
+            }
+            PacketType::Suback => {
+                let suback = self.raw.recv_body::<SubackPacket>(&header).await?;
+
+                let pid = suback.packet_identifier;
+
+                if let Some(h) = self.session.sub_handle(pid) {
+                    let topic_filters = h.topic_filters();
+                    h.remove();
+
+                    // The SUBACK packet contains a return code for each topic filter in the SUBSCRIBE packet.
+                    if suback.reason_codes.len() != topic_filters {
+                        error!("received mismatched SUBACK");
+                        self.raw.close_with(None);
+                        return Err(MqttError::Server);
+                    }
+
+                    Event::Suback(Suback {
+                        packet_identifier: pid,
+                        reason_string: None,
+                        user_properties: Vec::new(),
+                        reason_codes: suback.reason_codes,
+                    })
+                } else {
+                    debug!("packet identifier {} in SUBACK not in use", pid);
+                    Event::Ignored
+                }
+            }
+            PacketType::Unsuback => {
+                let unsuback = self.raw.recv_body::<UnsubackPacket>(&header).await?;
+
+                let pid = unsuback.packet_identifier;
+
+                if let Some(h) = self.session.unsub_handle(pid) {
+                    h.remove();
+
+                    Event::Unsuback(Suback {
+                        packet_identifier: pid,
+                        reason_string: None,
+                        user_properties: Vec::new(),
+                        // MQTT version 3.1.1 UNSUBACK packets don't contain reason codes.
+                        reason_codes: ReasonCodes::new_unchecked(Bytes::from([].as_slice())),
+                    })
+                } else {
+                    debug!("packet identifier {} in UNSUBACK not in use", pid);
+                    Event::Ignored
+                }
+            }
+            PacketType::Publish => {
+                let publish = self.raw.recv_body::<PublishPacket>(&header).await?;
+
+                let publish = Publish {
+                    ack_mode: AckMode::default(),
+                    dup: publish.dup,
+                    identified_qos: publish.identified_qos,
+                    retain: publish.retain,
+                    topic: publish.topic,
+                    payload_format_indicator: None,
+                    message_expiry_interval: None,
+                    response_topic: None,
+                    correlation_data: None,
+                    user_properties: Vec::new(),
+                    subscription_identifiers: Vec::new(),
+                    content_type: None,
+                    message: publish.message,
+                };
+
+                let ack_mode = if manual_ack_when(&publish) {
+                    AckMode::Manual
+                } else {
+                    AckMode::Automatic
+                };
+
+                let publish = Publish {
+                    ack_mode,
+                    ..publish
+                };
+
+                let (action, event) = self
+                    .session
+                    .inbound_publish(publish.identified_qos, publish.ack_mode);
+
+                match action {
+                    Response::Release(_) | Response::Complete(_) => unreachable!(),
+
+                    Response::None => {}
+                    Response::Acknowledge(_) => {
+                        let puback =
+                            PubackPacket::new(publish.identified_qos.packet_identifier().unwrap());
+                        debug!("sending PUBACK packet {}", puback.packet_identifier);
+
+                        self.raw.send(&puback).await?;
+                        self.raw.flush().await?;
+                    }
+                    Response::Receive(_) => {
+                        let pubrec =
+                            PubrecPacket::new(publish.identified_qos.packet_identifier().unwrap());
+                        debug!("sending PUBREC packet {}", pubrec.packet_identifier);
+
+                        self.raw.send(&pubrec).await?;
+                        self.raw.flush().await?;
+                    }
+                    Response::Disconnect(_) => {
+                        error!("invalid PUBLISH packet rejected by state machine");
+                        self.raw.close_with(None);
+                    }
+                }
+
+                match event {
+                    SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Received(_)
+                    | SmEvent::Released(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Publish => Event::Publish(publish),
+                    SmEvent::Duplicate(ack_mode) => {
+                        let publish = Publish {
+                            ack_mode,
+                            ..publish
+                        };
+                        Event::Duplicate(publish)
+                    }
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                }
+            }
+            PacketType::Puback => {
+                let puback = self.raw.recv_body::<PubackPacket>(&header).await?;
+                let pid = puback.packet_identifier;
+
+                let (action, event) = self.session.inbound_puback(pid, ReasonCode::Success);
+
+                match action {
+                    Response::Acknowledge(_)
+                    | Response::Receive(_)
+                    | Response::Release(_)
+                    | Response::Complete(_) => unreachable!(),
+
+                    Response::None => {}
+                    Response::Disconnect(_) => {
+                        error!("invalid PUBACK packet rejected by state machine");
+                        self.raw.close_with(None);
+                    }
+                }
+
+                match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Received(_)
+                    | SmEvent::Released(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Acknowledged => {
+                        Event::PublishAcknowledged(Self::puback(pid, AckMode::default()))
+                    }
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                }
+            }
+            PacketType::Pubrec => {
+                let pubrec = self.raw.recv_body::<PubrecPacket>(&header).await?;
+                let pid = pubrec.packet_identifier;
+
+                let (action, event) = self.session.inbound_pubrec(pid, ReasonCode::Success);
+
+                match action {
+                    Response::Acknowledge(_) | Response::Receive(_) | Response::Complete(_) => {
+                        unreachable!()
+                    }
+
+                    Response::None => {}
+                    Response::Release(_) => {
+                        let pubrel = PubrelPacket::new(pid);
+
+                        debug!("sending PUBREL packet {}", pubrel.packet_identifier);
+
+                        self.raw.send(&pubrel).await?;
+                        self.raw.flush().await?;
+                    }
+                    Response::Disconnect(_) => {
+                        error!("invalid PUBREC packet rejected by state machine");
+                        self.raw.close_with(None);
+                    }
+                }
+
+                match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Released(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Received(mode) => Event::PublishReceived(Self::puback(pid, mode)),
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                }
+            }
+            PacketType::Pubrel => {
+                let pubrel = self.raw.recv_body::<PubrelPacket>(&header).await?;
+                let pid = pubrel.packet_identifier;
+
+                let (action, event) = self.session.inbound_pubrel(pid, ReasonCode::Success);
+
+                match action {
+                    Response::Acknowledge(_) | Response::Receive(_) | Response::Release(_) => {
+                        unreachable!()
+                    }
+
+                    Response::None => {}
+                    Response::Complete(_) => {
+                        let pubcomp = PubcompPacket::new(pid);
+
+                        debug!("sending PUBCOMP packet {}", pubcomp.packet_identifier);
+
+                        self.raw.send(&pubcomp).await?;
+                        self.raw.flush().await?;
+                    }
+                    Response::Disconnect(_) => {
+                        error!("invalid PUBREL packet rejected by state machine");
+                        self.raw.close_with(None);
+                    }
+                }
+
+                match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Received(_)
+                    | SmEvent::Completed => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Released(mode) => Event::PublishReleased(Self::puback(pid, mode)),
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                }
+            }
+            PacketType::Pubcomp => {
+                let pubcomp = self.raw.recv_body::<PubcompPacket>(&header).await?;
+                let pid = pubcomp.packet_identifier;
+
+                let (action, event) = self.session.inbound_pubcomp(pid, ReasonCode::Success);
+
+                match action {
+                    Response::Acknowledge(_)
+                    | Response::Receive(_)
+                    | Response::Release(_)
+                    | Response::Complete(_) => unreachable!(),
+
+                    Response::None => {}
+                    Response::Disconnect(_) => {
+                        error!("invalid PUBCOMP packet rejected by state machine");
+                        self.raw.close_with(None);
+                    }
+                }
+
+                match event {
+                    SmEvent::Publish
+                    | SmEvent::Duplicate(_)
+                    | SmEvent::Aborted
+                    | SmEvent::Rejected
+                    | SmEvent::Acknowledged
+                    | SmEvent::Received(_)
+                    | SmEvent::Released(_) => unreachable!(),
+
+                    SmEvent::Ignored => Event::Ignored,
+                    SmEvent::Completed => {
+                        Event::PublishComplete(Self::puback(pid, AckMode::default()))
+                    }
+                    SmEvent::ServerError => return Err(MqttError::Server),
+                }
+            }
+            t @ (PacketType::Connect
+            | PacketType::Subscribe
+            | PacketType::Unsubscribe
+            | PacketType::Pingreq
+            | PacketType::Disconnect) => {
+                error!(
+                    "received a packet that the server is not allowed to send: {:?}",
+                    t
+                );
+
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            }
+            #[cfg(feature = "v5")]
+            PacketType::Auth => {
+                error!("received AUTH packet which MQTT version 3.1.1 does not define");
+
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            }
+            PacketType::Connack => {
+                error!("received unexpected CONNACK packet");
+
+                self.raw.close_with(None);
+                return Err(MqttError::Server);
+            }
+        };
+
+        Ok(event)
+    }
+
+    /// Creates the content of a successful acknowledgement event as MQTT version 3.1.1
+    /// acknowledgement packets contain nothing but the packet identifier.
+    fn puback<const MAX_USER_PROPERTIES: usize>(
+        packet_identifier: PacketIdentifier,
+        ack_mode: AckMode,
+    ) -> Puback<'c, MAX_USER_PROPERTIES> {
+        Puback {
+            ack_mode,
+            packet_identifier,
+            reason_code: ReasonCode::Success,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use embedded_io_adapters::tokio_1::FromTokio;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex},
+        join,
+    };
+    use tokio_test::{assert_err, assert_ok};
+
+    #[cfg(feature = "alloc")]
+    use crate::buffer::AllocBuffer;
+    #[cfg(feature = "bump")]
+    use crate::buffer::BumpBuffer;
+    use crate::{
+        client::{
+            MqttError,
+            event::{Event, Puback, Publish, Suback},
+            options::{ConnectOptions, FilterOptions, PublicationOptions, TopicReference},
+            v3::Client,
+        },
+        types::{IdentifiedQoS, MqttString, ReasonCode, TopicFilter, TopicName},
+    };
+
+    /// Reads the CONNECT packet of a client connecting with clean session and without client
+    /// identifier and answers it with a CONNACK packet with the return code.
+    async fn accept(s: &mut DuplexStream, return_code: u8) {
+        let mut connect = [0; 14];
+        assert_ok!(s.read_exact(&mut connect).await);
+        assert_eq!(
+            connect[..10],
+            [0x10, 0x0C, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x02]
+        );
+        assert_ok!(s.write_all(&[0x20, 0x02, 0x00, return_code]).await);
+    }
+
+    async fn expect(s: &mut DuplexStream, packet: &[u8]) {
+        let mut buf = [0; 16];
+        let buf = &mut buf[..packet.len()];
+        assert_ok!(s.read_exact(buf).await);
+        assert_eq!(buf, packet);
+    }
+
+    fn topic() -> TopicName<'static> {
+        TopicName::new(MqttString::try_from("t").unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn connack_return_codes() {
+        for (return_code, reason_code) in [
+            (0x01, ReasonCode::UnsupportedProtocolVersion),
+            (0x02, ReasonCode::ClientIdentifierNotValid),
+            (0x03, ReasonCode::ServerUnavailable),
+            (0x04, ReasonCode::BadUserNameOrPassword),
+            (0x05, ReasonCode::NotAuthorized),
+        ] {
+            #[cfg(feature = "alloc")]
+            let mut b = AllocBuffer;
+            #[cfg(feature = "bump")]
+            let mut b = [0; 64];
+            #[cfg(feature = "bump")]
+            let mut b = BumpBuffer::new(&mut b);
+            let (net, mut s) = duplex(64);
+
+            let mut c = Client::<'_, _, _, 1, 1, 1>::new(&mut b);
+
+            let tx = accept(&mut s, return_code);
+            let rx = async {
+                let options = ConnectOptions::new().clean_start();
+                let e = assert_err!(c.connect(FromTokio::new(net), &options, None).await);
+                assert!(matches!(e, MqttError::Disconnect { reason, .. } if reason == reason_code));
+            };
+
+            join!(rx, tx);
+        }
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn connack_accepted() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (net, mut s) = duplex(64);
+
+        let mut c = Client::<'_, _, _, 1, 1, 1>::new(&mut b);
+
+        let tx = accept(&mut s, 0x00);
+        let rx = async {
+            let options = ConnectOptions::new().clean_start();
+            let connected = assert_ok!(c.connect(FromTokio::new(net), &options, None).await);
+            assert!(!connected.session_present);
+        };
+
+        join!(rx, tx);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn qos1_flows() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (net, mut s) = duplex(64);
+
+        let mut c = Client::<'_, _, _, 1, 1, 1>::new(&mut b);
+
+        let tx = async {
+            accept(&mut s, 0x00).await;
+
+            expect(&mut s, &[0x32, 0x06, 0x00, 0x01, b't', 0x00, 0x01, b'm']).await;
+            assert_ok!(s.write_all(&[0x40, 0x02, 0x00, 0x01]).await);
+
+            let publish = [0x32, 0x06, 0x00, 0x01, b't', 0x00, 0x05, b'x'];
+            assert_ok!(s.write_all(&publish).await);
+            expect(&mut s, &[0x40, 0x02, 0x00, 0x05]).await;
+        };
+        let rx = async {
+            let options = ConnectOptions::new().clean_start();
+            assert_ok!(c.connect(FromTokio::new(net), &options, None).await);
+
+            let pid = assert_ok!(
+                c.publish(
+                    &PublicationOptions::new(TopicReference::Name(topic())).at_least_once(),
+                    "m".as_bytes().into(),
+                )
+                .await
+            )
+            .unwrap();
+
+            match assert_ok!(c.poll().await) {
+                Event::PublishAcknowledged(Puback {
+                    packet_identifier,
+                    reason_code: ReasonCode::Success,
+                    ..
+                }) => assert_eq!(packet_identifier, pid),
+                e => panic!("unexpected event {e:?}"),
+            }
+
+            match assert_ok!(c.poll().await) {
+                Event::Publish(Publish {
+                    identified_qos: IdentifiedQoS::AtLeastOnce(pid),
+                    topic,
+                    message,
+                    ..
+                }) => {
+                    assert_eq!(pid.get().get(), 5);
+                    assert_eq!(topic, self::topic());
+                    assert_eq!(&*message, b"x");
+                }
+                e => panic!("unexpected event {e:?}"),
+            }
+        };
+
+        join!(rx, tx);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn qos2_flows() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (net, mut s) = duplex(64);
+
+        let mut c = Client::<'_, _, _, 1, 1, 1>::new(&mut b);
+
+        let tx = async {
+            accept(&mut s, 0x00).await;
+
+            expect(&mut s, &[0x34, 0x06, 0x00, 0x01, b't', 0x00, 0x01, b'm']).await;
+            assert_ok!(s.write_all(&[0x50, 0x02, 0x00, 0x01]).await);
+            expect(&mut s, &[0x62, 0x02, 0x00, 0x01]).await;
+            assert_ok!(s.write_all(&[0x70, 0x02, 0x00, 0x01]).await);
+
+            let publish = [0x34, 0x06, 0x00, 0x01, b't', 0x00, 0x07, b'x'];
+            assert_ok!(s.write_all(&publish).await);
+            expect(&mut s, &[0x50, 0x02, 0x00, 0x07]).await;
+            assert_ok!(s.write_all(&[0x62, 0x02, 0x00, 0x07]).await);
+            expect(&mut s, &[0x70, 0x02, 0x00, 0x07]).await;
+        };
+        let rx = async {
+            let options = ConnectOptions::new().clean_start();
+            assert_ok!(c.connect(FromTokio::new(net), &options, None).await);
+
+            let pid = assert_ok!(
+                c.publish(
+                    &PublicationOptions::new(TopicReference::Name(topic())).exactly_once(),
+                    "m".as_bytes().into(),
+                )
+                .await
+            )
+            .unwrap();
+
+            match assert_ok!(c.poll().await) {
+                Event::PublishReceived(Puback {
+                    packet_identifier, ..
+                }) => assert_eq!(packet_identifier, pid),
+                e => panic!("unexpected event {e:?}"),
+            }
+            match assert_ok!(c.poll().await) {
+                Event::PublishComplete(Puback {
+                    packet_identifier, ..
+                }) => assert_eq!(packet_identifier, pid),
+                e => panic!("unexpected event {e:?}"),
+            }
+
+            match assert_ok!(c.poll().await) {
+                Event::Publish(Publish {
+                    identified_qos: IdentifiedQoS::ExactlyOnce(pid),
+                    message,
+                    ..
+                }) => {
+                    assert_eq!(pid.get().get(), 7);
+                    assert_eq!(&*message, b"x");
+                }
+                e => panic!("unexpected event {e:?}"),
+            }
+            match assert_ok!(c.poll().await) {
+                Event::PublishReleased(Puback {
+                    packet_identifier, ..
+                }) => assert_eq!(packet_identifier.get().get(), 7),
+                e => panic!("unexpected event {e:?}"),
+            }
+        };
+
+        join!(rx, tx);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn suback_failure() {
+        #[cfg(feature = "alloc")]
+        let mut b = AllocBuffer;
+        #[cfg(feature = "bump")]
+        let mut b = [0; 64];
+        #[cfg(feature = "bump")]
+        let mut b = BumpBuffer::new(&mut b);
+        let (net, mut s) = duplex(64);
+
+        let mut c = Client::<'_, _, _, 1, 1, 1>::new(&mut b);
+
+        let tx = async {
+            accept(&mut s, 0x00).await;
+
+            #[rustfmt::skip]
+            expect(&mut s, &[
+                0x82, 0x0A, 0x00, 0x01,
+                0x00, 0x01, b'a', 0x01,
+                0x00, 0x01, b'b', 0x00,
+            ]).await;
+            assert_ok!(s.write_all(&[0x90, 0x04, 0x00, 0x01, 0x01, 0x80]).await);
+        };
+        let rx = async {
+            let options = ConnectOptions::new().clean_start();
+            assert_ok!(c.connect(FromTokio::new(net), &options, None).await);
+
+            let filter = |f| TopicFilter::new(MqttString::try_from(f).unwrap()).unwrap();
+            let pid = assert_ok!(
+                c.subscribe_many(&[
+                    (filter("a"), FilterOptions::new().at_least_once()),
+                    (filter("b"), FilterOptions::new()),
+                ])
+                .await
+            );
+
+            match assert_ok!(c.poll().await) {
+                Event::Suback(Suback {
+                    packet_identifier,
+                    reason_codes,
+                    ..
+                }) => {
+                    assert_eq!(packet_identifier, pid);
+                    assert_eq!(reason_codes.len(), 2);
+                    assert_eq!(reason_codes.get(0), Some(ReasonCode::GrantedQoS1));
+                    assert_eq!(reason_codes.get(1), Some(ReasonCode::UnspecifiedError));
+                }
+                e => panic!("unexpected event {e:?}"),
+            }
+        };
+
+        join!(rx, tx);
+    }
+}
@@ -6,10 +6,9 @@ use crate::{config::SessionExpiryInterval, types::VarByteInt};
 /// Other values which might be added in the future here are:
 /// * Client's receive maximum: this is currently a const generic of the client type
 ///   and is therefore not required as an in-memory value.
-/// * Client's topic alias maximum: incoming topic aliases are currently not supported,
-///   this is configured into the protocol via a topic alias maximum value of 0.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// The session expiry interval requested by the client. Note that this is NOT
     /// necessarily the actual session expiry interval value in force which is used by
@@ -53,6 +52,16 @@ pub struct Config {
     /// [`Client::connect`]: crate::client::Client::connect
     /// [`ConnectOptions`]: crate::client::options::ConnectOptions
     pub request_problem_information: bool,
+
+    /// The highest value of a topic alias the server is allowed to use in PUBLISH packets.
+    /// A value of 0 means that the server must not use topic aliases.
+    ///
+    /// This value is configured when calling [`Client::connect`] from the value in
+    /// [`ConnectOptions`].
+    ///
+    /// [`Client::connect`]: crate::client::Client::connect
+    /// [`ConnectOptions`]: crate::client::options::ConnectOptions
+    pub topic_alias_maximum: u16,
 }
 
 impl Default for Config {
@@ -61,6 +70,7 @@ impl Default for Config {
             session_expiry_interval: SessionExpiryInterval::default(),
             maximum_accepted_remaining_length: VarByteInt::MAX_ENCODABLE,
             request_problem_information: false,
+            topic_alias_maximum: 0,
         }
     }
 }
@@ -6,6 +6,11 @@ mod shared;
 
 use core::num::NonZero;
 
+use crate::{
+    fmt::{panic, unreachable},
+    types::VarByteInt,
+};
+
 pub use client::Config as ClientConfig;
 pub use server::Config as ServerConfig;
 pub use shared::Config as SharedConfig;
@@ -13,6 +18,7 @@ pub use shared::Config as SharedConfig;
 /// Keep alive mechanism within a connection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeepAlive {
     /// There is no keep alive mechanism. Any amount of time can pass between 2 MQTT packets
     #[default]
@@ -36,6 +42,7 @@ impl KeepAlive {
 /// The handling of a session after a disconnection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SessionExpiryInterval {
     /// The session ends the moment a DISCONNECT packet is sent or the network connection closes.
     #[default]
@@ -49,6 +56,7 @@ pub enum SessionExpiryInterval {
 /// Maximum packet size. Exceeding this is a protocol error.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MaximumPacketSize {
     /// There is no imposed limit on how large packets can be. The technical limit is
     /// [`VarByteInt::MAX_ENCODABLE`] + 5 (size of fixed header).
@@ -64,10 +72,44 @@ pub enum MaximumPacketSize {
 }
 
 impl MaximumPacketSize {
+    #[cfg(feature = "v5")]
     pub(crate) fn as_u32(self) -> u32 {
         match self {
             Self::Unlimited => u32::MAX,
             Self::Limit(l) => l.get(),
         }
     }
+
+    /// Returns the greatest remaining length of a packet that does not exceed this maximum packet
+    /// size.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the limit is 1.
+    pub(crate) fn max_remaining_len(self) -> u32 {
+        // Empirical maximum packet size mapping
+        // -------------------------------------------------------------------------------------------------------
+        //         remaining length              | fixed header length |              max packet size
+        //                               0..=127 |                   2 |                                   2..=129
+        //                          128..=16_383 |                   3 |                              131..=16_386
+        //                    16_384..=2_097_151 |                   4 |                        16_388..=2_097_155
+        // 2_097_152..=VarByteInt::MAX_ENCODABLE |                   5 | 2_097_157..=(VarByteInt::MAX_ENCODABLE+5)
+
+        const MAX_POSSIBLE_PACKET_SIZE: u32 = VarByteInt::MAX_ENCODABLE + 5;
+
+        match self {
+            Self::Unlimited => u32::MAX,
+            Self::Limit(l) => match l.get() {
+                0 => unreachable!("NonZero invariant"),
+                1 => panic!(
+                    "every MQTT packet is at least 2 bytes long, a smaller maximum packet size makes no sense"
+                ),
+                2..=129 => l.get() - 2,
+                130..=16_386 => l.get() - 3,
+                16_387..=2_097_155 => l.get() - 4,
+                2_097_156..MAX_POSSIBLE_PACKET_SIZE => l.get() - 5,
+                MAX_POSSIBLE_PACKET_SIZE.. => VarByteInt::MAX_ENCODABLE,
+            },
+        }
+    }
 }
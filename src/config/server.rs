@@ -6,6 +6,7 @@ use crate::{config::MaximumPacketSize, types::QoS};
 /// These values are used by the client to enforce protocol correctness.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// Maximum concurrent [`QoS::AtLeastOnce`] & [`QoS::ExactlyOnce`] publications that the
     /// server is willing to accept.
@@ -3,6 +3,7 @@ use crate::config::{KeepAlive, SessionExpiryInterval};
 /// Negotiated configuration valid for the duration of a connection.
 #[derive(Debug, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
     /// The negotiated [`KeepAlive`] interval in seconds allowed to expire between
     /// sending two packets without the connection being closed.
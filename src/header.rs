@@ -154,7 +154,7 @@ impl PacketType {
             13 => Ok(PacketType::Pingresp),
             14 => Ok(PacketType::Disconnect),
 
-            #[cfg(feature = "v3")]
+            #[cfg(not(feature = "v5"))]
             15 => Err(Reserved),
 
             #[cfg(feature = "v5")]
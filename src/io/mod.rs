@@ -7,4 +7,4 @@ pub(crate) mod err;
 pub(crate) mod read;
 pub(crate) mod write;
 
-pub use net::Transport;
+pub use net::{Split, Transport};
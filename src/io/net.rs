@@ -4,3 +4,20 @@ use crate::eio::{Read, Write};
 pub trait Transport: Read + Write {}
 
 impl<T> Transport for T where T: Read + Write {}
+
+/// A [`Transport`] which can be split into a read half and a write half that can be used
+/// concurrently, such as the halves of a TCP socket.
+pub trait Split: Transport {
+    /// The read half of the transport.
+    type Reader<'a>: Read
+    where
+        Self: 'a;
+
+    /// The write half of the transport.
+    type Writer<'a>: Write
+    where
+        Self: 'a;
+
+    /// Borrows the transport as its read half and its write half.
+    fn split(&mut self) -> (Self::Reader<'_>, Self::Writer<'_>);
+}
@@ -188,6 +188,7 @@ impl<'r, 'b, R: Read, B: BufferProvider<'b>> BodyReader<'r, 'b, R, B> {
         self.remaining_len
     }
 
+    #[cfg(feature = "v5")]
     pub async fn skip(
         &mut self,
         len: usize,
@@ -1,6 +1,8 @@
 #![no_std]
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
+// The shared option, event and error types document their use with the MQTT version 5.0 client.
+#![cfg_attr(not(feature = "v5"), allow(rustdoc::broken_intra_doc_links))]
 #![warn(clippy::missing_safety_doc)]
 #![deny(clippy::unnecessary_safety_doc)]
 #![deny(clippy::unnecessary_safety_comment)]
@@ -32,6 +34,7 @@ pub mod config;
 pub mod header;
 pub mod io;
 pub mod session;
+pub mod time;
 pub mod types;
 
 pub use bytes::Bytes;
@@ -1,11 +1,70 @@
+mod pings;
 mod rx;
 mod tx;
 
+pub use pings::{PingreqPacket, PingrespPacket};
 pub use rx::*;
 pub use tx::*;
 
+#[cfg(feature = "v5")]
+use core::{
+    convert::Infallible,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
 use crate::header::PacketType;
+#[cfg(feature = "v5")]
+use crate::{buffer::BufferProvider, eio::ErrorType, header::FixedHeader, io::read::BodyReader};
 
 pub trait Packet {
     const PACKET_TYPE: PacketType;
 }
+
+/// The error returned when writing into a byte slice.
+#[cfg(feature = "v5")]
+pub type SliceWriteError = <&'static mut [u8] as ErrorType>::Error;
+
+/// Encodes a packet into the start of a byte slice and returns the amount of bytes written.
+///
+/// # Errors
+///
+/// Returns [`TxError::Write`] if the packet does not fit into `buf`.
+#[cfg(feature = "v5")]
+pub fn encode<P: TxPacket>(packet: &P, buf: &mut [u8]) -> Result<usize, TxError<SliceWriteError>> {
+    let capacity = buf.len();
+    let mut remaining = buf;
+
+    complete(packet.send(&mut remaining)).ok_or(TxError::WriteZero)??;
+
+    Ok(capacity - remaining.len())
+}
+
+/// Decodes a packet whose fixed header has already been read from a byte slice
+/// containing its complete body.
+///
+/// # Errors
+///
+/// Returns the [`RxError`] of decoding the packet.
+#[cfg(feature = "v5")]
+pub fn decode<'p, P: RxPacket<'p>, B: BufferProvider<'p>>(
+    header: &FixedHeader,
+    mut body: &[u8],
+    buffer: &mut B,
+) -> Result<P, RxError<Infallible, B::ProvisionError>> {
+    let reader = BodyReader::new(&mut body, buffer, header.remaining_len.size());
+
+    complete(P::receive(header, reader)).ok_or(RxError::UnexpectedEOF)?
+}
+
+/// Polls an encoding or decoding future on byte slices once.
+///
+/// Reading from and writing to byte slices never waits, so these futures are ready the first
+/// time they are polled. Returns [`None`] otherwise.
+#[cfg(feature = "v5")]
+fn complete<F: Future>(future: F) -> Option<F::Output> {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
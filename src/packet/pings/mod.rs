@@ -6,9 +6,9 @@ use crate::{
     fmt::trace,
     header::{FixedHeader, PacketType},
     io::{read::BodyReader, write::Writable},
+    packet::pings::types::{PingPacketType, Req, Resp},
     packet::{Packet, RxError, RxPacket, TxError, TxPacket},
     types::VarByteInt,
-    v5::packet::pings::types::{PingPacketType, Req, Resp},
 };
 
 mod types;
@@ -73,8 +73,8 @@ impl<T: PingPacketType> GenericPingPacket<T> {
 mod unit {
     mod req {
         use crate::{
+            packet::PingreqPacket,
             test::{rx::decode, tx::encode},
-            v5::packet::PingreqPacket,
         };
 
         #[tokio::test]
@@ -99,8 +99,8 @@ mod unit {
 
     mod resp {
         use crate::{
+            packet::PingrespPacket,
             test::{rx::decode, tx::encode},
-            v5::packet::PingrespPacket,
         };
 
         #[tokio::test]
@@ -1,3 +1,5 @@
+#[cfg(feature = "v5")]
+use crate::v5::property::AtMostOncePropertyError;
 use crate::{
     buffer::BufferProvider,
     eio::Read,
@@ -6,11 +8,9 @@ use crate::{
         err::{BodyReadError, ReadError},
         read::BodyReader,
     },
-    packet::Packet,
-    v5::property::AtMostOncePropertyError,
 };
 
-pub trait RxPacket<'p>: Packet + Sized {
+pub trait RxPacket<'p>: Sized {
     /// Receives a packet. Must check the fixed header for correctness.
     async fn receive<R: Read, B: BufferProvider<'p>>(
         header: &FixedHeader,
@@ -66,6 +66,7 @@ impl<E, B: core::fmt::Debug> From<ReadError<E>> for RxError<E, B> {
         }
     }
 }
+#[cfg(feature = "v5")]
 impl<E, B: core::fmt::Debug> From<AtMostOncePropertyError<ReadError<BodyReadError<E, B>>>>
     for RxError<E, B>
 {
@@ -1,12 +1,6 @@
-use crate::{
-    eio::Write,
-    io::{
-        err::WriteError,
-        write::{Writable, wlen},
-    },
-    packet::Packet,
-    types::VarByteInt,
-};
+#[cfg(feature = "v5")]
+use crate::io::write::{Writable, wlen};
+use crate::{eio::Write, io::err::WriteError, packet::Packet, types::VarByteInt};
 
 pub trait TxPacket: Packet {
     /// Returns the remaining length of a packet.
@@ -16,6 +10,7 @@ pub trait TxPacket: Packet {
 
     /// Returns the full length of the packet from the first byte of the fixed header
     /// to the last byte of the payload.
+    #[cfg(feature = "v5")]
     fn encoded_len(&self) -> usize {
         let l = self.remaining_len();
         // (type + flags) + remaining length encoded + remaining length
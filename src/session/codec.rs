@@ -0,0 +1,405 @@
+use core::num::NonZero;
+
+use heapless::Vec;
+
+use crate::{
+    client::options::AckMode,
+    session::{LocalPublishState, PeerPublishState, Session},
+    types::PacketIdentifier,
+};
+
+/// Error returned when encoding a [`Session`] failed because the buffer is smaller than
+/// [`Session::encoded_len`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BufferTooSmall;
+
+/// Error returned when decoding a [`Session`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DecodeError {
+    /// The data has been encoded with a format version this version of the crate does not
+    /// support.
+    UnsupportedVersion(u8),
+
+    /// The data ends before the end of the encoded session.
+    UnexpectedEnd,
+
+    /// The data does not contain a valid session, e.g. because of an unknown publication state,
+    /// a packet identifier of zero or one used twice, or trailing bytes.
+    Malformed,
+
+    /// The encoded session has more entries than the capacity of the session it is decoded into.
+    NoCapacity,
+}
+
+/// The bit set in an encoded publication state if its flow is acknowledged with
+/// [`AckMode::Manual`].
+const MANUAL: u8 = 0x10;
+
+fn encode_ack_mode(ack_mode: AckMode) -> u8 {
+    match ack_mode {
+        AckMode::Automatic => 0,
+        AckMode::Manual => MANUAL,
+    }
+}
+
+fn decode_ack_mode(byte: u8) -> AckMode {
+    if byte & MANUAL == 0 {
+        AckMode::Automatic
+    } else {
+        AckMode::Manual
+    }
+}
+
+impl LocalPublishState {
+    fn encode(self) -> u8 {
+        match self {
+            Self::DuePublishAtLeastOnce => 0,
+            Self::DuePublishExactlyOnce(mode) => 1 | encode_ack_mode(mode),
+            Self::AwaitAck => 2,
+            Self::AwaitRec(mode) => 3 | encode_ack_mode(mode),
+            Self::DueRel(mode) => 4 | encode_ack_mode(mode),
+            Self::AwaitComp(mode) => 5 | encode_ack_mode(mode),
+        }
+    }
+
+    fn decode(byte: u8) -> Result<Self, DecodeError> {
+        let mode = decode_ack_mode(byte);
+
+        match byte {
+            0 => Ok(Self::DuePublishAtLeastOnce),
+            2 => Ok(Self::AwaitAck),
+            _ => match byte & !MANUAL {
+                1 => Ok(Self::DuePublishExactlyOnce(mode)),
+                3 => Ok(Self::AwaitRec(mode)),
+                4 => Ok(Self::DueRel(mode)),
+                5 => Ok(Self::AwaitComp(mode)),
+                _ => Err(DecodeError::Malformed),
+            },
+        }
+    }
+}
+
+impl PeerPublishState {
+    fn encode(self) -> u8 {
+        match self {
+            Self::AwaitPublishAtLeastOnce => 0,
+            Self::AwaitPublishExactlyOnce(mode) => 1 | encode_ack_mode(mode),
+            Self::DueAck => 2,
+            Self::DueRec => 3,
+            Self::AwaitRel(mode) => 4 | encode_ack_mode(mode),
+            Self::AwaitReRel => 5,
+            Self::DueComp => 6,
+        }
+    }
+
+    fn decode(byte: u8) -> Result<Self, DecodeError> {
+        let mode = decode_ack_mode(byte);
+
+        match byte {
+            0 => Ok(Self::AwaitPublishAtLeastOnce),
+            2 => Ok(Self::DueAck),
+            3 => Ok(Self::DueRec),
+            5 => Ok(Self::AwaitReRel),
+            6 => Ok(Self::DueComp),
+            _ => match byte & !MANUAL {
+                1 => Ok(Self::AwaitPublishExactlyOnce(mode)),
+                4 => Ok(Self::AwaitRel(mode)),
+                _ => Err(DecodeError::Malformed),
+            },
+        }
+    }
+}
+
+struct Encoder<'b> {
+    buffer: &'b mut [u8],
+    written: usize,
+}
+
+impl Encoder<'_> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), BufferTooSmall> {
+        let end = self.written + bytes.len();
+        self.buffer
+            .get_mut(self.written..end)
+            .ok_or(BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.written = end;
+        Ok(())
+    }
+
+    fn len(&mut self, len: usize) -> Result<(), BufferTooSmall> {
+        // Invariant: The session's vectors are limited to 65535 entries by the client's
+        // const assertions.
+        self.put(&(len as u16).to_be_bytes())
+    }
+
+    fn pid(&mut self, packet_identifier: PacketIdentifier) -> Result<(), BufferTooSmall> {
+        self.put(&packet_identifier.get_u16().to_be_bytes())
+    }
+}
+
+struct Decoder<'b> {
+    bytes: &'b [u8],
+}
+
+impl Decoder<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let (bytes, rest) = self
+            .bytes
+            .split_first_chunk()
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        self.take::<1>().map(|[b]| b)
+    }
+
+    fn len(&mut self) -> Result<usize, DecodeError> {
+        self.take().map(u16::from_be_bytes).map(usize::from)
+    }
+
+    fn pid(&mut self) -> Result<PacketIdentifier, DecodeError> {
+        NonZero::new(u16::from_be_bytes(self.take()?))
+            .map(PacketIdentifier::new)
+            .ok_or(DecodeError::Malformed)
+    }
+
+    fn entries<T, const N: usize>(
+        &mut self,
+        mut entry: impl FnMut(&mut Self) -> Result<(PacketIdentifier, T), DecodeError>,
+    ) -> Result<Vec<(PacketIdentifier, T), N>, DecodeError> {
+        let len = self.len()?;
+        if len > N {
+            return Err(DecodeError::NoCapacity);
+        }
+
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let (pid, value) = entry(self)?;
+            if entries.iter().any(|(p, _)| *p == pid) {
+                return Err(DecodeError::Malformed);
+            }
+            entries
+                .push((pid, value))
+                .map_err(|_| DecodeError::NoCapacity)?;
+        }
+
+        Ok(entries)
+    }
+}
+
+impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MAXIMUM: usize>
+    Session<SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>
+{
+    /// The version of the binary format written by [`Self::encode_into`].
+    pub const ENCODING_VERSION: u8 = 1;
+
+    /// Returns the amount of bytes [`Self::encode_into`] writes.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        // version + 4 * entry count + entries
+        1 + 4 * 2
+            + (self.subs.len() + self.unsubs.len()) * (2 + 4)
+            + (self.inbound_publishes.len() + self.outbound_publishes.len()) * (2 + 1)
+    }
+
+    /// Encodes the session state into a compact, versioned binary format, e.g. to keep it in
+    /// non-volatile memory while the device is powered off. The session can be restored with
+    /// [`Self::decode`] and passed to [`Client::with_session`]. As the states of all incomplete
+    /// [`QoS::ExactlyOnce`] flows are kept, they are completed exactly once after reconnecting.
+    ///
+    /// [`Client::with_session`]: crate::client::Client::with_session
+    /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
+    ///
+    /// Returns the amount of bytes written, which is [`Self::encoded_len`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BufferTooSmall`] if the buffer is shorter than [`Self::encoded_len`]. The
+    /// contents of the buffer are unspecified in this case.
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, BufferTooSmall> {
+        let mut e = Encoder { buffer, written: 0 };
+
+        e.put(&[Self::ENCODING_VERSION])?;
+
+        for subs in [&self.subs, &self.unsubs] {
+            e.len(subs.len())?;
+            for &(pid, topic_filters) in subs {
+                e.pid(pid)?;
+                let topic_filters = u32::try_from(topic_filters).unwrap_or(u32::MAX);
+                e.put(&topic_filters.to_be_bytes())?;
+            }
+        }
+
+        e.len(self.inbound_publishes.len())?;
+        for &(pid, state) in &self.inbound_publishes {
+            e.pid(pid)?;
+            e.put(&[state.encode()])?;
+        }
+
+        e.len(self.outbound_publishes.len())?;
+        for &(pid, state) in &self.outbound_publishes {
+            e.pid(pid)?;
+            e.put(&[state.encode()])?;
+        }
+
+        Ok(e.written)
+    }
+
+    /// Decodes a session state encoded with [`Self::encode_into`]. The capacities of the session
+    /// may differ from those of the encoded session as long as its entries fit.
+    ///
+    /// # Errors
+    ///
+    /// * [`DecodeError::UnsupportedVersion`] if the format version is unknown
+    /// * [`DecodeError::UnexpectedEnd`] if the data is incomplete
+    /// * [`DecodeError::Malformed`] if the data does not contain a valid session
+    /// * [`DecodeError::NoCapacity`] if the entries do not fit into the session
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut d = Decoder { bytes };
+
+        match d.u8()? {
+            Self::ENCODING_VERSION => {}
+            v => return Err(DecodeError::UnsupportedVersion(v)),
+        }
+
+        let mut topic_filters = |d: &mut Decoder<'_>| {
+            let pid = d.pid()?;
+            let topic_filters = u32::from_be_bytes(d.take()?);
+            let topic_filters = usize::try_from(topic_filters).unwrap_or(usize::MAX);
+            Ok((pid, topic_filters))
+        };
+
+        let session = Self {
+            subs: d.entries(&mut topic_filters)?,
+            unsubs: d.entries(&mut topic_filters)?,
+            inbound_publishes: d.entries(|d| Ok((d.pid()?, PeerPublishState::decode(d.u8()?)?)))?,
+            outbound_publishes: d
+                .entries(|d| Ok((d.pid()?, LocalPublishState::decode(d.u8()?)?)))?,
+        };
+
+        if !d.bytes.is_empty() {
+            return Err(DecodeError::Malformed);
+        }
+
+        // Packet identifiers are shared between outgoing PUBLISH, SUBSCRIBE and UNSUBSCRIBE
+        // packets.
+        let mut outgoing = session
+            .subs
+            .iter()
+            .map(|(p, _)| p)
+            .chain(session.unsubs.iter().map(|(p, _)| p))
+            .chain(session.outbound_publishes.iter().map(|(p, _)| p));
+        while let Some(pid) = outgoing.next() {
+            if outgoing.clone().any(|p| p == pid) {
+                return Err(DecodeError::Malformed);
+            }
+        }
+
+        Ok(session)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use crate::{
+        client::options::AckMode,
+        session::{
+            LocalPublishState, PeerPublishState, Session,
+            codec::{BufferTooSmall, DecodeError},
+        },
+        types::PacketIdentifier,
+    };
+
+    #[test]
+    fn roundtrip() {
+        let one = PacketIdentifier::ONE;
+        let two = one.next();
+        let three = two.next();
+
+        let mut s = Session::<2, 8, 8>::default();
+        s.subs.push((one, 3)).unwrap();
+        s.unsubs.push((two, 1)).unwrap();
+        for state in [
+            PeerPublishState::AwaitPublishAtLeastOnce,
+            PeerPublishState::AwaitPublishExactlyOnce(AckMode::Manual),
+            PeerPublishState::DueAck,
+            PeerPublishState::DueRec,
+            PeerPublishState::AwaitRel(AckMode::Automatic),
+            PeerPublishState::AwaitReRel,
+            PeerPublishState::DueComp,
+        ] {
+            let pid = s.inbound_publishes.last().map_or(one, |(p, _)| p.next());
+            s.inbound_publishes.push((pid, state)).unwrap();
+        }
+        for state in [
+            LocalPublishState::DuePublishAtLeastOnce,
+            LocalPublishState::DuePublishExactlyOnce(AckMode::Automatic),
+            LocalPublishState::AwaitAck,
+            LocalPublishState::AwaitRec(AckMode::Manual),
+            LocalPublishState::DueRel(AckMode::Manual),
+            LocalPublishState::AwaitComp(AckMode::Automatic),
+        ] {
+            let pid = s.outbound_publishes.last().map_or(three, |(p, _)| p.next());
+            s.outbound_publishes.push((pid, state)).unwrap();
+        }
+
+        let mut buffer = [0; 64];
+        let n = s.encode_into(&mut buffer).unwrap();
+        assert_eq!(n, s.encoded_len());
+        assert_eq!(s.encode_into(&mut buffer[..n - 1]), Err(BufferTooSmall));
+
+        assert_eq!(Session::<2, 8, 8>::decode(&buffer[..n]), Ok(s.clone()));
+        assert_eq!(
+            Session::<2, 8, 8>::decode(&buffer[..n - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Session::<2, 4, 8>::decode(&buffer[..n]),
+            Err(DecodeError::NoCapacity)
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let s = Session::<1, 1, 1>::default();
+        let mut buffer = [0; 9];
+        assert_eq!(s.encode_into(&mut buffer), Ok(9));
+        assert_eq!(buffer, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(Session::<1, 1, 1>::decode(&buffer), Ok(s));
+    }
+
+    #[test]
+    fn malformed() {
+        let decode = Session::<2, 2, 2>::decode;
+
+        assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(
+            decode(&[2, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+        // Trailing byte
+        assert_eq!(
+            decode(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(DecodeError::Malformed)
+        );
+        // Packet identifier zero
+        assert_eq!(
+            decode(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0]),
+            Err(DecodeError::Malformed)
+        );
+        // Unknown outgoing publication state
+        assert_eq!(
+            decode(&[1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 6]),
+            Err(DecodeError::Malformed)
+        );
+        // Packet identifier of a SUBSCRIBE packet used by a publication
+        assert_eq!(
+            decode(&[1, 0, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 1, 2]),
+            Err(DecodeError::Malformed)
+        );
+    }
+}
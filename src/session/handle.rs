@@ -18,14 +18,14 @@ pub struct FreeHandle<
 impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MAXIMUM: usize>
     FreeHandle<'_, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>
 {
-    pub fn outbound_sub(self) -> Result<(), Error> {
+    pub fn outbound_sub(self, topic_filters: usize) -> Result<(), Error> {
         self.session
             .subs
-            .push(self.packet_identifier)
+            .push((self.packet_identifier, topic_filters))
             .inspect(|_| {
                 trace!(
-                    "initiating subscription {{ pid=#{} }}",
-                    self.packet_identifier
+                    "initiating subscription {{ pid=#{}, topic_filters={} }}",
+                    self.packet_identifier, topic_filters
                 )
             })
             .map_err(|_| {
@@ -37,14 +37,14 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
                 Error::NoCapacity
             })
     }
-    pub fn outbound_unsub(self) -> Result<(), Error> {
+    pub fn outbound_unsub(self, topic_filters: usize) -> Result<(), Error> {
         self.session
             .unsubs
-            .push(self.packet_identifier)
+            .push((self.packet_identifier, topic_filters))
             .inspect(|_| {
                 trace!(
-                    "initiating unsubscription {{ pid=#{} }}",
-                    self.packet_identifier
+                    "initiating unsubscription {{ pid=#{}, topic_filters={} }}",
+                    self.packet_identifier, topic_filters
                 )
             })
             .map_err(|_| {
@@ -110,7 +110,11 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
         self.session.subs.swap_remove(self.i);
     }
     fn packet_identifier(&self) -> PacketIdentifier {
-        *self.session.subs.get(self.i).unwrap()
+        self.session.subs.get(self.i).unwrap().0
+    }
+    /// Returns the amount of topic filters in the in-flight SUBSCRIBE packet.
+    pub(crate) fn topic_filters(&self) -> usize {
+        self.session.subs.get(self.i).unwrap().1
     }
 }
 
@@ -133,7 +137,12 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
         self.session.unsubs.swap_remove(self.i);
     }
     fn packet_identifier(&self) -> PacketIdentifier {
-        *self.session.unsubs.get(self.i).unwrap()
+        self.session.unsubs.get(self.i).unwrap().0
+    }
+    /// Returns the amount of topic filters in the in-flight UNSUBSCRIBE packet.
+    #[cfg(any(test, feature = "v5"))]
+    pub(crate) fn topic_filters(&self) -> usize {
+        self.session.unsubs.get(self.i).unwrap().1
     }
 }
 
@@ -695,6 +704,7 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
     pub(crate) fn packet_identifier(&self) -> PacketIdentifier {
         self.session.outbound_publishes.get(self.i).unwrap().0
     }
+    #[cfg(feature = "v3")]
     pub(crate) fn next(self) -> Option<Self> {
         let i = self.i + 1;
 
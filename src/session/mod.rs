@@ -10,8 +10,11 @@ use crate::{
     types::{IdentifiedQoS, PacketIdentifier, QoS, ReasonCode},
 };
 
+mod codec;
 pub(crate) mod handle;
 
+pub use codec::{BufferTooSmall, DecodeError};
+
 /// The state of an incomplete [`QoS::AtLeastOnce`] or [`QoS::ExactlyOnce`] publication by the
 /// client.
 ///
@@ -19,6 +22,7 @@ pub(crate) mod handle;
 /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocalPublishState {
     /// A [`QoS::AtLeastOnce`] PUBLISH packet must be resent after a reconnection. The
     /// specification demands a retransmission in this case, not following it is a protocol
@@ -81,6 +85,7 @@ impl LocalPublishState {
 /// [`QoS::ExactlyOnce`]: crate::types::QoS::ExactlyOnce
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PeerPublishState {
     /// A [`QoS::AtLeastOnce`] PUBLISH packet must be resent by the server after a reconnection.
     /// The specification demands a retransmission in this case, not following it is a protocol
@@ -189,15 +194,18 @@ pub(crate) enum Event {
 /// Client identifier is not stored here as it would lead to inconsistencies with the underyling allocation system.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Session<
     const SUBSCRIBE_MAXIMUM: usize,
     const RECEIVE_MAXIMUM: usize,
     const SEND_MAXIMUM: usize,
 > {
-    /// The currently in-flight subscriptions.
-    pub subs: Vec<PacketIdentifier, SUBSCRIBE_MAXIMUM>,
-    /// The currently in-flight unsubscriptions.
-    pub unsubs: Vec<PacketIdentifier, SUBSCRIBE_MAXIMUM>,
+    /// The currently in-flight subscriptions and the amount of topic filters in their SUBSCRIBE
+    /// packets.
+    pub subs: Vec<(PacketIdentifier, usize), SUBSCRIBE_MAXIMUM>,
+    /// The currently in-flight unsubscriptions and the amount of topic filters in their
+    /// UNSUBSCRIBE packets.
+    pub unsubs: Vec<(PacketIdentifier, usize), SUBSCRIBE_MAXIMUM>,
 
     /// The currently in-flight incoming publications.
     pub inbound_publishes: Vec<(PacketIdentifier, PeerPublishState), RECEIVE_MAXIMUM>,
@@ -245,7 +253,7 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
     ) -> Option<SubHandle<'_, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>> {
         self.subs
             .iter()
-            .position(|&p| p == packet_identifier)
+            .position(|&(p, _)| p == packet_identifier)
             .map(|i| SubHandle { session: self, i })
     }
 
@@ -256,7 +264,7 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
     ) -> Option<UnsubHandle<'_, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>> {
         self.unsubs
             .iter()
-            .position(|&p| p == packet_identifier)
+            .position(|&(p, _)| p == packet_identifier)
             .map(|i| UnsubHandle { session: self, i })
     }
     /// Obtains a handle to a packet identifier used for an incoming, currently in-flight PUBLISH
@@ -298,6 +306,7 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
     /// PUBLISH packet / handshake, where *first* means that subsequent calls to
     /// [`OutboundHandle::next`] allow iteration over all packet identifiers belonging to an outgoing,
     /// currently in-flight PUBLISH packet / handshake.
+    #[cfg(feature = "v3")]
     pub(crate) fn outbound_iter(
         &mut self,
     ) -> Option<OutboundHandle<'_, SUBSCRIBE_MAXIMUM, RECEIVE_MAXIMUM, SEND_MAXIMUM>> {
@@ -378,12 +387,12 @@ impl<const SUBSCRIBE_MAXIMUM: usize, const RECEIVE_MAXIMUM: usize, const SEND_MA
     pub(crate) fn reconnect(&mut self) {
         trace!("reconnection resets:");
 
-        for pid in &self.subs {
+        for (pid, _) in &self.subs {
             trace!("#{}: AwaitSuback -> Untracked", pid);
         }
         self.subs.clear();
 
-        for pid in &self.unsubs {
+        for (pid, _) in &self.unsubs {
             trace!("#{}: AwaitUnsuback -> Untracked", pid);
         }
         self.unsubs.clear();
@@ -2068,8 +2077,8 @@ mod unit {
         #[test]
         fn no_sub_buffer_exceedance() {
             let mut no_sub = Session::<0, 1, 1>::default();
-            assert_err!(no_sub.free_handle().unwrap().outbound_sub());
-            assert_err!(no_sub.free_handle().unwrap().outbound_unsub());
+            assert_err!(no_sub.free_handle().unwrap().outbound_sub(1));
+            assert_err!(no_sub.free_handle().unwrap().outbound_unsub(1));
             assert_eq!(
                 no_sub.inbound_publish(
                     IdentifiedQoS::ExactlyOnce(PacketIdentifier::ONE),
@@ -2087,11 +2096,30 @@ mod unit {
             const SOME: usize = 5;
             let mut some_sub = Session::<SOME, 1, 1>::default();
             for _ in 0..SOME {
-                assert_ok!(some_sub.free_handle().unwrap().outbound_sub());
-                assert_ok!(some_sub.free_handle().unwrap().outbound_unsub());
+                assert_ok!(some_sub.free_handle().unwrap().outbound_sub(1));
+                assert_ok!(some_sub.free_handle().unwrap().outbound_unsub(1));
             }
-            assert_err!(no_sub.free_handle().unwrap().outbound_sub());
-            assert_err!(no_sub.free_handle().unwrap().outbound_unsub());
+            assert_err!(no_sub.free_handle().unwrap().outbound_sub(1));
+            assert_err!(no_sub.free_handle().unwrap().outbound_unsub(1));
+        }
+
+        #[test_log::test]
+        #[test]
+        fn tracked_topic_filters() {
+            let mut session = Session::<2, 1, 1>::default();
+            let h = session.free_handle().unwrap();
+            let pid = h.packet_identifier;
+            assert_ok!(h.outbound_sub(40));
+            assert_eq!(session.sub_handle(pid).unwrap().topic_filters(), 40);
+
+            let h = session.free_handle().unwrap();
+            let unsub_pid = h.packet_identifier;
+            assert_ok!(h.outbound_unsub(12));
+            assert_eq!(session.unsub_handle(unsub_pid).unwrap().topic_filters(), 12);
+
+            session.reconnect();
+            assert!(session.sub_handle(pid).is_none());
+            assert!(session.unsub_handle(unsub_pid).is_none());
         }
 
         #[test_log::test]
@@ -2100,11 +2128,11 @@ mod unit {
             let mut no_out_pub = Session::<1, 1, 0>::default();
             let h = no_out_pub.free_handle().unwrap();
             let pid = h.packet_identifier;
-            assert_ok!(h.outbound_sub());
+            assert_ok!(h.outbound_sub(1));
             no_out_pub.sub_handle(pid).unwrap().remove();
             let h = no_out_pub.free_handle().unwrap();
             let pid = h.packet_identifier;
-            assert_ok!(h.outbound_unsub());
+            assert_ok!(h.outbound_unsub(1));
             no_out_pub.unsub_handle(pid).unwrap().remove();
             assert_eq!(
                 no_out_pub.inbound_publish(
@@ -2642,11 +2670,11 @@ mod unit {
             let mut no_in_pub = Session::<1, 0, 1>::default();
             let h = no_in_pub.free_handle().unwrap();
             let pid = h.packet_identifier;
-            assert_ok!(h.outbound_sub());
+            assert_ok!(h.outbound_sub(1));
             no_in_pub.sub_handle(pid).unwrap().remove();
             let h = no_in_pub.free_handle().unwrap();
             let pid = h.packet_identifier;
-            assert_ok!(h.outbound_unsub());
+            assert_ok!(h.outbound_unsub(1));
             no_in_pub.unsub_handle(pid).unwrap().remove();
             assert_eq!(
                 no_in_pub.inbound_publish(
@@ -3427,4 +3455,27 @@ mod unit {
 
         assert!(sm.outbound_publishes.is_empty());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        use crate::session::{LocalPublishState, PeerPublishState};
+
+        let pid = PacketIdentifier::ONE;
+        let mut sm = Session::<2, 2, 2>::default();
+        sm.subs.push((pid, 2)).unwrap();
+        sm.inbound_publishes
+            .push((pid, PeerPublishState::AwaitRel(AckMode::Manual)))
+            .unwrap();
+        sm.outbound_publishes
+            .push((pid.next(), LocalPublishState::AwaitComp(AckMode::Automatic)))
+            .unwrap();
+
+        let json = serde_json::to_string(&sm).unwrap();
+        assert_eq!(
+            json,
+            r#"{"subs":[[1,2]],"unsubs":[],"inbound_publishes":[[1,{"AwaitRel":"Manual"}]],"outbound_publishes":[[2,{"AwaitComp":"Automatic"}]]}"#
+        );
+        assert_eq!(serde_json::from_str::<Session<2, 2, 2>>(&json).unwrap(), sm);
+    }
 }
@@ -12,7 +12,7 @@ use crate::{
         err::ReadError,
         read::{BodyReader, Readable},
     },
-    packet::RxPacket,
+    packet::{Packet, RxPacket},
     test::read::SliceReader,
     types::VarByteInt,
 };
@@ -39,7 +39,12 @@ impl<R: Read> Readable<R> for FixedHeader {
     }
 }
 
-pub async fn decode_packet<'a, T: RxPacket<'a>, const N: usize, const REMAINING_LEN: usize>(
+pub async fn decode_packet<
+    'a,
+    T: RxPacket<'a> + Packet,
+    const N: usize,
+    const REMAINING_LEN: usize,
+>(
     bytes: [u8; N],
     buffer: &'a mut [u8],
 ) -> T {
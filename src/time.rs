@@ -0,0 +1,76 @@
+//! Contains the time primitives used to schedule keep alive pings.
+
+use core::{ops::Add, time::Duration};
+
+/// A point in time of a monotonic clock with millisecond resolution.
+///
+/// The epoch is defined by the [`Clock`] which created the instant, so only instants of the same
+/// clock can be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Instant(u64);
+
+impl Instant {
+    /// Creates an instant the given amount of milliseconds after the epoch of its clock.
+    #[must_use]
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(millis)
+    }
+
+    /// Returns the amount of milliseconds passed between the epoch of its clock and this instant.
+    #[must_use]
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    /// Returns the time passed since `earlier`, or zero if `earlier` is later than this instant.
+    #[must_use]
+    pub const fn saturating_duration_since(self, earlier: Self) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Self;
+
+    /// Saturates at the latest representable instant.
+    fn add(self, rhs: Duration) -> Self {
+        let millis = u64::try_from(rhs.as_millis()).unwrap_or(u64::MAX);
+        Self(self.0.saturating_add(millis))
+    }
+}
+
+/// A monotonic clock, e.g. backed by `embassy_time::Instant::now` or `std::time::Instant::now`.
+pub trait Clock {
+    /// Returns the current point in time. Must never return an instant earlier than a previously
+    /// returned one.
+    fn now(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        C::now(self)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::time::Duration;
+
+    use crate::time::Instant;
+
+    #[test]
+    fn add_saturates() {
+        let i = Instant::from_millis(1_000);
+        assert_eq!((i + Duration::from_secs(2)).as_millis(), 3_000);
+        assert_eq!((i + Duration::from_secs(u64::MAX)).as_millis(), u64::MAX);
+    }
+
+    #[test]
+    fn duration_since() {
+        let a = Instant::from_millis(1_500);
+        let b = Instant::from_millis(4_000);
+        assert_eq!(b.saturating_duration_since(a), Duration::from_millis(2_500));
+        assert_eq!(a.saturating_duration_since(b), Duration::ZERO);
+    }
+}
@@ -59,6 +59,62 @@ impl<'a> defmt::Format for MqttBinary<'a> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for MqttBinary<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_bytes())
+    }
+}
+
+/// Borrows the data from the deserializer if possible. Otherwise, the data is copied into an
+/// owned [`MqttBinary`] with the `alloc` feature and rejected without.
+#[cfg(feature = "serde")]
+impl<'de: 'b, 'b> serde::Deserialize<'de> for MqttBinary<'b> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = MqttBinary<'de>;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("at most 65535 bytes")
+            }
+
+            fn visit_borrowed_bytes<E: serde::de::Error>(
+                self,
+                v: &'de [u8],
+            ) -> Result<Self::Value, E> {
+                MqttBinary::from_slice(v).map_err(|_| E::invalid_length(v.len(), &self))
+            }
+
+            #[cfg(feature = "alloc")]
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                self.visit_byte_buf(v.into())
+            }
+
+            #[cfg(feature = "alloc")]
+            fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                let len = v.len();
+                MqttBinary::try_from(v).map_err(|_| E::invalid_length(len, &self))
+            }
+
+            #[cfg(feature = "alloc")]
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut v = Vec::new();
+                while let Some(b) = seq.next_element()? {
+                    v.push(b);
+                }
+                self.visit_byte_buf(v)
+            }
+        }
+
+        deserializer.deserialize_bytes(Visitor)
+    }
+}
+
 impl<'b> TryFrom<&'b [u8]> for MqttBinary<'b> {
     type Error = TooLargeToEncode;
 
@@ -104,6 +104,27 @@ impl TryFrom<u32> for VarByteInt {
         Self::new(value).ok_or(TooLargeToEncode)
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for VarByteInt {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for VarByteInt {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+
+        Self::new(value).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(u64::from(value)),
+                &"an integer of at most 268435455",
+            )
+        })
+    }
+}
 impl From<u16> for VarByteInt {
     fn from(value: u16) -> Self {
         Self(u32::from(value))
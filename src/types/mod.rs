@@ -7,16 +7,17 @@ mod qos;
 mod reason_code;
 mod string;
 mod topic;
+#[cfg(feature = "v5")]
 mod will;
 
-pub(crate) use topic::{NoLocalSharedSubscription, SubscriptionFilter};
+#[cfg(feature = "v5")]
 pub(crate) use will::Will;
 
 pub use binary::MqttBinary;
 pub use int::VarByteInt;
 pub use pid::PacketIdentifier;
 pub use qos::{IdentifiedQoS, QoS};
-pub use reason_code::ReasonCode;
+pub use reason_code::{ReasonCode, ReasonCodes};
 pub use string::{MqttString, MqttStringError, MqttStringPair};
 pub use topic::{TopicFilter, TopicName};
 
@@ -11,6 +11,8 @@ use crate::{
 
 /// A simple wrapper around [`NonZero`]<[`u16`]>.
 #[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct PacketIdentifier(NonZero<u16>);
 
 impl PacketIdentifier {
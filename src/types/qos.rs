@@ -6,6 +6,7 @@ use crate::{
 /// MQTT's Quality of Service levels
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QoS {
     /// Quality of Service Level 0. Reliable delivery of publications at this level is guaranteed solely
     /// by the underlying transport mechanism within a continuous network connection. Publications can
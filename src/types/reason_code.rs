@@ -1,4 +1,5 @@
 use crate::{
+    Bytes,
     eio::{Read, Write},
     io::{
         err::{ReadError, WriteError},
@@ -15,6 +16,7 @@ use crate::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReasonCode {
     /// - CONNACK: The Connection is accepted.
     /// - PUBACK: The message is accepted. Publication of the QoS 1 message proceeds.
@@ -219,57 +221,63 @@ impl ReasonCode {
     pub const fn is_erroneous(&self) -> bool {
         self.value() >= 0x80
     }
+
+    /// Converts the numeric value of a reason code into the reason code. Returns [`None`] if the
+    /// value is not a reason code.
+    pub(crate) const fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Success), // Note: This is ambiguous - context determines the specific variant
+            0x01 => Some(Self::GrantedQoS1),
+            0x02 => Some(Self::GrantedQoS2),
+            0x04 => Some(Self::DisconnectWithWillMessage),
+            0x10 => Some(Self::NoMatchingSubscribers),
+            0x11 => Some(Self::NoSubscriptionExisted),
+            0x18 => Some(Self::ContinueAuthentication),
+            0x19 => Some(Self::ReAuthenticate),
+            0x80 => Some(Self::UnspecifiedError),
+            0x81 => Some(Self::MalformedPacket),
+            0x82 => Some(Self::ProtocolError),
+            0x83 => Some(Self::ImplementationSpecificError),
+            0x84 => Some(Self::UnsupportedProtocolVersion),
+            0x85 => Some(Self::ClientIdentifierNotValid),
+            0x86 => Some(Self::BadUserNameOrPassword),
+            0x87 => Some(Self::NotAuthorized),
+            0x88 => Some(Self::ServerUnavailable),
+            0x89 => Some(Self::ServerBusy),
+            0x8A => Some(Self::Banned),
+            0x8B => Some(Self::ServerShuttingDown),
+            0x8C => Some(Self::BadAuthenticationMethod),
+            0x8D => Some(Self::KeepAliveTimeout),
+            0x8E => Some(Self::SessionTakenOver),
+            0x8F => Some(Self::TopicFilterInvalid),
+            0x90 => Some(Self::TopicNameInvalid),
+            0x91 => Some(Self::PacketIdentifierInUse),
+            0x92 => Some(Self::PacketIdentifierNotFound),
+            0x93 => Some(Self::ReceiveMaximumExceeded),
+            0x94 => Some(Self::TopicAliasInvalid),
+            0x95 => Some(Self::PacketTooLarge),
+            0x96 => Some(Self::MessageRateTooHigh),
+            0x97 => Some(Self::QuotaExceeded),
+            0x98 => Some(Self::AdministrativeAction),
+            0x99 => Some(Self::PayloadFormatInvalid),
+            0x9A => Some(Self::RetainNotSupported),
+            0x9B => Some(Self::QoSNotSupported),
+            0x9C => Some(Self::UseAnotherServer),
+            0x9D => Some(Self::ServerMoved),
+            0x9E => Some(Self::SharedSubscriptionsNotSupported),
+            0x9F => Some(Self::ConnectionRateExceeded),
+            0xA0 => Some(Self::MaximumConnectTime),
+            0xA1 => Some(Self::SubscriptionIdentifiersNotSupported),
+            0xA2 => Some(Self::WildcardSubscriptionsNotSupported),
+            _ => None,
+        }
+    }
 }
 
 impl<R: Read> Readable<R> for ReasonCode {
     async fn read(net: &mut R) -> Result<Self, ReadError<R::Error>> {
         let value = u8::read(net).await?;
-        Ok(match value {
-            0x00 => Self::Success, // Note: This is ambiguous - context determines the specific variant
-            0x01 => Self::GrantedQoS1,
-            0x02 => Self::GrantedQoS2,
-            0x04 => Self::DisconnectWithWillMessage,
-            0x10 => Self::NoMatchingSubscribers,
-            0x11 => Self::NoSubscriptionExisted,
-            0x18 => Self::ContinueAuthentication,
-            0x19 => Self::ReAuthenticate,
-            0x80 => Self::UnspecifiedError,
-            0x81 => Self::MalformedPacket,
-            0x82 => Self::ProtocolError,
-            0x83 => Self::ImplementationSpecificError,
-            0x84 => Self::UnsupportedProtocolVersion,
-            0x85 => Self::ClientIdentifierNotValid,
-            0x86 => Self::BadUserNameOrPassword,
-            0x87 => Self::NotAuthorized,
-            0x88 => Self::ServerUnavailable,
-            0x89 => Self::ServerBusy,
-            0x8A => Self::Banned,
-            0x8B => Self::ServerShuttingDown,
-            0x8C => Self::BadAuthenticationMethod,
-            0x8D => Self::KeepAliveTimeout,
-            0x8E => Self::SessionTakenOver,
-            0x8F => Self::TopicFilterInvalid,
-            0x90 => Self::TopicNameInvalid,
-            0x91 => Self::PacketIdentifierInUse,
-            0x92 => Self::PacketIdentifierNotFound,
-            0x93 => Self::ReceiveMaximumExceeded,
-            0x94 => Self::TopicAliasInvalid,
-            0x95 => Self::PacketTooLarge,
-            0x96 => Self::MessageRateTooHigh,
-            0x97 => Self::QuotaExceeded,
-            0x98 => Self::AdministrativeAction,
-            0x99 => Self::PayloadFormatInvalid,
-            0x9A => Self::RetainNotSupported,
-            0x9B => Self::QoSNotSupported,
-            0x9C => Self::UseAnotherServer,
-            0x9D => Self::ServerMoved,
-            0x9E => Self::SharedSubscriptionsNotSupported,
-            0x9F => Self::ConnectionRateExceeded,
-            0xA0 => Self::MaximumConnectTime,
-            0xA1 => Self::SubscriptionIdentifiersNotSupported,
-            0xA2 => Self::WildcardSubscriptionsNotSupported,
-            _ => return Err(ReadError::ProtocolError),
-        })
+        Self::try_from_u8(value).ok_or(ReadError::ProtocolError)
     }
 }
 
@@ -282,3 +290,75 @@ impl Writable for ReasonCode {
         self.value().write(write).await
     }
 }
+
+/// The list of reason codes in the payload of a SUBACK or UNSUBACK packet. The reason code at
+/// index `i` belongs to the `i`-th topic filter of the acknowledged SUBSCRIBE or UNSUBSCRIBE
+/// packet.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ReasonCodes<'r>(Bytes<'r>);
+
+impl<'r> ReasonCodes<'r> {
+    /// Creates reason codes from their encoded values.
+    ///
+    /// Invariant: Every byte of `bytes` is the numeric value of a [`ReasonCode`].
+    pub(crate) const fn new_unchecked(bytes: Bytes<'r>) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the number of reason codes.
+    #[inline]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no reason codes.
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the reason code at `index` or [`None`] if `index` is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<ReasonCode> {
+        self.0.get(index).copied().map(Self::decode)
+    }
+
+    /// Returns an iterator over the reason codes.
+    pub fn iter(&self) -> impl Iterator<Item = ReasonCode> + '_ {
+        self.0.iter().copied().map(Self::decode)
+    }
+
+    /// Delegates to [`Bytes::as_borrowed`].
+    #[inline]
+    #[must_use]
+    pub const fn as_borrowed(&'r self) -> Self {
+        Self(self.0.as_borrowed())
+    }
+
+    fn decode(value: u8) -> ReasonCode {
+        // Safety: ReasonCodes only contains values of reason codes.
+        unsafe { ReasonCode::try_from_u8(value).unwrap_unchecked() }
+    }
+}
+
+impl core::fmt::Debug for ReasonCodes<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ReasonCodes<'_> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "[");
+        for (i, r) in self.iter().enumerate() {
+            if i > 0 {
+                defmt::write!(fmt, ", ");
+            }
+            defmt::write!(fmt, "{}", r);
+        }
+        defmt::write!(fmt, "]");
+    }
+}
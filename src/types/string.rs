@@ -112,6 +112,46 @@ impl<'a> defmt::Format for MqttString<'a> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for MqttString<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Borrows the string from the deserializer if possible. Otherwise, the string is copied into
+/// an owned [`MqttString`] with the `alloc` feature and rejected without.
+#[cfg(feature = "serde")]
+impl<'de: 's, 's> serde::Deserialize<'de> for MqttString<'s> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = MqttString<'de>;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                f.write_str("a string of at most 65535 bytes without null characters")
+            }
+
+            fn visit_borrowed_str<E: serde::de::Error>(
+                self,
+                v: &'de str,
+            ) -> Result<Self::Value, E> {
+                MqttString::from_str(v)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+
+            #[cfg(feature = "alloc")]
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                MqttString::try_from(String::from(v))
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
 impl<'s> TryFrom<MqttBinary<'s>> for MqttString<'s> {
     type Error = MqttStringError;
 
@@ -315,11 +355,14 @@ impl<'s> MqttString<'s> {
 
 /// A name-value pair of two [`MqttString`]'s.
 #[derive(Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MqttStringPair<'s> {
     /// The name part of the string pair.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub name: MqttString<'s>,
 
     /// The value part of the string pair.
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub value: MqttString<'s>,
 }
 
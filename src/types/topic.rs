@@ -1,16 +1,6 @@
 use const_fn::const_fn;
-use heapless::Vec;
-
-use crate::{
-    client::options::{RetainHandling, SubscriptionOptions},
-    eio::Write,
-    fmt::const_debug_assert,
-    io::{
-        err::WriteError,
-        write::{Writable, wlen},
-    },
-    types::MqttString,
-};
+
+use crate::{fmt::const_debug_assert, types::MqttString};
 
 /// A topic name string for that messages can be published on according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901241>.
 /// Cannot contain wildcard characters.
@@ -103,6 +93,29 @@ impl<'t> From<TopicName<'t>> for MqttString<'t> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for TopicName<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de: 't, 't> serde::Deserialize<'de> for TopicName<'t> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = MqttString::deserialize(deserializer)?;
+
+        if Self::is_valid(&string) {
+            Ok(Self(string))
+        } else {
+            Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(string.as_str()),
+                &"a topic name",
+            ))
+        }
+    }
+}
+
 /// A topic filter string for subscribing to certain topics according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901241>.
 /// Can contain wildcard characters.
 ///
@@ -247,6 +260,58 @@ impl<'t> TopicFilter<'t> {
         false
     }
 
+    /// Returns the topic filter without the `$share/{ShareName}/` prefix if it is the topic filter
+    /// of a shared subscription.
+    #[must_use]
+    pub fn without_share_name(&self) -> &str {
+        let s = self.0.as_str();
+
+        if self.is_shared() {
+            // Invariant: The share name is followed by a '/' and a topic filter.
+            match s[7..].split_once('/') {
+                Some((_, filter)) => filter,
+                None => s,
+            }
+        } else {
+            s
+        }
+    }
+
+    /// Returns whether a publication on `topic` matches this topic filter according to
+    /// <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901241>. The
+    /// `$share/{ShareName}/` prefix of a shared subscription is ignored.
+    ///
+    /// Examples:
+    /// - "sport/tennis/#" matches "sport/tennis" and "sport/tennis/player1/ranking"
+    /// - "sport/+" matches "sport/" but neither "sport" nor "sport/tennis/player1"
+    /// - "#" and "+/monitor" do not match "$SYS/monitor", but "$SYS/#" does
+    #[must_use]
+    pub fn matches(&self, topic: &TopicName<'_>) -> bool {
+        let filter = self.without_share_name();
+        let topic = topic.as_ref().as_str();
+
+        // [MQTT-4.7.2-1]
+        // A topic filter starting with a wildcard character must not match a topic name
+        // starting with a '$' character.
+        if topic.starts_with('$') && filter.starts_with(['#', '+']) {
+            return false;
+        }
+
+        let mut filter_levels = filter.split('/');
+        let mut topic_levels = topic.split('/');
+
+        loop {
+            match (filter_levels.next(), topic_levels.next()) {
+                // The multi-level wildcard also matches the parent level.
+                (Some("#"), _) => return true,
+                (Some("+"), Some(_)) => {}
+                (Some(f), Some(t)) if f == t => {}
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
     /// Creates a new topic filter while checking for correct syntax of the topic filter string.
     /// If the filter starts with "$share", the constraints for a shared subscription's topic
     /// filter are also enforced.
@@ -298,71 +363,26 @@ impl<'t> From<TopicFilter<'t>> for MqttString<'t> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SubscriptionFilter<'t> {
-    topic: TopicFilter<'t>,
-    subscription_options: u8,
-}
-
-/// The no local flag is set for a shared subscription, which is not allowed:
-///
-/// It is a Protocol Error to set the No Local bit to 1 on a Shared Subscription [MQTT-3.8.3-4].
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct NoLocalSharedSubscription;
-
-impl<const MAX_TOPIC_FILTERS: usize> Writable for Vec<SubscriptionFilter<'_>, MAX_TOPIC_FILTERS> {
-    fn written_len(&self) -> usize {
-        self.iter()
-            .map(|t| &t.topic)
-            .map(|t| t.written_len() + wlen!(u8))
-            .sum()
-    }
-
-    async fn write<W: Write>(&self, write: &mut W) -> Result<(), WriteError<W::Error>> {
-        for t in self {
-            t.topic.write(write).await?;
-            t.subscription_options.write(write).await?;
-        }
-
-        Ok(())
+#[cfg(feature = "serde")]
+impl serde::Serialize for TopicFilter<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
     }
 }
 
-impl<'t> SubscriptionFilter<'t> {
-    pub fn new(
-        topic: TopicFilter<'t>,
-        options: &SubscriptionOptions,
-    ) -> Result<Self, NoLocalSharedSubscription> {
-        if options.no_local && topic.is_shared() {
-            return Err(NoLocalSharedSubscription);
-        }
-
-        let retain_handling_bits = match options.retain_handling {
-            RetainHandling::AlwaysSend => 0x00,
-            RetainHandling::SendIfNotSubscribedBefore => 0x10,
-            RetainHandling::NeverSend => 0x20,
-        };
+#[cfg(feature = "serde")]
+impl<'de: 't, 't> serde::Deserialize<'de> for TopicFilter<'t> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = MqttString::deserialize(deserializer)?;
 
-        let retain_as_published_bit = match options.retain_as_published {
-            true => 0x08,
-            false => 0x00,
-        };
-
-        let no_local_bit = match options.no_local {
-            true => 0x04,
-            false => 0x00,
-        };
-
-        let qos_bits = options.qos.into_bits(0);
-
-        let subscribe_options_bits =
-            retain_handling_bits | retain_as_published_bit | no_local_bit | qos_bits;
-
-        Ok(Self {
-            topic,
-            subscription_options: subscribe_options_bits,
-        })
+        if Self::is_valid(&string) {
+            Ok(Self(string))
+        } else {
+            Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(string.as_str()),
+                &"a topic filter",
+            ))
+        }
     }
 }
 
@@ -578,4 +598,94 @@ mod unit {
         assert!(assert_valid!(TopicFilter, "$share/consumer1/+/finance").has_wildcard());
         assert!(assert_valid!(TopicFilter, "$share/consumer1/#").has_wildcard());
     }
+
+    macro_rules! assert_matches {
+        ($f:literal, $t:literal) => {
+            assert!(
+                assert_valid!(TopicFilter, $f).matches(&assert_valid!(TopicName, $t)),
+                "{} should match {}",
+                $f,
+                $t
+            );
+        };
+    }
+    macro_rules! assert_not_matches {
+        ($f:literal, $t:literal) => {
+            assert!(
+                !assert_valid!(TopicFilter, $f).matches(&assert_valid!(TopicName, $t)),
+                "{} should not match {}",
+                $f,
+                $t
+            );
+        };
+    }
+
+    #[test]
+    fn topic_filter_matches() {
+        assert_matches!("sport/tennis/player1", "sport/tennis/player1");
+        assert_not_matches!("sport/tennis/player1", "sport/tennis/player2");
+        assert_not_matches!("sport/tennis", "sport/tennis/player1");
+        assert_not_matches!("sport/tennis/player1", "sport/tennis");
+        assert_not_matches!("sport", "Sport");
+
+        assert_matches!("sport/tennis/player1/#", "sport/tennis/player1");
+        assert_matches!("sport/tennis/player1/#", "sport/tennis/player1/ranking");
+        assert_matches!(
+            "sport/tennis/player1/#",
+            "sport/tennis/player1/score/wimbledon"
+        );
+        assert_matches!("sport/#", "sport");
+        assert_matches!("#", "sport/tennis");
+        assert_matches!("#", "/");
+        assert_not_matches!("sport/tennis/#", "sport/tennisplayer1");
+
+        assert_matches!("sport/tennis/+", "sport/tennis/player1");
+        assert_not_matches!("sport/tennis/+", "sport/tennis/player1/ranking");
+        assert_matches!("sport/+", "sport/");
+        assert_not_matches!("sport/+", "sport");
+        assert_matches!("+/+", "/finance");
+        assert_matches!("/+", "/finance");
+        assert_not_matches!("+", "/finance");
+        assert_matches!("+/tennis/#", "sport/tennis/player1");
+    }
+
+    #[test]
+    fn topic_filter_matches_dollar_topics() {
+        assert_not_matches!("#", "$SYS/monitor/Clients");
+        assert_not_matches!("+/monitor/Clients", "$SYS/monitor/Clients");
+        assert_matches!("$SYS/#", "$SYS/monitor/Clients");
+        assert_matches!("$SYS/monitor/+", "$SYS/monitor/Clients");
+        assert_matches!("$SYS", "$SYS");
+        assert_matches!("a/+", "a/$b");
+    }
+
+    #[test]
+    fn topic_filter_matches_shared() {
+        assert_matches!("$share/group/sport/+", "sport/tennis");
+        assert_matches!("$share/group/#", "sport/tennis");
+        assert_not_matches!("$share/group/#", "$SYS/monitor");
+        assert_not_matches!("$share/group/sport", "group/sport");
+        assert_eq!(
+            assert_valid!(TopicFilter, "$share/consumer1//finance").without_share_name(),
+            "/finance"
+        );
+        assert_eq!(
+            assert_valid!(TopicFilter, "a/b").without_share_name(),
+            "a/b"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let name: TopicName<'_> = serde_json::from_str(r#""a/b""#).unwrap();
+        assert_eq!(name.as_ref().as_str(), "a/b");
+        assert_eq!(serde_json::to_string(&name).unwrap(), r#""a/b""#);
+
+        let filter: TopicFilter<'_> = serde_json::from_str(r#""a/+""#).unwrap();
+        assert_eq!(filter.as_ref().as_str(), "a/+");
+
+        assert!(serde_json::from_str::<TopicName<'_>>(r#""a/+""#).is_err());
+        assert!(serde_json::from_str::<TopicFilter<'_>>(r#""a/#/b""#).is_err());
+    }
 }
@@ -1 +1 @@
-
+pub mod packet;
@@ -0,0 +1,140 @@
+use crate::{
+    buffer::BufferProvider,
+    eio::Read,
+    fmt::{trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::read::{BodyReader, Readable},
+    packet::{Packet, RxError, RxPacket},
+    types::ReasonCode,
+};
+
+/// The return code of an MQTT version 3.1.1 CONNACK packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConnectReturnCode {
+    Accepted = 0x00,
+    UnacceptableProtocolVersion = 0x01,
+    IdentifierRejected = 0x02,
+    ServerUnavailable = 0x03,
+    BadUserNameOrPassword = 0x04,
+    NotAuthorized = 0x05,
+}
+
+impl ConnectReturnCode {
+    const fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Self::Accepted),
+            0x01 => Some(Self::UnacceptableProtocolVersion),
+            0x02 => Some(Self::IdentifierRejected),
+            0x03 => Some(Self::ServerUnavailable),
+            0x04 => Some(Self::BadUserNameOrPassword),
+            0x05 => Some(Self::NotAuthorized),
+            _ => None,
+        }
+    }
+}
+
+/// Maps the return code to the MQTT version 5.0 CONNACK reason code with the same meaning.
+impl From<ConnectReturnCode> for ReasonCode {
+    fn from(value: ConnectReturnCode) -> Self {
+        match value {
+            ConnectReturnCode::Accepted => Self::Success,
+            ConnectReturnCode::UnacceptableProtocolVersion => Self::UnsupportedProtocolVersion,
+            ConnectReturnCode::IdentifierRejected => Self::ClientIdentifierNotValid,
+            ConnectReturnCode::ServerUnavailable => Self::ServerUnavailable,
+            ConnectReturnCode::BadUserNameOrPassword => Self::BadUserNameOrPassword,
+            ConnectReturnCode::NotAuthorized => Self::NotAuthorized,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnackPacket {
+    pub session_present: bool,
+    pub return_code: ConnectReturnCode,
+}
+
+impl Packet for ConnackPacket {
+    const PACKET_TYPE: PacketType = PacketType::Connack;
+}
+impl<'p> RxPacket<'p> for ConnackPacket {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding CONNACK packet");
+
+        if header.flags() != 0 {
+            trace!("invalid CONNACK fixed header flags: {}", header.flags());
+            return Err(RxError::MalformedPacket);
+        }
+        if header.remaining_len.size() != 2 {
+            trace!(
+                "invalid CONNACK remaining length: {}",
+                header.remaining_len.size()
+            );
+            return Err(RxError::MalformedPacket);
+        }
+        let r = &mut reader;
+
+        verbose!("reading CONNACK flags field");
+        let connack_flags = u8::read(r).await?;
+
+        verbose!("reading return code field");
+        let return_code = u8::read(r).await?;
+        let Some(return_code) = ConnectReturnCode::try_from_u8(return_code) else {
+            trace!("invalid CONNACK return code: {}", return_code);
+            return Err(RxError::MalformedPacket);
+        };
+
+        // first 7 bits have to be set to 0
+        if connack_flags & 0xFE > 0 {
+            trace!("invalid CONNACK variable header flags: {}", connack_flags);
+            return Err(RxError::ProtocolError);
+        }
+
+        let session_present = connack_flags > 0;
+
+        if session_present && return_code != ConnectReturnCode::Accepted {
+            trace!("CONNACK packet with session present flag and non-zero return code");
+            return Err(RxError::ProtocolError);
+        }
+
+        Ok(Self {
+            session_present,
+            return_code,
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use crate::{
+        test::rx::decode,
+        types::ReasonCode,
+        v3::packet::{ConnackPacket, ConnectReturnCode},
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_accepted() {
+        let packet = decode!(ConnackPacket, 2, [0x20, 0x02, 0x01, 0x00]);
+
+        assert!(packet.session_present);
+        assert_eq!(packet.return_code, ConnectReturnCode::Accepted);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_refused() {
+        let packet = decode!(ConnackPacket, 2, [0x20, 0x02, 0x00, 0x04]);
+
+        assert!(!packet.session_present);
+        assert_eq!(packet.return_code, ConnectReturnCode::BadUserNameOrPassword);
+        assert_eq!(
+            ReasonCode::from(packet.return_code),
+            ReasonCode::BadUserNameOrPassword
+        );
+    }
+}
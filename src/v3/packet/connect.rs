@@ -0,0 +1,216 @@
+use crate::{
+    config::KeepAlive,
+    eio::Write,
+    header::{FixedHeader, PacketType},
+    io::write::{Writable, wlen},
+    packet::{Packet, TxError, TxPacket},
+    types::{MqttBinary, MqttString, QoS, TopicName, VarByteInt},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConnectPacket<'p> {
+    // CONNECT connect flags (will flag is implicit due to `will` being an Option<T>)
+    will_retain: bool,
+    will_qos: QoS,
+    clean_session: bool,
+
+    // CONNECT keep alive
+    keep_alive: KeepAlive,
+
+    // CONNECT payload
+    /// Must always be in Connect Payload. Can be length 0
+    client_identifier: MqttString<'p>,
+
+    /// Will topic and will message. Has to be present if `will_flag` is set
+    will: Option<(TopicName<'p>, MqttBinary<'p>)>,
+
+    /// Has to be present if `user_name` flag is set
+    user_name: Option<MqttString<'p>>,
+    /// Has to be present if `password` flag is set
+    password: Option<MqttBinary<'p>>,
+}
+impl Packet for ConnectPacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Connect;
+}
+impl TxPacket for ConnectPacket<'_> {
+    fn remaining_len(&self) -> VarByteInt {
+        let variable_header_length = wlen!([u8; 7]) + wlen!(u8) + wlen!(u16);
+
+        let will_length = self
+            .will
+            .as_ref()
+            .map(|(t, m)| t.written_len() + m.written_len())
+            .unwrap_or_default();
+
+        let body_length = self.client_identifier.written_len()
+            + will_length
+            + self.user_name.written_len()
+            + self.password.written_len();
+
+        let total_length = variable_header_length + body_length;
+
+        // max length: 327695
+        // Invariant: max length <= VarByteInt::MAX_ENCODABLE
+        //
+        // variable header: 10
+        // client identifier: 65537
+        // will topic: 65537
+        // will message: 65537
+        // username: 65537
+        // password: 65537
+        VarByteInt::new_unchecked(total_length as u32)
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, 0x00, self.remaining_len())
+            .write(write)
+            .await?;
+
+        let protocol_name = [0, 4, b'M', b'Q', b'T', b'T', 4];
+        let connect_flags = (u8::from(self.user_name.is_some()) << 7)
+            | (u8::from(self.password.is_some()) << 6)
+            | (u8::from(self.will_retain) << 5)
+            | self.will_qos.into_bits(3)
+            | (u8::from(self.will.is_some()) << 2)
+            | (u8::from(self.clean_session) << 1);
+
+        protocol_name.write(write).await?;
+        connect_flags.write(write).await?;
+        self.keep_alive.as_u16().write(write).await?;
+
+        self.client_identifier.write(write).await?;
+        if let Some((ref will_topic, ref will_message)) = self.will {
+            will_topic.write(write).await?;
+            will_message.write(write).await?;
+        }
+        self.user_name.write(write).await?;
+        self.password.write(write).await?;
+
+        Ok(())
+    }
+}
+
+impl<'p> ConnectPacket<'p> {
+    pub fn new(
+        client_identifier: MqttString<'p>,
+        clean_session: bool,
+        keep_alive: KeepAlive,
+    ) -> Self {
+        Self {
+            will_retain: false,
+            will_qos: QoS::AtMostOnce,
+            clean_session,
+            keep_alive,
+            client_identifier,
+            will: None,
+            user_name: None,
+            password: None,
+        }
+    }
+
+    pub fn add_user_name(&mut self, user_name: MqttString<'p>) {
+        self.user_name = Some(user_name);
+    }
+    pub fn add_password(&mut self, password: MqttBinary<'p>) {
+        self.password = Some(password);
+    }
+
+    pub fn add_will(
+        &mut self,
+        will_topic: TopicName<'p>,
+        will_message: MqttBinary<'p>,
+        will_qos: QoS,
+        will_retain: bool,
+    ) {
+        self.will_retain = will_retain;
+        self.will_qos = will_qos;
+        self.will = Some((will_topic, will_message));
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{
+        config::KeepAlive,
+        test::tx::encode,
+        types::{MqttBinary, MqttString, QoS, TopicName},
+        v3::packet::ConnectPacket,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_simple() {
+        let packet = ConnectPacket::new(
+            MqttString::try_from("a").unwrap(),
+            true,
+            KeepAlive::Seconds(NonZero::new(7439).unwrap()),
+        );
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0x10,       //
+            0x0D,       // remaining length
+            0x00,       // ---
+            0x04,       //
+            b'M',       //
+            b'Q',       //
+            b'T',       //
+            b'T',       // ---
+            0x04,       // Protocol level
+            0b00000010, // Connect flags
+            0x1D,       // Keep alive MSB
+            0x0F,       // Keep alive LSB
+            0x00,       // Client identifier len MSB
+            0x01,       // Client identifier len LSB
+            b'a',       // Client identifier
+        ]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_payload() {
+        let mut packet = ConnectPacket::new(
+            MqttString::try_from("giuqen").unwrap(),
+            false,
+            KeepAlive::Infinite,
+        );
+
+        packet.add_user_name(MqttString::try_from("Franz").unwrap());
+        packet.add_password(MqttBinary::try_from("pw".as_bytes()).unwrap());
+        packet.add_will(
+            TopicName::new(MqttString::try_from("w/t").unwrap()).unwrap(),
+            MqttBinary::try_from("bye".as_bytes()).unwrap(),
+            QoS::ExactlyOnce,
+            true,
+        );
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0x10,       //
+            0x27,       // remaining length
+            0x00,       // ---
+            0x04,       //
+            b'M',       //
+            b'Q',       //
+            b'T',       //
+            b'T',       // ---
+            0x04,       // Protocol level
+            0b11110100, // Connect flags
+            0x00,       // Keep alive MSB
+            0x00,       // Keep alive LSB
+            // Client identifier
+            0x00, 0x06, b'g', b'i', b'u', b'q', b'e', b'n',
+            // Will topic
+            0x00, 0x03, b'w', b'/', b't',
+            // Will message
+            0x00, 0x03, b'b', b'y', b'e',
+            // User name
+            0x00, 0x05, b'F', b'r', b'a', b'n', b'z',
+            // Password
+            0x00, 0x02, b'p', b'w',
+        ]);
+    }
+}
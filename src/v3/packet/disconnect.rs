@@ -0,0 +1,42 @@
+use crate::{
+    eio::Write,
+    header::{FixedHeader, PacketType},
+    io::write::Writable,
+    packet::{Packet, TxError, TxPacket},
+    types::VarByteInt,
+};
+
+/// The MQTT version 3.1.1 DISCONNECT packet only consists of its fixed header. It is never sent by
+/// the server.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisconnectPacket;
+
+impl Packet for DisconnectPacket {
+    const PACKET_TYPE: PacketType = PacketType::Disconnect;
+}
+impl TxPacket for DisconnectPacket {
+    fn remaining_len(&self) -> VarByteInt {
+        // Invariant: 0 <= VarByteInt::MAX_ENCODABLE
+        VarByteInt::new_unchecked(0)
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, 0x00, self.remaining_len())
+            .write(write)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use crate::{test::tx::encode, v3::packet::DisconnectPacket};
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode() {
+        encode!(DisconnectPacket, [0xE0, 0x00]);
+    }
+}
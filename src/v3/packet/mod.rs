@@ -0,0 +1,25 @@
+//! MQTT version 3.1.1 packets. Compared to MQTT version 5.0, they contain neither properties nor
+//! reason codes other than the CONNACK return code and the SUBACK return codes.
+
+mod connack;
+mod connect;
+mod disconnect;
+mod pubacks;
+mod publish;
+mod suback;
+mod subscribe;
+mod unsuback;
+mod unsubscribe;
+
+pub use connack::{ConnackPacket, ConnectReturnCode};
+pub use connect::ConnectPacket;
+pub use disconnect::DisconnectPacket;
+pub use pubacks::{PubackPacket, PubcompPacket, PubrecPacket, PubrelPacket};
+pub use publish::PublishPacket;
+pub use suback::SubackPacket;
+pub use subscribe::SubscribePacket;
+pub use unsuback::UnsubackPacket;
+pub use unsubscribe::UnsubscribePacket;
+
+// PINGREQ and PINGRESP are encoded identically in both protocol versions.
+pub use crate::packet::{PingreqPacket, PingrespPacket};
@@ -0,0 +1,238 @@
+//! Because PUBACK, PUBREC, PUBREL and PUBCOMP are identical except for their packet type and
+//! fixed header flags, a single generic structure is used for all of them.
+
+use core::marker::PhantomData;
+
+use crate::{
+    buffer::BufferProvider,
+    eio::{Read, Write},
+    fmt::{trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::{
+        read::{BodyReader, Readable},
+        write::Writable,
+    },
+    packet::{Packet, RxError, RxPacket, TxError, TxPacket},
+    types::{PacketIdentifier, VarByteInt},
+    v3::packet::pubacks::types::{Ack, Comp, PubackPacketType, Rec, Rel},
+};
+
+mod types;
+
+pub type PubackPacket = GenericPubackPacket<Ack>;
+pub type PubrecPacket = GenericPubackPacket<Rec>;
+pub type PubrelPacket = GenericPubackPacket<Rel>;
+pub type PubcompPacket = GenericPubackPacket<Comp>;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GenericPubackPacket<T> {
+    pub packet_identifier: PacketIdentifier,
+    _phantom_data: PhantomData<T>,
+}
+
+impl<T: PubackPacketType> Packet for GenericPubackPacket<T> {
+    const PACKET_TYPE: PacketType = T::PACKET_TYPE;
+}
+impl<'p, T: PubackPacketType> RxPacket<'p> for GenericPubackPacket<T> {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding {:?} packet", T::PACKET_TYPE);
+
+        if header.flags() != T::FLAGS {
+            trace!(
+                "invalid {:?} fixed header flags: {}",
+                T::PACKET_TYPE,
+                header.flags()
+            );
+            return Err(RxError::MalformedPacket);
+        }
+        if header.remaining_len.size() != 2 {
+            trace!(
+                "invalid {:?} remaining length: {}",
+                T::PACKET_TYPE,
+                header.remaining_len.size()
+            );
+            return Err(RxError::MalformedPacket);
+        }
+
+        verbose!("reading packet identifier field");
+        let packet_identifier = PacketIdentifier::read(&mut reader).await?;
+
+        Ok(Self::new(packet_identifier))
+    }
+}
+impl<T: PubackPacketType> TxPacket for GenericPubackPacket<T> {
+    fn remaining_len(&self) -> VarByteInt {
+        // Invariant: 2 <= VarByteInt::MAX_ENCODABLE
+        VarByteInt::new_unchecked(self.packet_identifier.written_len() as u32)
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, T::FLAGS, self.remaining_len())
+            .write(write)
+            .await?;
+
+        self.packet_identifier.write(write).await?;
+
+        Ok(())
+    }
+}
+
+impl<T: PubackPacketType> GenericPubackPacket<T> {
+    pub const fn new(packet_identifier: PacketIdentifier) -> Self {
+        Self {
+            packet_identifier,
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    mod ack {
+        use core::num::NonZero;
+
+        use crate::{
+            test::{rx::decode, tx::encode},
+            types::PacketIdentifier,
+            v3::packet::PubackPacket,
+        };
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn encode_simple() {
+            #[rustfmt::skip]
+            encode!(
+                PubackPacket::new(PacketIdentifier::new(NonZero::new(7439).unwrap())),
+                [
+                    0x40,
+                    0x02,
+                    0x1D, // Packet identifier MSB
+                    0x0F, // Packet identifier LSB
+                ]
+            );
+        }
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn decode_simple() {
+            let packet = decode!(PubackPacket, 2, [0x40, 0x02, 0x26, 0x29]);
+
+            assert_eq!(
+                packet.packet_identifier,
+                PacketIdentifier::new(NonZero::new(9769).unwrap())
+            );
+        }
+    }
+
+    mod rec {
+        use core::num::NonZero;
+
+        use crate::{
+            test::{rx::decode, tx::encode},
+            types::PacketIdentifier,
+            v3::packet::PubrecPacket,
+        };
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn encode_simple() {
+            #[rustfmt::skip]
+            encode!(
+                PubrecPacket::new(PacketIdentifier::new(NonZero::new(1).unwrap())),
+                [
+                    0x50,
+                    0x02,
+                    0x00, // Packet identifier MSB
+                    0x01, // Packet identifier LSB
+                ]
+            );
+        }
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn decode_simple() {
+            let packet = decode!(PubrecPacket, 2, [0x50, 0x02, 0xFF, 0xFF]);
+
+            assert_eq!(
+                packet.packet_identifier,
+                PacketIdentifier::new(NonZero::new(65535).unwrap())
+            );
+        }
+    }
+
+    mod rel {
+        use core::num::NonZero;
+
+        use crate::{
+            test::{rx::decode, tx::encode},
+            types::PacketIdentifier,
+            v3::packet::PubrelPacket,
+        };
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn encode_simple() {
+            #[rustfmt::skip]
+            encode!(
+                PubrelPacket::new(PacketIdentifier::new(NonZero::new(23197).unwrap())),
+                [
+                    0x62,
+                    0x02,
+                    0x5A, // Packet identifier MSB
+                    0x9D, // Packet identifier LSB
+                ]
+            );
+        }
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn decode_simple() {
+            let packet = decode!(PubrelPacket, 2, [0x62, 0x02, 0x5A, 0x9D]);
+
+            assert_eq!(
+                packet.packet_identifier,
+                PacketIdentifier::new(NonZero::new(23197).unwrap())
+            );
+        }
+    }
+
+    mod comp {
+        use core::num::NonZero;
+
+        use crate::{
+            test::{rx::decode, tx::encode},
+            types::PacketIdentifier,
+            v3::packet::PubcompPacket,
+        };
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn encode_simple() {
+            #[rustfmt::skip]
+            encode!(
+                PubcompPacket::new(PacketIdentifier::new(NonZero::new(258).unwrap())),
+                [
+                    0x70,
+                    0x02,
+                    0x01, // Packet identifier MSB
+                    0x02, // Packet identifier LSB
+                ]
+            );
+        }
+
+        #[tokio::test]
+        #[test_log::test]
+        async fn decode_simple() {
+            let packet = decode!(PubcompPacket, 2, [0x70, 0x02, 0x01, 0x02]);
+
+            assert_eq!(
+                packet.packet_identifier,
+                PacketIdentifier::new(NonZero::new(258).unwrap())
+            );
+        }
+    }
+}
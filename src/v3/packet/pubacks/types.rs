@@ -0,0 +1,39 @@
+use crate::header::PacketType;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Ack;
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rec;
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rel;
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Comp;
+
+pub trait PubackPacketType {
+    const PACKET_TYPE: PacketType;
+    const FLAGS: u8;
+}
+
+impl PubackPacketType for Ack {
+    const PACKET_TYPE: PacketType = PacketType::Puback;
+    const FLAGS: u8 = 0x00;
+}
+
+impl PubackPacketType for Rec {
+    const PACKET_TYPE: PacketType = PacketType::Pubrec;
+    const FLAGS: u8 = 0x00;
+}
+
+impl PubackPacketType for Rel {
+    const PACKET_TYPE: PacketType = PacketType::Pubrel;
+    const FLAGS: u8 = 0x02;
+}
+
+impl PubackPacketType for Comp {
+    const PACKET_TYPE: PacketType = PacketType::Pubcomp;
+    const FLAGS: u8 = 0x00;
+}
@@ -0,0 +1,245 @@
+use crate::{
+    buffer::BufferProvider,
+    bytes::Bytes,
+    eio::{Read, Write},
+    fmt::{trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::{
+        read::{BodyReader, Readable, Store},
+        write::Writable,
+    },
+    packet::{Packet, RxError, RxPacket, TxError, TxPacket},
+    types::{IdentifiedQoS, PacketIdentifier, QoS, TooLargeToEncode, TopicName, VarByteInt},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PublishPacket<'p> {
+    pub dup: bool,
+    pub identified_qos: IdentifiedQoS,
+    pub retain: bool,
+
+    pub topic: TopicName<'p>,
+    pub message: Bytes<'p>,
+}
+
+impl Packet for PublishPacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Publish;
+}
+impl<'p> RxPacket<'p> for PublishPacket<'p> {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding PUBLISH packet");
+
+        let flags = header.flags();
+
+        verbose!("decoding PUBLISH flags");
+        let dup = flags >> 3 == 1;
+        let qos = QoS::try_from_bits((flags >> 1) & 0x03).ok_or(RxError::MalformedPacket)?;
+        let retain = flags & 0x01 == 1;
+
+        let r = &mut reader;
+
+        // Topic aliases don't exist in MQTT version 3.1.1, the topic name must always be present.
+        verbose!("reading topic name field");
+        let topic = TopicName::read(r).await?;
+
+        let identified_qos = match qos {
+            QoS::AtMostOnce => IdentifiedQoS::AtMostOnce,
+            QoS::AtLeastOnce => {
+                verbose!("reading packet identifier field");
+                IdentifiedQoS::AtLeastOnce(PacketIdentifier::read(r).await?)
+            }
+            QoS::ExactlyOnce => {
+                verbose!("reading packet identifier field");
+                IdentifiedQoS::ExactlyOnce(PacketIdentifier::read(r).await?)
+            }
+        };
+
+        let message_len = r.remaining_len();
+
+        verbose!("reading PUBLISH payload ({} bytes)", message_len);
+
+        let message = r.read_and_store(message_len).await?;
+
+        Ok(Self {
+            dup,
+            identified_qos,
+            retain,
+            topic,
+            message,
+        })
+    }
+}
+impl TxPacket for PublishPacket<'_> {
+    fn remaining_len(&self) -> VarByteInt {
+        // Safety: PUBLISH packets that are too long to encode cannot be created
+        unsafe { self.remaining_len_raw().unwrap_unchecked() }
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        let qos: QoS = self.identified_qos.into();
+        let flags = (u8::from(self.dup) << 3) | qos.into_bits(1) | u8::from(self.retain);
+
+        FixedHeader::new(Self::PACKET_TYPE, flags, self.remaining_len())
+            .write(write)
+            .await?;
+
+        self.topic.write(write).await?;
+
+        if let Some(p) = self.identified_qos.packet_identifier() {
+            p.write(write).await?;
+        }
+
+        self.message.write(write).await?;
+
+        Ok(())
+    }
+}
+
+impl<'p> PublishPacket<'p> {
+    pub fn new(
+        dup: bool,
+        identified_qos: IdentifiedQoS,
+        retain: bool,
+        topic: TopicName<'p>,
+        message: Bytes<'p>,
+    ) -> Result<Self, TooLargeToEncode> {
+        let p = Self {
+            dup,
+            identified_qos,
+            retain,
+            topic,
+            message,
+        };
+
+        p.remaining_len_raw().map(|_| p)
+    }
+
+    fn remaining_len_raw(&self) -> Result<VarByteInt, TooLargeToEncode> {
+        let variable_header_length = self.topic.written_len()
+            + self
+                .identified_qos
+                .packet_identifier()
+                .as_ref()
+                .map(Writable::written_len)
+                .unwrap_or_default();
+
+        let total_length = variable_header_length + self.message.len();
+
+        // max length = 65539 + MAX_MESSAGE_LENGTH
+        //
+        // topic name: 65537
+        // packet identifier: 2
+        // message: MAX_MESSAGE_LENGTH
+        VarByteInt::try_from(total_length as u32)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{
+        bytes::Bytes,
+        test::{rx::decode, tx::encode},
+        types::{IdentifiedQoS, MqttString, PacketIdentifier, TopicName},
+        v3::packet::PublishPacket,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_simple() {
+        let packet = PublishPacket::new(
+            false,
+            IdentifiedQoS::AtLeastOnce(PacketIdentifier::new(NonZero::new(5897).unwrap())),
+            false,
+            TopicName::new(MqttString::try_from("test/topic").unwrap()).unwrap(),
+            Bytes::from("hello".as_bytes()),
+        )
+        .unwrap();
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0x32,
+            0x13,
+            0x00, // Topic Name
+            0x0A, //
+            b't', //
+            b'e', //
+            b's', //
+            b't', //
+            b'/', //
+            b't', //
+            b'o', //
+            b'p', //
+            b'i', //
+            b'c', // Topic Name
+            0x17, // Packet identifier
+            0x09, // Packet identifier
+            b'h', // Payload
+            b'e', //
+            b'l', //
+            b'l', //
+            b'o', // Payload
+        ]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_qos0_retain() {
+        let packet = PublishPacket::new(
+            true,
+            IdentifiedQoS::AtMostOnce,
+            true,
+            TopicName::new(MqttString::try_from("a").unwrap()).unwrap(),
+            Bytes::from("hi".as_bytes()),
+        )
+        .unwrap();
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0x39,
+            0x05,
+            0x00, // Topic Name
+            0x01, //
+            b'a', // Topic Name
+            b'h', // Payload
+            b'i', // Payload
+        ]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_simple() {
+        #[rustfmt::skip]
+        let packet = decode!(PublishPacket, 10, [
+            0x34,
+            0x0A,
+            0x00, // Topic Name
+            0x03, //
+            b'a', //
+            b'/', //
+            b'b', // Topic Name
+            0x00, // Packet identifier
+            0x2A, // Packet identifier
+            b'x', // Payload
+            b'y', //
+            b'z', // Payload
+        ]);
+
+        assert!(!packet.dup);
+        assert!(!packet.retain);
+        assert_eq!(
+            packet.identified_qos,
+            IdentifiedQoS::ExactlyOnce(PacketIdentifier::new(NonZero::new(42).unwrap()))
+        );
+        assert_eq!(
+            packet.topic,
+            TopicName::new(MqttString::try_from("a/b").unwrap()).unwrap()
+        );
+        assert_eq!(packet.message, Bytes::from("xyz".as_bytes()));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    buffer::BufferProvider,
+    eio::Read,
+    fmt::{trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::read::{BodyReader, Readable, Store},
+    packet::{Packet, RxError, RxPacket},
+    types::{PacketIdentifier, ReasonCodes},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SubackPacket<'p> {
+    pub packet_identifier: PacketIdentifier,
+
+    /// The return codes of MQTT version 3.1.1 share their values with the reason codes
+    /// [`ReasonCode::Success`], [`ReasonCode::GrantedQoS1`], [`ReasonCode::GrantedQoS2`] and
+    /// [`ReasonCode::UnspecifiedError`] (failure).
+    ///
+    /// [`ReasonCode::Success`]: crate::types::ReasonCode::Success
+    /// [`ReasonCode::GrantedQoS1`]: crate::types::ReasonCode::GrantedQoS1
+    /// [`ReasonCode::GrantedQoS2`]: crate::types::ReasonCode::GrantedQoS2
+    /// [`ReasonCode::UnspecifiedError`]: crate::types::ReasonCode::UnspecifiedError
+    pub reason_codes: ReasonCodes<'p>,
+}
+
+impl Packet for SubackPacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Suback;
+}
+impl<'p> RxPacket<'p> for SubackPacket<'p> {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding SUBACK packet");
+
+        if header.flags() != 0 {
+            trace!("invalid SUBACK fixed header flags: {}", header.flags());
+            return Err(RxError::MalformedPacket);
+        }
+        let r = &mut reader;
+
+        verbose!("reading packet identifier field");
+        let packet_identifier = PacketIdentifier::read(r).await?;
+
+        if r.remaining_len() == 0 {
+            trace!("SUBACK packet does not contain a return code");
+            return Err(RxError::ProtocolError);
+        }
+
+        verbose!("reading return code fields");
+        let reason_codes = r.read_and_store(r.remaining_len()).await?;
+
+        for &value in reason_codes.iter() {
+            if !matches!(value, 0x00 | 0x01 | 0x02 | 0x80) {
+                trace!("invalid SUBACK return code value: {}", value);
+                return Err(RxError::ProtocolError);
+            }
+        }
+
+        // Invariant: All allowed return codes are values of reason codes.
+        let reason_codes = ReasonCodes::new_unchecked(reason_codes);
+
+        Ok(Self {
+            packet_identifier,
+            reason_codes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use heapless::Vec;
+
+    use crate::{
+        test::rx::decode,
+        types::{PacketIdentifier, ReasonCode},
+        v3::packet::SubackPacket,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_payload() {
+        #[rustfmt::skip]
+        let packet = decode!(
+            SubackPacket,
+            6,
+            [
+                0x90,
+                0x06,
+
+                0x17, 0x89,
+
+                // Return codes
+                0x00, 0x01, 0x02, 0x80,
+            ]
+        );
+
+        assert_eq!(
+            packet.packet_identifier,
+            PacketIdentifier::new(NonZero::new(6025).unwrap())
+        );
+
+        let reason_codes: Vec<_, 4> = [
+            ReasonCode::Success,
+            ReasonCode::GrantedQoS1,
+            ReasonCode::GrantedQoS2,
+            ReasonCode::UnspecifiedError,
+        ]
+        .into();
+        assert!(packet.reason_codes.iter().eq(reason_codes));
+    }
+}
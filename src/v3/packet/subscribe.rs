@@ -0,0 +1,144 @@
+use crate::{
+    client::options::FilterOptions,
+    eio::Write,
+    header::{FixedHeader, PacketType},
+    io::write::{Writable, wlen},
+    packet::{Packet, TxError, TxPacket},
+    types::{PacketIdentifier, TooLargeToEncode, TopicFilter, VarByteInt},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SubscribePacket<'p> {
+    packet_identifier: PacketIdentifier,
+
+    /// The topic filters and their subscription options. Only the quality of service of the
+    /// options is sent, the other options don't exist in MQTT version 3.1.1.
+    subscribe_filters: &'p [(TopicFilter<'p>, FilterOptions)],
+}
+
+impl Packet for SubscribePacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Subscribe;
+}
+impl TxPacket for SubscribePacket<'_> {
+    fn remaining_len(&self) -> VarByteInt {
+        // Safety: SUBSCRIBE packets that are too long to encode cannot be created
+        unsafe { self.remaining_len_raw().unwrap_unchecked() }
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, 0x02, self.remaining_len())
+            .write(write)
+            .await?;
+
+        self.packet_identifier.write(write).await?;
+
+        for (t, o) in self.subscribe_filters {
+            t.write(write).await?;
+            o.qos.into_bits(0).write(write).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'p> SubscribePacket<'p> {
+    /// If the amount of topic filters is less than or equal to 4095, it is guaranteed that
+    /// `TooLargeToEncode` is never returned.
+    pub fn new(
+        packet_identifier: PacketIdentifier,
+        subscribe_filters: &'p [(TopicFilter<'p>, FilterOptions)],
+    ) -> Result<Self, TooLargeToEncode> {
+        let p = Self {
+            packet_identifier,
+            subscribe_filters,
+        };
+
+        // Refer to `SubscribePacket::remaining_len_raw` as to why this is true.
+        if p.subscribe_filters.len() <= 4095 {
+            Ok(p)
+        } else {
+            p.remaining_len_raw().map(|_| p)
+        }
+    }
+
+    fn remaining_len_raw(&self) -> Result<VarByteInt, TooLargeToEncode> {
+        let variable_header_length = self.packet_identifier.written_len();
+
+        let body_length: usize = self
+            .subscribe_filters
+            .iter()
+            .map(|(t, _)| t.written_len() + wlen!(u8))
+            .sum();
+
+        let total_length = variable_header_length + body_length;
+
+        // max length = topic filters * 65538 + 2
+        // Invariant: topic filters <= 4095 => max length <= VarByteInt::MAX_ENCODABLE
+        //
+        // packet identifier: 2
+        // topic filters: topic filters * 65538
+        VarByteInt::try_from(total_length as u32)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{
+        client::options::{FilterOptions, RetainHandling},
+        test::tx::encode,
+        types::{MqttString, PacketIdentifier, TopicFilter},
+        v3::packet::SubscribePacket,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_payload() {
+        let topics = [
+            (
+                TopicFilter::new(MqttString::try_from("test/hello").unwrap()).unwrap(),
+                FilterOptions::new().no_local(),
+            ),
+            (
+                TopicFilter::new(MqttString::try_from("a/#").unwrap()).unwrap(),
+                FilterOptions::new()
+                    .retain_handling(RetainHandling::NeverSend)
+                    .retain_as_published()
+                    .exactly_once(),
+            ),
+        ];
+        let packet =
+            SubscribePacket::new(PacketIdentifier::new(NonZero::new(23197).unwrap()), &topics)
+                .unwrap();
+
+        #[rustfmt::skip]
+        encode!(packet, [
+                0x82, //
+                0x15, // remaining length
+                0x5A, // Packet identifier MSB
+                0x9D, // Packet identifier LSB
+                0x00, // Payload - Topic Filter
+                0x0A, // |
+                b't', // |
+                b'e', // |
+                b's', // |
+                b't', // |
+                b'/', // |
+                b'h', // |
+                b'e', // |
+                b'l', // |
+                b'l', // |
+                b'o', // Payload - Topic Filter
+                0x00, // Payload - Requested QoS
+                0x00, // Payload - Topic Filter
+                0x03, // |
+                b'a', // |
+                b'/', // |
+                b'#', // Payload - Topic Filter
+                0x02, // Payload - Requested QoS
+            ]
+        );
+    }
+}
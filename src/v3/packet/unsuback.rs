@@ -0,0 +1,62 @@
+use crate::{
+    buffer::BufferProvider,
+    eio::Read,
+    fmt::{trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::read::{BodyReader, Readable},
+    packet::{Packet, RxError, RxPacket},
+    types::PacketIdentifier,
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnsubackPacket {
+    pub packet_identifier: PacketIdentifier,
+}
+
+impl Packet for UnsubackPacket {
+    const PACKET_TYPE: PacketType = PacketType::Unsuback;
+}
+impl<'p> RxPacket<'p> for UnsubackPacket {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding UNSUBACK packet");
+
+        if header.flags() != 0 {
+            trace!("invalid UNSUBACK fixed header flags: {}", header.flags());
+            return Err(RxError::MalformedPacket);
+        }
+        if header.remaining_len.size() != 2 {
+            trace!(
+                "invalid UNSUBACK remaining length: {}",
+                header.remaining_len.size()
+            );
+            return Err(RxError::MalformedPacket);
+        }
+
+        verbose!("reading packet identifier field");
+        let packet_identifier = PacketIdentifier::read(&mut reader).await?;
+
+        Ok(Self { packet_identifier })
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{test::rx::decode, types::PacketIdentifier, v3::packet::UnsubackPacket};
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_simple() {
+        let packet = decode!(UnsubackPacket, 2, [0xB0, 0x02, 0x5A, 0x9D]);
+
+        assert_eq!(
+            packet.packet_identifier,
+            PacketIdentifier::new(NonZero::new(23197).unwrap())
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    eio::Write,
+    header::{FixedHeader, PacketType},
+    io::write::Writable,
+    packet::{Packet, TxError, TxPacket},
+    types::{PacketIdentifier, TooLargeToEncode, TopicFilter, VarByteInt},
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnsubscribePacket<'p> {
+    packet_identifier: PacketIdentifier,
+
+    topic_filters: &'p [TopicFilter<'p>],
+}
+
+impl Packet for UnsubscribePacket<'_> {
+    const PACKET_TYPE: PacketType = PacketType::Unsubscribe;
+}
+impl TxPacket for UnsubscribePacket<'_> {
+    fn remaining_len(&self) -> VarByteInt {
+        // Safety: UNSUBSCRIBE packets that are too long to encode cannot be created
+        unsafe { self.remaining_len_raw().unwrap_unchecked() }
+    }
+
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, 0x02, self.remaining_len())
+            .write(write)
+            .await?;
+
+        self.packet_identifier.write(write).await?;
+
+        for t in self.topic_filters {
+            t.write(write).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'p> UnsubscribePacket<'p> {
+    /// If the amount of topic filters is less than or equal to 4095, it is guaranteed that
+    /// `TooLargeToEncode` is never returned.
+    pub fn new(
+        packet_identifier: PacketIdentifier,
+        topic_filters: &'p [TopicFilter<'p>],
+    ) -> Result<Self, TooLargeToEncode> {
+        let p = Self {
+            packet_identifier,
+            topic_filters,
+        };
+
+        // Refer to `UnsubscribePacket::remaining_len_raw` as to why this is true.
+        if p.topic_filters.len() <= 4095 {
+            Ok(p)
+        } else {
+            p.remaining_len_raw().map(|_| p)
+        }
+    }
+
+    fn remaining_len_raw(&self) -> Result<VarByteInt, TooLargeToEncode> {
+        let variable_header_length = self.packet_identifier.written_len();
+
+        let body_length: usize = self.topic_filters.iter().map(Writable::written_len).sum();
+
+        let total_length = variable_header_length + body_length;
+
+        // max length = topic filters * 65537 + 2
+        // Invariant: topic filters <= 4095 => max length <= VarByteInt::MAX_ENCODABLE
+        //
+        // packet identifier: 2
+        // topic filters: topic filters * 65537
+        VarByteInt::try_from(total_length as u32)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use core::num::NonZero;
+
+    use crate::{
+        test::tx::encode,
+        types::{MqttString, PacketIdentifier, TopicFilter},
+        v3::packet::UnsubscribePacket,
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_payload() {
+        let topics = [
+            TopicFilter::new(MqttString::try_from("test/hello").unwrap()).unwrap(),
+            TopicFilter::new(MqttString::try_from("a/+").unwrap()).unwrap(),
+        ];
+        let packet =
+            UnsubscribePacket::new(PacketIdentifier::new(NonZero::new(1287).unwrap()), &topics)
+                .unwrap();
+
+        #[rustfmt::skip]
+        encode!(packet, [
+                0xA2, //
+                0x13, // remaining length
+                0x05, // Packet identifier MSB
+                0x07, // Packet identifier LSB
+                0x00, // Payload - Topic Filter
+                0x0A, // |
+                b't', // |
+                b'e', // |
+                b's', // |
+                b't', // |
+                b'/', // |
+                b'h', // |
+                b'e', // |
+                b'l', // |
+                b'l', // |
+                b'o', // Payload - Topic Filter
+                0x00, // Payload - Topic Filter
+                0x03, // |
+                b'a', // |
+                b'/', // |
+                b'+', // Payload - Topic Filter
+            ]
+        );
+    }
+}
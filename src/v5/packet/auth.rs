@@ -0,0 +1,379 @@
+use heapless::Vec;
+
+use crate::{
+    buffer::BufferProvider,
+    eio::{Read, Write},
+    fmt::{const_assert, trace, verbose},
+    header::{FixedHeader, PacketType},
+    io::{
+        read::{BodyReader, Readable},
+        write::{Writable, wlen},
+    },
+    packet::{Packet, RxError, RxPacket, TxError, TxPacket},
+    types::{ReasonCode, VarByteInt},
+    v5::property::{
+        AtMostOnceProperty, AuthenticationData, AuthenticationMethod, PropertyType, ReasonString,
+        UserProperty,
+    },
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AuthPacket<'p, const MAX_USER_PROPERTIES: usize> {
+    pub reason_code: ReasonCode,
+
+    /// Only [`None`] if the packet was received with a remaining length of 0
+    pub authentication_method: Option<AuthenticationMethod<'p>>,
+    pub authentication_data: Option<AuthenticationData<'p>>,
+    pub reason_string: Option<ReasonString<'p>>,
+    pub user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
+}
+
+impl<const MAX_USER_PROPERTIES: usize> Packet for AuthPacket<'_, MAX_USER_PROPERTIES> {
+    const PACKET_TYPE: PacketType = PacketType::Auth;
+}
+impl<'p, const MAX_USER_PROPERTIES: usize> RxPacket<'p> for AuthPacket<'p, MAX_USER_PROPERTIES> {
+    async fn receive<R: Read, B: BufferProvider<'p>>(
+        header: &FixedHeader,
+        mut reader: BodyReader<'_, 'p, R, B>,
+    ) -> Result<Self, RxError<R::Error, B::ProvisionError>> {
+        trace!("decoding AUTH packet");
+
+        if header.flags() != 0 {
+            trace!("invalid AUTH fixed header flags: {}", header.flags());
+            return Err(RxError::MalformedPacket);
+        }
+
+        let r = &mut reader;
+
+        if header.remaining_len.size() == 0 {
+            verbose!("received minimal AUTH packet");
+
+            return Ok(Self {
+                reason_code: ReasonCode::Success,
+                authentication_method: None,
+                authentication_data: None,
+                reason_string: None,
+                user_properties: Vec::new(),
+            });
+        }
+
+        verbose!("reading reason code field");
+        let auth_reason_code = ReasonCode::read(r).await?;
+
+        if !matches!(
+            auth_reason_code,
+            ReasonCode::Success | ReasonCode::ContinueAuthentication | ReasonCode::ReAuthenticate
+        ) {
+            trace!("invalid AUTH reason code: {:?}", auth_reason_code);
+            return Err(RxError::ProtocolError);
+        }
+
+        // The property length can only be omitted together with the reason code.
+        verbose!("reading property length field");
+        let properties_length = VarByteInt::read(r).await?.size();
+
+        verbose!("property length: {} bytes", properties_length);
+
+        if r.remaining_len() != properties_length {
+            trace!("invalid AUTH property length for remaining packet length");
+            return Err(RxError::MalformedPacket);
+        }
+
+        let mut authentication_method = None;
+        let mut authentication_data = None;
+        let mut reason_string = None;
+        let mut user_properties = Vec::new();
+
+        while r.remaining_len() > 0 {
+            verbose!(
+                "reading property identifier (remaining length: {} bytes)",
+                r.remaining_len()
+            );
+            let property_type = PropertyType::read(r).await?;
+
+            verbose!(
+                "reading {:?} property body (remaining length: {} bytes)",
+                property_type,
+                r.remaining_len()
+            );
+            match property_type {
+                PropertyType::AuthenticationMethod => authentication_method.try_set(r).await?,
+                PropertyType::AuthenticationData => authentication_data.try_set(r).await?,
+                PropertyType::ReasonString => reason_string.try_set(r).await?,
+                PropertyType::UserProperty if !user_properties.is_full() => {
+                    let user_property = UserProperty::read(r).await?;
+
+                    // Safety: `!Vec::is_full` guarantees there is space
+                    unsafe { user_properties.push_unchecked(user_property) };
+                }
+                PropertyType::UserProperty => {
+                    UserProperty::skip(r).await?;
+                }
+                // Malformed packet according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901029>
+                p => {
+                    trace!("invalid AUTH property: {:?}", p);
+                    return Err(RxError::MalformedPacket);
+                }
+            };
+        }
+
+        // Protocol error according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901223>
+        if authentication_method.is_none() {
+            trace!("AUTH packet is missing the authentication method");
+            return Err(RxError::ProtocolError);
+        }
+
+        Ok(Self {
+            reason_code: auth_reason_code,
+            authentication_method,
+            authentication_data,
+            reason_string,
+            user_properties,
+        })
+    }
+}
+impl<const MAX_USER_PROPERTIES: usize> TxPacket for AuthPacket<'_, MAX_USER_PROPERTIES> {
+    async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
+        FixedHeader::new(Self::PACKET_TYPE, 0x00, self.remaining_len())
+            .write(write)
+            .await?;
+
+        self.reason_code.write(write).await?;
+
+        let properties_length = self.properties_length();
+        properties_length.write(write).await?;
+
+        self.authentication_method.write(write).await?;
+        self.authentication_data.write(write).await?;
+        self.reason_string.write(write).await?;
+
+        for user_property in &self.user_properties {
+            user_property.write(write).await?;
+        }
+
+        Ok(())
+    }
+
+    fn remaining_len(&self) -> VarByteInt {
+        let variable_header_length = wlen!(ReasonCode);
+
+        let properties_length = self.properties_length();
+        let total_properties_length = properties_length.size() + properties_length.written_len();
+
+        let total_length = variable_header_length + total_properties_length;
+
+        // max length = MAX_USER_PROPERTIES * 131077 + 196619
+        // Invariant: MAX_USER_PROPERTIES <= 2046 => max length <= VarByteInt::MAX_ENCODABLE
+        // variable header (reason_code): 1
+        // property length: 4
+        // properties: MAX_USER_PROPERTIES * 131077 + 196614
+        VarByteInt::new_unchecked(total_length as u32)
+    }
+}
+
+impl<'p, const MAX_USER_PROPERTIES: usize> AuthPacket<'p, MAX_USER_PROPERTIES> {
+    pub const fn new(
+        reason_code: ReasonCode,
+        authentication_method: AuthenticationMethod<'p>,
+        authentication_data: Option<AuthenticationData<'p>>,
+        user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
+    ) -> Self {
+        const {
+            const_assert!(MAX_USER_PROPERTIES <= 2046);
+        }
+
+        Self {
+            reason_code,
+            authentication_method: Some(authentication_method),
+            authentication_data,
+            reason_string: None,
+            user_properties,
+        }
+    }
+
+    fn properties_length(&self) -> VarByteInt {
+        let len = self.authentication_method.written_len()
+            + self.authentication_data.written_len()
+            + self.reason_string.written_len()
+            + self
+                .user_properties
+                .iter()
+                .map(Writable::written_len)
+                .sum::<usize>();
+
+        // max length = MAX_USER_PROPERTIES * 131077 + 196614
+        // Invariant: MAX_USER_PROPERTIES <= 2046 => max length <= VarByteInt::MAX_ENCODABLE
+        //
+        // authentication method: 65538
+        // authentication data: 65538
+        // reason string: 65538
+        // user properties: MAX_USER_PROPERTIES * 131077
+        VarByteInt::new_unchecked(len as u32)
+    }
+}
+
+#[cfg(test)]
+mod unit {
+    use heapless::Vec;
+
+    use crate::{
+        test::{rx::decode, tx::encode},
+        types::{MqttBinary, MqttString, MqttStringPair, ReasonCode},
+        v5::{
+            packet::AuthPacket,
+            property::{AuthenticationData, AuthenticationMethod, ReasonString, UserProperty},
+        },
+    };
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_simple() {
+        let packet = AuthPacket::<16>::new(
+            ReasonCode::ContinueAuthentication,
+            AuthenticationMethod(MqttString::from_str("PLAIN").unwrap()),
+            None,
+            Vec::new(),
+        );
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0xF0, //
+            0x0A, // remaining length
+            0x18, // reason code
+            0x08, // property length
+
+            // Authentication Method
+            0x15, 0x00, 0x05, b'P', b'L', b'A', b'I', b'N',
+        ]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn encode_properties() {
+        let packet = AuthPacket::<16>::new(
+            ReasonCode::ReAuthenticate,
+            AuthenticationMethod(MqttString::from_str("TOKEN").unwrap()),
+            Some(AuthenticationData(
+                MqttBinary::from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]).unwrap(),
+            )),
+            [UserProperty(MqttStringPair::new(
+                MqttString::from_str("k").unwrap(),
+                MqttString::from_str("v").unwrap(),
+            ))]
+            .into(),
+        );
+
+        #[rustfmt::skip]
+        encode!(packet, [
+            0xF0, //
+            0x18, // remaining length
+            0x19, // reason code
+            0x16, // property length
+
+            // Authentication Method
+            0x15, 0x00, 0x05, b'T', b'O', b'K', b'E', b'N',
+
+            // Authentication Data
+            0x16, 0x00, 0x04, 0xDE, 0xAD, 0xBE, 0xEF,
+
+            // User Property
+            0x26, 0x00, 0x01, b'k',
+                  0x00, 0x01, b'v',
+        ]);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_minimal() {
+        let packet = decode!(AuthPacket<16>, 0, [0xF0, 0x00]);
+
+        assert_eq!(packet.reason_code, ReasonCode::Success);
+        assert!(packet.authentication_method.is_none());
+        assert!(packet.authentication_data.is_none());
+        assert!(packet.reason_string.is_none());
+        assert!(packet.user_properties.is_empty());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_properties() {
+        #[rustfmt::skip]
+        let packet = decode!(AuthPacket<16>, 43, [
+            0xF0,
+            0x2B,
+            0x18, // Reason code
+            0x29, // Property length
+
+            // Authentication Method
+            0x15, 0x00, 0x0D, b'S', b'C', b'R', b'A', b'M', b'-', b'S', b'H', b'A', b'-', b'2', b'5', b'6',
+
+            // Authentication Data
+            0x16, 0x00, 0x05, b'r', b'=', b'a', b'b', b'c',
+
+            // Reason String
+            0x1F, 0x00, 0x04, b'm', b'o', b'r', b'e',
+
+            // User Property
+            0x26, 0x00, 0x03, b'k', b'e', b'y',
+                  0x00, 0x02, b'v', b'1',
+        ]);
+
+        assert_eq!(packet.reason_code, ReasonCode::ContinueAuthentication);
+        assert_eq!(
+            packet.authentication_method,
+            Some(AuthenticationMethod(
+                MqttString::from_str("SCRAM-SHA-256").unwrap()
+            ))
+        );
+        assert_eq!(
+            packet.authentication_data,
+            Some(AuthenticationData(
+                MqttBinary::from_slice(b"r=abc").unwrap()
+            ))
+        );
+        assert_eq!(
+            packet.reason_string,
+            Some(ReasonString(MqttString::from_str("more").unwrap()))
+        );
+        assert_eq!(
+            packet.user_properties.as_slice(),
+            &[UserProperty(MqttStringPair::new(
+                MqttString::from_str("key").unwrap(),
+                MqttString::from_str("v1").unwrap()
+            ))]
+        );
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_incomplete_user_properties() {
+        #[rustfmt::skip]
+        let packet = decode!(AuthPacket<1>, 24, [
+            0xF0,
+            0x18,
+            0x00, // Reason code
+            0x16, // Property length
+
+            // Authentication Method
+            0x15, 0x00, 0x01, b'X',
+
+            // User Property
+            0x26, 0x00, 0x02, b'k', b'1',
+                  0x00, 0x02, b'v', b'1',
+
+            // User Property
+            0x26, 0x00, 0x02, b'k', b'2',
+                  0x00, 0x02, b'v', b'2',
+        ]);
+
+        assert_eq!(packet.reason_code, ReasonCode::Success);
+        assert_eq!(
+            packet.user_properties.as_slice(),
+            &[UserProperty(MqttStringPair::new(
+                MqttString::from_str("k1").unwrap(),
+                MqttString::from_str("v1").unwrap()
+            ))]
+        );
+    }
+}
@@ -10,10 +10,11 @@ use crate::{
     packet::{Packet, RxError, RxPacket},
     types::{ReasonCode, VarByteInt},
     v5::property::{
-        AssignedClientIdentifier, AtMostOnceProperty, MaximumQoS, PropertyType, ReasonString,
-        ReceiveMaximum, ResponseInformation, RetainAvailable, ServerKeepAlive, ServerReference,
-        SharedSubscriptionAvailable, SubscriptionIdentifierAvailable, TopicAliasMaximum,
-        UserProperty, WildcardSubscriptionAvailable,
+        AssignedClientIdentifier, AtMostOnceProperty, AuthenticationData, AuthenticationMethod,
+        MaximumQoS, PropertyType, ReasonString, ReceiveMaximum, ResponseInformation,
+        RetainAvailable, ServerKeepAlive, ServerReference, SharedSubscriptionAvailable,
+        SubscriptionIdentifierAvailable, TopicAliasMaximum, UserProperty,
+        WildcardSubscriptionAvailable,
     },
 };
 
@@ -39,10 +40,8 @@ pub struct ConnackPacket<'p, const MAX_USER_PROPERTIES: usize> {
     pub server_keep_alive: Option<ServerKeepAlive>,
     pub response_information: Option<ResponseInformation<'p>>,
     pub server_reference: Option<ServerReference<'p>>,
-    // authentication method is currently unused and does not have to be read into memory.
-    // pub authentication_method: Option<AuthenticationMethod<'p>>,
-    // authentication data is currently unused and does not have to be read into memory.
-    // pub authentication_data: Option<AuthenticationData<'p>>,
+    pub authentication_method: Option<AuthenticationMethod<'p>>,
+    pub authentication_data: Option<AuthenticationData<'p>>,
 }
 
 impl<const MAX_USER_PROPERTIES: usize> Packet for ConnackPacket<'_, MAX_USER_PROPERTIES> {
@@ -66,6 +65,37 @@ impl<'p, const MAX_USER_PROPERTIES: usize> RxPacket<'p> for ConnackPacket<'p, MA
 
         verbose!("reading reason code field");
         let connect_reason_code = ReasonCode::read(r).await?;
+
+        // A server which only supports MQTT version 3.1.1 rejects the protocol level with a version 3.1.1
+        // CONNACK packet containing return code 0x01 (unacceptable protocol version) and no properties.
+        if header.remaining_len.value() == 2
+            && connack_flags == 0
+            && connect_reason_code == ReasonCode::GrantedQoS1
+        {
+            trace!("received MQTT version 3.1.1 CONNACK packet rejecting the protocol level");
+            return Ok(Self {
+                session_present: false,
+                reason_code: ReasonCode::UnsupportedProtocolVersion,
+                session_expiry_interval: None,
+                receive_maximum: None,
+                maximum_qos: None,
+                retain_available: None,
+                maximum_packet_size: None,
+                assigned_client_identifier: None,
+                topic_alias_maximum: None,
+                reason_string: None,
+                user_properties: Vec::new(),
+                wildcard_subscription_available: None,
+                subscription_identifier_available: None,
+                shared_subscription_available: None,
+                server_keep_alive: None,
+                response_information: None,
+                server_reference: None,
+                authentication_method: None,
+                authentication_data: None,
+            });
+        }
+
         if !matches!(
             connect_reason_code,
             ReasonCode::Success
@@ -126,9 +156,8 @@ impl<'p, const MAX_USER_PROPERTIES: usize> RxPacket<'p> for ConnackPacket<'p, MA
         let mut server_keep_alive = None;
         let mut response_information = None;
         let mut server_reference = None;
-
-        let mut seen_authentication_method = false;
-        let mut seen_authentication_data = false;
+        let mut authentication_method = None;
+        let mut authentication_data = None;
 
         while r.remaining_len() > 0 {
             verbose!(
@@ -167,20 +196,8 @@ impl<'p, const MAX_USER_PROPERTIES: usize> RxPacket<'p> for ConnackPacket<'p, MA
                 PropertyType::ServerKeepAlive => server_keep_alive.try_set(r).await?,
                 PropertyType::ResponseInformation => response_information.try_set(r).await?,
                 PropertyType::ServerReference => server_reference.try_set(r).await?,
-                PropertyType::AuthenticationMethod if seen_authentication_method => return Err(RxError::ProtocolError),
-                PropertyType::AuthenticationMethod => {
-                    seen_authentication_method = true;
-                    let len = u16::read(r).await? as usize;
-                    verbose!("skipping authentication method ({} bytes)", len);
-                    r.skip(len).await?;
-                }
-                PropertyType::AuthenticationData if seen_authentication_data => return Err(RxError::ProtocolError),
-                PropertyType::AuthenticationData => {
-                    seen_authentication_data = true;
-                    let len = u16::read(r).await? as usize;
-                    verbose!("skipping authentication data ({} bytes)", len);
-                    r.skip(len).await?;
-                }
+                PropertyType::AuthenticationMethod => authentication_method.try_set(r).await?,
+                PropertyType::AuthenticationData => authentication_data.try_set(r).await?,
                 p => {
                     // Malformed packet according to <https://docs.oasis-open.org/mqtt/mqtt/v5.0/os/mqtt-v5.0-os.html#_Toc3901029>
                     trace!("invalid CONNACK property: {:?}", p);
@@ -207,6 +224,8 @@ impl<'p, const MAX_USER_PROPERTIES: usize> RxPacket<'p> for ConnackPacket<'p, MA
             server_keep_alive,
             response_information,
             server_reference,
+            authentication_method,
+            authentication_data,
         })
     }
 }
@@ -218,14 +237,15 @@ mod unit {
     use crate::{
         config::{KeepAlive, MaximumPacketSize, SessionExpiryInterval},
         test::rx::decode,
-        types::{MqttString, MqttStringPair, QoS, ReasonCode},
+        types::{MqttBinary, MqttString, MqttStringPair, QoS, ReasonCode},
         v5::{
             packet::ConnackPacket,
             property::{
-                AssignedClientIdentifier, MaximumQoS, ReasonString, ReceiveMaximum,
-                ResponseInformation, RetainAvailable, ServerKeepAlive, ServerReference,
-                SharedSubscriptionAvailable, SubscriptionIdentifierAvailable, TopicAliasMaximum,
-                UserProperty, WildcardSubscriptionAvailable,
+                AssignedClientIdentifier, AuthenticationData, AuthenticationMethod, MaximumQoS,
+                ReasonString, ReceiveMaximum, ResponseInformation, RetainAvailable,
+                ServerKeepAlive, ServerReference, SharedSubscriptionAvailable,
+                SubscriptionIdentifierAvailable, TopicAliasMaximum, UserProperty,
+                WildcardSubscriptionAvailable,
             },
         },
     };
@@ -253,8 +273,19 @@ mod unit {
         assert!(packet.server_keep_alive.is_none());
         assert!(packet.response_information.is_none());
         assert!(packet.server_reference.is_none());
-        // assert!(packet.authentication_method.is_none());
-        // assert!(packet.authentication_data.is_none());
+        assert!(packet.authentication_method.is_none());
+        assert!(packet.authentication_data.is_none());
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn decode_v3_unacceptable_protocol_version() {
+        let packet = decode!(ConnackPacket<16>, 2, [0x20, 0x02, 0x00, 0x01]);
+
+        assert_eq!(packet.reason_code, ReasonCode::UnsupportedProtocolVersion);
+        assert!(!packet.session_present);
+        assert!(packet.user_properties.is_empty());
+        assert!(packet.reason_string.is_none());
     }
 
     #[tokio::test]
@@ -393,18 +424,18 @@ mod unit {
                 MqttString::try_from("server.example.com").unwrap()
             ))
         );
-        // assert_eq!(
-        //     packet.authentication_method,
-        //     Some(AuthenticationMethod(
-        //         MqttString::try_from("SCRAM-SHA-256").unwrap()
-        //     ))
-        // );
-        // assert_eq!(
-        //     packet.authentication_data,
-        //     Some(AuthenticationData(
-        //         MqttBinary::try_from("auth_data".as_bytes()).unwrap()
-        //     ))
-        // );
+        assert_eq!(
+            packet.authentication_method,
+            Some(AuthenticationMethod(
+                MqttString::try_from("SCRAM-SHA-256").unwrap()
+            ))
+        );
+        assert_eq!(
+            packet.authentication_data,
+            Some(AuthenticationData(
+                MqttBinary::try_from("auth_data".as_bytes()).unwrap()
+            ))
+        );
     }
 
     #[tokio::test]
@@ -213,6 +213,20 @@ impl<'p, const MAX_USER_PROPERTIES: usize> ConnectPacket<'p, MAX_USER_PROPERTIES
         self.password = Some(password);
     }
 
+    pub fn add_topic_alias_maximum(&mut self, topic_alias_maximum: u16) {
+        // A topic alias maximum of 0 is the default and can be omitted on the network.
+        self.topic_alias_maximum = (topic_alias_maximum > 0).then_some(topic_alias_maximum.into());
+    }
+
+    pub fn add_authentication(
+        &mut self,
+        authentication_method: MqttString<'p>,
+        authentication_data: Option<MqttBinary<'p>>,
+    ) {
+        self.authentication_method = Some(authentication_method.into());
+        self.authentication_data = authentication_data.map(Into::into);
+    }
+
     pub fn add_will(
         &mut self,
         will: Will<'p, MAX_USER_PROPERTIES>,
@@ -283,7 +297,7 @@ mod unit {
     #[tokio::test]
     #[test_log::test]
     async fn encode_properties() {
-        let packet = ConnectPacket::<16>::new(
+        let mut packet = ConnectPacket::<16>::new(
             MqttString::try_from("a").unwrap(),
             false,
             KeepAlive::Infinite,
@@ -309,10 +323,12 @@ mod unit {
             .into(),
         );
 
+        packet.add_topic_alias_maximum(4660);
+
         #[rustfmt::skip]
         encode!(packet, [
             0x10,       //
-            0x54,       // remaining length
+            0x57,       // remaining length
             0x00,       // ---
             0x04,       //
             b'M',       //
@@ -323,7 +339,7 @@ mod unit {
             0b00000000, // Connect flags
             0x00,       // Keep alive MSB
             0x00,       // Keep alive LSB
-            0x46,       // Property length
+            0x49,       // Property length
 
             0x11,       // Session expiry interval
             0x00, 0x7C, 0x26, 0xC7,
@@ -334,6 +350,9 @@ mod unit {
             0x27,       // Maximum packet size
             0x00, 0x23, 0x3E, 0xD5,
 
+            0x22,       // Topic alias maximum
+            0x12, 0x34,
+
             0x19,       // Request response information
             0x01,
 
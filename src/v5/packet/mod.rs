@@ -1,21 +1,22 @@
-// mod auth;
+mod auth;
 mod connack;
 mod connect;
 mod disconnect;
-mod pings;
 mod pubacks;
 mod publish;
 mod subacks;
 mod subscribe;
 mod unsubscribe;
 
-// pub use auth::AuthPacket;
+pub use auth::AuthPacket;
 pub use connack::ConnackPacket;
 pub use connect::ConnectPacket;
 pub use disconnect::DisconnectPacket;
-pub use pings::{PingreqPacket, PingrespPacket};
 pub use pubacks::{GenericPubackPacket, PubackPacket, PubcompPacket, PubrecPacket, PubrelPacket};
 pub use publish::PublishPacket;
 pub use subacks::{SubackPacket, UnsubackPacket};
 pub use subscribe::SubscribePacket;
 pub use unsubscribe::UnsubscribePacket;
+
+// PINGREQ and PINGRESP are encoded identically in both protocol versions.
+pub use crate::packet::{PingreqPacket, PingrespPacket};
@@ -307,6 +307,25 @@ impl<'p, const MAX_SUBSCRIPTION_IDENTIFIERS: usize, const MAX_USER_PROPERTIES: u
         p.remaining_len_raw().map(|_| p)
     }
 
+    /// Replaces the topic reference if the packet can still be encoded afterwards.
+    ///
+    /// # Returns:
+    /// The previous topic reference.
+    pub fn replace_topic(
+        &mut self,
+        topic: TopicReference<'p>,
+    ) -> Result<TopicReference<'p>, TooLargeToEncode> {
+        let previous = core::mem::replace(&mut self.topic, topic);
+
+        match self.remaining_len_raw() {
+            Ok(_) => Ok(previous),
+            Err(e) => {
+                self.topic = previous;
+                Err(e)
+            }
+        }
+    }
+
     fn remaining_len_raw(&self) -> Result<VarByteInt, TooLargeToEncode> {
         let topic_name_length = self
             .topic
@@ -8,11 +8,11 @@ use crate::{
     fmt::{trace, verbose},
     header::{FixedHeader, PacketType},
     io::{
-        read::{BodyReader, Readable},
+        read::{BodyReader, Readable, Store},
         write::Writable,
     },
     packet::{Packet, RxError, RxPacket},
-    types::{PacketIdentifier, ReasonCode, VarByteInt},
+    types::{PacketIdentifier, ReasonCode, ReasonCodes, VarByteInt},
     v5::{
         packet::subacks::types::{Suback, SubackPacketType, Unsuback},
         property::{AtMostOnceProperty, PropertyType, ReasonString, UserProperty},
@@ -21,35 +21,30 @@ use crate::{
 
 mod types;
 
-pub type SubackPacket<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> =
-    GenericSubackPacket<'p, Suback, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>;
-pub type UnsubackPacket<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> =
-    GenericSubackPacket<'p, Unsuback, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>;
+pub type SubackPacket<'p, const MAX_USER_PROPERTIES: usize> =
+    GenericSubackPacket<'p, Suback, MAX_USER_PROPERTIES>;
+pub type UnsubackPacket<'p, const MAX_USER_PROPERTIES: usize> =
+    GenericSubackPacket<'p, Unsuback, MAX_USER_PROPERTIES>;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct GenericSubackPacket<
-    'p,
-    T,
-    const MAX_TOPIC_FILTERS: usize,
-    const MAX_USER_PROPERTIES: usize,
-> {
+pub struct GenericSubackPacket<'p, T, const MAX_USER_PROPERTIES: usize> {
     pub packet_identifier: PacketIdentifier,
 
     pub reason_string: Option<ReasonString<'p>>,
     pub user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
 
-    pub reason_codes: Vec<ReasonCode, MAX_TOPIC_FILTERS>,
+    pub reason_codes: ReasonCodes<'p>,
     _phantom_data: PhantomData<&'p T>,
 }
 
-impl<T: SubackPacketType, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> Packet
-    for GenericSubackPacket<'_, T, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
+impl<T: SubackPacketType, const MAX_USER_PROPERTIES: usize> Packet
+    for GenericSubackPacket<'_, T, MAX_USER_PROPERTIES>
 {
     const PACKET_TYPE: PacketType = T::PACKET_TYPE;
 }
-impl<'p, T: SubackPacketType, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
-    RxPacket<'p> for GenericSubackPacket<'p, T, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
+impl<'p, T: SubackPacketType, const MAX_USER_PROPERTIES: usize> RxPacket<'p>
+    for GenericSubackPacket<'p, T, MAX_USER_PROPERTIES>
 {
     async fn receive<R: Read, B: BufferProvider<'p>>(
         header: &FixedHeader,
@@ -137,10 +132,14 @@ impl<'p, T: SubackPacketType, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PRO
             };
         }
 
-        let mut reason_codes = Vec::new();
-        while r.remaining_len() > 0 {
-            verbose!("reading reason code field");
-            let reason_code = ReasonCode::read(r).await?;
+        verbose!("reading reason code fields");
+        let reason_codes = r.read_and_store(r.remaining_len()).await?;
+
+        for &value in reason_codes.iter() {
+            let Some(reason_code) = ReasonCode::try_from_u8(value) else {
+                trace!("invalid {:?} reason code value: {}", T::PACKET_TYPE, value);
+                return Err(RxError::ProtocolError);
+            };
 
             if !T::reason_code_allowed(reason_code) {
                 trace!(
@@ -150,16 +149,10 @@ impl<'p, T: SubackPacketType, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PRO
                 );
                 return Err(RxError::ProtocolError);
             }
-
-            // This currently doesn't return a const space related error as we only send SUBSCRIBE packets with a single
-            // reason code and therefore expect the server to send ACKs with one reason code as well.
-            // This is not really the correct error in the decoding sense but it's ok and more efficient for now and allows
-            // us to get rid of the public error counterpart.
-            reason_codes
-                .push(reason_code)
-                .map_err(|_| RxError::ProtocolError)?;
         }
 
+        let reason_codes = ReasonCodes::new_unchecked(reason_codes);
+
         Ok(Self {
             packet_identifier,
             reason_string,
@@ -191,7 +184,7 @@ mod unit {
         async fn decode_payload() {
             #[rustfmt::skip]
             let packet = decode!(
-                SubackPacket<12, 16>,
+                SubackPacket<16>,
                 15,
                 [
                     0x90,
@@ -227,7 +220,7 @@ mod unit {
                 ReasonCode::TopicFilterInvalid,
             ]
             .into();
-            assert_eq!(packet.reason_codes, reason_codes);
+            assert!(packet.reason_codes.iter().eq(reason_codes));
         }
 
         #[tokio::test]
@@ -235,7 +228,7 @@ mod unit {
         async fn decode_properties() {
             #[rustfmt::skip]
             let packet = decode!(
-                SubackPacket<1, 16>,
+                SubackPacket<16>,
                 61,
                 [
                     0x90,
@@ -281,7 +274,7 @@ mod unit {
             );
 
             let reason_codes: Vec<_, 1> = [ReasonCode::Success].into();
-            assert_eq!(packet.reason_codes, reason_codes);
+            assert!(packet.reason_codes.iter().eq(reason_codes));
         }
 
         #[tokio::test]
@@ -289,7 +282,7 @@ mod unit {
         async fn decode_incomplete_user_properties() {
             #[rustfmt::skip]
             let packet = decode!(
-                SubackPacket<1, 1>,
+                SubackPacket<1>,
                 31,
                 [
                     0x90,
@@ -344,7 +337,7 @@ mod unit {
         async fn decode_payload() {
             #[rustfmt::skip]
             let packet = decode!(
-                UnsubackPacket<7, 16>,
+                UnsubackPacket<16>,
                 10,
                 [
                     0xB0,
@@ -375,7 +368,7 @@ mod unit {
             ]
             .into();
 
-            assert_eq!(packet.reason_codes, reason_codes);
+            assert!(packet.reason_codes.iter().eq(reason_codes));
         }
 
         #[tokio::test]
@@ -383,7 +376,7 @@ mod unit {
         async fn decode_properties() {
             #[rustfmt::skip]
             let packet = decode!(
-                UnsubackPacket<1, 16>,
+                UnsubackPacket<16>,
                 78,
                 [
                     0xB0, 
@@ -434,7 +427,7 @@ mod unit {
             );
 
             let reason_codes: Vec<_, 1> = [ReasonCode::Success].into();
-            assert_eq!(packet.reason_codes, reason_codes);
+            assert!(packet.reason_codes.iter().eq(reason_codes));
         }
 
         #[tokio::test]
@@ -442,7 +435,7 @@ mod unit {
         async fn decode_incomplete_user_properties() {
             #[rustfmt::skip]
             let packet = decode!(
-                UnsubackPacket<1, 2>,
+                UnsubackPacket<2>,
                 31,
                 [
                     0xB0,
@@ -1,39 +1,54 @@
 use heapless::Vec;
 
 use crate::{
+    client::options::FilterOptions,
     eio::Write,
     header::{FixedHeader, PacketType},
-    io::write::Writable,
+    io::{
+        err::WriteError,
+        write::{Writable, wlen},
+    },
     packet::{Packet, TxError, TxPacket},
-    types::{PacketIdentifier, SubscriptionFilter, TooLargeToEncode, VarByteInt},
+    types::{PacketIdentifier, TooLargeToEncode, TopicFilter, VarByteInt},
     v5::property::{SubscriptionIdentifier, UserProperty},
 };
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SubscribePacket<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> {
+pub struct SubscribePacket<'p, const MAX_USER_PROPERTIES: usize> {
     packet_identifier: PacketIdentifier,
 
     subscription_identifier: Option<SubscriptionIdentifier>,
     user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
 
-    subscribe_filters: Vec<SubscriptionFilter<'p>, MAX_TOPIC_FILTERS>,
+    /// The topic filters and their subscription options.
+    subscribe_filters: &'p [(TopicFilter<'p>, FilterOptions)],
 }
 
-impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> Packet
-    for SubscribePacket<'_, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
+impl Writable for [(TopicFilter<'_>, FilterOptions)] {
+    fn written_len(&self) -> usize {
+        self.iter().map(|(t, _)| t.written_len() + wlen!(u8)).sum()
+    }
+
+    async fn write<W: Write>(&self, write: &mut W) -> Result<(), WriteError<W::Error>> {
+        for (t, o) in self {
+            t.write(write).await?;
+            o.into_bits().write(write).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const MAX_USER_PROPERTIES: usize> Packet for SubscribePacket<'_, MAX_USER_PROPERTIES> {
     const PACKET_TYPE: PacketType = PacketType::Subscribe;
 }
-impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> TxPacket
-    for SubscribePacket<'_, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
+impl<const MAX_USER_PROPERTIES: usize> TxPacket for SubscribePacket<'_, MAX_USER_PROPERTIES> {
     fn remaining_len(&self) -> VarByteInt {
         // Safety: SUBSCRIBE packets that are too long to encode cannot be created
         unsafe { self.remaining_len_raw().unwrap_unchecked() }
     }
 
-    /// If `MAX_TOPIC_FILTERS` is to less than or equal to 4095, it is guaranteed that `TxError::RemainingLenExceeded` is never returned.
     async fn send<W: Write>(&self, write: &mut W) -> Result<(), TxError<W::Error>> {
         FixedHeader::new(Self::PACKET_TYPE, 0x02, self.remaining_len())
             .write(write)
@@ -53,14 +68,12 @@ impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> TxPacket
     }
 }
 
-impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
-    SubscribePacket<'p, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
+impl<'p, const MAX_USER_PROPERTIES: usize> SubscribePacket<'p, MAX_USER_PROPERTIES> {
     pub fn new(
         packet_identifier: PacketIdentifier,
         subscription_identifier: Option<SubscriptionIdentifier>,
         user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
-        subscribe_filters: Vec<SubscriptionFilter<'p>, MAX_TOPIC_FILTERS>,
+        subscribe_filters: &'p [(TopicFilter<'p>, FilterOptions)],
     ) -> Result<Self, TooLargeToEncode> {
         let p = Self {
             packet_identifier,
@@ -70,7 +83,7 @@ impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
         };
 
         // Refer to `SubscribePacket::remaining_len_raw` as to why this is true.
-        if MAX_USER_PROPERTIES <= 1021 && MAX_TOPIC_FILTERS <= 2053 {
+        if MAX_USER_PROPERTIES <= 1021 && p.subscribe_filters.len() <= 2053 {
             Ok(p)
         } else {
             p.remaining_len_raw().map(|_| p)
@@ -87,25 +100,25 @@ impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
 
         let total_length = variable_header_length + total_properties_length + body_length;
 
-        // max length = MAX_USER_PROPERTIES * 131077 + MAX_TOPIC_FILTERS * 65538 + 11
+        // max length = MAX_USER_PROPERTIES * 131077 + topic filters * 65538 + 11
         // Invariant: The following inequation must be fulfilled to guarantee
         // max length <= VarByteInt::MAX_ENCODABLE:
         //
-        // MAX_USER_PROPERTIES * 131077 + MAX_TOPIC_FILTERS * 65538 + 11 <= VarByteInt::MAX_ENCODABLE
+        // MAX_USER_PROPERTIES * 131077 + topic filters * 65538 + 11 <= VarByteInt::MAX_ENCODABLE
         //
         // Given the maximum allowed value of MAX_USER_PROPERTIES = 1021 in the client, the following
         // inequation must be fulfilled:
         //
-        // 1021 * 131077 + MAX_TOPIC_FILTERS * 65538 + 11 <= VarByteInt::MAX_ENCODABLE
+        // 1021 * 131077 + topic filters * 65538 + 11 <= VarByteInt::MAX_ENCODABLE
         //
         // This results in the statement:
         //
-        // MAX_TOPIC_FILTERS <= 2053 => max length <= VarByteInt::MAX_ENCODABLE
+        // topic filters <= 2053 => max length <= VarByteInt::MAX_ENCODABLE
         //
         // packet identifier: 2
         // property length: 4
         // properties: MAX_USER_PROPERTIES * 131077 + 5
-        // topic filters: MAX_TOPIC_FILTERS * 65538
+        // topic filters: topic filters * 65538
         VarByteInt::try_from(total_length as u32)
     }
 
@@ -133,12 +146,9 @@ mod unit {
     use heapless::Vec;
 
     use crate::{
-        client::options::{RetainHandling, SubscriptionOptions},
+        client::options::{FilterOptions, RetainHandling},
         test::tx::encode,
-        types::{
-            MqttString, MqttStringPair, PacketIdentifier, SubscriptionFilter, TopicFilter,
-            VarByteInt,
-        },
+        types::{MqttString, MqttStringPair, PacketIdentifier, TopicFilter, VarByteInt},
         v5::{
             packet::SubscribePacket,
             property::{SubscriptionIdentifier, UserProperty},
@@ -149,25 +159,23 @@ mod unit {
     #[test_log::test]
     async fn encode_payload() {
         let topics = [
-            SubscriptionFilter::new(
+            (
                 TopicFilter::new(MqttString::try_from("test/hello").unwrap()).unwrap(),
-                &SubscriptionOptions::new().no_local(),
-            )
-            .unwrap(),
-            SubscriptionFilter::new(
+                FilterOptions::new().no_local(),
+            ),
+            (
                 TopicFilter::new(MqttString::try_from("asdfjklo/#").unwrap()).unwrap(),
-                &SubscriptionOptions::new()
+                FilterOptions::new()
                     .retain_handling(RetainHandling::NeverSend)
                     .retain_as_published()
                     .exactly_once(),
-            )
-            .unwrap(),
+            ),
         ];
-        let packet: SubscribePacket<'_, 2, 0> = SubscribePacket::new(
+        let packet: SubscribePacket<'_, 0> = SubscribePacket::new(
             PacketIdentifier::new(NonZero::new(23197).unwrap()),
             None,
             Vec::new(),
-            topics.into(),
+            &topics,
         )
         .unwrap();
 
@@ -211,14 +219,12 @@ mod unit {
     #[tokio::test]
     #[test_log::test]
     async fn encode_properties() {
-        let topics = [SubscriptionFilter::new(
+        let topics = [(
             TopicFilter::new(MqttString::try_from("abc/+/y").unwrap()).unwrap(),
-            &SubscriptionOptions::new()
+            FilterOptions::new()
                 .retain_handling(RetainHandling::SendIfNotSubscribedBefore)
-                .retain_as_published()
-                .subscription_identifier(VarByteInt::from(23459u16)),
-        )
-        .unwrap()];
+                .retain_as_published(),
+        )];
 
         let user_properties = [
             UserProperty(MqttStringPair::new(
@@ -231,13 +237,13 @@ mod unit {
             )),
         ];
 
-        let packet: SubscribePacket<'_, 10, 16> = SubscribePacket::new(
+        let packet: SubscribePacket<'_, 16> = SubscribePacket::new(
             PacketIdentifier::new(NonZero::new(23197).unwrap()),
             Some(SubscriptionIdentifier(
                 VarByteInt::new(87986078u32).unwrap(),
             )),
             user_properties.into(),
-            topics.into(),
+            &topics,
         )
         .unwrap();
 
@@ -11,22 +11,18 @@ use crate::{
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct UnsubscribePacket<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> {
+pub struct UnsubscribePacket<'p, const MAX_USER_PROPERTIES: usize> {
     packet_identifier: PacketIdentifier,
 
     user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
 
-    topic_filters: Vec<TopicFilter<'p>, MAX_TOPIC_FILTERS>,
+    topic_filters: &'p [TopicFilter<'p>],
 }
 
-impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> Packet
-    for UnsubscribePacket<'_, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
+impl<const MAX_USER_PROPERTIES: usize> Packet for UnsubscribePacket<'_, MAX_USER_PROPERTIES> {
     const PACKET_TYPE: PacketType = PacketType::Unsubscribe;
 }
-impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> TxPacket
-    for UnsubscribePacket<'_, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
+impl<const MAX_USER_PROPERTIES: usize> TxPacket for UnsubscribePacket<'_, MAX_USER_PROPERTIES> {
     fn remaining_len(&self) -> VarByteInt {
         // Safety: UNSUBSCRIBE packets that are too long to encode cannot be created
         unsafe { self.remaining_len_raw().unwrap_unchecked() }
@@ -44,7 +40,7 @@ impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> TxPacket
             user_property.write(write).await?;
         }
 
-        for t in &self.topic_filters {
+        for t in self.topic_filters {
             t.write(write).await?;
         }
 
@@ -52,15 +48,13 @@ impl<const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize> TxPacket
     }
 }
 
-impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
-    UnsubscribePacket<'p, MAX_TOPIC_FILTERS, MAX_USER_PROPERTIES>
-{
-    /// If `MAX_TOPIC_FILTERS` is to less than or equal to 2053 and `MAX_USER_PROPERTIES` is
+impl<'p, const MAX_USER_PROPERTIES: usize> UnsubscribePacket<'p, MAX_USER_PROPERTIES> {
+    /// If the amount of topic filters is less than or equal to 2053 and `MAX_USER_PROPERTIES` is
     /// less than or equal to 1021, it is guaranteed that `TooLargeToEncode` is never returned.
     pub fn new(
         packet_identifier: PacketIdentifier,
         user_properties: Vec<UserProperty<'p>, MAX_USER_PROPERTIES>,
-        topic_filters: Vec<TopicFilter<'p>, MAX_TOPIC_FILTERS>,
+        topic_filters: &'p [TopicFilter<'p>],
     ) -> Result<Self, TooLargeToEncode> {
         let p = Self {
             packet_identifier,
@@ -69,7 +63,7 @@ impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
         };
 
         // Refer to `UnsubscribePacket::remaining_len_raw` as to why this is true.
-        if MAX_USER_PROPERTIES <= 1021 && MAX_TOPIC_FILTERS <= 2053 {
+        if MAX_USER_PROPERTIES <= 1021 && p.topic_filters.len() <= 2053 {
             Ok(p)
         } else {
             p.remaining_len_raw().map(|_| p)
@@ -86,25 +80,25 @@ impl<'p, const MAX_TOPIC_FILTERS: usize, const MAX_USER_PROPERTIES: usize>
 
         let total_length = variable_header_length + total_properties_length + body_length;
 
-        // max length = MAX_USER_PROPERTIES * 131077 + MAX_TOPIC_FILTERS * 65537 + 6
+        // max length = MAX_USER_PROPERTIES * 131077 + topic filters * 65537 + 6
         // Invariant: The following inequation must be fulfilled to guarantee
         // max length <= VarByteInt::MAX_ENCODABLE:
         //
-        // MAX_USER_PROPERTIES * 131077 + MAX_TOPIC_FILTERS * 65537 + 6 <= VarByteInt::MAX_ENCODABLE
+        // MAX_USER_PROPERTIES * 131077 + topic filters * 65537 + 6 <= VarByteInt::MAX_ENCODABLE
         //
         // Given the maximum allowed value of MAX_USER_PROPERTIES = 1021 in the client, the following
         // inequation must be fulfilled:
         //
-        // 1021 * 131077 + MAX_TOPIC_FILTERS * 65537 + 6 <= VarByteInt::MAX_ENCODABLE
+        // 1021 * 131077 + topic filters * 65537 + 6 <= VarByteInt::MAX_ENCODABLE
         //
         // This results in the statement:
         //
-        // MAX_TOPIC_FILTERS <= 2053 => max length <= VarByteInt::MAX_ENCODABLE
+        // topic filters <= 2053 => max length <= VarByteInt::MAX_ENCODABLE
         //
         // packet identifier: 2
         // property length: 4
         // properties: MAX_USER_PROPERTIES * 131077
-        // topic filters: MAX_TOPIC_FILTERS * 65537
+        // topic filters: topic filters * 65537
         VarByteInt::try_from(total_length as u32)
     }
 
@@ -143,10 +137,10 @@ mod unit {
             TopicFilter::new(MqttString::try_from("test/#").unwrap()).unwrap(),
         ];
 
-        let packet = UnsubscribePacket::<2, 16>::new(
+        let packet = UnsubscribePacket::<16>::new(
             PacketIdentifier::new(NonZero::new(9874).unwrap()),
             Vec::new(),
-            topics.into(),
+            &topics,
         )
         .unwrap();
 
@@ -191,10 +185,10 @@ mod unit {
             )),
         ];
 
-        let packet = UnsubscribePacket::<1, 16>::new(
+        let packet = UnsubscribePacket::<16>::new(
             PacketIdentifier::new(NonZero::new(23913).unwrap()),
             user_properties.into(),
-            topics.into(),
+            &topics,
         )
         .unwrap();
 
@@ -34,12 +34,15 @@ pub const NO_SESSION_CONNECT_OPTIONS: &ConnectOptions<'static> = &ConnectOptions
     clean_start: true,
     keep_alive: KeepAlive::Infinite,
     maximum_packet_size: MaximumPacketSize::Unlimited,
+    topic_alias_maximum: 0,
     session_expiry_interval: SessionExpiryInterval::EndOnDisconnect,
     request_response_information: false,
     request_problem_information: true,
     user_properties: &[],
     user_name: Some(USERNAME),
     password: Some(PASSWORD),
+    authentication_method: None,
+    authentication_data: None,
     will: None,
 };
 
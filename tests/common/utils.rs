@@ -129,13 +129,13 @@ pub async fn subscribe<'c>(
                 packet_identifier,
                 reason_string: _,
                 user_properties: _,
-                reason_code,
+                reason_codes,
             }) if packet_identifier == pid => {
-                info!("Subscribed with reason code {:?}", reason_code);
-                break match reason_code {
-                    ReasonCode::Success => Ok(QoS::AtMostOnce),
-                    ReasonCode::GrantedQoS1 => Ok(QoS::AtLeastOnce),
-                    ReasonCode::GrantedQoS2 => Ok(QoS::ExactlyOnce),
+                info!("Subscribed with reason codes {:?}", reason_codes);
+                break match reason_codes.get(0) {
+                    Some(ReasonCode::Success) => Ok(QoS::AtMostOnce),
+                    Some(ReasonCode::GrantedQoS1) => Ok(QoS::AtLeastOnce),
+                    Some(ReasonCode::GrantedQoS2) => Ok(QoS::ExactlyOnce),
                     _ => unreachable!(),
                 };
             }
@@ -143,7 +143,7 @@ pub async fn subscribe<'c>(
                 packet_identifier,
                 reason_string: _,
                 user_properties: _,
-                reason_code: _,
+                reason_codes: _,
             }) => {
                 warn!(
                     "Expected SUBACK for packet identifier {:?}, but received SUBACK for packet identifier {:?}",
@@ -172,16 +172,16 @@ pub async fn unsubscribe<'c>(
                 packet_identifier,
                 reason_string: _,
                 user_properties: _,
-                reason_code,
+                reason_codes,
             }) if packet_identifier == pid => {
-                info!("Unsubscribed with reason code {:?}", reason_code);
+                info!("Unsubscribed with reason codes {:?}", reason_codes);
                 break Ok(());
             }
             Event::Unsuback(Suback {
                 packet_identifier,
                 reason_string: _,
                 user_properties: _,
-                reason_code: _,
+                reason_codes: _,
             }) => {
                 warn!(
                     "Expected UNSUBACK for packet identifier {:?}, but received UNSUBACK for packet identifier {:?}",
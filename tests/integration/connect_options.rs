@@ -737,7 +737,7 @@ async fn receive_maximum() {
     ));
     if let Event::Suback(e) = e {
         assert_eq!(e.packet_identifier, pid);
-        assert!(e.reason_code.is_success());
+        assert!(e.reason_codes.iter().all(|r| r.is_success()));
     } else {
         panic!()
     }
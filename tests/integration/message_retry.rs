@@ -1766,7 +1766,7 @@ async fn incoming_automatic_qos1_write_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         }) if pid == packet_identifier => {}
                         _ => panic!("Should only receive a SUBACK"),
                     }
@@ -1882,7 +1882,7 @@ async fn incoming_manual_qos1_write_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         }) if pid == packet_identifier => {}
                         _ => panic!("Should only receive a SUBACK"),
                     }
@@ -2004,7 +2004,7 @@ async fn incoming_automatic_qos1_read_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         })) if pid == packet_identifier => {}
                         Ok(_) => panic!("Should only receive a SUBACK"),
                         Err(_) => {
@@ -2119,7 +2119,7 @@ async fn incoming_manual_qos1_read_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         })) if pid == packet_identifier => {}
                         Ok(_) => panic!("Should only receive a SUBACK"),
                         Err(_) => {
@@ -2247,7 +2247,7 @@ async fn incoming_automatic_qos2_write_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         }) if pid == packet_identifier => {}
                         _ => panic!("Should only receive a SUBACK"),
                     }
@@ -2391,7 +2391,7 @@ async fn incoming_manual_qos2_write_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         }) if pid == packet_identifier => {}
                         _ => panic!("Should only receive a SUBACK"),
                     }
@@ -2556,7 +2556,7 @@ async fn incoming_automatic_qos2_read_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         })) if pid == packet_identifier => {}
                         Ok(_) => panic!("Should only receive a SUBACK"),
                         Err(_) => {
@@ -2714,7 +2714,7 @@ async fn incoming_manual_qos2_read_fail_retry() {
                             packet_identifier,
                             reason_string: _,
                             user_properties: _,
-                            reason_code: _,
+                            reason_codes: _,
                         })) if pid == packet_identifier => {}
                         Ok(_) => panic!("Should only receive a SUBACK"),
                         Err(_) => {
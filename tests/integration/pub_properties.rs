@@ -2,6 +2,8 @@ use std::{num::NonZero, time::Duration};
 
 use rust_mqtt::{
     client::{
+        Client,
+        alias::LruTopicAliases,
         event::Publish,
         options::{PublicationOptions, TopicReference},
     },
@@ -15,8 +17,9 @@ use tokio_test::assert_err;
 
 use crate::common::{
     BROKER_ADDRESS, DEFAULT_DC_OPTIONS, DEFAULT_QOS0_SUB_OPTIONS, NO_SESSION_CONNECT_OPTIONS,
+    TestClient,
     assert::{assert_ok, assert_published, assert_recv, assert_recv_excl, assert_subscribe},
-    utils::{connected_client, disconnect, unique_topic},
+    utils::{ALLOC, connected_client, disconnect, tcp_connection, unique_topic},
 };
 
 #[tokio::test]
@@ -260,6 +263,54 @@ async fn topic_alias_remap() {
     join!(receiver1, receiver2, publisher);
 }
 
+#[tokio::test]
+#[test_log::test]
+async fn topic_alias_manager() {
+    let (topic_name, topic_filter) = unique_topic();
+    let msg = "Weeks of coding can save you hours of planning.";
+
+    let mut rx =
+        assert_ok!(connected_client(BROKER_ADDRESS, NO_SESSION_CONNECT_OPTIONS, None).await);
+
+    let manager = Box::leak(Box::new(LruTopicAliases::<4, 64>::new()));
+    let mut tx: TestClient = Client::new(ALLOC.get());
+    tx.manage_topic_aliases_with(manager);
+    assert_ok!(
+        tx.connect(
+            assert_ok!(tcp_connection(BROKER_ADDRESS).await),
+            NO_SESSION_CONNECT_OPTIONS,
+            None
+        )
+        .await
+    );
+
+    let publisher = async {
+        sleep(Duration::from_secs(1)).await;
+
+        let pub_options =
+            PublicationOptions::new(TopicReference::Name(topic_name.clone())).at_least_once();
+
+        assert_published!(tx, pub_options.clone(), msg.into());
+        assert_published!(tx, pub_options.clone(), msg.into());
+        assert_published!(tx, pub_options.clone(), msg.into());
+
+        disconnect(&mut tx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    let receiver = async {
+        let options = DEFAULT_QOS0_SUB_OPTIONS.at_least_once();
+        assert_subscribe!(rx, &options, topic_filter.clone());
+
+        assert_recv_excl!(rx, topic_name);
+        assert_recv_excl!(rx, topic_name);
+        assert_recv_excl!(rx, topic_name);
+
+        disconnect(&mut rx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    join!(receiver, publisher);
+}
+
 #[tokio::test]
 #[test_log::test]
 async fn payload_format_indicator() {
@@ -3,10 +3,13 @@ use std::time::Duration;
 use log::info;
 use rust_mqtt::{
     client::{
-        event::Publish,
-        options::{PublicationOptions, TopicReference, UnsubscriptionOptions},
+        event::{Event, Publish, Suback},
+        options::{
+            FilterOptions, PublicationOptions, SubscribeOptions, TopicReference,
+            UnsubscriptionOptions,
+        },
     },
-    types::{IdentifiedQoS, QoS},
+    types::{IdentifiedQoS, QoS, ReasonCode},
 };
 use tokio::{
     join,
@@ -613,3 +616,134 @@ async fn recv_min_pub_qos1() {
 
     join!(receiver, publisher);
 }
+
+#[tokio::test]
+#[test_log::test]
+async fn subscribe_many_recv() {
+    let (topic_name_1, topic_filter_1) = unique_topic();
+    let (topic_name_2, topic_filter_2) = unique_topic();
+    let msg = "one packet, two subscriptions";
+
+    let mut tx =
+        assert_ok!(connected_client(BROKER_ADDRESS, NO_SESSION_CONNECT_OPTIONS, None).await);
+    let mut rx =
+        assert_ok!(connected_client(BROKER_ADDRESS, NO_SESSION_CONNECT_OPTIONS, None).await);
+
+    let publisher = async {
+        sleep(Duration::from_secs(1)).await;
+
+        for topic_name in [&topic_name_1, &topic_name_2] {
+            let pub_options =
+                PublicationOptions::new(TopicReference::Name(topic_name.clone())).at_least_once();
+            assert_published!(tx, pub_options, msg.into());
+        }
+
+        disconnect(&mut tx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    let receiver = async {
+        let subscriptions = [
+            (topic_filter_1.clone(), FilterOptions::new()),
+            (topic_filter_2.clone(), FilterOptions::new().at_least_once()),
+        ];
+        let pid = assert_ok!(
+            rx.subscribe_many(&subscriptions, &SubscribeOptions::new())
+                .await
+        );
+
+        match assert_ok!(rx.poll().await) {
+            Event::Suback(Suback {
+                packet_identifier,
+                reason_string: _,
+                user_properties: _,
+                reason_codes,
+            }) if packet_identifier == pid => {
+                assert!(
+                    reason_codes
+                        .iter()
+                        .eq([ReasonCode::Success, ReasonCode::GrantedQoS1])
+                );
+            }
+            e => panic!("Expected Event::Suback, but received {:?}", e),
+        }
+
+        let Publish { identified_qos, .. } = assert_recv_excl!(rx, topic_name_1);
+        assert_eq!(
+            <IdentifiedQoS as Into<QoS>>::into(identified_qos),
+            QoS::AtMostOnce
+        );
+
+        let Publish { identified_qos, .. } = assert_recv_excl!(rx, topic_name_2);
+        assert_eq!(
+            <IdentifiedQoS as Into<QoS>>::into(identified_qos),
+            QoS::AtLeastOnce
+        );
+
+        disconnect(&mut rx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    join!(receiver, publisher);
+}
+
+#[tokio::test]
+#[test_log::test]
+async fn unsubscribe_many_no_recv() {
+    let (topic_name_1, topic_filter_1) = unique_topic();
+    let (topic_name_2, topic_filter_2) = unique_topic();
+    let msg = "Nobody is listening anymore.";
+
+    let mut tx =
+        assert_ok!(connected_client(BROKER_ADDRESS, NO_SESSION_CONNECT_OPTIONS, None).await);
+    let mut rx =
+        assert_ok!(connected_client(BROKER_ADDRESS, NO_SESSION_CONNECT_OPTIONS, None).await);
+
+    let publisher = async {
+        sleep(Duration::from_secs(2)).await;
+
+        for topic_name in [&topic_name_1, &topic_name_2] {
+            let pub_options =
+                PublicationOptions::new(TopicReference::Name(topic_name.clone())).at_least_once();
+            assert_published!(tx, pub_options, msg.into());
+        }
+
+        disconnect(&mut tx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    let receiver = async {
+        assert_subscribe!(rx, DEFAULT_QOS0_SUB_OPTIONS, topic_filter_1.clone());
+        assert_subscribe!(rx, DEFAULT_QOS0_SUB_OPTIONS, topic_filter_2.clone());
+
+        let topic_filters = [topic_filter_1.clone(), topic_filter_2.clone()];
+        let pid = assert_ok!(
+            rx.unsubscribe_many(&topic_filters, &UnsubscriptionOptions::new())
+                .await
+        );
+
+        match assert_ok!(rx.poll().await) {
+            Event::Unsuback(Suback {
+                packet_identifier,
+                reason_string: _,
+                user_properties: _,
+                reason_codes,
+            }) if packet_identifier == pid => {
+                assert!(
+                    reason_codes
+                        .iter()
+                        .eq([ReasonCode::Success, ReasonCode::Success])
+                );
+            }
+            e => panic!("Expected Event::Unsuback, but received {:?}", e),
+        }
+
+        assert_err!(
+            timeout(Duration::from_secs(10), async {
+                assert_recv!(rx);
+            })
+            .await,
+            "Expected to receive nothing"
+        );
+        disconnect(&mut rx, DEFAULT_DC_OPTIONS).await;
+    };
+
+    join!(receiver, publisher);
+}
@@ -1,6 +1,6 @@
-#[cfg(feature = "alloc")]
+#[cfg(all(feature = "alloc", feature = "v5"))]
 mod common;
-#[cfg(feature = "alloc")]
+#[cfg(all(feature = "alloc", feature = "v5"))]
 mod integration;
-#[cfg(feature = "alloc")]
+#[cfg(all(feature = "alloc", feature = "v5"))]
 mod load;